@@ -15,6 +15,7 @@ use crate::model::tag::{Tag, TagBmc, TagForCreate};
 use crate::model::validation::{Validation, ValidationBmc, ValidationForCreate};
 use crate::model::validation_file::{ValidationFile, ValidationFileBmc, ValidationFileForCreate};
 use crate::model::{self, ModelManager};
+use sqlx::{Postgres, Transaction};
 pub use test_db::TestDb;
 pub use test_db::init_test_env;
 
@@ -22,6 +23,35 @@ pub async fn init_test() -> TestDb {
     test_db::new_test_db().await
 }
 
+/// Replays migration 010 (validation reviews) inside `tx` as if the validations `legacy`
+/// inserts had been written before it; `legacy` is one `INSERT .. RETURNING id`. The review
+/// columns are dropped first, so roll `tx` back when done.
+pub async fn replay_validation_review_migration(
+    tx: &mut Transaction<'_, Postgres>,
+    legacy: &str,
+) -> model::Result<Vec<i64>> {
+    sqlx::raw_sql(
+        "ALTER TABLE public.validation DROP CONSTRAINT validation_review_state_check;
+        DROP TABLE public.validation_review_event;
+        ALTER TABLE public.validation
+            DROP COLUMN review_state,
+            DROP COLUMN submitted_by,
+            DROP COLUMN submitted_at,
+            DROP COLUMN reviewed_by,
+            DROP COLUMN reviewed_at,
+            DROP COLUMN review_comment;",
+    )
+    .execute(&mut **tx)
+    .await?;
+    let ids = sqlx::query_scalar(legacy).fetch_all(&mut **tx).await?;
+
+    let migration = include_str!("../../../airlab-web/migrations/010_validation_review.sql")
+        .replace("BEGIN;", "")
+        .replace("COMMIT;", "");
+    sqlx::raw_sql(&migration).execute(&mut **tx).await?;
+    Ok(ids)
+}
+
 fn seeded_names(unique_str: &str) -> [String; 4] {
     [
         format!("{unique_str}-01.a"),
@@ -311,6 +341,7 @@ pub fn get_validation_seed(unique_str: &str) -> Vec<ValidationForCreate> {
             is_archived: Some(false),
             created_at: Some(chrono::offset::Utc::now()),
             updated_at: Some(chrono::offset::Utc::now()),
+            protocol_template_id: None,
         })
         .collect()
 }
@@ -1,5 +1,9 @@
 use base64::engine::{Engine, general_purpose};
 
+pub fn b64_encode(content: impl AsRef<[u8]>) -> String {
+    general_purpose::STANDARD.encode(content)
+}
+
 pub fn b64u_encode(content: impl AsRef<[u8]>) -> String {
     general_purpose::URL_SAFE_NO_PAD.encode(content)
 }
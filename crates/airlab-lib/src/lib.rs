@@ -7,6 +7,7 @@ pub mod model;
 pub mod pwd;
 pub mod time;
 pub mod token;
+pub mod units;
 
 #[doc(hidden)]
 pub mod _dev_utils;
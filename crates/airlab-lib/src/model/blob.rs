@@ -0,0 +1,339 @@
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::model::Result;
+use serde::Serialize;
+use sqlx::{FromRow, Postgres, Transaction};
+
+/// Bookkeeping row of a content-addressed blob. The file itself lives in the blob store.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blob {
+    pub sha256: String,
+    pub size: i64,
+    pub ref_count: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Last time the blob was written, referenced or released.
+    pub touched_at: chrono::DateTime<chrono::Utc>,
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A blob row deleted in a still open transaction. Writers of the same content wait for
+/// it, so the stored object can be removed before [`BlobRemoval::commit`]; dropping the
+/// removal keeps the row.
+#[derive(Debug)]
+pub struct BlobRemoval {
+    pub blob: Blob,
+    tx: Transaction<'static, Postgres>,
+}
+
+impl BlobRemoval {
+    pub async fn commit(self) -> Result<Blob> {
+        self.tx.commit().await?;
+        Ok(self.blob)
+    }
+}
+
+/// A validation file that still carries a pre-blob-store MD5 hash.
+#[derive(Debug, Clone, FromRow)]
+pub struct LegacyValidationFile {
+    pub id: i64,
+    pub validation_id: i64,
+    pub group_id: i64,
+    pub hash: String,
+    pub extension: String,
+}
+
+/// Whether `hash` has the shape of a blob key, i.e. lowercase hex SHA-256.
+pub fn is_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+pub struct BlobBmc;
+
+impl BlobBmc {
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, sha256: &str) -> Result<Option<Blob>> {
+        let _ = ctx;
+        let blob = sqlx::query_as::<_, Blob>(
+            "SELECT sha256, size, ref_count, created_at, touched_at, verified_at FROM blob WHERE sha256 = $1",
+        )
+        .bind(sha256)
+        .fetch_optional(mm.db())
+        .await?;
+        Ok(blob)
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Blob>> {
+        let _ = ctx;
+        let blobs = sqlx::query_as::<_, Blob>(
+            "SELECT sha256, size, ref_count, created_at, touched_at, verified_at FROM blob ORDER BY sha256",
+        )
+        .fetch_all(mm.db())
+        .await?;
+        Ok(blobs)
+    }
+
+    /// Ids of the validation files that point at `sha256`.
+    pub async fn references(ctx: &Ctx, mm: &ModelManager, sha256: &str) -> Result<Vec<i64>> {
+        let _ = ctx;
+        let ids = sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM validation_file WHERE hash = $1 ORDER BY id",
+        )
+        .bind(sha256)
+        .fetch_all(mm.db())
+        .await?;
+        Ok(ids)
+    }
+
+    pub async fn mark_verified(ctx: &Ctx, mm: &ModelManager, sha256: &[String]) -> Result<()> {
+        let _ = ctx;
+        sqlx::query("UPDATE blob SET verified_at = NOW() WHERE sha256 = ANY($1)")
+            .bind(sha256)
+            .execute(mm.db())
+            .await?;
+        Ok(())
+    }
+
+    /// Records that `sha256` is being written, creating its row if needed. The row is
+    /// touched before the store looks for the object, so a GC either sees it or has
+    /// already finished removing the object.
+    pub async fn touch(ctx: &Ctx, mm: &ModelManager, sha256: &str, size: i64) -> Result<()> {
+        let _ = ctx;
+        sqlx::query(
+            r#"
+            INSERT INTO blob (sha256, size) VALUES ($1, $2)
+            ON CONFLICT (sha256) DO UPDATE SET touched_at = NOW()
+            "#,
+        )
+        .bind(sha256)
+        .bind(size)
+        .execute(mm.db())
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes the row of an unreferenced blob not touched since `older_than`, or returns
+    /// `None` if the blob is referenced again, too young or already gone.
+    pub async fn delete_unreferenced(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        sha256: &str,
+        older_than: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<BlobRemoval>> {
+        let _ = ctx;
+        let mut tx = mm.db().begin().await?;
+        let blob = sqlx::query_as::<_, Blob>(
+            r#"
+            DELETE FROM blob
+            WHERE sha256 = $1 AND ref_count = 0 AND touched_at < $2
+            RETURNING sha256, size, ref_count, created_at, touched_at, verified_at
+            "#,
+        )
+        .bind(sha256)
+        .bind(older_than)
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(blob.map(|blob| BlobRemoval { blob, tx }))
+    }
+
+    /// Claims a stored object that has no row, or returns `None` if a row showed up. The
+    /// claim holds a short-lived row, so writers of the same content wait for the removal.
+    pub async fn delete_orphan(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        sha256: &str,
+        size: i64,
+    ) -> Result<Option<BlobRemoval>> {
+        let _ = ctx;
+        let mut tx = mm.db().begin().await?;
+        let blob = sqlx::query_as::<_, Blob>(
+            r#"
+            INSERT INTO blob (sha256, size) VALUES ($1, $2)
+            ON CONFLICT (sha256) DO NOTHING
+            RETURNING sha256, size, ref_count, created_at, touched_at, verified_at
+            "#,
+        )
+        .bind(sha256)
+        .bind(size)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(blob) = blob else {
+            return Ok(None);
+        };
+        sqlx::query("DELETE FROM blob WHERE sha256 = $1")
+            .bind(sha256)
+            .execute(&mut *tx)
+            .await?;
+        Ok(Some(BlobRemoval { blob, tx }))
+    }
+
+    /// Validation files that have not been moved into the blob store yet.
+    pub async fn list_legacy_files(
+        ctx: &Ctx,
+        mm: &ModelManager,
+    ) -> Result<Vec<LegacyValidationFile>> {
+        let _ = ctx;
+        let files = sqlx::query_as::<_, LegacyValidationFile>(
+            r#"
+            SELECT f.id, f.validation_id, v.group_id, f.hash, f.extension
+            FROM validation_file f
+            JOIN validation v ON v.id = f.validation_id
+            WHERE f.hash !~ '^[0-9a-f]{64}$'
+            ORDER BY f.id
+            "#,
+        )
+        .fetch_all(mm.db())
+        .await?;
+        Ok(files)
+    }
+
+    /// Points a legacy validation file at its imported blob.
+    pub async fn set_file_blob(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        file_id: i64,
+        sha256: &str,
+        size: i64,
+    ) -> Result<()> {
+        let _ = ctx;
+        sqlx::query("UPDATE validation_file SET hash = $2, size = $3 WHERE id = $1")
+            .bind(file_id)
+            .bind(sha256)
+            .bind(size)
+            .execute(mm.db())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::validation_file::{ValidationFileBmc, ValidationFileForCreate};
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn file_c(validation_id: i64, created_by: i64, hash: &str) -> ValidationFileForCreate {
+        ValidationFileForCreate {
+            validation_id,
+            created_by,
+            hash: hash.to_string(),
+            size: 3,
+            name: Some("blob.txt".into()),
+            extension: "txt".into(),
+            description: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn is_sha256_requires_lowercase_hex_digest() {
+        assert!(is_sha256(&"ab".repeat(32)));
+        assert!(!is_sha256(&"AB".repeat(32)));
+        assert!(!is_sha256("d41d8cd98f00b204e9800998ecf8427e"));
+    }
+
+    #[tokio::test]
+    async fn ref_count_follows_validation_files() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let sha = "0f".repeat(32);
+
+        let first = ValidationFileBmc::create(&ctx, &mm, file_c(1011, 1303, &sha)).await?;
+        let second = ValidationFileBmc::create(&ctx, &mm, file_c(1024, 1303, &sha)).await?;
+        let blob = BlobBmc::get(&ctx, &mm, &sha).await?.ok_or("blob row")?;
+        assert_eq!((blob.size, blob.ref_count), (3, 2));
+        assert_eq!(
+            BlobBmc::references(&ctx, &mm, &sha).await?,
+            vec![first, second]
+        );
+
+        ValidationFileBmc::delete(&ctx, &mm, first).await?;
+        let future = chrono::Utc::now() + chrono::Duration::hours(1);
+        assert!(
+            BlobBmc::delete_unreferenced(&ctx, &mm, &sha, future)
+                .await?
+                .is_none()
+        );
+
+        ValidationFileBmc::delete(&ctx, &mm, second).await?;
+        let blob = BlobBmc::get(&ctx, &mm, &sha).await?.ok_or("blob row")?;
+        assert_eq!(blob.ref_count, 0);
+        let removal = BlobBmc::delete_unreferenced(&ctx, &mm, &sha, future)
+            .await?
+            .ok_or("unreferenced blob should be removed")?;
+        assert_eq!(removal.commit().await?.sha256, sha);
+        assert!(BlobBmc::get(&ctx, &mm, &sha).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn removals_skip_touched_blobs_and_keep_the_row_until_committed() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let sha = "2d".repeat(32);
+        let past = chrono::Utc::now() - chrono::Duration::hours(1);
+        let future = chrono::Utc::now() + chrono::Duration::hours(1);
+
+        BlobBmc::touch(&ctx, &mm, &sha, 4).await?;
+        let blob = BlobBmc::get(&ctx, &mm, &sha).await?.ok_or("blob row")?;
+        assert_eq!((blob.size, blob.ref_count), (4, 0));
+        assert!(
+            BlobBmc::delete_unreferenced(&ctx, &mm, &sha, past)
+                .await?
+                .is_none()
+        );
+
+        let removal = BlobBmc::delete_unreferenced(&ctx, &mm, &sha, future).await?;
+        drop(removal.ok_or("blob should be claimed")?);
+        assert!(BlobBmc::get(&ctx, &mm, &sha).await?.is_some());
+        assert!(BlobBmc::delete_orphan(&ctx, &mm, &sha, 4).await?.is_none());
+
+        BlobBmc::delete_unreferenced(&ctx, &mm, &sha, future)
+            .await?
+            .ok_or("blob should be claimed")?
+            .commit()
+            .await?;
+        BlobBmc::delete_orphan(&ctx, &mm, &sha, 4)
+            .await?
+            .ok_or("orphan should be claimed")?
+            .commit()
+            .await?;
+        assert!(BlobBmc::get(&ctx, &mm, &sha).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn legacy_files_are_moved_onto_blobs() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = ValidationFileBmc::create(
+            &ctx,
+            &mm,
+            file_c(1011, 1303, "d41d8cd98f00b204e9800998ecf8427e"),
+        )
+        .await?;
+
+        let legacy = BlobBmc::list_legacy_files(&ctx, &mm).await?;
+        let file = legacy
+            .iter()
+            .find(|file| file.id == id)
+            .ok_or("legacy file should be listed")?;
+        assert_eq!(file.group_id, 1000);
+
+        let sha = "1e".repeat(32);
+        BlobBmc::set_file_blob(&ctx, &mm, id, &sha, 5).await?;
+        assert!(
+            !BlobBmc::list_legacy_files(&ctx, &mm)
+                .await?
+                .iter()
+                .any(|file| file.id == id)
+        );
+        let blob = BlobBmc::get(&ctx, &mm, &sha).await?.ok_or("blob row")?;
+        assert_eq!((blob.size, blob.ref_count), (5, 1));
+
+        Ok(())
+    }
+}
@@ -1,15 +1,22 @@
 use crate::ctx::Ctx;
-use crate::model::ModelManager;
-use crate::model::Result;
 use crate::model::base::{self, DbBmc};
+use crate::model::clone_application::{
+    ApplicationFilter, CloneApplications, normalize_application,
+};
 use crate::model::helpers::{
     bool_or, i64_or, opt_bool, opt_i64, opt_string, opt_value, opt_vec_i64, string_or,
 };
+use crate::model::{Error, ModelManager, Result};
 use modql::field::Fields;
-use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use modql::filter::{
+    FilterNodes, IntoSeaError, ListOptions, OpValValue, OpValsInt64, OpValsString, OpValsValue,
+    SeaResult,
+};
+use sea_query::extension::postgres::PgBinOper;
+use sea_query::{ColumnRef, Condition, ConditionExpression, SimpleExpr};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::FromRow;
+use sqlx::{FromRow, PgConnection};
 
 #[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize, Default)]
 pub struct CloneId {
@@ -46,6 +53,15 @@ pub struct Clone {
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl Clone {
+    pub fn applications(&self) -> CloneApplications {
+        self.application
+            .as_ref()
+            .map(CloneApplications::from_json)
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Fields, Deserialize, Clone, Debug)]
 pub struct CloneForCreate {
     #[serde(rename = "groupId")]
@@ -148,6 +164,37 @@ pub struct CloneFilter {
     protein_id: Option<OpValsInt64>,
     group_id: Option<OpValsInt64>,
     name: Option<OpValsString>,
+    /// `{"$eq": {"application": "IMC", "status": 0}}` or `$not`; see [`ApplicationFilter::parse`].
+    #[modql(to_sea_condition_fn = "application_to_sea_condition")]
+    application: Option<OpValsValue>,
+}
+
+fn application_to_sea_condition(
+    col: &ColumnRef,
+    op_val: OpValValue,
+) -> SeaResult<ConditionExpression> {
+    let contains = |value: &Value| -> SeaResult<SimpleExpr> {
+        let filter = ApplicationFilter::parse(value).ok_or_else(|| {
+            IntoSeaError::custom(format!("invalid clone application filter: {value}"))
+        })?;
+        Ok(SimpleExpr::binary(
+            SimpleExpr::Column(col.clone()),
+            PgBinOper::Contains,
+            SimpleExpr::Value(sea_query::Value::Json(Some(Box::new(filter.containment())))),
+        ))
+    };
+    let condition = match op_val {
+        OpValValue::Eq(value) => Condition::all().add(contains(&value)?),
+        OpValValue::Not(value) => Condition::all().add(contains(&value)?).not(),
+        // modql 0.4 parses `$in` as `NotIn`, so list operators are ambiguous; OR several
+        // filter groups instead.
+        _ => {
+            return Err(IntoSeaError::custom(
+                "clone application filter supports $eq and $not",
+            ));
+        }
+    };
+    Ok(ConditionExpression::Condition(condition))
 }
 
 pub struct CloneBmc;
@@ -157,7 +204,9 @@ impl DbBmc for CloneBmc {
 }
 
 impl CloneBmc {
-    pub async fn create(ctx: &Ctx, mm: &ModelManager, clone_c: CloneForCreate) -> Result<i64> {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, mut clone_c: CloneForCreate) -> Result<i64> {
+        check_reactivity(mm, clone_c.group_id, clone_c.reactivity.as_deref()).await?;
+        clone_c.application = normalize_application(clone_c.application);
         base::create::<Self, _>(ctx, mm, clone_c).await
     }
     pub async fn create_full(ctx: &Ctx, mm: &ModelManager, clone_c: Clone) -> Result<i64> {
@@ -198,14 +247,69 @@ impl CloneBmc {
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
-        clone_u: CloneForUpdate,
+        mut clone_u: CloneForUpdate,
     ) -> Result<()> {
+        if let Some(reactivity) = clone_u.reactivity.as_deref() {
+            let clone = Self::get(ctx, mm, id).await?;
+            check_reactivity(mm, clone.group_id, Some(reactivity)).await?;
+        }
+        clone_u.application = normalize_application(clone_u.application);
         base::update::<Self, _>(ctx, mm, id, clone_u).await
     }
 
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
         base::delete::<Self>(ctx, mm, id).await
     }
+
+    /// Rewrites legacy `application` shapes into the canonical one; returns the rows changed.
+    pub async fn normalize_applications(ctx: &Ctx, conn: &mut PgConnection) -> Result<u64> {
+        let _ = ctx;
+        let rows = sqlx::query_as::<_, (i64, Value)>(
+            "SELECT id, application FROM clone WHERE application IS NOT NULL ORDER BY id",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut normalized = 0;
+        for (id, application) in rows {
+            let canonical = CloneApplications::from_json(&application).to_json();
+            if canonical == application {
+                continue;
+            }
+            sqlx::query("UPDATE clone SET application = $1 WHERE id = $2")
+                .bind(canonical)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            normalized += 1;
+        }
+
+        Ok(normalized)
+    }
+}
+
+/// Rejects reactivity entries that are not species of the clone's group.
+async fn check_reactivity(
+    mm: &ModelManager,
+    group_id: i64,
+    reactivity: Option<&[i64]>,
+) -> Result<()> {
+    let Some(species_ids) = reactivity.filter(|ids| !ids.is_empty()) else {
+        return Ok(());
+    };
+    let known: Vec<i64> =
+        sqlx::query_scalar("SELECT id FROM species WHERE id = ANY($1) AND group_id = $2")
+            .bind(species_ids)
+            .bind(group_id)
+            .fetch_all(mm.db())
+            .await?;
+    match species_ids.iter().find(|id| !known.contains(id)) {
+        Some(id) => Err(Error::EntityNotFound {
+            entity: "species",
+            id: *id,
+        }),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -381,6 +485,93 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_clone_create_normalizes_application_and_filters_by_pair() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let mut clone_c = _dev_utils::get_clone_seed("test_clone_application").remove(0);
+        clone_c.application = Some(json!({"2": true, "6": {"status": "So-So", "note": "weak"}}));
+        let id = CloneBmc::create(&ctx, &mm, clone_c).await?;
+
+        let clone = CloneBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(
+            clone.application,
+            Some(json!({"2": {"status": 0}, "6": {"status": 1, "note": "weak"}}))
+        );
+
+        let filters: Vec<CloneFilter> = serde_json::from_value(json!([{
+            "group_id": {"$eq": 1000},
+            "application": {"$eq": {"application": "WB", "status": 1}}
+        }]))?;
+        let ids: Vec<i64> = CloneBmc::list(&ctx, &mm, Some(filters), None)
+            .await?
+            .into_iter()
+            .map(|clone| clone.id)
+            .collect();
+        assert_eq!(ids, vec![id]);
+
+        let filters: Vec<CloneFilter> = serde_json::from_value(json!([
+            {
+                "group_id": {"$eq": 1000},
+                "application": {"$eq": {"application": 6, "status": 0}}
+            },
+            {
+                "group_id": {"$eq": 1000},
+                "application": {"$eq": {"application": 3}}
+            }
+        ]))?;
+        assert!(
+            CloneBmc::list(&ctx, &mm, Some(filters), None)
+                .await?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clone_rejects_reactivity_outside_group_species() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let mut clone_c = _dev_utils::get_clone_seed("test_clone_reactivity").remove(0);
+        clone_c.reactivity = Some(vec![1004, 44]);
+
+        let res = CloneBmc::create(&ctx, &mm, clone_c.clone()).await;
+        assert!(matches!(
+            res,
+            Err(Error::EntityNotFound {
+                entity: "species",
+                id: 44
+            })
+        ));
+
+        clone_c.reactivity = Some(vec![1004, 1014]);
+        let id = CloneBmc::create(&ctx, &mm, clone_c).await?;
+        let res = CloneBmc::update(
+            &ctx,
+            &mm,
+            id,
+            CloneForUpdate {
+                reactivity: Some(vec![9999]),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(Error::EntityNotFound {
+                entity: "species",
+                id: 9999
+            })
+        ));
+        assert_eq!(
+            CloneBmc::get(&ctx, &mm, id).await?.reactivity,
+            Some(vec![1004, 1014])
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_clone_delete_err_not_found() -> Result<()> {
         let mm = _dev_utils::init_test().await;
@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Application a clone can be used for, numbered like `validation.application`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Application {
+    Smc,
+    Imc,
+    Fc,
+    If,
+    Ihc,
+    IhcF,
+    Wb,
+}
+
+impl Application {
+    pub const ALL: [Self; 7] = [
+        Self::Smc,
+        Self::Imc,
+        Self::Fc,
+        Self::If,
+        Self::Ihc,
+        Self::IhcF,
+        Self::Wb,
+    ];
+
+    pub const fn id(self) -> i64 {
+        match self {
+            Self::Smc => 0,
+            Self::Imc => 1,
+            Self::Fc => 2,
+            Self::If => 3,
+            Self::Ihc => 4,
+            Self::IhcF => 5,
+            Self::Wb => 6,
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Smc => "SMC",
+            Self::Imc => "IMC",
+            Self::Fc => "FC",
+            Self::If => "IF",
+            Self::Ihc => "IHC",
+            Self::IhcF => "IHC-F",
+            Self::Wb => "WB",
+        }
+    }
+
+    pub fn from_id(id: i64) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|application| application.id() == id)
+    }
+
+    /// Accepts the numeric id (as number or string) or the label, case-insensitively.
+    pub fn parse(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(number) => number.as_i64().and_then(Self::from_id),
+            Value::String(raw) => Self::parse_str(raw),
+            _ => None,
+        }
+    }
+
+    pub fn parse_str(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if let Ok(id) = raw.parse::<i64>() {
+            return Self::from_id(id);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|application| application.label().eq_ignore_ascii_case(raw))
+    }
+}
+
+/// Suitability of a clone for one application, numbered like `validation.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ApplicationStatus {
+    Yes,
+    SoSo,
+    No,
+    Undefined,
+}
+
+impl ApplicationStatus {
+    pub const ALL: [Self; 4] = [Self::Yes, Self::SoSo, Self::No, Self::Undefined];
+
+    pub const fn id(self) -> i64 {
+        match self {
+            Self::Yes => 0,
+            Self::SoSo => 1,
+            Self::No => 2,
+            Self::Undefined => 3,
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Yes => "Yes",
+            Self::SoSo => "So-So",
+            Self::No => "No",
+            Self::Undefined => "Undefined",
+        }
+    }
+
+    pub fn from_id(id: i64) -> Option<Self> {
+        Self::ALL.into_iter().find(|status| status.id() == id)
+    }
+
+    /// Whether the clone can be used for the application at all.
+    pub const fn is_usable(self) -> bool {
+        matches!(self, Self::Yes | Self::SoSo)
+    }
+
+    pub fn parse(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(number) => number.as_i64().and_then(Self::from_id),
+            Value::String(raw) => {
+                let raw = raw.trim();
+                if let Ok(id) = raw.parse::<i64>() {
+                    return Self::from_id(id);
+                }
+                Self::ALL.into_iter().find(|status| {
+                    status.label().eq_ignore_ascii_case(raw)
+                        || status.label().replace('-', "").eq_ignore_ascii_case(raw)
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationSuitability {
+    pub application: Application,
+    pub status: ApplicationStatus,
+    pub note: Option<String>,
+}
+
+/// Typed view of `clone.application`.
+///
+/// The canonical jsonb shape is `{"<application id>": {"status": <status id>, "note": "..."}}`.
+/// Legacy shapes (`{"1": true}`, `{"1": "true"}`, `[1, 2]`, lists of objects) are read
+/// leniently: a checked (`true`) entry is Yes and an unchecked (`false`) one No, while
+/// `null` and unknown entries are dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloneApplications {
+    entries: BTreeMap<Application, ApplicationSuitability>,
+}
+
+impl CloneApplications {
+    pub fn from_json(value: &Value) -> Self {
+        let mut applications = Self::default();
+        match value {
+            Value::Object(map) => {
+                for (key, raw) in map {
+                    if let Some(application) = Application::parse_str(key) {
+                        applications.insert_raw(application, raw);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    match item {
+                        Value::Object(map) => {
+                            if let Some(application) =
+                                map.get("application").and_then(Application::parse)
+                            {
+                                applications.insert_raw(application, item);
+                            }
+                        }
+                        other => {
+                            if let Some(application) = Application::parse(other) {
+                                applications.insert(application, ApplicationStatus::Yes, None);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        applications
+    }
+
+    fn insert_raw(&mut self, application: Application, raw: &Value) {
+        match raw {
+            Value::Bool(true) => self.insert(application, ApplicationStatus::Yes, None),
+            Value::String(flag) if flag.eq_ignore_ascii_case("true") => {
+                self.insert(application, ApplicationStatus::Yes, None);
+            }
+            Value::Bool(false) => self.insert(application, ApplicationStatus::No, None),
+            Value::String(flag) if flag.eq_ignore_ascii_case("false") => {
+                self.insert(application, ApplicationStatus::No, None);
+            }
+            Value::Null => {}
+            Value::Object(map) => {
+                let status = map
+                    .get("status")
+                    .and_then(ApplicationStatus::parse)
+                    .unwrap_or(ApplicationStatus::Undefined);
+                let note = map
+                    .get("note")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|note| !note.is_empty())
+                    .map(str::to_string);
+                self.insert(application, status, note);
+            }
+            other => {
+                if let Some(status) = ApplicationStatus::parse(other) {
+                    self.insert(application, status, None);
+                }
+            }
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        application: Application,
+        status: ApplicationStatus,
+        note: Option<String>,
+    ) {
+        self.entries.insert(
+            application,
+            ApplicationSuitability {
+                application,
+                status,
+                note,
+            },
+        );
+    }
+
+    pub fn get(&self, application: Application) -> Option<&ApplicationSuitability> {
+        self.entries.get(&application)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ApplicationSuitability> {
+        self.entries.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let map = self
+            .entries
+            .values()
+            .map(|entry| {
+                let mut value = json!({ "status": entry.status.id() });
+                if let Some(note) = &entry.note {
+                    value["note"] = json!(note);
+                }
+                (entry.application.id().to_string(), value)
+            })
+            .collect::<Map<_, _>>();
+        Value::Object(map)
+    }
+}
+
+/// Rewrites an incoming `application` payload into the canonical shape.
+pub fn normalize_application(value: Option<Value>) -> Option<Value> {
+    value.map(|value| CloneApplications::from_json(&value).to_json())
+}
+
+/// An application, optionally narrowed to one status, used to filter clones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplicationFilter {
+    pub application: Application,
+    pub status: Option<ApplicationStatus>,
+}
+
+impl ApplicationFilter {
+    /// Accepts an application (`1`, `"IMC"`) or `{"application": .., "status": ..}`.
+    pub fn parse(value: &Value) -> Option<Self> {
+        match value {
+            Value::Object(map) => {
+                let application = map.get("application").and_then(Application::parse)?;
+                let status = match map.get("status") {
+                    None | Some(Value::Null) => None,
+                    Some(status) => Some(ApplicationStatus::parse(status)?),
+                };
+                Some(Self {
+                    application,
+                    status,
+                })
+            }
+            other => Application::parse(other).map(|application| Self {
+                application,
+                status: None,
+            }),
+        }
+    }
+
+    /// The jsonb document a canonical `application` column must contain (`@>`) to match.
+    pub fn containment(&self) -> Value {
+        let entry = match self.status {
+            Some(status) => json!({ "status": status.id() }),
+            None => json!({}),
+        };
+        json!({ self.application.id().to_string(): entry })
+    }
+
+    pub fn matches(&self, applications: &CloneApplications) -> bool {
+        applications
+            .get(self.application)
+            .is_some_and(|entry| self.status.is_none_or(|status| entry.status == status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[test]
+    fn from_json_reads_legacy_boolean_map() {
+        let applications = CloneApplications::from_json(
+            &json!({"1": true, "2": "true", "3": false, "4": null, "x": true}),
+        );
+
+        let ids = applications
+            .iter()
+            .map(|entry| (entry.application.id(), entry.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                (1, ApplicationStatus::Yes),
+                (2, ApplicationStatus::Yes),
+                (3, ApplicationStatus::No)
+            ]
+        );
+    }
+
+    #[test]
+    fn from_json_reads_lists_and_typed_entries() {
+        let applications = CloneApplications::from_json(&json!([
+            "IMC",
+            {"application": 6, "status": "so-so", "note": " weak band "}
+        ]));
+
+        assert_eq!(
+            applications.get(Application::Imc).map(|entry| entry.status),
+            Some(ApplicationStatus::Yes)
+        );
+        let wb = applications.get(Application::Wb);
+        assert_eq!(wb.map(|entry| entry.status), Some(ApplicationStatus::SoSo));
+        assert_eq!(
+            wb.and_then(|entry| entry.note.as_deref()),
+            Some("weak band")
+        );
+    }
+
+    #[test]
+    fn to_json_round_trips_canonical_shape() {
+        let canonical = json!({"1": {"status": 0}, "4": {"status": 2, "note": "high background"}});
+        let applications = CloneApplications::from_json(&canonical);
+
+        assert_eq!(applications.to_json(), canonical);
+        assert_eq!(
+            normalize_application(Some(json!({"1": true}))),
+            Some(json!({"1": {"status": 0}}))
+        );
+    }
+
+    #[test]
+    fn application_filter_parses_pairs_and_matches() -> TestResult {
+        let filter = ApplicationFilter::parse(&json!({"application": "FC", "status": 0}))
+            .ok_or("filter should parse")?;
+        assert_eq!(filter.containment(), json!({"2": {"status": 0}}));
+
+        let applications = CloneApplications::from_json(&json!({"2": {"status": 0}}));
+        assert!(filter.matches(&applications));
+        assert!(!filter.matches(&CloneApplications::from_json(&json!({"2": {"status": 2}}))));
+
+        let any_status = ApplicationFilter::parse(&json!(2)).ok_or("filter should parse")?;
+        assert_eq!(any_status.containment(), json!({"2": {}}));
+        assert!(ApplicationFilter::parse(&json!({"application": 2, "status": "maybe"})).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ctx::Ctx;
+use crate::model::clone::{Clone, CloneBmc};
+use crate::model::clone_application::Application;
+use crate::model::conjugate::{Conjugate, ConjugateBmc, ConjugateFilter};
+use crate::model::lot::{Lot, LotBmc, LotFilter};
+use crate::model::protein::{Protein, ProteinBmc};
+use crate::model::species::SpeciesBmc;
+use crate::model::tag::TagBmc;
+use crate::model::validation::{Validation, ValidationBmc, ValidationFilter};
+use crate::model::validation_file::{ValidationFile, ValidationFileBmc, ValidationFileFilter};
+use crate::model::{Error, ModelManager, Result};
+use modql::filter::{ListOptions, OrderBys};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DossierConjugate {
+    pub conjugate: Conjugate,
+    pub tag_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DossierValidation {
+    pub validation: Validation,
+    pub species_name: Option<String>,
+    pub files: Vec<ValidationFile>,
+}
+
+/// Everything known about one clone, as put into its validation report.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneDossier {
+    pub clone: Clone,
+    pub protein: Protein,
+    pub host_species_name: Option<String>,
+    pub reactivity_names: Vec<String>,
+    pub application: Option<Application>,
+    pub lots: Vec<Lot>,
+    pub conjugates: Vec<DossierConjugate>,
+    pub validations: Vec<DossierValidation>,
+}
+
+pub struct CloneDossierBmc;
+
+impl CloneDossierBmc {
+    /// Loads the dossier of `clone_id`, skipping archived validations. With an
+    /// `application`, only its validations are included.
+    pub async fn load(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        clone_id: i64,
+        application: Option<Application>,
+    ) -> Result<CloneDossier> {
+        let clone = CloneBmc::get(ctx, mm, clone_id).await?;
+        let protein = ProteinBmc::get(ctx, mm, clone.protein_id).await?;
+        let by_id = || ListOptions {
+            order_bys: Some(OrderBys::from("id")),
+            ..Default::default()
+        };
+
+        let lot_filters: Vec<LotFilter> =
+            serde_json::from_value(json!([{ "clone_id": {"$eq": clone_id} }]))?;
+        let lots = LotBmc::list(ctx, mm, Some(lot_filters), Some(by_id())).await?;
+
+        let mut tag_names: HashMap<i64, String> = HashMap::new();
+        let mut conjugates = vec![];
+        for lot in &lots {
+            let filters: Vec<ConjugateFilter> =
+                serde_json::from_value(json!([{ "lot_id": {"$eq": lot.id} }]))?;
+            for conjugate in ConjugateBmc::list(ctx, mm, Some(filters), Some(by_id())).await? {
+                let tag_name = match tag_names.get(&conjugate.tag_id) {
+                    Some(name) => name.clone(),
+                    None => {
+                        let name = TagBmc::get(ctx, mm, conjugate.tag_id).await?.name;
+                        tag_names.insert(conjugate.tag_id, name.clone());
+                        name
+                    }
+                };
+                conjugates.push(DossierConjugate {
+                    conjugate,
+                    tag_name,
+                });
+            }
+        }
+
+        let mut filter = json!({
+            "clone_id": {"$eq": clone_id},
+            "is_archived": {"$eq": false}
+        });
+        if let Some(application) = application {
+            filter["application"] = json!({"$eq": application.id()});
+        }
+        let validation_filters: Vec<ValidationFilter> = serde_json::from_value(json!([filter]))?;
+        let mut species_names: HashMap<i64, String> = HashMap::new();
+        let mut species_name = async |id: i64| -> Result<String> {
+            if let Some(name) = species_names.get(&id) {
+                return Ok(name.clone());
+            }
+            let name = SpeciesBmc::get(ctx, mm, id).await?.name;
+            species_names.insert(id, name.clone());
+            Ok(name)
+        };
+
+        let mut validations = vec![];
+        for validation in
+            ValidationBmc::list(ctx, mm, Some(validation_filters), Some(by_id())).await?
+        {
+            let filters: Vec<ValidationFileFilter> =
+                serde_json::from_value(json!([{ "validation_id": {"$eq": validation.id} }]))?;
+            let files = ValidationFileBmc::list(ctx, mm, Some(filters), Some(by_id())).await?;
+            let species = match validation.species_id {
+                Some(id) => Some(species_name(id).await?),
+                None => None,
+            };
+            validations.push(DossierValidation {
+                validation,
+                species_name: species,
+                files,
+            });
+        }
+
+        let host_species_name = match clone.species_id {
+            Some(id) => Some(species_name(id).await?),
+            None => None,
+        };
+        // Legacy reactivity lists may point at species that no longer exist.
+        let mut reactivity_names = vec![];
+        for id in clone.reactivity.clone().unwrap_or_default() {
+            match species_name(id).await {
+                Ok(name) => reactivity_names.push(name),
+                Err(Error::EntityNotFound { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(CloneDossier {
+            clone,
+            protein,
+            host_species_name,
+            reactivity_names,
+            application,
+            lots,
+            conjugates,
+            validations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[tokio::test]
+    async fn load_collects_seeded_clone() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let seeds = _dev_utils::get_validation_file_seed("load_collects_seeded_clone");
+        _dev_utils::seed_validation_files(&ctx, &mm, &seeds).await?;
+
+        let dossier = CloneDossierBmc::load(&ctx, &mm, 1006, None).await?;
+
+        assert_eq!(dossier.protein.id, 1002);
+        assert_eq!(dossier.host_species_name.as_deref(), Some("Mouse"));
+        assert!(dossier.lots.iter().any(|lot| lot.id == 1007));
+        assert!(
+            dossier
+                .conjugates
+                .iter()
+                .any(|item| item.conjugate.id == 1008)
+        );
+        let seed = dossier
+            .validations
+            .iter()
+            .find(|item| item.validation.id == 1011)
+            .ok_or("seed validation should be included")?;
+        assert_eq!(seed.species_name.as_deref(), Some("Mouse"));
+        assert!(!seed.files.is_empty());
+
+        let dossier = CloneDossierBmc::load(&ctx, &mm, 1006, Some(Application::Wb)).await?;
+        assert!(dossier.validations.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,572 @@
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::conjugate::{ConjugateBmc, ConjugateForUpdate};
+use crate::model::helpers::{i64_or, opt_f64, opt_i64, opt_string, opt_value};
+use crate::model::{Error, ModelManager, Result};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+/// A labeling run. Amounts are in µg, volumes in µL and concentrations in µg/mL.
+#[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize)]
+pub struct ConjugationBatch {
+    pub id: i64,
+    pub group_id: i64,
+    pub lot_id: i64,
+    pub tag_id: i64,
+    pub conjugate_id: Option<i64>,
+    pub created_by: i64,
+    pub labeled_by: Option<i64>,
+    pub kit: Option<String>,
+    pub buffer: Option<String>,
+    pub antibody_amount_ug: f64,
+    pub measured_concentration: Option<f64>,
+    pub final_volume_ul: Option<f64>,
+    pub steps: Value,
+    pub note: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConjugationStep {
+    pub name: String,
+    #[serde(default)]
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl ConjugationBatch {
+    pub fn parsed_steps(&self) -> Vec<ConjugationStep> {
+        self.steps
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn recovered_amount_ug(&self) -> Option<f64> {
+        match (self.measured_concentration, self.final_volume_ul) {
+            (Some(concentration), Some(volume)) => Some(concentration * volume / 1000.0),
+            _ => None,
+        }
+    }
+
+    pub fn yield_percent(&self) -> Option<f64> {
+        if self.antibody_amount_ug <= 0.0 {
+            return None;
+        }
+        self.recovered_amount_ug()
+            .map(|recovered| recovered / self.antibody_amount_ug * 100.0)
+    }
+}
+
+/// Stock concentration of a conjugate pooled from several batches: total recovered
+/// antibody over total final volume. Batches without measurements are ignored.
+pub fn pooled_concentration(batches: &[ConjugationBatch]) -> Option<f64> {
+    let (amount, volume) = batches
+        .iter()
+        .filter_map(|batch| Some((batch.recovered_amount_ug()?, batch.final_volume_ul?)))
+        .fold((0.0, 0.0), |(amount, volume), (a, v)| {
+            (amount + a, volume + v)
+        });
+    if volume > 0.0 {
+        Some(amount / volume * 1000.0)
+    } else {
+        None
+    }
+}
+
+#[derive(Fields, Deserialize, Clone, Debug)]
+pub struct ConjugationBatchForCreate {
+    pub group_id: i64,
+    pub lot_id: i64,
+    pub tag_id: i64,
+    pub conjugate_id: Option<i64>,
+    pub created_by: i64,
+    pub labeled_by: Option<i64>,
+    pub kit: Option<String>,
+    pub buffer: Option<String>,
+    pub antibody_amount_ug: f64,
+    pub measured_concentration: Option<f64>,
+    pub final_volume_ul: Option<f64>,
+    pub steps: Value,
+    pub note: Option<String>,
+}
+
+impl From<Value> for ConjugationBatchForCreate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+
+        ConjugationBatchForCreate {
+            group_id: opt_i64(&obj, "groupId").unwrap_or(i64_or(&obj, "group_id", 0)),
+            lot_id: opt_i64(&obj, "lotId").unwrap_or(i64_or(&obj, "lot_id", 0)),
+            tag_id: opt_i64(&obj, "tagId").unwrap_or(i64_or(&obj, "tag_id", 0)),
+            conjugate_id: opt_i64(&obj, "conjugateId").or_else(|| opt_i64(&obj, "conjugate_id")),
+            created_by: opt_i64(&obj, "createdBy").unwrap_or(i64_or(&obj, "created_by", 0)),
+            labeled_by: opt_i64(&obj, "labeledBy").or_else(|| opt_i64(&obj, "labeled_by")),
+            kit: opt_string(&obj, "kit"),
+            buffer: opt_string(&obj, "buffer"),
+            antibody_amount_ug: opt_f64(&obj, "antibodyAmountUg")
+                .or_else(|| opt_f64(&obj, "antibody_amount_ug"))
+                .unwrap_or_default(),
+            measured_concentration: opt_f64(&obj, "measuredConcentration")
+                .or_else(|| opt_f64(&obj, "measured_concentration")),
+            final_volume_ul: opt_f64(&obj, "finalVolumeUl")
+                .or_else(|| opt_f64(&obj, "final_volume_ul")),
+            steps: opt_value(&obj, "steps").unwrap_or_else(|| Value::Array(vec![])),
+            note: opt_string(&obj, "note"),
+        }
+    }
+}
+
+#[derive(Fields, Default, Deserialize, Debug)]
+pub struct ConjugationBatchForUpdate {
+    pub conjugate_id: Option<i64>,
+    pub labeled_by: Option<i64>,
+    pub kit: Option<String>,
+    pub buffer: Option<String>,
+    pub antibody_amount_ug: Option<f64>,
+    pub measured_concentration: Option<f64>,
+    pub final_volume_ul: Option<f64>,
+    pub steps: Option<Value>,
+    pub note: Option<String>,
+}
+
+impl From<Value> for ConjugationBatchForUpdate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+
+        ConjugationBatchForUpdate {
+            conjugate_id: opt_i64(&obj, "conjugateId").or_else(|| opt_i64(&obj, "conjugate_id")),
+            labeled_by: opt_i64(&obj, "labeledBy").or_else(|| opt_i64(&obj, "labeled_by")),
+            kit: opt_string(&obj, "kit"),
+            buffer: opt_string(&obj, "buffer"),
+            antibody_amount_ug: opt_f64(&obj, "antibodyAmountUg")
+                .or_else(|| opt_f64(&obj, "antibody_amount_ug")),
+            measured_concentration: opt_f64(&obj, "measuredConcentration")
+                .or_else(|| opt_f64(&obj, "measured_concentration")),
+            final_volume_ul: opt_f64(&obj, "finalVolumeUl")
+                .or_else(|| opt_f64(&obj, "final_volume_ul")),
+            steps: opt_value(&obj, "steps"),
+            note: opt_string(&obj, "note"),
+        }
+    }
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug, Clone)]
+pub struct ConjugationBatchFilter {
+    id: Option<OpValsInt64>,
+    group_id: Option<OpValsInt64>,
+    lot_id: Option<OpValsInt64>,
+    tag_id: Option<OpValsInt64>,
+    conjugate_id: Option<OpValsInt64>,
+    kit: Option<OpValsString>,
+}
+
+pub struct ConjugationBatchBmc;
+
+impl DbBmc for ConjugationBatchBmc {
+    const TABLE: &'static str = "conjugation_batch";
+
+    fn has_timestamps() -> bool {
+        false
+    }
+}
+
+impl ConjugationBatchBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        batch_c: ConjugationBatchForCreate,
+    ) -> Result<i64> {
+        let conjugate_id = batch_c.conjugate_id;
+        let id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO conjugation_batch (group_id, lot_id, tag_id, conjugate_id, created_by, labeled_by, kit, buffer, antibody_amount_ug, measured_concentration, final_volume_ul, steps, note, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, NOW(), NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(batch_c.group_id)
+        .bind(batch_c.lot_id)
+        .bind(batch_c.tag_id)
+        .bind(batch_c.conjugate_id)
+        .bind(batch_c.created_by)
+        .bind(batch_c.labeled_by)
+        .bind(batch_c.kit)
+        .bind(batch_c.buffer)
+        .bind(batch_c.antibody_amount_ug)
+        .bind(batch_c.measured_concentration)
+        .bind(batch_c.final_volume_ul)
+        .bind(batch_c.steps)
+        .bind(batch_c.note)
+        .fetch_one(mm.db())
+        .await?;
+
+        if let Some(conjugate_id) = conjugate_id {
+            Self::sync_conjugate_concentration(ctx, mm, conjugate_id).await?;
+        }
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<ConjugationBatch> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<ConjugationBatchFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<ConjugationBatch>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn count(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<ConjugationBatchFilter>>,
+    ) -> Result<i64> {
+        base::count::<Self, _>(ctx, mm, filters).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        batch_u: ConjugationBatchForUpdate,
+    ) -> Result<()> {
+        // The previous conjugate loses the batch when it moves, so both are resynced.
+        let (conjugate_id, previous_conjugate_id) =
+            sqlx::query_as::<_, (Option<i64>, Option<i64>)>(
+                r#"
+            WITH previous AS (
+                SELECT conjugate_id AS previous_conjugate_id
+                FROM conjugation_batch
+                WHERE id = $10
+                FOR UPDATE
+            )
+            UPDATE conjugation_batch
+            SET
+                conjugate_id = COALESCE($1, conjugate_id),
+                labeled_by = COALESCE($2, labeled_by),
+                kit = COALESCE($3, kit),
+                buffer = COALESCE($4, buffer),
+                antibody_amount_ug = COALESCE($5, antibody_amount_ug),
+                measured_concentration = COALESCE($6, measured_concentration),
+                final_volume_ul = COALESCE($7, final_volume_ul),
+                steps = COALESCE($8, steps),
+                note = COALESCE($9, note),
+                updated_at = NOW()
+            FROM previous
+            WHERE id = $10
+            RETURNING conjugate_id, previous.previous_conjugate_id
+            "#,
+            )
+            .bind(batch_u.conjugate_id)
+            .bind(batch_u.labeled_by)
+            .bind(batch_u.kit)
+            .bind(batch_u.buffer)
+            .bind(batch_u.antibody_amount_ug)
+            .bind(batch_u.measured_concentration)
+            .bind(batch_u.final_volume_ul)
+            .bind(batch_u.steps)
+            .bind(batch_u.note)
+            .bind(id)
+            .fetch_optional(mm.db())
+            .await?
+            .ok_or(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })?;
+
+        if let Some(conjugate_id) = conjugate_id {
+            Self::sync_conjugate_concentration(ctx, mm, conjugate_id).await?;
+        }
+        if let Some(previous_conjugate_id) = previous_conjugate_id
+            && conjugate_id != Some(previous_conjugate_id)
+        {
+            Self::sync_conjugate_concentration(ctx, mm, previous_conjugate_id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let conjugate_id = sqlx::query_scalar::<_, Option<i64>>(
+            r#"
+            DELETE FROM conjugation_batch
+            WHERE id = $1
+            RETURNING conjugate_id
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(mm.db())
+        .await?
+        .ok_or(Error::EntityNotFound {
+            entity: Self::TABLE,
+            id,
+        })?;
+
+        if let Some(conjugate_id) = conjugate_id {
+            Self::sync_conjugate_concentration(ctx, mm, conjugate_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Recomputes the conjugate's stock concentration from all of its batches. The stored
+    /// value is left untouched when no batch has been measured yet, and cleared once the
+    /// conjugate has no batches left, as it then only reflects batches that are gone.
+    pub async fn sync_conjugate_concentration(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        conjugate_id: i64,
+    ) -> Result<Option<f64>> {
+        let filters: Vec<ConjugationBatchFilter> = serde_json::from_value(serde_json::json!([
+            {
+                "conjugate_id": {"$eq": conjugate_id}
+            }
+        ]))?;
+        let batches = Self::list(ctx, mm, Some(filters), None).await?;
+        if batches.is_empty() {
+            sqlx::query(
+                "UPDATE conjugate SET concentration = NULL, updated_at = NOW() WHERE id = $1",
+            )
+            .bind(conjugate_id)
+            .execute(mm.db())
+            .await?;
+            return Ok(None);
+        }
+        let concentration = pooled_concentration(&batches);
+        if let Some(concentration) = concentration {
+            ConjugateBmc::update(
+                ctx,
+                mm,
+                conjugate_id,
+                ConjugateForUpdate {
+                    concentration: Some(concentration),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        }
+
+        Ok(concentration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::conjugate::ConjugateForCreate;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn batch_for_create(conjugate_id: Option<i64>) -> ConjugationBatchForCreate {
+        ConjugationBatchForCreate {
+            group_id: 1000,
+            lot_id: 1007,
+            tag_id: 1005,
+            conjugate_id,
+            created_by: 1303,
+            labeled_by: Some(1303),
+            kit: Some("MaxPar X8".into()),
+            buffer: Some("W-buffer".into()),
+            antibody_amount_ug: 100.0,
+            measured_concentration: Some(400.0),
+            final_volume_ul: Some(200.0),
+            steps: json!([
+                {"name": "reduction", "started_at": "2024-01-01T10:00:00Z", "finished_at": "2024-01-01T10:30:00Z"},
+                {"name": "conjugation"}
+            ]),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn yield_and_pooled_concentration_are_computed() {
+        let now = chrono::Utc::now().naive_utc();
+        let batch = |measured: Option<f64>, volume: Option<f64>| ConjugationBatch {
+            id: 1,
+            group_id: 1,
+            lot_id: 1,
+            tag_id: 1,
+            conjugate_id: Some(1),
+            created_by: 1,
+            labeled_by: None,
+            kit: None,
+            buffer: None,
+            antibody_amount_ug: 100.0,
+            measured_concentration: measured,
+            final_volume_ul: volume,
+            steps: json!([]),
+            note: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let first = batch(Some(400.0), Some(200.0));
+        assert_eq!(first.recovered_amount_ug(), Some(80.0));
+        assert_eq!(first.yield_percent(), Some(80.0));
+
+        let pooled =
+            pooled_concentration(&[first, batch(Some(100.0), Some(200.0)), batch(None, None)]);
+        assert_eq!(pooled, Some(250.0));
+        assert_eq!(pooled_concentration(&[batch(None, Some(10.0))]), None);
+    }
+
+    #[tokio::test]
+    async fn test_conjugation_batch_create_updates_conjugate_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+
+        let id = ConjugationBatchBmc::create(&ctx, &mm, batch_for_create(Some(1008))).await?;
+
+        let batch = ConjugationBatchBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(batch.parsed_steps().len(), 2);
+        assert_eq!(batch.parsed_steps()[0].name, "reduction");
+        let conjugate = ConjugateBmc::get(&ctx, &mm, 1008).await?;
+        assert_eq!(conjugate.concentration, Some(400.0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conjugation_batch_update_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = ConjugationBatchBmc::create(&ctx, &mm, batch_for_create(None)).await?;
+
+        ConjugationBatchBmc::update(
+            &ctx,
+            &mm,
+            id,
+            ConjugationBatchForUpdate::from(json!({
+                "conjugateId": 1019,
+                "measuredConcentration": 250.0,
+                "kit": "Lightning-Link"
+            })),
+        )
+        .await?;
+
+        let batch = ConjugationBatchBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(batch.kit.as_deref(), Some("Lightning-Link"));
+        assert_eq!(batch.yield_percent(), Some(50.0));
+        let conjugate = ConjugateBmc::get(&ctx, &mm, 1019).await?;
+        assert_eq!(conjugate.concentration, Some(250.0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conjugation_batch_move_and_delete_resync_conjugates() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let measured = |conjugate_id, concentration| {
+            ConjugationBatchForCreate::from(json!({
+                "groupId": 1000,
+                "lotId": 1007,
+                "tagId": 1005,
+                "conjugateId": conjugate_id,
+                "createdBy": 1303,
+                "antibodyAmountUg": 100.0,
+                "measuredConcentration": concentration,
+                "finalVolumeUl": 100.0
+            }))
+        };
+        let conjugate = || {
+            ConjugateForCreate::from(json!({
+                "groupId": 1000,
+                "createdBy": 1303,
+                "lotId": 1007,
+                "tagId": 1005
+            }))
+        };
+        let (from, to) = (
+            ConjugateBmc::create(&ctx, &mm, conjugate()).await?,
+            ConjugateBmc::create(&ctx, &mm, conjugate()).await?,
+        );
+        ConjugationBatchBmc::create(&ctx, &mm, measured(from, 100.0)).await?;
+        let moved = ConjugationBatchBmc::create(&ctx, &mm, measured(from, 300.0)).await?;
+        assert_eq!(
+            ConjugateBmc::get(&ctx, &mm, from).await?.concentration,
+            Some(200.0)
+        );
+
+        ConjugationBatchBmc::update(
+            &ctx,
+            &mm,
+            moved,
+            ConjugationBatchForUpdate::from(json!({"conjugateId": to})),
+        )
+        .await?;
+        assert_eq!(
+            ConjugateBmc::get(&ctx, &mm, from).await?.concentration,
+            Some(100.0)
+        );
+        assert_eq!(
+            ConjugateBmc::get(&ctx, &mm, to).await?.concentration,
+            Some(300.0)
+        );
+        let extra = ConjugationBatchBmc::create(&ctx, &mm, measured(from, 400.0)).await?;
+        assert_eq!(
+            ConjugateBmc::get(&ctx, &mm, from).await?.concentration,
+            Some(250.0)
+        );
+
+        ConjugationBatchBmc::delete(&ctx, &mm, extra).await?;
+        assert_eq!(
+            ConjugateBmc::get(&ctx, &mm, from).await?.concentration,
+            Some(100.0)
+        );
+        assert!(matches!(
+            ConjugationBatchBmc::delete(&ctx, &mm, extra).await,
+            Err(Error::EntityNotFound { .. })
+        ));
+
+        ConjugationBatchBmc::delete(&ctx, &mm, moved).await?;
+        assert_eq!(ConjugateBmc::get(&ctx, &mm, to).await?.concentration, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conjugation_batch_update_err_not_found() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+
+        let result =
+            ConjugationBatchBmc::update(&ctx, &mm, 999_999, ConjugationBatchForUpdate::default())
+                .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::EntityNotFound {
+                entity: "conjugation_batch",
+                id: 999_999
+            })
+        ));
+
+        Ok(())
+    }
+}
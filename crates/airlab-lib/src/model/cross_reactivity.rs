@@ -0,0 +1,503 @@
+use crate::ctx::Ctx;
+use crate::model::clone_application::{Application, ApplicationStatus, CloneApplications};
+use crate::model::validation::ReviewState;
+use crate::model::{ModelManager, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgConnection};
+use std::collections::{HashMap, HashSet};
+
+/// What is known about one clone against one species.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactivityCell {
+    Untested,
+    /// Listed in `clone.reactivity` without a conclusive validation.
+    Claimed,
+    ValidatedPositive,
+    ValidatedNegative,
+}
+
+impl ReactivityCell {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Untested => "untested",
+            Self::Claimed => "claimed",
+            Self::ValidatedPositive => "validated_positive",
+            Self::ValidatedNegative => "validated_negative",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeciesColumn {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossReactivityRow {
+    pub clone_id: i64,
+    pub clone_name: String,
+    pub protein_id: i64,
+    pub protein_name: String,
+    /// One cell per entry of [`CrossReactivityMatrix::species`], in the same order.
+    pub cells: Vec<ReactivityCell>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossReactivityMatrix {
+    pub group_id: i64,
+    pub application: Option<Application>,
+    pub species: Vec<SpeciesColumn>,
+    pub rows: Vec<CrossReactivityRow>,
+}
+
+impl CrossReactivityMatrix {
+    pub fn to_csv(&self) -> String {
+        let mut header = vec![
+            "protein".to_string(),
+            "clone".to_string(),
+            "clone_id".to_string(),
+        ];
+        header.extend(self.species.iter().map(|species| species.name.clone()));
+
+        let mut lines = vec![csv_line(&header)];
+        for row in &self.rows {
+            let mut fields = vec![
+                row.protein_name.clone(),
+                row.clone_name.clone(),
+                row.clone_id.to_string(),
+            ];
+            fields.extend(row.cells.iter().map(|cell| cell.label().to_string()));
+            lines.push(csv_line(&fields));
+        }
+
+        let mut csv = lines.join("\r\n");
+        csv.push_str("\r\n");
+        csv
+    }
+}
+
+/// Joins `fields` into one CSV line. Fields a spreadsheet would read as a formula get a
+/// leading `'`, so names like `=HYPERLINK(..)` stay text.
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+                format!("'{field}")
+            } else {
+                field.clone()
+            };
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A clone as read for the report.
+#[derive(Debug, Clone)]
+pub struct ReactivityClone {
+    pub clone_id: i64,
+    pub clone_name: String,
+    pub protein_id: i64,
+    pub protein_name: String,
+    pub reactivity: Vec<i64>,
+    pub applications: CloneApplications,
+}
+
+/// An approved validation verdict as read for the report.
+#[derive(Debug, Clone, Copy)]
+pub struct ReactivityValidation {
+    pub clone_id: i64,
+    pub species_id: i64,
+    pub application: i64,
+    pub status: i64,
+}
+
+/// Builds the matrix. Validations override claims: any positive (Yes or So-So) validation
+/// wins over negative ones; `Undefined` validations are ignored. With an `application`, only
+/// its validations count and only clones claimed or validated for it are listed.
+pub fn build_matrix(
+    group_id: i64,
+    application: Option<Application>,
+    species: Vec<SpeciesColumn>,
+    clones: Vec<ReactivityClone>,
+    validations: &[ReactivityValidation],
+) -> CrossReactivityMatrix {
+    let mut verdicts: HashMap<(i64, i64), ReactivityCell> = HashMap::new();
+    let mut validated_clones: HashSet<i64> = HashSet::new();
+    for validation in validations {
+        if application.is_some_and(|application| application.id() != validation.application) {
+            continue;
+        }
+        let cell = match ApplicationStatus::from_id(validation.status) {
+            Some(status) if status.is_usable() => ReactivityCell::ValidatedPositive,
+            Some(ApplicationStatus::No) => ReactivityCell::ValidatedNegative,
+            _ => continue,
+        };
+        validated_clones.insert(validation.clone_id);
+        verdicts
+            .entry((validation.clone_id, validation.species_id))
+            .and_modify(|current| {
+                if cell == ReactivityCell::ValidatedPositive {
+                    *current = cell;
+                }
+            })
+            .or_insert(cell);
+    }
+
+    let rows = clones
+        .into_iter()
+        .filter(|clone| match application {
+            Some(application) => {
+                validated_clones.contains(&clone.clone_id)
+                    || clone
+                        .applications
+                        .get(application)
+                        .is_some_and(|entry| entry.status.is_usable())
+            }
+            None => true,
+        })
+        .map(|clone| {
+            let cells = species
+                .iter()
+                .map(|species| {
+                    verdicts
+                        .get(&(clone.clone_id, species.id))
+                        .copied()
+                        .unwrap_or_else(|| {
+                            if clone.reactivity.contains(&species.id) {
+                                ReactivityCell::Claimed
+                            } else {
+                                ReactivityCell::Untested
+                            }
+                        })
+                })
+                .collect();
+            CrossReactivityRow {
+                clone_id: clone.clone_id,
+                clone_name: clone.clone_name,
+                protein_id: clone.protein_id,
+                protein_name: clone.protein_name,
+                cells,
+            }
+        })
+        .collect();
+
+    CrossReactivityMatrix {
+        group_id,
+        application,
+        species,
+        rows,
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct ReactivityCloneRow {
+    clone_id: i64,
+    clone_name: String,
+    protein_id: i64,
+    protein_name: Option<String>,
+    reactivity: Option<Vec<i64>>,
+    application: Option<Value>,
+}
+
+pub struct CrossReactivityBmc;
+
+impl CrossReactivityBmc {
+    /// Proteins/clones × species of a group, skipping archived clones and validations and
+    /// counting approved validations only.
+    pub async fn matrix(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        group_id: i64,
+        application: Option<Application>,
+    ) -> Result<CrossReactivityMatrix> {
+        let _ = ctx;
+        let mut conn = mm.db().acquire().await?;
+        Self::matrix_in(&mut conn, group_id, application).await
+    }
+
+    async fn matrix_in(
+        conn: &mut PgConnection,
+        group_id: i64,
+        application: Option<Application>,
+    ) -> Result<CrossReactivityMatrix> {
+        let species = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, name FROM species WHERE group_id = $1 ORDER BY name, id",
+        )
+        .bind(group_id)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|(id, name)| SpeciesColumn { id, name })
+        .collect();
+
+        let clones = sqlx::query_as::<_, ReactivityCloneRow>(
+            r#"
+            SELECT
+                c.id AS clone_id,
+                c.name AS clone_name,
+                c.protein_id AS protein_id,
+                p.name AS protein_name,
+                c.reactivity AS reactivity,
+                c.application AS application
+            FROM clone c
+            LEFT JOIN protein p ON p.id = c.protein_id
+            WHERE c.group_id = $1 AND NOT c.is_archived
+            ORDER BY p.name, c.name, c.id
+            "#,
+        )
+        .bind(group_id)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| ReactivityClone {
+            clone_id: row.clone_id,
+            clone_name: row.clone_name,
+            protein_id: row.protein_id,
+            protein_name: row.protein_name.unwrap_or_default(),
+            reactivity: row.reactivity.unwrap_or_default(),
+            applications: row
+                .application
+                .as_ref()
+                .map(CloneApplications::from_json)
+                .unwrap_or_default(),
+        })
+        .collect();
+
+        let validations = sqlx::query_as::<_, (i64, i64, i64, i64)>(
+            r#"
+            SELECT clone_id, species_id, application, status
+            FROM validation
+            WHERE group_id = $1 AND species_id IS NOT NULL AND NOT is_archived
+                AND review_state = $2
+            "#,
+        )
+        .bind(group_id)
+        .bind(ReviewState::Approved.as_str())
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(
+            |(clone_id, species_id, application, status)| ReactivityValidation {
+                clone_id,
+                species_id,
+                application,
+                status,
+            },
+        )
+        .collect::<Vec<_>>();
+
+        Ok(build_matrix(
+            group_id,
+            application,
+            species,
+            clones,
+            &validations,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::validation::{ValidationBmc, ValidationForCreate};
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn clone(clone_id: i64, reactivity: Vec<i64>, application: Value) -> ReactivityClone {
+        ReactivityClone {
+            clone_id,
+            clone_name: format!("clone-{clone_id}"),
+            protein_id: 1,
+            protein_name: "CD3".into(),
+            reactivity,
+            applications: CloneApplications::from_json(&application),
+        }
+    }
+
+    fn species() -> Vec<SpeciesColumn> {
+        vec![
+            SpeciesColumn {
+                id: 1,
+                name: "Human".into(),
+            },
+            SpeciesColumn {
+                id: 2,
+                name: "Mouse".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn build_matrix_prefers_validations_over_claims() {
+        let validations = [
+            ReactivityValidation {
+                clone_id: 10,
+                species_id: 1,
+                application: 1,
+                status: 2,
+            },
+            ReactivityValidation {
+                clone_id: 10,
+                species_id: 2,
+                application: 1,
+                status: 2,
+            },
+            ReactivityValidation {
+                clone_id: 10,
+                species_id: 2,
+                application: 2,
+                status: 0,
+            },
+        ];
+        let matrix = build_matrix(
+            1,
+            None,
+            species(),
+            vec![clone(10, vec![1], json!({})), clone(11, vec![2], json!({}))],
+            &validations,
+        );
+
+        assert_eq!(
+            matrix.rows[0].cells,
+            vec![
+                ReactivityCell::ValidatedNegative,
+                ReactivityCell::ValidatedPositive
+            ]
+        );
+        assert_eq!(
+            matrix.rows[1].cells,
+            vec![ReactivityCell::Untested, ReactivityCell::Claimed]
+        );
+    }
+
+    #[test]
+    fn build_matrix_restricts_to_application() {
+        let validations = [ReactivityValidation {
+            clone_id: 10,
+            species_id: 2,
+            application: 2,
+            status: 0,
+        }];
+        let matrix = build_matrix(
+            1,
+            Some(Application::Imc),
+            species(),
+            vec![
+                clone(10, vec![], json!({})),
+                clone(11, vec![1], json!({"1": true})),
+            ],
+            &validations,
+        );
+
+        assert_eq!(matrix.rows.len(), 1);
+        assert_eq!(matrix.rows[0].clone_id, 11);
+        assert_eq!(
+            matrix.rows[0].cells,
+            vec![ReactivityCell::Claimed, ReactivityCell::Untested]
+        );
+    }
+
+    #[test]
+    fn to_csv_quotes_fields() {
+        let mut matrix = build_matrix(1, None, species(), vec![clone(10, vec![1], json!({}))], &[]);
+        matrix.rows[0].clone_name = "OKT3, \"hi\"".into();
+        matrix.rows[0].protein_name = "=HYPERLINK(\"x\")".into();
+
+        assert_eq!(
+            matrix.to_csv(),
+            "protein,clone,clone_id,Human,Mouse\r\n\"'=HYPERLINK(\"\"x\"\")\",\"OKT3, \"\"hi\"\"\",10,claimed,untested\r\n"
+        );
+        assert_eq!(
+            csv_line(&["+1".into(), "-CD3".into(), "@x".into(), "CD-3".into()]),
+            "'+1,'-CD3,'@x,CD-3"
+        );
+        assert_eq!(csv_line(&["\tx".into(), "\rx".into()]), "'\tx,\"'\rx\"");
+    }
+
+    #[tokio::test]
+    async fn matrix_reads_seeded_group() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let validation = |status| {
+            ValidationForCreate::from(json!({
+                "groupId": 1000,
+                "createdBy": 1303,
+                "cloneId": 1016,
+                "speciesId": 1014,
+                "application": 1,
+                "status": status
+            }))
+        };
+        let approved = ValidationBmc::create(&ctx, &mm, validation(2)).await?;
+        ValidationBmc::create(&ctx, &mm, validation(0)).await?;
+        sqlx::query("UPDATE validation SET review_state = 'approved' WHERE id = $1")
+            .bind(approved)
+            .execute(mm.db())
+            .await?;
+
+        let matrix = CrossReactivityBmc::matrix(&ctx, &mm, 1000, None).await?;
+
+        let species_ids = matrix
+            .species
+            .iter()
+            .map(|species| species.id)
+            .collect::<Vec<_>>();
+        assert_eq!(species_ids, vec![1004, 1014]);
+        let seed = matrix
+            .rows
+            .iter()
+            .find(|row| row.clone_id == 1016)
+            .ok_or("seed clone should be listed")?;
+        // The Yes above is a draft and does not outweigh the approved No.
+        assert_eq!(
+            seed.cells,
+            vec![ReactivityCell::Untested, ReactivityCell::ValidatedNegative]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn matrix_counts_validations_from_before_reviews() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let mut tx = mm.db().begin().await?;
+        _dev_utils::replay_validation_review_migration(
+            &mut tx,
+            "INSERT INTO public.validation \
+             (group_id, created_by, clone_id, species_id, application, status) \
+             VALUES (1000, 1303, 1016, 1014, 6, 2) RETURNING id",
+        )
+        .await?;
+
+        let matrix = CrossReactivityBmc::matrix_in(&mut tx, 1000, Some(Application::Wb)).await?;
+        tx.rollback().await?;
+
+        let seed = matrix
+            .rows
+            .iter()
+            .find(|row| row.clone_id == 1016)
+            .ok_or("seed clone should be listed")?;
+        let column = matrix
+            .species
+            .iter()
+            .position(|species| species.id == 1014)
+            .ok_or("species 1014 should be a column")?;
+        assert_eq!(seed.cells[column], ReactivityCell::ValidatedNegative);
+
+        Ok(())
+    }
+}
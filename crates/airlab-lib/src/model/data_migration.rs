@@ -0,0 +1,69 @@
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::model::Result;
+use serde::Serialize;
+use sqlx::{PgConnection, Postgres, Transaction};
+
+/// A one-time data migration that is running. Its `data_migration` row is written in the
+/// still open transaction, so other instances starting at the same time wait for it and
+/// then skip the migration; dropping the run rolls its changes back.
+#[derive(Debug)]
+pub struct DataMigrationRun {
+    name: &'static str,
+    tx: Transaction<'static, Postgres>,
+}
+
+impl DataMigrationRun {
+    /// Connection of the migration transaction.
+    pub fn conn(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    /// Stores `report` with the migration and commits its changes.
+    pub async fn finish(mut self, report: &impl Serialize) -> Result<()> {
+        sqlx::query("UPDATE data_migration SET report = $1 WHERE name = $2")
+            .bind(serde_json::to_value(report)?)
+            .bind(self.name)
+            .execute(&mut *self.tx)
+            .await?;
+        self.tx.commit().await?;
+        Ok(())
+    }
+}
+
+pub struct DataMigrationBmc;
+
+impl DataMigrationBmc {
+    /// Starts the migration `name`, or returns `None` when it already ran.
+    pub async fn begin(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        name: &'static str,
+    ) -> Result<Option<DataMigrationRun>> {
+        let _ = ctx;
+        let mut tx = mm.db().begin().await?;
+        let inserted =
+            sqlx::query("INSERT INTO data_migration (name) VALUES ($1) ON CONFLICT DO NOTHING")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        Ok((inserted == 1).then_some(DataMigrationRun { name, tx }))
+    }
+
+    /// Report stored by the migration `name`, if it ran.
+    pub async fn report(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        name: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        let _ = ctx;
+        let report = sqlx::query_scalar::<_, Option<serde_json::Value>>(
+            "SELECT report FROM data_migration WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(mm.db())
+        .await?;
+        Ok(report.flatten())
+    }
+}
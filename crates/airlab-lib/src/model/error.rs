@@ -20,6 +20,28 @@ pub enum Error {
 
     CountFail,
 
+    /// The validation is submitted or approved and can no longer be edited.
+    ValidationLocked {
+        id: i64,
+    },
+    ReviewTransitionNotAllowed {
+        id: i64,
+        from: &'static str,
+        action: &'static str,
+    },
+    ReviewNotPermitted {
+        id: i64,
+        reason: &'static str,
+    },
+    ReviewCommentRequired {
+        id: i64,
+    },
+    /// A titration point, or the list of them, is not a tested concentration.
+    InvalidTitrationPoints {
+        index: Option<usize>,
+        reason: &'static str,
+    },
+
     CantCreateModelManagerProvider(String),
 
     #[from]
@@ -1,9 +1,15 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::module_inception)]
 pub mod base;
+pub mod blob;
 pub mod clone;
+pub mod clone_application;
+pub mod clone_dossier;
 pub mod collection;
 pub mod conjugate;
+pub mod conjugation_batch;
+pub mod cross_reactivity;
+pub mod data_migration;
 mod error;
 pub mod group;
 pub mod helpers;
@@ -12,13 +18,17 @@ pub mod member;
 pub mod panel;
 pub mod panel_element;
 pub mod protein;
+pub mod protocol_template;
 pub mod provider;
+pub mod saved_search;
 pub mod species;
 pub mod storage;
 mod store;
 pub mod tag;
+pub mod titration;
 pub mod user;
 pub mod validation;
+pub mod validation_consensus;
 pub mod validation_file;
 
 use sqlx::{Pool, Postgres};
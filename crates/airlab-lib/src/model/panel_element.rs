@@ -3,11 +3,12 @@ use crate::model::ModelManager;
 use crate::model::Result;
 use crate::model::base::{self, DbBmc};
 use crate::model::helpers::{i64_or, opt_f32};
+use crate::units::{MigrationReport, Quantity, UnparseableValue};
 use modql::field::Fields;
 use modql::filter::{FilterNodes, ListOptions, OpValsInt64};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::FromRow;
+use sqlx::{FromRow, PgConnection};
 
 #[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize, Default)]
 pub struct PanelElement {
@@ -22,6 +23,12 @@ pub struct PanelElement {
     pub concentration: Option<f32>,
 }
 
+impl PanelElement {
+    pub fn concentration_quantity(&self) -> Option<Quantity> {
+        Quantity::from_dilution_type(self.dilution_type, f64::from(self.concentration?))
+    }
+}
+
 #[derive(Fields, Deserialize, Clone, Debug)]
 pub struct PanelElementForCreate {
     #[serde(rename = "panelId")]
@@ -133,6 +140,38 @@ impl PanelElementBmc {
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
         base::delete::<Self>(ctx, mm, id).await
     }
+
+    /// Records concentrations whose `dilution_type` maps to no known unit.
+    pub async fn report_untyped_concentrations(
+        ctx: &Ctx,
+        conn: &mut PgConnection,
+        report: &mut MigrationReport,
+    ) -> Result<()> {
+        let _ = ctx;
+        let rows = sqlx::query_as::<_, (i64, i64, f32)>(
+            r#"
+            SELECT id, dilution_type, concentration
+            FROM panel_element
+            WHERE concentration IS NOT NULL AND dilution_type NOT IN ($1, $2)
+            ORDER BY id
+            "#,
+        )
+        .bind(crate::units::DILUTION_TYPE_UG_PER_ML)
+        .bind(crate::units::DILUTION_TYPE_DILUTION)
+        .fetch_all(conn)
+        .await?;
+
+        for (id, dilution_type, concentration) in rows {
+            report.unparseable.push(UnparseableValue {
+                entity: Self::TABLE,
+                id,
+                value: concentration.to_string(),
+                error: format!("unknown dilution type {dilution_type}"),
+            });
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,502 @@
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::helpers::{i64_or, opt_bool, opt_i64, opt_string, opt_string_any};
+use crate::model::validation::ValidationForCreate;
+use crate::model::{Error, ModelManager, Result};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+/// Group-level protocol for one application, used to prefill validation records.
+#[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize)]
+pub struct ProtocolTemplate {
+    pub id: i64,
+    pub group_id: i64,
+    pub created_by: i64,
+    pub name: String,
+    pub application: i64,
+    pub description: Option<String>,
+    pub incubation_conditions: Option<String>,
+    pub fixation: Option<i64>,
+    pub fixation_notes: Option<String>,
+    pub antigen_retrieval_type: Option<String>,
+    pub antigen_retrieval_time: Option<String>,
+    pub antigen_retrieval_temperature: Option<String>,
+    pub saponin: Option<bool>,
+    pub saponin_concentration: Option<String>,
+    pub methanol_treatment: Option<bool>,
+    pub methanol_treatment_concentration: Option<String>,
+    pub surface_staining: Option<bool>,
+    pub surface_staining_concentration: Option<String>,
+    pub is_archived: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl ProtocolTemplate {
+    /// Copies the protocol fields into `validation_c`; values already set on the
+    /// validation win, and the template's application is used when none is given.
+    pub fn prefill(&self, validation_c: &mut ValidationForCreate) {
+        fn fill<T: Clone>(target: &mut Option<T>, value: &Option<T>) {
+            if target.is_none() {
+                target.clone_from(value);
+            }
+        }
+
+        validation_c.protocol_template_id = Some(self.id);
+        validation_c.application.get_or_insert(self.application);
+        fill(
+            &mut validation_c.incubation_conditions,
+            &self.incubation_conditions,
+        );
+        fill(&mut validation_c.fixation, &self.fixation);
+        fill(&mut validation_c.fixation_notes, &self.fixation_notes);
+        fill(
+            &mut validation_c.antigen_retrieval_type,
+            &self.antigen_retrieval_type,
+        );
+        fill(
+            &mut validation_c.antigen_retrieval_time,
+            &self.antigen_retrieval_time,
+        );
+        fill(
+            &mut validation_c.antigen_retrieval_temperature,
+            &self.antigen_retrieval_temperature,
+        );
+        fill(&mut validation_c.saponin, &self.saponin);
+        fill(
+            &mut validation_c.saponin_concentration,
+            &self.saponin_concentration,
+        );
+        fill(
+            &mut validation_c.methanol_treatment,
+            &self.methanol_treatment,
+        );
+        fill(
+            &mut validation_c.methanol_treatment_concentration,
+            &self.methanol_treatment_concentration,
+        );
+        fill(&mut validation_c.surface_staining, &self.surface_staining);
+        fill(
+            &mut validation_c.surface_staining_concentration,
+            &self.surface_staining_concentration,
+        );
+    }
+}
+
+#[derive(Fields, Deserialize, Clone, Debug)]
+pub struct ProtocolTemplateForCreate {
+    pub group_id: i64,
+    pub created_by: i64,
+    pub name: String,
+    pub application: i64,
+    pub description: Option<String>,
+    pub incubation_conditions: Option<String>,
+    pub fixation: Option<i64>,
+    pub fixation_notes: Option<String>,
+    pub antigen_retrieval_type: Option<String>,
+    pub antigen_retrieval_time: Option<String>,
+    pub antigen_retrieval_temperature: Option<String>,
+    pub saponin: Option<bool>,
+    pub saponin_concentration: Option<String>,
+    pub methanol_treatment: Option<bool>,
+    pub methanol_treatment_concentration: Option<String>,
+    pub surface_staining: Option<bool>,
+    pub surface_staining_concentration: Option<String>,
+}
+
+impl From<Value> for ProtocolTemplateForCreate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+        let fields = ProtocolTemplateForUpdate::from(obj.clone());
+
+        ProtocolTemplateForCreate {
+            group_id: opt_i64(&obj, "groupId").unwrap_or(i64_or(&obj, "group_id", 0)),
+            created_by: opt_i64(&obj, "createdBy").unwrap_or(i64_or(&obj, "created_by", 0)),
+            name: fields.name.unwrap_or_default(),
+            application: fields.application.unwrap_or_default(),
+            description: fields.description,
+            incubation_conditions: fields.incubation_conditions,
+            fixation: fields.fixation,
+            fixation_notes: fields.fixation_notes,
+            antigen_retrieval_type: fields.antigen_retrieval_type,
+            antigen_retrieval_time: fields.antigen_retrieval_time,
+            antigen_retrieval_temperature: fields.antigen_retrieval_temperature,
+            saponin: fields.saponin,
+            saponin_concentration: fields.saponin_concentration,
+            methanol_treatment: fields.methanol_treatment,
+            methanol_treatment_concentration: fields.methanol_treatment_concentration,
+            surface_staining: fields.surface_staining,
+            surface_staining_concentration: fields.surface_staining_concentration,
+        }
+    }
+}
+
+#[derive(Fields, Default, Deserialize, Debug)]
+pub struct ProtocolTemplateForUpdate {
+    pub name: Option<String>,
+    pub application: Option<i64>,
+    pub description: Option<String>,
+    pub incubation_conditions: Option<String>,
+    pub fixation: Option<i64>,
+    pub fixation_notes: Option<String>,
+    pub antigen_retrieval_type: Option<String>,
+    pub antigen_retrieval_time: Option<String>,
+    pub antigen_retrieval_temperature: Option<String>,
+    pub saponin: Option<bool>,
+    pub saponin_concentration: Option<String>,
+    pub methanol_treatment: Option<bool>,
+    pub methanol_treatment_concentration: Option<String>,
+    pub surface_staining: Option<bool>,
+    pub surface_staining_concentration: Option<String>,
+    pub is_archived: Option<bool>,
+}
+
+impl From<Value> for ProtocolTemplateForUpdate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+        let bool_any = |camel: &str, snake: &str| opt_bool(&obj, camel).or(opt_bool(&obj, snake));
+
+        ProtocolTemplateForUpdate {
+            name: opt_string(&obj, "name"),
+            application: opt_i64(&obj, "application"),
+            description: opt_string(&obj, "description"),
+            incubation_conditions: opt_string_any(
+                &obj,
+                &["incubationConditions", "incubation_conditions"],
+            ),
+            fixation: opt_i64(&obj, "fixation"),
+            fixation_notes: opt_string_any(&obj, &["fixationNotes", "fixation_notes"]),
+            antigen_retrieval_type: opt_string_any(
+                &obj,
+                &["antigenRetrievalType", "antigen_retrieval_type"],
+            ),
+            antigen_retrieval_time: opt_string_any(
+                &obj,
+                &["antigenRetrievalTime", "antigen_retrieval_time"],
+            ),
+            antigen_retrieval_temperature: opt_string_any(
+                &obj,
+                &[
+                    "antigenRetrievalTemperature",
+                    "antigen_retrieval_temperature",
+                ],
+            ),
+            saponin: opt_bool(&obj, "saponin"),
+            saponin_concentration: opt_string_any(
+                &obj,
+                &["saponinConcentration", "saponin_concentration"],
+            ),
+            methanol_treatment: bool_any("methanolTreatment", "methanol_treatment"),
+            methanol_treatment_concentration: opt_string_any(
+                &obj,
+                &[
+                    "methanolTreatmentConcentration",
+                    "methanol_treatment_concentration",
+                ],
+            ),
+            surface_staining: bool_any("surfaceStaining", "surface_staining"),
+            surface_staining_concentration: opt_string_any(
+                &obj,
+                &[
+                    "surfaceStainingConcentration",
+                    "surface_staining_concentration",
+                ],
+            ),
+            is_archived: bool_any("isArchived", "is_archived"),
+        }
+    }
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug, Clone)]
+pub struct ProtocolTemplateFilter {
+    id: Option<OpValsInt64>,
+    group_id: Option<OpValsInt64>,
+    application: Option<OpValsInt64>,
+    name: Option<OpValsString>,
+    is_archived: Option<OpValsBool>,
+}
+
+pub struct ProtocolTemplateBmc;
+
+impl DbBmc for ProtocolTemplateBmc {
+    const TABLE: &'static str = "protocol_template";
+
+    fn has_timestamps() -> bool {
+        false
+    }
+}
+
+impl ProtocolTemplateBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        template_c: ProtocolTemplateForCreate,
+    ) -> Result<i64> {
+        let _ = ctx;
+        let id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO protocol_template (group_id, created_by, name, application, description, incubation_conditions, fixation, fixation_notes, antigen_retrieval_type, antigen_retrieval_time, antigen_retrieval_temperature, saponin, saponin_concentration, methanol_treatment, methanol_treatment_concentration, surface_staining, surface_staining_concentration, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, NOW(), NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(template_c.group_id)
+        .bind(template_c.created_by)
+        .bind(template_c.name)
+        .bind(template_c.application)
+        .bind(template_c.description)
+        .bind(template_c.incubation_conditions)
+        .bind(template_c.fixation)
+        .bind(template_c.fixation_notes)
+        .bind(template_c.antigen_retrieval_type)
+        .bind(template_c.antigen_retrieval_time)
+        .bind(template_c.antigen_retrieval_temperature)
+        .bind(template_c.saponin)
+        .bind(template_c.saponin_concentration)
+        .bind(template_c.methanol_treatment)
+        .bind(template_c.methanol_treatment_concentration)
+        .bind(template_c.surface_staining)
+        .bind(template_c.surface_staining_concentration)
+        .fetch_one(mm.db())
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<ProtocolTemplate> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<ProtocolTemplateFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<ProtocolTemplate>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn count(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<ProtocolTemplateFilter>>,
+    ) -> Result<i64> {
+        base::count::<Self, _>(ctx, mm, filters).await
+    }
+
+    /// Changes the template only; validations created from it keep their copied values.
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        template_u: ProtocolTemplateForUpdate,
+    ) -> Result<()> {
+        let _ = ctx;
+        let count = sqlx::query(
+            r#"
+            UPDATE protocol_template
+            SET
+                name = COALESCE($1, name),
+                application = COALESCE($2, application),
+                description = COALESCE($3, description),
+                incubation_conditions = COALESCE($4, incubation_conditions),
+                fixation = COALESCE($5, fixation),
+                fixation_notes = COALESCE($6, fixation_notes),
+                antigen_retrieval_type = COALESCE($7, antigen_retrieval_type),
+                antigen_retrieval_time = COALESCE($8, antigen_retrieval_time),
+                antigen_retrieval_temperature = COALESCE($9, antigen_retrieval_temperature),
+                saponin = COALESCE($10, saponin),
+                saponin_concentration = COALESCE($11, saponin_concentration),
+                methanol_treatment = COALESCE($12, methanol_treatment),
+                methanol_treatment_concentration = COALESCE($13, methanol_treatment_concentration),
+                surface_staining = COALESCE($14, surface_staining),
+                surface_staining_concentration = COALESCE($15, surface_staining_concentration),
+                is_archived = COALESCE($16, is_archived),
+                updated_at = NOW()
+            WHERE id = $17
+            "#,
+        )
+        .bind(template_u.name)
+        .bind(template_u.application)
+        .bind(template_u.description)
+        .bind(template_u.incubation_conditions)
+        .bind(template_u.fixation)
+        .bind(template_u.fixation_notes)
+        .bind(template_u.antigen_retrieval_type)
+        .bind(template_u.antigen_retrieval_time)
+        .bind(template_u.antigen_retrieval_temperature)
+        .bind(template_u.saponin)
+        .bind(template_u.saponin_concentration)
+        .bind(template_u.methanol_treatment)
+        .bind(template_u.methanol_treatment_concentration)
+        .bind(template_u.surface_staining)
+        .bind(template_u.surface_staining_concentration)
+        .bind(template_u.is_archived)
+        .bind(id)
+        .execute(mm.db())
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::delete::<Self>(ctx, mm, id).await
+    }
+
+    /// Prefills `validation_c` from its `protocol_template_id`, if set. The template has
+    /// to belong to the validation's group.
+    pub async fn prefill_validation(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        validation_c: &mut ValidationForCreate,
+    ) -> Result<()> {
+        let Some(id) = validation_c.protocol_template_id else {
+            return Ok(());
+        };
+        let template = Self::get(ctx, mm, id).await?;
+        if template.group_id != validation_c.group_id {
+            return Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            });
+        }
+        template.prefill(validation_c);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::validation::ValidationBmc;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn ihc_template() -> ProtocolTemplateForCreate {
+        ProtocolTemplateForCreate::from(json!({
+            "groupId": 1000,
+            "createdBy": 1303,
+            "name": "IHC citrate",
+            "application": 4,
+            "antigenRetrievalType": "citrate pH 6",
+            "antigenRetrievalTime": "20 min",
+            "antigenRetrievalTemperature": "95 °C",
+            "fixation": 1,
+            "saponin": false
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_validation_create_prefills_from_template() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let template_id = ProtocolTemplateBmc::create(&ctx, &mm, ihc_template()).await?;
+
+        let id = ValidationBmc::create(
+            &ctx,
+            &mm,
+            ValidationForCreate::from(json!({
+                "groupId": 1000,
+                "createdBy": 1303,
+                "cloneId": 1006,
+                "protocolTemplateId": template_id,
+                "antigenRetrievalTime": "30 min"
+            })),
+        )
+        .await?;
+
+        let validation = ValidationBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(validation.protocol_template_id, Some(template_id));
+        assert_eq!(validation.application, 4);
+        assert_eq!(
+            validation.antigen_retrieval_type.as_deref(),
+            Some("citrate pH 6")
+        );
+        assert_eq!(validation.antigen_retrieval_time.as_deref(), Some("30 min"));
+        assert_eq!(validation.fixation, Some(1));
+        assert_eq!(validation.saponin, Some(false));
+
+        let filters = serde_json::from_value(json!([
+            {"protocol_template_id": {"$eq": template_id}}
+        ]))?;
+        let validations = ValidationBmc::list(&ctx, &mm, Some(filters), None).await?;
+        assert_eq!(
+            validations.iter().map(|v| v.id).collect::<Vec<_>>(),
+            vec![id]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation_create_rejects_foreign_template() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let template_id = ProtocolTemplateBmc::create(&ctx, &mm, ihc_template()).await?;
+
+        let res = ValidationBmc::create(
+            &ctx,
+            &mm,
+            ValidationForCreate::from(json!({
+                "groupId": 1,
+                "createdBy": 261,
+                "cloneId": 3124,
+                "protocolTemplateId": template_id
+            })),
+        )
+        .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::EntityNotFound {
+                entity: "protocol_template",
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_template_update_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = ProtocolTemplateBmc::create(&ctx, &mm, ihc_template()).await?;
+
+        ProtocolTemplateBmc::update(
+            &ctx,
+            &mm,
+            id,
+            ProtocolTemplateForUpdate::from(json!({"antigenRetrievalTime": "15 min"})),
+        )
+        .await?;
+
+        let template = ProtocolTemplateBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(template.antigen_retrieval_time.as_deref(), Some("15 min"));
+        assert_eq!(
+            template.antigen_retrieval_type.as_deref(),
+            Some("citrate pH 6")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::helpers::{i64_or, opt_bool, opt_i64, opt_string, opt_value};
+use crate::model::{Error, ModelManager, Result};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use std::collections::HashSet;
+
+/// A named search request of a member, private unless shared with the member's group.
+#[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub group_id: i64,
+    pub created_by: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// The search request as posted to the search API.
+    pub request: Value,
+    pub is_shared: bool,
+    /// Whether runs report the items that started matching since the previous run.
+    pub notify: bool,
+    /// Ids of all items the search matched when it last ran, sorted.
+    pub matched_ids: Vec<i64>,
+    pub last_run_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl SavedSearch {
+    /// The ids of `ids` the search did not match when it last ran, in order. Nothing is
+    /// new on the first run.
+    pub fn new_matches(&self, ids: &[i64]) -> Vec<i64> {
+        if self.last_run_at.is_none() {
+            return vec![];
+        }
+        let previous = self.matched_ids.iter().collect::<HashSet<_>>();
+        ids.iter()
+            .filter(|id| !previous.contains(id))
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Fields, Deserialize, Clone, Debug)]
+pub struct SavedSearchForCreate {
+    pub group_id: i64,
+    pub created_by: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub request: Value,
+    pub is_shared: bool,
+    pub notify: bool,
+}
+
+impl From<Value> for SavedSearchForCreate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+        let fields = SavedSearchForUpdate::from(obj.clone());
+
+        SavedSearchForCreate {
+            group_id: opt_i64(&obj, "groupId").unwrap_or(i64_or(&obj, "group_id", 0)),
+            created_by: opt_i64(&obj, "createdBy").unwrap_or(i64_or(&obj, "created_by", 0)),
+            name: fields.name.unwrap_or_default(),
+            description: fields.description,
+            request: fields
+                .request
+                .unwrap_or_else(|| Value::Object(Default::default())),
+            is_shared: fields.is_shared.unwrap_or_default(),
+            notify: fields.notify.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Fields, Default, Deserialize, Debug)]
+pub struct SavedSearchForUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub request: Option<Value>,
+    pub is_shared: Option<bool>,
+    pub notify: Option<bool>,
+}
+
+impl From<Value> for SavedSearchForUpdate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+
+        SavedSearchForUpdate {
+            name: opt_string(&obj, "name"),
+            description: opt_string(&obj, "description"),
+            request: opt_value(&obj, "request"),
+            is_shared: opt_bool(&obj, "isShared").or_else(|| opt_bool(&obj, "is_shared")),
+            notify: opt_bool(&obj, "notify"),
+        }
+    }
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug, Clone)]
+pub struct SavedSearchFilter {
+    id: Option<OpValsInt64>,
+    group_id: Option<OpValsInt64>,
+    created_by: Option<OpValsInt64>,
+    name: Option<OpValsString>,
+    is_shared: Option<OpValsBool>,
+    notify: Option<OpValsBool>,
+}
+
+pub struct SavedSearchBmc;
+
+impl DbBmc for SavedSearchBmc {
+    const TABLE: &'static str = "saved_search";
+
+    fn has_timestamps() -> bool {
+        false
+    }
+}
+
+impl SavedSearchBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        search_c: SavedSearchForCreate,
+    ) -> Result<i64> {
+        let _ = ctx;
+        let id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO saved_search (group_id, created_by, name, description, request, is_shared, notify, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(search_c.group_id)
+        .bind(search_c.created_by)
+        .bind(search_c.name)
+        .bind(search_c.description)
+        .bind(search_c.request)
+        .bind(search_c.is_shared)
+        .bind(search_c.notify)
+        .fetch_one(mm.db())
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<SavedSearch> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<SavedSearchFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<SavedSearch>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn count(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<SavedSearchFilter>>,
+    ) -> Result<i64> {
+        base::count::<Self, _>(ctx, mm, filters).await
+    }
+
+    /// The searches `user_id` saved and those shared with the groups the user is an active
+    /// member of, by name.
+    pub async fn list_visible(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        group_id: Option<i64>,
+    ) -> Result<Vec<SavedSearch>> {
+        let _ = ctx;
+        let searches = sqlx::query_as::<_, SavedSearch>(
+            r#"
+            SELECT s.*
+            FROM saved_search s
+            JOIN member m ON m.group_id = s.group_id AND m.user_id = $1 AND m.is_active
+            WHERE (s.created_by = m.id OR s.is_shared)
+              AND ($2::BIGINT IS NULL OR s.group_id = $2)
+            ORDER BY s.name, s.id
+            "#,
+        )
+        .bind(user_id)
+        .bind(group_id)
+        .fetch_all(mm.db())
+        .await?;
+
+        Ok(searches)
+    }
+
+    /// Like [`Self::get`], but searches `user_id` may not see are not found either.
+    pub async fn get_visible(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        user_id: i64,
+    ) -> Result<SavedSearch> {
+        let search = Self::get(ctx, mm, id).await?;
+        Self::list_visible(ctx, mm, user_id, Some(search.group_id))
+            .await?
+            .into_iter()
+            .find(|search| search.id == id)
+            .ok_or(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        search_u: SavedSearchForUpdate,
+    ) -> Result<()> {
+        let _ = ctx;
+        let count = sqlx::query(
+            r#"
+            UPDATE saved_search
+            SET
+                name = COALESCE($1, name),
+                description = COALESCE($2, description),
+                request = COALESCE($3, request),
+                is_shared = COALESCE($4, is_shared),
+                notify = COALESCE($5, notify),
+                updated_at = NOW()
+            WHERE id = $6
+            "#,
+        )
+        .bind(search_u.name)
+        .bind(search_u.description)
+        .bind(search_u.request)
+        .bind(search_u.is_shared)
+        .bind(search_u.notify)
+        .bind(id)
+        .execute(mm.db())
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stores what a run matched and returns the ids that are new since the previous run.
+    pub async fn record_run(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        ids: &[i64],
+    ) -> Result<Vec<i64>> {
+        let search = Self::get(ctx, mm, id).await?;
+        let new_ids = search.new_matches(ids);
+        sqlx::query(
+            r#"
+            UPDATE saved_search
+            SET matched_ids = $1, last_run_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(ids)
+        .bind(id)
+        .execute(mm.db())
+        .await?;
+
+        Ok(new_ids)
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::delete::<Self>(ctx, mm, id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn saved_search(name: &str, created_by: i64, is_shared: bool) -> SavedSearchForCreate {
+        SavedSearchForCreate::from(json!({
+            "groupId": 1000,
+            "createdBy": created_by,
+            "name": name,
+            "request": {"return_type": "Clone", "filters": []},
+            "isShared": is_shared,
+            "notify": true
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_saved_search_visible_to_creator_or_when_shared() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let private_id =
+            SavedSearchBmc::create(&ctx, &mm, saved_search("private clones", 1303, false)).await?;
+        let shared_id =
+            SavedSearchBmc::create(&ctx, &mm, saved_search("shared clones", 1303, true)).await?;
+
+        let ids = |searches: Vec<SavedSearch>| {
+            searches
+                .into_iter()
+                .map(|search| search.id)
+                .collect::<HashSet<_>>()
+        };
+        let owner = ids(SavedSearchBmc::list_visible(&ctx, &mm, 1001, Some(1000)).await?);
+        assert!(owner.contains(&private_id) && owner.contains(&shared_id));
+        let colleague = ids(SavedSearchBmc::list_visible(&ctx, &mm, 1002, None).await?);
+        assert!(!colleague.contains(&private_id) && colleague.contains(&shared_id));
+        let outsider = ids(SavedSearchBmc::list_visible(&ctx, &mm, 261, None).await?);
+        assert!(!outsider.contains(&shared_id));
+
+        assert!(matches!(
+            SavedSearchBmc::get_visible(&ctx, &mm, private_id, 1002).await,
+            Err(Error::EntityNotFound {
+                entity: "saved_search",
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_saved_search_record_run_returns_new_matches() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = SavedSearchBmc::create(&ctx, &mm, saved_search("new clones", 1304, false)).await?;
+
+        assert!(
+            SavedSearchBmc::record_run(&ctx, &mm, id, &[1006])
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            SavedSearchBmc::record_run(&ctx, &mm, id, &[1016, 1006]).await?,
+            vec![1016]
+        );
+        assert!(
+            SavedSearchBmc::record_run(&ctx, &mm, id, &[1016])
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            SavedSearchBmc::get(&ctx, &mm, id).await?.matched_ids,
+            vec![1016]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,682 @@
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::helpers::{i64_or, opt_f64, opt_i64, opt_string, opt_value};
+use crate::model::{Error, ModelManager, Result};
+use crate::units::{ConcentrationUnit, ConversionContext, Quantity};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+/// Share of the best signal/noise a lower concentration must reach to be preferred.
+const OPTIMUM_TOLERANCE: f64 = 0.9;
+
+/// Ranks the points of one series by strength. Dilutions are read against a stock of 1, so
+/// a higher factor ranks as the lower concentration.
+const RELATIVE_STOCK: ConversionContext = ConversionContext {
+    mw_kda: None,
+    stock_ug_per_ml: Some(1.0),
+};
+
+#[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize)]
+pub struct Titration {
+    pub id: i64,
+    pub group_id: i64,
+    pub conjugate_id: i64,
+    pub validation_id: Option<i64>,
+    pub created_by: i64,
+    pub points: Value,
+    /// The chosen optimum, in the unit of the points.
+    pub optimal_concentration: Option<f64>,
+    pub note: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+/// One tested concentration of a titration series.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TitrationPoint {
+    pub concentration: f64,
+    /// Unit of `concentration`, µg/mL when not given. All points of a titration share it.
+    #[serde(default)]
+    pub unit: ConcentrationUnit,
+    #[serde(default)]
+    pub signal: Option<f64>,
+    #[serde(default)]
+    pub noise: Option<f64>,
+    /// Qualitative rating, higher is better.
+    #[serde(default)]
+    pub score: Option<i64>,
+}
+
+impl TitrationPoint {
+    pub const fn quantity(&self) -> Quantity {
+        Quantity::new(self.concentration, self.unit)
+    }
+
+    /// Orders points of one unit from the lowest concentration up.
+    fn strength(&self) -> f64 {
+        self.quantity()
+            .to_ug_per_ml(&RELATIVE_STOCK)
+            .unwrap_or(f64::INFINITY)
+    }
+
+    pub fn signal_to_noise(&self) -> Option<f64> {
+        match (self.signal, self.noise) {
+            (Some(signal), Some(noise)) if noise > 0.0 => Some(signal / noise),
+            _ => None,
+        }
+    }
+}
+
+impl Titration {
+    pub fn parsed_points(&self) -> Vec<TitrationPoint> {
+        parse_points(&self.points)
+    }
+
+    /// The chosen optimum, or the computed recommendation when none was chosen.
+    pub fn recommended_concentration(&self) -> Option<Quantity> {
+        let points = self.parsed_points();
+        match self.optimal_concentration {
+            Some(optimum) => {
+                let unit = points.first().map(|point| point.unit).unwrap_or_default();
+                Some(Quantity::new(optimum, unit))
+            }
+            None => recommend_concentration(&points),
+        }
+    }
+}
+
+/// The points of `value` when each is a tested concentration, for writes; reads use the
+/// lenient [`parse_points`].
+pub fn validate_points(value: &Value) -> Result<Vec<TitrationPoint>> {
+    let invalid = |index, reason| Error::InvalidTitrationPoints { index, reason };
+    let items = value
+        .as_array()
+        .ok_or_else(|| invalid(None, "points must be a list"))?;
+    let points = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let point = serde_json::from_value::<TitrationPoint>(item.clone()).map_err(|_| {
+                invalid(
+                    Some(index),
+                    "point needs a numeric concentration and a known unit",
+                )
+            })?;
+            if !point.concentration.is_finite() || point.concentration <= 0.0 {
+                return Err(invalid(Some(index), "concentration must be positive"));
+            }
+            if [point.signal, point.noise]
+                .into_iter()
+                .flatten()
+                .any(|value| !value.is_finite() || value < 0.0)
+            {
+                return Err(invalid(
+                    Some(index),
+                    "signal and noise must not be negative",
+                ));
+            }
+            Ok(point)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(index) = points.iter().position(|point| point.unit != points[0].unit) {
+        return Err(invalid(Some(index), "points must share one unit"));
+    }
+    Ok(points)
+}
+
+pub fn parse_points(value: &Value) -> Vec<TitrationPoint> {
+    let Some(items) = value.as_array() else {
+        return vec![];
+    };
+    items
+        .iter()
+        .filter_map(|item| serde_json::from_value::<TitrationPoint>(item.clone()).ok())
+        .filter(|point| point.concentration.is_finite() && point.concentration > 0.0)
+        .collect()
+}
+
+/// Picks the lowest concentration whose signal/noise is within `OPTIMUM_TOLERANCE` of
+/// the best one. Series without measured noise fall back to the best qualitative score,
+/// again preferring the lowest concentration on ties. Series mixing units have none.
+pub fn recommend_concentration(points: &[TitrationPoint]) -> Option<Quantity> {
+    if points.iter().any(|point| point.unit != points[0].unit) {
+        return None;
+    }
+    let mut sorted: Vec<&TitrationPoint> = points.iter().collect();
+    sorted.sort_by(|a, b| a.strength().total_cmp(&b.strength()));
+
+    let best_ratio = sorted
+        .iter()
+        .filter_map(|point| point.signal_to_noise())
+        .max_by(f64::total_cmp);
+    if let Some(best_ratio) = best_ratio {
+        return sorted
+            .iter()
+            .find(|point| {
+                point
+                    .signal_to_noise()
+                    .is_some_and(|ratio| ratio >= best_ratio * OPTIMUM_TOLERANCE)
+            })
+            .map(|point| point.quantity());
+    }
+
+    let best_score = sorted.iter().filter_map(|point| point.score).max()?;
+    sorted
+        .iter()
+        .find(|point| point.score == Some(best_score))
+        .map(|point| point.quantity())
+}
+
+#[derive(Fields, Deserialize, Clone, Debug)]
+pub struct TitrationForCreate {
+    pub group_id: i64,
+    pub conjugate_id: i64,
+    pub validation_id: Option<i64>,
+    pub created_by: i64,
+    pub points: Value,
+    pub optimal_concentration: Option<f64>,
+    pub note: Option<String>,
+}
+
+impl From<Value> for TitrationForCreate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+
+        TitrationForCreate {
+            group_id: opt_i64(&obj, "groupId").unwrap_or(i64_or(&obj, "group_id", 0)),
+            conjugate_id: opt_i64(&obj, "conjugateId").unwrap_or(i64_or(&obj, "conjugate_id", 0)),
+            validation_id: opt_i64(&obj, "validationId").or_else(|| opt_i64(&obj, "validation_id")),
+            created_by: opt_i64(&obj, "createdBy").unwrap_or(i64_or(&obj, "created_by", 0)),
+            points: opt_value(&obj, "points").unwrap_or_else(|| Value::Array(vec![])),
+            optimal_concentration: opt_f64(&obj, "optimalConcentration")
+                .or_else(|| opt_f64(&obj, "optimal_concentration")),
+            note: opt_string(&obj, "note"),
+        }
+    }
+}
+
+#[derive(Fields, Default, Deserialize, Debug)]
+pub struct TitrationForUpdate {
+    pub validation_id: Option<i64>,
+    pub points: Option<Value>,
+    pub optimal_concentration: Option<f64>,
+    /// Set by an explicit `null` optimum, which clears the chosen one.
+    pub clear_optimal_concentration: bool,
+    pub note: Option<String>,
+}
+
+impl From<Value> for TitrationForUpdate {
+    fn from(v: Value) -> Self {
+        let obj = match v {
+            Value::Object(map) => Value::Object(map),
+            _ => Value::Object(Default::default()),
+        };
+
+        TitrationForUpdate {
+            validation_id: opt_i64(&obj, "validationId").or_else(|| opt_i64(&obj, "validation_id")),
+            points: opt_value(&obj, "points"),
+            optimal_concentration: opt_f64(&obj, "optimalConcentration")
+                .or_else(|| opt_f64(&obj, "optimal_concentration")),
+            clear_optimal_concentration: ["optimalConcentration", "optimal_concentration"]
+                .into_iter()
+                .any(|key| obj.get(key).is_some_and(Value::is_null)),
+            note: opt_string(&obj, "note"),
+        }
+    }
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug, Clone)]
+pub struct TitrationFilter {
+    id: Option<OpValsInt64>,
+    group_id: Option<OpValsInt64>,
+    conjugate_id: Option<OpValsInt64>,
+    validation_id: Option<OpValsInt64>,
+    created_by: Option<OpValsInt64>,
+}
+
+pub struct TitrationBmc;
+
+impl DbBmc for TitrationBmc {
+    const TABLE: &'static str = "titration";
+
+    fn has_timestamps() -> bool {
+        false
+    }
+}
+
+impl TitrationBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        titration_c: TitrationForCreate,
+    ) -> Result<i64> {
+        let _ = ctx;
+        validate_points(&titration_c.points)?;
+        let id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO titration (group_id, conjugate_id, validation_id, created_by, points, optimal_concentration, note, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(titration_c.group_id)
+        .bind(titration_c.conjugate_id)
+        .bind(titration_c.validation_id)
+        .bind(titration_c.created_by)
+        .bind(titration_c.points)
+        .bind(titration_c.optimal_concentration)
+        .bind(titration_c.note)
+        .fetch_one(mm.db())
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Titration> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TitrationFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<Titration>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn count(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TitrationFilter>>,
+    ) -> Result<i64> {
+        base::count::<Self, _>(ctx, mm, filters).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        titration_u: TitrationForUpdate,
+    ) -> Result<()> {
+        let _ = ctx;
+        if let Some(points) = &titration_u.points {
+            validate_points(points)?;
+        }
+        let count = sqlx::query(
+            r#"
+            UPDATE titration
+            SET
+                validation_id = COALESCE($1, validation_id),
+                points = COALESCE($2, points),
+                optimal_concentration = CASE
+                    WHEN $3 THEN NULL
+                    ELSE COALESCE($4, optimal_concentration)
+                END,
+                note = COALESCE($5, note),
+                updated_at = NOW()
+            WHERE id = $6
+            "#,
+        )
+        .bind(titration_u.validation_id)
+        .bind(titration_u.points)
+        .bind(titration_u.clear_optimal_concentration)
+        .bind(titration_u.optimal_concentration)
+        .bind(titration_u.note)
+        .bind(id)
+        .execute(mm.db())
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::delete::<Self>(ctx, mm, id).await
+    }
+
+    /// Concentration to prefill when the conjugate is added to a panel, taken from its
+    /// most recently updated titration that yields a recommendation.
+    pub async fn suggested_concentration(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        conjugate_id: i64,
+    ) -> Result<Option<Quantity>> {
+        let _ = ctx;
+        let titrations = sqlx::query_as::<_, Titration>(
+            r#"
+            SELECT id, group_id, conjugate_id, validation_id, created_by, points,
+                   optimal_concentration, note, created_at, updated_at
+            FROM titration
+            WHERE conjugate_id = $1
+            ORDER BY updated_at DESC, id DESC
+            "#,
+        )
+        .bind(conjugate_id)
+        .fetch_all(mm.db())
+        .await?;
+
+        Ok(titrations
+            .iter()
+            .find_map(Titration::recommended_concentration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn point(concentration: f64, signal: f64, noise: f64) -> TitrationPoint {
+        TitrationPoint {
+            concentration,
+            signal: Some(signal),
+            noise: Some(noise),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recommend_prefers_lowest_concentration_near_best_ratio() {
+        let points = vec![
+            point(4.0, 200.0, 10.0),
+            point(0.5, 50.0, 10.0),
+            point(1.0, 190.0, 10.0),
+            point(2.0, 195.0, 10.0),
+        ];
+
+        assert_eq!(
+            recommend_concentration(&points),
+            Some(Quantity::ug_per_ml(1.0))
+        );
+    }
+
+    #[test]
+    fn recommend_ranks_dilutions_by_concentration_and_skips_mixed_units() {
+        let dilution = |factor, signal| TitrationPoint {
+            unit: ConcentrationUnit::Dilution,
+            ..point(factor, signal, 10.0)
+        };
+        let points = vec![
+            dilution(100.0, 200.0),
+            dilution(400.0, 100.0),
+            dilution(200.0, 190.0),
+        ];
+        assert_eq!(
+            recommend_concentration(&points),
+            Some(Quantity::dilution(200.0))
+        );
+
+        let mixed = vec![point(1.0, 190.0, 10.0), dilution(100.0, 200.0)];
+        assert_eq!(recommend_concentration(&mixed), None);
+    }
+
+    #[test]
+    fn recommend_falls_back_to_scores() {
+        let points = vec![
+            TitrationPoint {
+                concentration: 2.0,
+                score: Some(3),
+                ..Default::default()
+            },
+            TitrationPoint {
+                concentration: 1.0,
+                score: Some(2),
+                ..Default::default()
+            },
+            TitrationPoint {
+                concentration: 4.0,
+                score: Some(3),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            recommend_concentration(&points),
+            Some(Quantity::ug_per_ml(2.0))
+        );
+        assert_eq!(recommend_concentration(&[]), None);
+    }
+
+    #[test]
+    fn parse_points_skips_invalid_entries() {
+        let points = parse_points(&json!([
+            {"concentration": 1.0, "signal": 10.0, "noise": 2.0},
+            {"concentration": -1.0, "score": 2},
+            {"signal": 3.0},
+            "garbage"
+        ]));
+
+        assert_eq!(points, vec![point(1.0, 10.0, 2.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_titration_create_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+
+        let id = TitrationBmc::create(
+            &ctx,
+            &mm,
+            TitrationForCreate {
+                group_id: 1000,
+                conjugate_id: 1008,
+                validation_id: Some(1011),
+                created_by: 1303,
+                points: json!([{"concentration": 1.0, "signal": 20.0, "noise": 2.0}]),
+                optimal_concentration: None,
+                note: Some("first run".into()),
+            },
+        )
+        .await?;
+
+        let titration = TitrationBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(titration.conjugate_id, 1008);
+        assert_eq!(titration.validation_id, Some(1011));
+        assert_eq!(titration.parsed_points().len(), 1);
+        assert_eq!(
+            titration.recommended_concentration(),
+            Some(Quantity::ug_per_ml(1.0))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_titration_update_and_suggest_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = TitrationBmc::create(
+            &ctx,
+            &mm,
+            TitrationForCreate::from(json!({
+                "groupId": 1000,
+                "conjugateId": 1019,
+                "createdBy": 1304,
+                "points": [
+                    {"concentration": 0.5, "signal": 5.0, "noise": 1.0},
+                    {"concentration": 1.0, "signal": 12.0, "noise": 1.0}
+                ]
+            })),
+        )
+        .await?;
+
+        let suggested = TitrationBmc::suggested_concentration(&ctx, &mm, 1019).await?;
+        assert_eq!(suggested, Some(Quantity::ug_per_ml(1.0)));
+
+        TitrationBmc::update(
+            &ctx,
+            &mm,
+            id,
+            TitrationForUpdate::from(json!({"optimalConcentration": 0.75})),
+        )
+        .await?;
+
+        let suggested = TitrationBmc::suggested_concentration(&ctx, &mm, 1019).await?;
+        assert_eq!(suggested, Some(Quantity::ug_per_ml(0.75)));
+
+        TitrationBmc::update(
+            &ctx,
+            &mm,
+            id,
+            TitrationForUpdate::from(json!({"optimalConcentration": null, "note": "redo"})),
+        )
+        .await?;
+        let titration = TitrationBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(titration.optimal_concentration, None);
+        assert_eq!(
+            titration.recommended_concentration(),
+            Some(Quantity::ug_per_ml(1.0))
+        );
+        assert_eq!(
+            TitrationBmc::suggested_concentration(&ctx, &mm, 4292).await?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_titration_rejects_invalid_points() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let create = |points: Value| {
+            TitrationForCreate::from(json!({
+                "groupId": 1000,
+                "conjugateId": 1008,
+                "createdBy": 1303,
+                "points": points
+            }))
+        };
+
+        let result = TitrationBmc::create(
+            &ctx,
+            &mm,
+            create(json!([{"concentration": 1.0}, {"concentration": -1.0}])),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::InvalidTitrationPoints { index: Some(1), .. })
+        ));
+        let result = TitrationBmc::create(&ctx, &mm, create(json!({"concentration": 1.0}))).await;
+        assert!(matches!(
+            result,
+            Err(Error::InvalidTitrationPoints { index: None, .. })
+        ));
+        let result = TitrationBmc::create(
+            &ctx,
+            &mm,
+            create(json!([
+                {"concentration": 1.0},
+                {"concentration": 1.0, "unit": "ug/mL"},
+                {"concentration": 200.0, "unit": "dilution"}
+            ])),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::InvalidTitrationPoints { index: Some(2), .. })
+        ));
+        let result = TitrationBmc::create(
+            &ctx,
+            &mm,
+            create(json!([{"concentration": 1.0, "unit": "furlongs"}])),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::InvalidTitrationPoints { index: Some(0), .. })
+        ));
+
+        let id = TitrationBmc::create(&ctx, &mm, create(json!([{"concentration": 1.0}]))).await?;
+        let result = TitrationBmc::update(
+            &ctx,
+            &mm,
+            id,
+            TitrationForUpdate::from(json!({"points": [{"concentration": 2.0, "noise": -1.0}]})),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::InvalidTitrationPoints { index: Some(0), .. })
+        ));
+        assert_eq!(
+            TitrationBmc::get(&ctx, &mm, id)
+                .await?
+                .parsed_points()
+                .len(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_titration_list_by_filter_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+
+        for conjugate_id in [1008, 1008, 1019] {
+            TitrationBmc::create(
+                &ctx,
+                &mm,
+                TitrationForCreate {
+                    group_id: 1000,
+                    conjugate_id,
+                    validation_id: None,
+                    created_by: 1303,
+                    points: json!([]),
+                    optimal_concentration: None,
+                    note: None,
+                },
+            )
+            .await?;
+        }
+
+        let filters: Vec<TitrationFilter> = serde_json::from_value(json!([
+            {
+                "conjugate_id": { "$eq": 1008 }
+            }
+        ]))?;
+        let titrations = TitrationBmc::list(&ctx, &mm, Some(filters), None).await?;
+
+        assert_eq!(titrations.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_titration_delete_err_not_found() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+
+        let result = TitrationBmc::delete(&ctx, &mm, 999_999).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::EntityNotFound {
+                entity: "titration",
+                id: 999_999
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 use crate::ctx::Ctx;
-use crate::model::ModelManager;
-use crate::model::Result;
 use crate::model::base::{self, DbBmc};
 use crate::model::helpers::{i64_or, opt_bool, opt_datetime, opt_i64, opt_string};
+use crate::model::member::MemberBmc;
+use crate::model::protocol_template::ProtocolTemplateBmc;
+use crate::model::{Error, ModelManager, Result};
+use crate::units::{self, MigrationReport, Quantity, UnparseableValue};
 use modql::field::Fields;
-use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::FromRow;
+use sqlx::{FromRow, PgConnection};
 
 #[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize, Default)]
 pub struct MinValidation {
@@ -76,6 +78,199 @@ pub struct Validation {
     pub created_at: chrono::DateTime<chrono::Utc>,
     #[serde(rename = "updatedAt")]
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    #[serde(rename = "reviewState")]
+    pub review_state: String,
+    #[serde(rename = "submittedBy")]
+    pub submitted_by: Option<i64>,
+    #[serde(rename = "submittedAt")]
+    pub submitted_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "reviewedBy")]
+    pub reviewed_by: Option<i64>,
+    #[serde(rename = "reviewedAt")]
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "reviewComment")]
+    pub review_comment: Option<String>,
+    #[serde(rename = "protocolTemplateId")]
+    pub protocol_template_id: Option<i64>,
+}
+
+impl Validation {
+    pub fn review_state(&self) -> ReviewState {
+        ReviewState::parse(&self.review_state).unwrap_or(ReviewState::Draft)
+    }
+
+    pub fn concentration_quantity(&self) -> Option<Quantity> {
+        Quantity::parse(
+            self.concentration.as_deref()?,
+            self.concentration_unit.as_deref(),
+        )
+        .ok()
+    }
+}
+
+/// Member role allowed to approve or reject validations, between Standard (10) and
+/// Admin (100); admins can review as well.
+pub const REVIEWER_ROLE: i64 = 50;
+
+/// Sign-off state of a validation, stored in `validation.review_state`.
+///
+/// `draft → submitted → approved | rejected`; a rejected validation can be edited and
+/// submitted again, and a submission can be withdrawn back to draft. Only drafts and
+/// rejected validations can be edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    Draft,
+    Submitted,
+    Approved,
+    Rejected,
+}
+
+impl ReviewState {
+    pub const ALL: [Self; 4] = [Self::Draft, Self::Submitted, Self::Approved, Self::Rejected];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Submitted => "submitted",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|state| state.as_str().eq_ignore_ascii_case(raw.trim()))
+    }
+
+    pub const fn is_editable(self) -> bool {
+        matches!(self, Self::Draft | Self::Rejected)
+    }
+
+    /// State reached by `action`, or `None` if the action is not allowed from here.
+    pub const fn transition(self, action: ReviewAction) -> Option<Self> {
+        match (self, action) {
+            (Self::Draft | Self::Rejected, ReviewAction::Submit) => Some(Self::Submitted),
+            (Self::Submitted, ReviewAction::Withdraw) => Some(Self::Draft),
+            (Self::Submitted, ReviewAction::Approve) => Some(Self::Approved),
+            (Self::Submitted, ReviewAction::Reject) => Some(Self::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewAction {
+    Submit,
+    Withdraw,
+    Approve,
+    Reject,
+}
+
+impl ReviewAction {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Withdraw => "withdraw",
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+        }
+    }
+
+    const fn is_review(self) -> bool {
+        matches!(self, Self::Approve | Self::Reject)
+    }
+}
+
+/// The member performing a review action.
+#[derive(Debug, Clone, Copy)]
+pub struct ReviewActor {
+    pub member_id: i64,
+    pub group_id: i64,
+    pub role: i64,
+}
+
+/// Checks `action` on `validation` by `actor` and returns the resulting state.
+///
+/// Anyone in the group may submit; only the submitter may withdraw. Approving and
+/// rejecting needs [`REVIEWER_ROLE`] and a member other than the author and the
+/// submitter, and a rejection must carry a comment.
+pub fn check_review(
+    validation: &Validation,
+    action: ReviewAction,
+    actor: &ReviewActor,
+    comment: Option<&str>,
+) -> Result<ReviewState> {
+    let id = validation.id;
+    let from = validation.review_state();
+    let to = from
+        .transition(action)
+        .ok_or(Error::ReviewTransitionNotAllowed {
+            id,
+            from: from.as_str(),
+            action: action.as_str(),
+        })?;
+
+    if actor.group_id != validation.group_id {
+        return Err(Error::ReviewNotPermitted {
+            id,
+            reason: "not a member of the validation's group",
+        });
+    }
+    if action == ReviewAction::Withdraw && validation.submitted_by != Some(actor.member_id) {
+        return Err(Error::ReviewNotPermitted {
+            id,
+            reason: "only the submitter can withdraw",
+        });
+    }
+    if action.is_review() {
+        if actor.role < REVIEWER_ROLE {
+            return Err(Error::ReviewNotPermitted {
+                id,
+                reason: "reviewer role required",
+            });
+        }
+        if validation.created_by == actor.member_id
+            || validation.submitted_by == Some(actor.member_id)
+        {
+            return Err(Error::ReviewNotPermitted {
+                id,
+                reason: "a second member has to review",
+            });
+        }
+    }
+    if action == ReviewAction::Reject && comment.is_none_or(|comment| comment.trim().is_empty()) {
+        return Err(Error::ReviewCommentRequired { id });
+    }
+
+    Ok(to)
+}
+
+/// One entry of a validation's review history.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReviewEvent {
+    pub id: i64,
+    pub validation_id: i64,
+    pub action: String,
+    pub from_state: String,
+    pub to_state: String,
+    pub actor_id: i64,
+    pub comment: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Rewrites a concentration/unit pair to its canonical form when it can be parsed.
+fn normalize_concentration(concentration: &mut Option<String>, unit: &mut Option<String>) {
+    let Some(value) = concentration.as_deref() else {
+        return;
+    };
+    if let Ok((value, code)) = units::normalize_legacy(value, unit.as_deref()) {
+        *concentration = Some(value);
+        *unit = Some(code);
+    }
 }
 
 #[derive(Fields, Deserialize, Clone, Debug)]
@@ -133,6 +328,8 @@ pub struct ValidationForCreate {
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "protocolTemplateId")]
+    pub protocol_template_id: Option<i64>,
 }
 
 impl From<Value> for ValidationForCreate {
@@ -173,6 +370,7 @@ impl From<Value> for ValidationForCreate {
             is_archived: opt_bool(&obj, "isArchived"),
             created_at: opt_datetime(&obj, "createdAt"),
             updated_at: opt_datetime(&obj, "updatedAt"),
+            protocol_template_id: opt_i64(&obj, "protocolTemplateId"),
         }
     }
 }
@@ -264,6 +462,9 @@ pub struct ValidationFilter {
     status: Option<OpValsInt64>,
 
     tissue: Option<OpValsString>,
+    review_state: Option<OpValsString>,
+    protocol_template_id: Option<OpValsInt64>,
+    is_archived: Option<OpValsBool>,
 }
 
 pub struct ValidationBmc;
@@ -276,8 +477,13 @@ impl ValidationBmc {
     pub async fn create(
         ctx: &Ctx,
         mm: &ModelManager,
-        validation_c: ValidationForCreate,
+        mut validation_c: ValidationForCreate,
     ) -> Result<i64> {
+        ProtocolTemplateBmc::prefill_validation(ctx, mm, &mut validation_c).await?;
+        normalize_concentration(
+            &mut validation_c.concentration,
+            &mut validation_c.concentration_unit,
+        );
         base::create::<Self, _>(ctx, mm, validation_c).await
     }
     pub async fn create_full(
@@ -322,14 +528,196 @@ impl ValidationBmc {
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
-        validation_u: ValidationForUpdate,
+        mut validation_u: ValidationForUpdate,
     ) -> Result<()> {
+        Self::ensure_editable(mm, id).await?;
+        normalize_concentration(
+            &mut validation_u.concentration,
+            &mut validation_u.concentration_unit,
+        );
         base::update::<Self, _>(ctx, mm, id, validation_u).await
     }
 
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        Self::ensure_editable(mm, id).await?;
         base::delete::<Self>(ctx, mm, id).await
     }
+
+    /// Fails with [`Error::ValidationLocked`] unless the validation is a draft or rejected.
+    pub async fn ensure_editable(mm: &ModelManager, id: i64) -> Result<()> {
+        let state =
+            sqlx::query_scalar::<_, String>("SELECT review_state FROM validation WHERE id = $1")
+                .bind(id)
+                .fetch_optional(mm.db())
+                .await?;
+        match state.as_deref().and_then(ReviewState::parse) {
+            Some(state) if !state.is_editable() => Err(Error::ValidationLocked { id }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies a review action by `member_id` and records it in the review history.
+    pub async fn review(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        action: ReviewAction,
+        member_id: i64,
+        comment: Option<String>,
+    ) -> Result<Validation> {
+        let validation = Self::get(ctx, mm, id).await?;
+        let member = MemberBmc::get(ctx, mm, member_id).await?;
+        let actor = ReviewActor {
+            member_id,
+            group_id: member.group_id,
+            role: member.role,
+        };
+        let comment = comment
+            .map(|comment| comment.trim().to_string())
+            .filter(|comment| !comment.is_empty());
+        let from = validation.review_state();
+        let to = check_review(&validation, action, &actor, comment.as_deref())?;
+
+        // Guarded by the current state so concurrent reviews cannot both succeed; the
+        // history entry is written in the same transaction.
+        let mut tx = mm.db().begin().await?;
+        let updated = sqlx::query(
+            r#"
+            UPDATE validation SET
+                review_state = $3,
+                submitted_by = CASE WHEN $4 THEN $5 ELSE submitted_by END,
+                submitted_at = CASE WHEN $4 THEN NOW() ELSE submitted_at END,
+                reviewed_by = CASE WHEN $6 THEN $5 WHEN $4 THEN NULL ELSE reviewed_by END,
+                reviewed_at = CASE WHEN $6 THEN NOW() WHEN $4 THEN NULL ELSE reviewed_at END,
+                review_comment = CASE WHEN $6 THEN $7 ELSE review_comment END,
+                updated_at = NOW()
+            WHERE id = $1 AND review_state = $2
+            "#,
+        )
+        .bind(id)
+        .bind(from.as_str())
+        .bind(to.as_str())
+        .bind(action == ReviewAction::Submit)
+        .bind(member_id)
+        .bind(action.is_review())
+        .bind(comment.as_deref())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::ReviewTransitionNotAllowed {
+                id,
+                from: from.as_str(),
+                action: action.as_str(),
+            });
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO validation_review_event
+                (validation_id, action, from_state, to_state, actor_id, comment)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(id)
+        .bind(action.as_str())
+        .bind(from.as_str())
+        .bind(to.as_str())
+        .bind(member_id)
+        .bind(comment)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Self::get(ctx, mm, id).await
+    }
+
+    /// Submitted validations of a group awaiting review, oldest submission first.
+    pub async fn review_queue(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        group_id: i64,
+    ) -> Result<Vec<Validation>> {
+        let filters: Vec<ValidationFilter> = serde_json::from_value(serde_json::json!([{
+            "group_id": {"$eq": group_id},
+            "review_state": {"$eq": ReviewState::Submitted.as_str()},
+            "is_archived": {"$eq": false}
+        }]))?;
+        let list_options: ListOptions = serde_json::from_value(serde_json::json!({
+            "order_bys": ["submitted_at", "id"]
+        }))?;
+        Self::list(ctx, mm, Some(filters), Some(list_options)).await
+    }
+
+    pub async fn review_history(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> Result<Vec<ValidationReviewEvent>> {
+        let _ = ctx;
+        let events = sqlx::query_as::<_, ValidationReviewEvent>(
+            r#"
+            SELECT id, validation_id, action, from_state, to_state, actor_id, comment, created_at
+            FROM validation_review_event
+            WHERE validation_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(id)
+        .fetch_all(mm.db())
+        .await?;
+        Ok(events)
+    }
+
+    /// Rewrites legacy free-text concentrations into canonical value/unit pairs and
+    /// records every value that could not be parsed.
+    pub async fn normalize_concentrations(
+        ctx: &Ctx,
+        conn: &mut PgConnection,
+        report: &mut MigrationReport,
+    ) -> Result<()> {
+        let _ = ctx;
+        let rows = sqlx::query_as::<_, (i64, String, Option<String>)>(
+            r#"
+            SELECT id, concentration, concentration_unit
+            FROM validation
+            WHERE concentration IS NOT NULL AND btrim(concentration) <> ''
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for (id, concentration, unit) in rows {
+            match units::normalize_legacy(&concentration, unit.as_deref()) {
+                Ok((value, code)) if value == concentration && Some(&code) == unit.as_ref() => {
+                    report.unchanged += 1;
+                }
+                Ok((value, code)) => {
+                    sqlx::query(
+                        "UPDATE validation SET concentration = $1, concentration_unit = $2 WHERE id = $3",
+                    )
+                    .bind(value)
+                    .bind(code)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+                    report.normalized += 1;
+                }
+                Err(err) => report.unparseable.push(UnparseableValue {
+                    entity: Self::TABLE,
+                    id,
+                    value: match unit {
+                        Some(unit) => format!("{concentration} {unit}"),
+                        None => concentration,
+                    },
+                    error: err.to_string(),
+                }),
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -376,6 +764,7 @@ mod tests {
             is_archived: Some(false),
             created_at: Some(chrono::offset::Utc::now()),
             updated_at: Some(chrono::offset::Utc::now()),
+            protocol_template_id: None,
         };
         let id = ValidationBmc::create(&ctx, &mm, validation_c).await?;
 
@@ -555,4 +944,129 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn check_review_requires_second_member_with_reviewer_role() {
+        let validation = Validation {
+            id: 1,
+            group_id: 1000,
+            created_by: 1303,
+            review_state: "submitted".into(),
+            submitted_by: Some(1303),
+            ..Default::default()
+        };
+        let reviewer = ReviewActor {
+            member_id: 1304,
+            group_id: 1000,
+            role: REVIEWER_ROLE,
+        };
+
+        assert!(matches!(
+            check_review(&validation, ReviewAction::Approve, &reviewer, None),
+            Ok(ReviewState::Approved)
+        ));
+        assert!(matches!(
+            check_review(
+                &validation,
+                ReviewAction::Approve,
+                &ReviewActor {
+                    member_id: 1303,
+                    ..reviewer
+                },
+                None
+            ),
+            Err(Error::ReviewNotPermitted { .. })
+        ));
+        assert!(matches!(
+            check_review(
+                &validation,
+                ReviewAction::Approve,
+                &ReviewActor {
+                    role: 0,
+                    ..reviewer
+                },
+                None
+            ),
+            Err(Error::ReviewNotPermitted { .. })
+        ));
+        assert!(matches!(
+            check_review(&validation, ReviewAction::Reject, &reviewer, Some("  ")),
+            Err(Error::ReviewCommentRequired { id: 1 })
+        ));
+        assert!(matches!(
+            check_review(&validation, ReviewAction::Submit, &reviewer, None),
+            Err(Error::ReviewTransitionNotAllowed {
+                from: "submitted",
+                action: "submit",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validation_review_locks_approved() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        sqlx::query("UPDATE member SET role = $1 WHERE id = 1304")
+            .bind(REVIEWER_ROLE)
+            .execute(mm.db())
+            .await?;
+
+        let submitted =
+            ValidationBmc::review(&ctx, &mm, 1011, ReviewAction::Submit, 1303, None).await?;
+        assert_eq!(submitted.review_state(), ReviewState::Submitted);
+        assert_eq!(submitted.submitted_by, Some(1303));
+
+        let queue = ValidationBmc::review_queue(&ctx, &mm, 1000).await?;
+        assert_eq!(
+            queue
+                .iter()
+                .map(|validation| validation.id)
+                .collect::<Vec<_>>(),
+            vec![1011]
+        );
+
+        let approved = ValidationBmc::review(
+            &ctx,
+            &mm,
+            1011,
+            ReviewAction::Approve,
+            1304,
+            Some("looks good".into()),
+        )
+        .await?;
+        assert_eq!(approved.review_state(), ReviewState::Approved);
+        assert_eq!(approved.reviewed_by, Some(1304));
+        assert_eq!(approved.review_comment.as_deref(), Some("looks good"));
+
+        let res = ValidationBmc::update(
+            &ctx,
+            &mm,
+            1011,
+            ValidationForUpdate {
+                application: 1,
+                status: 0,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(res, Err(Error::ValidationLocked { id: 1011 })));
+        let res = ValidationBmc::delete(&ctx, &mm, 1011).await;
+        assert!(matches!(res, Err(Error::ValidationLocked { id: 1011 })));
+
+        let history = ValidationBmc::review_history(&ctx, &mm, 1011).await?;
+        assert_eq!(
+            history
+                .iter()
+                .map(|event| (
+                    event.action.as_str(),
+                    event.to_state.as_str(),
+                    event.actor_id
+                ))
+                .collect::<Vec<_>>(),
+            vec![("submit", "submitted", 1303), ("approve", "approved", 1304)]
+        );
+
+        Ok(())
+    }
 }
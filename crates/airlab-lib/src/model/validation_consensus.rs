@@ -0,0 +1,290 @@
+use crate::ctx::Ctx;
+use crate::model::clone_application::ApplicationStatus;
+use crate::model::validation::ReviewState;
+use crate::model::{ModelManager, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection};
+use std::collections::{BTreeMap, HashMap};
+
+/// Age at which a validation counts half as much as a fresh one.
+const RECENCY_HALF_LIFE_DAYS: f64 = 365.0;
+/// Lowest score still summarised as Yes / So-So.
+const YES_THRESHOLD: f64 = 0.75;
+const SO_SO_THRESHOLD: f64 = 0.4;
+
+/// One approved validation verdict as seen by the scorer.
+#[derive(Debug, Clone, FromRow)]
+pub struct ConsensusVote {
+    pub clone_id: i64,
+    pub application: i64,
+    pub status: i64,
+    pub lot_id: Option<i64>,
+    /// Member who approved the validation.
+    pub reviewer: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Aggregated verdict for one clone × application.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationConsensus {
+    pub clone_id: i64,
+    pub application: i64,
+    /// Weighted mean of the votes, 0.0 (No) to 1.0 (Yes).
+    pub score: f64,
+    /// Status id (as in `validation.status`) the score rounds to.
+    pub status: i64,
+    /// Votes that counted, i.e. excluding `Undefined`.
+    pub votes: usize,
+    /// Sum of the vote weights; low values mean old or lopsided evidence.
+    pub weight: f64,
+}
+
+const fn status_value(status: ApplicationStatus) -> Option<f64> {
+    match status {
+        ApplicationStatus::Yes => Some(1.0),
+        ApplicationStatus::SoSo => Some(0.5),
+        ApplicationStatus::No => Some(0.0),
+        ApplicationStatus::Undefined => None,
+    }
+}
+
+fn recency_weight(updated_at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age_days = (now - updated_at).num_seconds().max(0) as f64 / 86_400.0;
+    0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS)
+}
+
+/// Scores votes per clone × application.
+///
+/// Each vote is weighted by recency (half-life of a year) and shares its weight with the other
+/// votes on the same lot and with the other votes approved by the same reviewer, so repeated
+/// runs on one lot or signed off by one person do not outvote independent evidence.
+/// `Undefined` votes are ignored.
+pub fn score_votes(votes: &[ConsensusVote], now: DateTime<Utc>) -> Vec<ValidationConsensus> {
+    let mut by_key: BTreeMap<(i64, i64), Vec<(&ConsensusVote, f64)>> = BTreeMap::new();
+    for vote in votes {
+        let Some(value) = ApplicationStatus::from_id(vote.status).and_then(status_value) else {
+            continue;
+        };
+        by_key
+            .entry((vote.clone_id, vote.application))
+            .or_default()
+            .push((vote, value));
+    }
+
+    by_key
+        .into_iter()
+        .map(|((clone_id, application), votes)| {
+            let mut per_lot: HashMap<Option<i64>, usize> = HashMap::new();
+            let mut per_reviewer: HashMap<i64, usize> = HashMap::new();
+            for (vote, _) in &votes {
+                *per_lot.entry(vote.lot_id).or_default() += 1;
+                *per_reviewer.entry(vote.reviewer).or_default() += 1;
+            }
+
+            let (mut weighted, mut weight) = (0.0, 0.0);
+            for (vote, value) in &votes {
+                let lot_share = vote.lot_id.map_or(1, |_| per_lot[&vote.lot_id]);
+                let w = recency_weight(vote.updated_at, now)
+                    / lot_share as f64
+                    / per_reviewer[&vote.reviewer] as f64;
+                weighted += w * value;
+                weight += w;
+            }
+            let score = if weight > 0.0 { weighted / weight } else { 0.0 };
+
+            ValidationConsensus {
+                clone_id,
+                application,
+                score,
+                status: consensus_status(score).id(),
+                votes: votes.len(),
+                weight,
+            }
+        })
+        .collect()
+}
+
+pub fn consensus_status(score: f64) -> ApplicationStatus {
+    if score >= YES_THRESHOLD {
+        ApplicationStatus::Yes
+    } else if score >= SO_SO_THRESHOLD {
+        ApplicationStatus::SoSo
+    } else {
+        ApplicationStatus::No
+    }
+}
+
+pub struct ValidationConsensusBmc;
+
+impl ValidationConsensusBmc {
+    /// Consensus for every clone × application of a group from its approved validations,
+    /// skipping archived ones.
+    pub async fn list_for_group(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        group_id: i64,
+    ) -> Result<Vec<ValidationConsensus>> {
+        let _ = ctx;
+        let mut conn = mm.db().acquire().await?;
+        Self::list_in(&mut conn, group_id).await
+    }
+
+    async fn list_in(conn: &mut PgConnection, group_id: i64) -> Result<Vec<ValidationConsensus>> {
+        let votes = sqlx::query_as::<_, ConsensusVote>(
+            r#"
+            SELECT
+                clone_id,
+                application,
+                status,
+                lot_id,
+                COALESCE(reviewed_by, created_by) AS reviewer,
+                updated_at
+            FROM validation
+            WHERE group_id = $1 AND NOT is_archived AND review_state = $2
+            "#,
+        )
+        .bind(group_id)
+        .bind(ReviewState::Approved.as_str())
+        .fetch_all(conn)
+        .await?;
+
+        Ok(score_votes(&votes, Utc::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::validation::{ValidationBmc, ValidationForCreate};
+    use chrono::Duration;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn vote(status: i64, lot_id: Option<i64>, reviewer: i64, age_days: i64) -> ConsensusVote {
+        ConsensusVote {
+            clone_id: 1,
+            application: 1,
+            status,
+            lot_id,
+            reviewer,
+            updated_at: now() - Duration::days(age_days),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap_or_default()
+    }
+
+    #[test]
+    fn score_votes_ignores_undefined_and_maps_status() {
+        let scores = score_votes(&[vote(0, Some(1), 1, 0), vote(3, Some(2), 2, 0)], now());
+
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].votes, 1);
+        assert!((scores[0].score - 1.0).abs() < 1e-9);
+        assert_eq!(scores[0].status, ApplicationStatus::Yes.id());
+    }
+
+    #[test]
+    fn score_votes_shares_weight_within_lot_and_reviewer() {
+        // Three Yes runs by one reviewer on one lot vs. one independent No.
+        let scores = score_votes(
+            &[
+                vote(0, Some(1), 1, 0),
+                vote(0, Some(1), 1, 0),
+                vote(0, Some(1), 1, 0),
+                vote(2, Some(2), 2, 0),
+            ],
+            now(),
+        );
+
+        // The three runs share one vote: (3 × 1/9) / (3 × 1/9 + 1).
+        assert!((scores[0].score - 0.25).abs() < 1e-9);
+        assert_eq!(scores[0].status, ApplicationStatus::No.id());
+    }
+
+    #[test]
+    fn score_votes_prefers_recent_evidence() {
+        let scores = score_votes(&[vote(2, Some(1), 1, 730), vote(0, Some(2), 2, 0)], now());
+
+        // Two half-lives: the old No weighs a quarter of the fresh Yes.
+        assert!((scores[0].score - 0.8).abs() < 1e-9);
+        assert_eq!(scores[0].status, ApplicationStatus::Yes.id());
+    }
+
+    #[tokio::test]
+    async fn list_for_group_scores_approved_validations_per_reviewer() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let create = async |status, reviewed_by: Option<i64>| -> TestResult {
+            let id = ValidationBmc::create(
+                &ctx,
+                &mm,
+                ValidationForCreate::from(json!({
+                    "groupId": 1000,
+                    "createdBy": 1303,
+                    "cloneId": 1016,
+                    "application": 4,
+                    "status": status
+                })),
+            )
+            .await?;
+            if let Some(reviewed_by) = reviewed_by {
+                sqlx::query(
+                    "UPDATE validation SET review_state = 'approved', reviewed_by = $2 WHERE id = $1",
+                )
+                .bind(id)
+                .bind(reviewed_by)
+                .execute(mm.db())
+                .await?;
+            }
+            Ok(())
+        };
+        // Two Yes approved by one reviewer share a vote against a No approved by another;
+        // the draft No does not count.
+        create(0, Some(1000)).await?;
+        create(0, Some(1000)).await?;
+        create(2, Some(1304)).await?;
+        create(2, None).await?;
+
+        let scores = ValidationConsensusBmc::list_for_group(&ctx, &mm, 1000).await?;
+
+        let score = scores
+            .iter()
+            .find(|score| score.clone_id == 1016 && score.application == 4)
+            .ok_or("clone should be scored")?;
+        assert_eq!(score.votes, 3);
+        assert!((score.score - 0.5).abs() < 1e-6);
+        assert_eq!(score.status, ApplicationStatus::SoSo.id());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_for_group_counts_validations_from_before_reviews() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let mut tx = mm.db().begin().await?;
+        _dev_utils::replay_validation_review_migration(
+            &mut tx,
+            "INSERT INTO public.validation (group_id, created_by, clone_id, application, status) \
+             VALUES (1000, 1303, 1006, 5, 0), (1000, 1304, 1006, 5, 0) RETURNING id",
+        )
+        .await?;
+
+        let scores = ValidationConsensusBmc::list_in(&mut tx, 1000).await?;
+        tx.rollback().await?;
+
+        let score = scores
+            .iter()
+            .find(|score| score.clone_id == 1006 && score.application == 5)
+            .ok_or("clone should be scored")?;
+        assert_eq!(score.votes, 2);
+        assert_eq!(score.status, ApplicationStatus::Yes.id());
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use crate::ctx::Ctx;
 use crate::model::ModelManager;
-use crate::model::Result;
 use crate::model::base::{self, DbBmc};
+use crate::model::{Error, Result};
 use modql::field::Fields;
 use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
 use serde::{Deserialize, Serialize};
@@ -104,6 +104,46 @@ impl ValidationFileBmc {
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
         base::delete::<Self>(ctx, mm, id).await
     }
+
+    /// Total size in bytes of all files uploaded to the validations of a group.
+    pub async fn group_usage(ctx: &Ctx, mm: &ModelManager, group_id: i64) -> Result<i64> {
+        let _ = ctx;
+        let usage = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(f.size), 0)::BIGINT
+            FROM validation_file f
+            JOIN validation v ON v.id = f.validation_id
+            WHERE v.group_id = $1
+            "#,
+        )
+        .bind(group_id)
+        .fetch_one(mm.db())
+        .await?;
+        Ok(usage)
+    }
+
+    /// Replaces the metadata extracted from the file's content.
+    pub async fn set_meta(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        meta: serde_json::Value,
+    ) -> Result<()> {
+        let _ = ctx;
+        let count = sqlx::query("UPDATE validation_file SET meta = $2 WHERE id = $1")
+            .bind(id)
+            .bind(meta)
+            .execute(mm.db())
+            .await?
+            .rows_affected();
+        if count == 0 {
+            return Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -260,6 +300,60 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_validation_file_group_usage_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = ValidationFileBmc::create(
+            &ctx,
+            &mm,
+            ValidationFileForCreate {
+                name: Some("test_group_usage_ok".to_string()),
+                created_by: 1303,
+                validation_id: 1011,
+                hash: String::new(),
+                size: 4096,
+                extension: "pdf".into(),
+                description: None,
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .await?;
+
+        assert!(ValidationFileBmc::group_usage(&ctx, &mm, 1000).await? >= 4096);
+        assert_eq!(ValidationFileBmc::group_usage(&ctx, &mm, -1).await?, 0);
+
+        ValidationFileBmc::delete(&ctx, &mm, id).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation_file_set_meta_ok() -> TestResult {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let tname = "test_validation_file_set_meta_ok";
+        let seeds = _dev_utils::get_validation_file_seed(tname);
+        let fx_validation_file = _dev_utils::seed_validation_files(&ctx, &mm, &seeds)
+            .await?
+            .remove(0);
+        let meta = json!({"acquisition": {"format": "mcd"}, "warnings": []});
+
+        ValidationFileBmc::set_meta(&ctx, &mm, fx_validation_file.id, meta.clone()).await?;
+
+        let validation_file = ValidationFileBmc::get(&ctx, &mm, fx_validation_file.id).await?;
+        assert_eq!(validation_file.meta, Some(meta));
+        assert!(matches!(
+            ValidationFileBmc::set_meta(&ctx, &mm, 100, json!({})).await,
+            Err(Error::EntityNotFound {
+                entity: "validation_file",
+                id: 100
+            })
+        ));
+
+        ValidationFileBmc::delete(&ctx, &mm, fx_validation_file.id).await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_validation_file_delete_err_not_found() -> Result<()> {
         let mm = _dev_utils::init_test().await;
@@ -0,0 +1,413 @@
+#![allow(clippy::module_name_repetitions)]
+use serde::{Deserialize, Serialize};
+
+/// `PanelElement.dilution_type` value for concentrations in µg/mL.
+pub const DILUTION_TYPE_UG_PER_ML: i64 = 0;
+/// `PanelElement.dilution_type` value for 1:x dilutions of the conjugate stock.
+pub const DILUTION_TYPE_DILUTION: i64 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConcentrationUnit {
+    /// The unit of panel element concentrations and conjugate stocks.
+    #[default]
+    #[serde(rename = "ug/mL")]
+    UgPerMl,
+    #[serde(rename = "mg/mL")]
+    MgPerMl,
+    #[serde(rename = "nM")]
+    Nanomolar,
+    #[serde(rename = "dilution")]
+    Dilution,
+}
+
+impl ConcentrationUnit {
+    /// Canonical ASCII code, as stored in `validation.concentration_unit`.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::UgPerMl => "ug/mL",
+            Self::MgPerMl => "mg/mL",
+            Self::Nanomolar => "nM",
+            Self::Dilution => "dilution",
+        }
+    }
+
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::UgPerMl => "µg/mL",
+            Self::MgPerMl => "mg/mL",
+            Self::Nanomolar => "nM",
+            Self::Dilution => "1:x",
+        }
+    }
+
+    pub fn parse(unit: &str) -> Result<Self> {
+        let normalized = unit
+            .trim()
+            .replace(['µ', 'μ'], "u")
+            .replace(' ', "")
+            .to_ascii_lowercase();
+        match normalized.as_str() {
+            "ug/ml" | "mcg/ml" | "ugml" | "ug/ml-1" | "mg/l" => Ok(Self::UgPerMl),
+            "mg/ml" | "mgml" | "g/l" => Ok(Self::MgPerMl),
+            "nm" | "nmol/l" | "nmol" => Ok(Self::Nanomolar),
+            "dilution" | "1:" | "1/" | "1:x" | "1/x" | "1/__" => Ok(Self::Dilution),
+            _ => Err(Error::UnknownUnit(unit.to_string())),
+        }
+    }
+}
+
+/// Extra facts needed to convert between unit families.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConversionContext {
+    /// Molecular weight of the antibody in kDa, for molar conversions.
+    pub mw_kda: Option<f64>,
+    /// Concentration of the stock in µg/mL, for dilution conversions.
+    pub stock_ug_per_ml: Option<f64>,
+}
+
+/// Molecular weight assumed for IgG antibodies when none is known.
+pub const IGG_MW_KDA: f64 = 150.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: ConcentrationUnit,
+}
+
+impl Quantity {
+    pub const fn new(value: f64, unit: ConcentrationUnit) -> Self {
+        Self { value, unit }
+    }
+
+    pub const fn ug_per_ml(value: f64) -> Self {
+        Self::new(value, ConcentrationUnit::UgPerMl)
+    }
+
+    pub const fn dilution(factor: f64) -> Self {
+        Self::new(factor, ConcentrationUnit::Dilution)
+    }
+
+    /// Parses legacy free-text values such as `"2 µg/ml"`, `"1:200"` or `"0,5"` with a
+    /// separately stored unit like `"mg/mL"`.
+    pub fn parse(value: &str, unit: Option<&str>) -> Result<Self> {
+        let raw = value.trim();
+        if raw.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        if let Some(factor) = parse_dilution(raw) {
+            return Self::checked(factor, ConcentrationUnit::Dilution, raw);
+        }
+
+        let split = raw
+            .char_indices()
+            .find(|(_, c)| !(c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+' | 'e' | 'E')))
+            .map_or(raw.len(), |(idx, _)| idx);
+        let (number, suffix) = raw.split_at(split);
+        let number = number.trim().replace(',', ".");
+        let amount: f64 = number
+            .parse()
+            .map_err(|_| Error::InvalidNumber(raw.to_string()))?;
+
+        let suffix = suffix.trim();
+        let unit = match (
+            suffix.is_empty(),
+            unit.map(str::trim).filter(|u| !u.is_empty()),
+        ) {
+            (false, _) => parse_unit_with_scale(suffix)?,
+            (true, Some(unit)) => parse_unit_with_scale(unit)?,
+            (true, None) => return Err(Error::MissingUnit(raw.to_string())),
+        };
+
+        let amount = match unit.1 {
+            Scale::Milli => amount / 1000.0,
+            Scale::One => amount,
+            Scale::Kilo => amount * 1000.0,
+        };
+        Self::checked(amount, unit.0, raw)
+    }
+
+    /// Maps `PanelElement.dilution_type` and `concentration` onto a quantity.
+    pub fn from_dilution_type(dilution_type: i64, concentration: f64) -> Option<Self> {
+        match dilution_type {
+            DILUTION_TYPE_UG_PER_ML => Some(Self::ug_per_ml(concentration)),
+            DILUTION_TYPE_DILUTION => Some(Self::dilution(concentration)),
+            _ => None,
+        }
+    }
+
+    pub fn to_ug_per_ml(&self, context: &ConversionContext) -> Option<f64> {
+        let value = match self.unit {
+            ConcentrationUnit::UgPerMl => self.value,
+            ConcentrationUnit::MgPerMl => self.value * 1000.0,
+            ConcentrationUnit::Nanomolar => {
+                self.value * context.mw_kda.unwrap_or(IGG_MW_KDA) / 1000.0
+            }
+            ConcentrationUnit::Dilution => {
+                if self.value <= 0.0 {
+                    return None;
+                }
+                context.stock_ug_per_ml? / self.value
+            }
+        };
+        Some(value)
+    }
+
+    pub fn convert(&self, target: ConcentrationUnit, context: &ConversionContext) -> Option<Self> {
+        if self.unit == target {
+            return Some(*self);
+        }
+        let ug_per_ml = self.to_ug_per_ml(context)?;
+        let value = match target {
+            ConcentrationUnit::UgPerMl => ug_per_ml,
+            ConcentrationUnit::MgPerMl => ug_per_ml / 1000.0,
+            ConcentrationUnit::Nanomolar => {
+                ug_per_ml * 1000.0 / context.mw_kda.unwrap_or(IGG_MW_KDA)
+            }
+            ConcentrationUnit::Dilution => {
+                if ug_per_ml <= 0.0 {
+                    return None;
+                }
+                context.stock_ug_per_ml? / ug_per_ml
+            }
+        };
+        Some(Self::new(value, target))
+    }
+
+    /// Volume of stock in µL to reach this quantity in `total_volume_ul`.
+    pub fn stock_volume_ul(
+        &self,
+        total_volume_ul: f64,
+        context: &ConversionContext,
+    ) -> Option<f64> {
+        let target = self.to_ug_per_ml(context)?;
+        let stock = context.stock_ug_per_ml.filter(|stock| *stock > 0.0)?;
+        Some(total_volume_ul * target / stock)
+    }
+
+    fn checked(value: f64, unit: ConcentrationUnit, raw: &str) -> Result<Self> {
+        if value.is_finite() && value > 0.0 {
+            Ok(Self::new(value, unit))
+        } else {
+            Err(Error::InvalidNumber(raw.to_string()))
+        }
+    }
+}
+
+impl core::fmt::Display for Quantity {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.unit {
+            ConcentrationUnit::Dilution => write!(fmt, "1:{}", format_value(self.value)),
+            unit => write!(fmt, "{} {}", format_value(self.value), unit.symbol()),
+        }
+    }
+}
+
+fn parse_dilution(raw: &str) -> Option<f64> {
+    let compact = raw.replace(' ', "");
+    let rest = compact
+        .strip_prefix("1:")
+        .or_else(|| compact.strip_prefix("1/"))?;
+    rest.replace(',', ".").parse().ok()
+}
+
+/// Significant digits kept when a value is written out, enough for any measured
+/// concentration while hiding the binary noise of scaling, e.g. `0.7000000000000001`.
+const SIGNIFICANT_DIGITS: i32 = 10;
+
+/// `value` as the shortest text of at most [`SIGNIFICANT_DIGITS`] significant digits.
+pub fn format_value(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = usize::try_from(SIGNIFICANT_DIGITS - 1 - magnitude).unwrap_or(0);
+    let text = format!("{value:.decimals$}");
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+/// Factor between an accepted input unit and its canonical unit. Kept exact rather
+/// than as a float factor, since `700.0 * 0.001` is not `0.7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scale {
+    Milli,
+    One,
+    Kilo,
+}
+
+/// Units that are accepted on input but normalized to a canonical unit with a scale.
+fn parse_unit_with_scale(unit: &str) -> Result<(ConcentrationUnit, Scale)> {
+    let normalized = unit
+        .trim()
+        .replace(['µ', 'μ'], "u")
+        .replace(' ', "")
+        .to_ascii_lowercase();
+    match normalized.as_str() {
+        "ng/ml" | "ug/l" => Ok((ConcentrationUnit::UgPerMl, Scale::Milli)),
+        "pm" | "pmol/l" => Ok((ConcentrationUnit::Nanomolar, Scale::Milli)),
+        "um" | "umol/l" => Ok((ConcentrationUnit::Nanomolar, Scale::Kilo)),
+        _ => ConcentrationUnit::parse(unit).map(|unit| (unit, Scale::One)),
+    }
+}
+
+/// Outcome of rewriting legacy concentration values into canonical units.
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub normalized: usize,
+    pub unchanged: usize,
+    pub unparseable: Vec<UnparseableValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnparseableValue {
+    pub entity: &'static str,
+    pub id: i64,
+    pub value: String,
+    pub error: String,
+}
+
+/// Canonical `(concentration, concentration_unit)` strings for a legacy pair.
+pub fn normalize_legacy(value: &str, unit: Option<&str>) -> Result<(String, String)> {
+    let quantity = Quantity::parse(value, unit)?;
+    Ok((
+        format_value(quantity.value),
+        quantity.unit.code().to_string(),
+    ))
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Error {
+    Empty,
+    InvalidNumber(String),
+    UnknownUnit(String),
+    MissingUnit(String),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_legacy_notations() {
+        assert_eq!(
+            Quantity::parse("2 µg/ml", None),
+            Ok(Quantity::ug_per_ml(2.0))
+        );
+        assert_eq!(
+            Quantity::parse("0,5", Some("mg/mL")),
+            Ok(Quantity::new(0.5, ConcentrationUnit::MgPerMl))
+        );
+        assert_eq!(
+            Quantity::parse("1:200", None),
+            Ok(Quantity::dilution(200.0))
+        );
+        assert_eq!(
+            Quantity::parse("1 / 50", None),
+            Ok(Quantity::dilution(50.0))
+        );
+        assert_eq!(
+            Quantity::parse("100", Some("1:")),
+            Ok(Quantity::dilution(100.0))
+        );
+        assert_eq!(
+            Quantity::parse("10nM", None),
+            Ok(Quantity::new(10.0, ConcentrationUnit::Nanomolar))
+        );
+        assert_eq!(
+            Quantity::parse("500 ng/mL", None),
+            Ok(Quantity::ug_per_ml(0.5))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_ambiguous_values() {
+        assert_eq!(Quantity::parse("", None), Err(Error::Empty));
+        assert_eq!(
+            Quantity::parse("2", None),
+            Err(Error::MissingUnit("2".into()))
+        );
+        assert_eq!(
+            Quantity::parse("a lot", Some("ug/mL")),
+            Err(Error::InvalidNumber("a lot".into()))
+        );
+        assert_eq!(
+            Quantity::parse("2 drops", None),
+            Err(Error::UnknownUnit("drops".into()))
+        );
+    }
+
+    #[test]
+    fn convert_goes_through_ug_per_ml() {
+        let context = ConversionContext {
+            mw_kda: None,
+            stock_ug_per_ml: Some(500.0),
+        };
+
+        let dilution = Quantity::dilution(250.0);
+        assert_eq!(dilution.to_ug_per_ml(&context), Some(2.0));
+        assert_eq!(
+            Quantity::ug_per_ml(2.0).convert(ConcentrationUnit::Dilution, &context),
+            Some(Quantity::dilution(250.0))
+        );
+        assert_eq!(
+            Quantity::new(10.0, ConcentrationUnit::Nanomolar).to_ug_per_ml(&context),
+            Some(1.5)
+        );
+        assert_eq!(
+            dilution.convert(ConcentrationUnit::UgPerMl, &ConversionContext::default()),
+            None
+        );
+        assert_eq!(
+            Quantity::ug_per_ml(2.0).stock_volume_ul(100.0, &context),
+            Some(0.4)
+        );
+    }
+
+    #[test]
+    fn normalize_legacy_returns_canonical_codes() {
+        assert_eq!(
+            normalize_legacy("2,50 ug/ml", None),
+            Ok(("2.5".to_string(), "ug/mL".to_string()))
+        );
+        assert_eq!(
+            normalize_legacy("1/100", Some("µg/mL")),
+            Ok(("100".to_string(), "dilution".to_string()))
+        );
+        assert_eq!(
+            normalize_legacy("700 ng/mL", None),
+            Ok(("0.7".to_string(), "ug/mL".to_string()))
+        );
+        assert_eq!(
+            normalize_legacy("0.3 uM", None),
+            Ok(("300".to_string(), "nM".to_string()))
+        );
+        assert_eq!(format_value(1.0 / 3.0), "0.3333333333");
+        assert_eq!(format_value(0.000_012_5), "0.0000125");
+        assert_eq!(format_value(2_500_000.0), "2500000");
+    }
+
+    #[test]
+    fn display_uses_symbols() {
+        assert_eq!(Quantity::ug_per_ml(2.5).to_string(), "2.5 µg/mL");
+        assert_eq!(Quantity::dilution(200.0).to_string(), "1:200");
+        assert_eq!(
+            Quantity::from_dilution_type(DILUTION_TYPE_DILUTION, 100.0),
+            Some(Quantity::dilution(100.0))
+        );
+        assert_eq!(Quantity::from_dilution_type(7, 1.0), None);
+    }
+}
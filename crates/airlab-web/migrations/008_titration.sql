@@ -0,0 +1,39 @@
+BEGIN;
+
+CREATE TABLE public.titration (
+    id BIGSERIAL PRIMARY KEY,
+    group_id BIGINT NOT NULL,
+    conjugate_id BIGINT NOT NULL,
+    validation_id BIGINT NULL,
+    created_by BIGINT NOT NULL,
+    points JSONB NOT NULL DEFAULT '[]'::jsonb,
+    optimal_concentration DOUBLE PRECISION NULL,
+    note TEXT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_titration_group_id
+    ON public.titration (group_id);
+
+CREATE INDEX IF NOT EXISTS idx_titration_conjugate_id
+    ON public.titration (conjugate_id);
+
+CREATE INDEX IF NOT EXISTS idx_titration_validation_id
+    ON public.titration (validation_id);
+
+ALTER TABLE public.titration
+    ADD CONSTRAINT titration_conjugate_id_fkey
+    FOREIGN KEY (conjugate_id)
+    REFERENCES public.conjugate(id)
+    ON DELETE CASCADE
+    ON UPDATE RESTRICT;
+
+ALTER TABLE public.titration
+    ADD CONSTRAINT titration_validation_id_fkey
+    FOREIGN KEY (validation_id)
+    REFERENCES public.validation(id)
+    ON DELETE SET NULL
+    ON UPDATE RESTRICT;
+
+COMMIT;
@@ -0,0 +1,52 @@
+BEGIN;
+
+CREATE TABLE public.conjugation_batch (
+    id BIGSERIAL PRIMARY KEY,
+    group_id BIGINT NOT NULL,
+    lot_id BIGINT NOT NULL,
+    tag_id BIGINT NOT NULL,
+    conjugate_id BIGINT NULL,
+    created_by BIGINT NOT NULL,
+    labeled_by BIGINT NULL,
+    kit TEXT NULL,
+    buffer TEXT NULL,
+    antibody_amount_ug DOUBLE PRECISION NOT NULL,
+    measured_concentration DOUBLE PRECISION NULL,
+    final_volume_ul DOUBLE PRECISION NULL,
+    steps JSONB NOT NULL DEFAULT '[]'::jsonb,
+    note TEXT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_conjugation_batch_group_id
+    ON public.conjugation_batch (group_id);
+
+CREATE INDEX IF NOT EXISTS idx_conjugation_batch_lot_id
+    ON public.conjugation_batch (lot_id);
+
+CREATE INDEX IF NOT EXISTS idx_conjugation_batch_conjugate_id
+    ON public.conjugation_batch (conjugate_id);
+
+ALTER TABLE public.conjugation_batch
+    ADD CONSTRAINT conjugation_batch_lot_id_fkey
+    FOREIGN KEY (lot_id)
+    REFERENCES public.lot(id)
+    ON DELETE RESTRICT
+    ON UPDATE RESTRICT;
+
+ALTER TABLE public.conjugation_batch
+    ADD CONSTRAINT conjugation_batch_tag_id_fkey
+    FOREIGN KEY (tag_id)
+    REFERENCES public.tag(id)
+    ON DELETE RESTRICT
+    ON UPDATE RESTRICT;
+
+ALTER TABLE public.conjugation_batch
+    ADD CONSTRAINT conjugation_batch_conjugate_id_fkey
+    FOREIGN KEY (conjugate_id)
+    REFERENCES public.conjugate(id)
+    ON DELETE SET NULL
+    ON UPDATE RESTRICT;
+
+COMMIT;
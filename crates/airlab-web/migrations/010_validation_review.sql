@@ -0,0 +1,44 @@
+BEGIN;
+
+-- New validations start out as drafts so they stay editable until submitted.
+ALTER TABLE public.validation
+    ADD COLUMN IF NOT EXISTS review_state TEXT NOT NULL DEFAULT 'draft',
+    ADD COLUMN IF NOT EXISTS submitted_by BIGINT NULL,
+    ADD COLUMN IF NOT EXISTS submitted_at TIMESTAMP WITH TIME ZONE NULL,
+    ADD COLUMN IF NOT EXISTS reviewed_by BIGINT NULL,
+    ADD COLUMN IF NOT EXISTS reviewed_at TIMESTAMP WITH TIME ZONE NULL,
+    ADD COLUMN IF NOT EXISTS review_comment TEXT NULL;
+
+ALTER TABLE public.validation
+    ADD CONSTRAINT validation_review_state_check
+    CHECK (review_state IN ('draft', 'submitted', 'approved', 'rejected'));
+
+-- Validations from before reviews existed were the evidence everyone relied on, so they
+-- count as approved rather than dropping out of consensus and cross-reactivity.
+UPDATE public.validation SET review_state = 'approved';
+
+CREATE INDEX IF NOT EXISTS idx_validation_group_review_state
+    ON public.validation (group_id, review_state);
+
+CREATE TABLE public.validation_review_event (
+    id BIGSERIAL PRIMARY KEY,
+    validation_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    from_state TEXT NOT NULL,
+    to_state TEXT NOT NULL,
+    actor_id BIGINT NOT NULL,
+    comment TEXT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_validation_review_event_validation_id
+    ON public.validation_review_event (validation_id);
+
+ALTER TABLE public.validation_review_event
+    ADD CONSTRAINT validation_review_event_validation_id_fkey
+    FOREIGN KEY (validation_id)
+    REFERENCES public.validation(id)
+    ON DELETE CASCADE
+    ON UPDATE RESTRICT;
+
+COMMIT;
@@ -0,0 +1,43 @@
+BEGIN;
+
+CREATE TABLE public.protocol_template (
+    id BIGSERIAL PRIMARY KEY,
+    group_id BIGINT NOT NULL,
+    created_by BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    application BIGINT NOT NULL,
+    description TEXT NULL,
+    incubation_conditions TEXT NULL,
+    fixation BIGINT NULL,
+    fixation_notes TEXT NULL,
+    antigen_retrieval_type TEXT NULL,
+    antigen_retrieval_time TEXT NULL,
+    antigen_retrieval_temperature TEXT NULL,
+    saponin BOOLEAN NULL,
+    saponin_concentration TEXT NULL,
+    methanol_treatment BOOLEAN NULL,
+    methanol_treatment_concentration TEXT NULL,
+    surface_staining BOOLEAN NULL,
+    surface_staining_concentration TEXT NULL,
+    is_archived BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_protocol_template_group_application_name
+    ON public.protocol_template (group_id, application, name);
+
+ALTER TABLE public.validation
+    ADD COLUMN IF NOT EXISTS protocol_template_id BIGINT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_validation_protocol_template_id
+    ON public.validation (protocol_template_id);
+
+ALTER TABLE public.validation
+    ADD CONSTRAINT validation_protocol_template_id_fkey
+    FOREIGN KEY (protocol_template_id)
+    REFERENCES public.protocol_template(id)
+    ON DELETE SET NULL
+    ON UPDATE RESTRICT;
+
+COMMIT;
@@ -0,0 +1,36 @@
+BEGIN;
+
+-- Content-addressed validation file blobs, keyed by SHA-256. `ref_count` is the number
+-- of validation_file rows pointing at the blob and is maintained by the trigger below.
+CREATE TABLE public.blob (
+    sha256 TEXT PRIMARY KEY CHECK (sha256 ~ '^[0-9a-f]{64}$'),
+    size BIGINT NOT NULL,
+    ref_count BIGINT NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    verified_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_blob_unreferenced
+    ON public.blob (created_at)
+    WHERE ref_count = 0;
+
+-- Legacy rows carry an MD5 hash and are not counted until they are imported.
+CREATE OR REPLACE FUNCTION public.validation_file_blob_ref() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') AND OLD.hash ~ '^[0-9a-f]{64}$' THEN
+        UPDATE public.blob SET ref_count = ref_count - 1 WHERE sha256 = OLD.hash;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') AND NEW.hash ~ '^[0-9a-f]{64}$' THEN
+        INSERT INTO public.blob (sha256, size, ref_count)
+        VALUES (NEW.hash, NEW.size, 1)
+        ON CONFLICT (sha256) DO UPDATE SET ref_count = public.blob.ref_count + 1;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER validation_file_blob_ref
+    AFTER INSERT OR DELETE OR UPDATE OF hash ON public.validation_file
+    FOR EACH ROW EXECUTE FUNCTION public.validation_file_blob_ref();
+
+COMMIT;
@@ -0,0 +1,49 @@
+BEGIN;
+
+-- Every write to a table shown in the search shadows takes the next version and is
+-- announced on the `search_shadow` channel, so each airlab-web instance can patch its
+-- in-memory shadows. Notifications are delivered on commit, but the version is taken at
+-- write time, so a transaction that commits later can carry a lower version; listeners
+-- must not rely on versions arriving in order.
+CREATE SEQUENCE IF NOT EXISTS public.search_shadow_version;
+
+CREATE OR REPLACE FUNCTION public.search_shadow_notify() RETURNS trigger AS $$
+DECLARE
+    row_id BIGINT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        row_id := OLD.id;
+    ELSE
+        row_id := NEW.id;
+    END IF;
+    PERFORM pg_notify(
+        'search_shadow',
+        json_build_object(
+            'table', TG_TABLE_NAME,
+            'id', row_id,
+            'version', nextval('public.search_shadow_version')
+        )::text
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    shadowed TEXT;
+BEGIN
+    FOREACH shadowed IN ARRAY ARRAY[
+        'user', 'member', 'group', 'clone', 'protein', 'provider', 'species', 'tag', 'lot',
+        'conjugate', 'panel', 'panel_element', 'validation', 'storage', 'collection'
+    ] LOOP
+        EXECUTE format(
+            'CREATE TRIGGER search_shadow_notify
+                AFTER INSERT OR UPDATE OR DELETE ON public.%I
+                FOR EACH ROW EXECUTE FUNCTION public.search_shadow_notify()',
+            shadowed
+        );
+    END LOOP;
+END;
+$$;
+
+COMMIT;
@@ -0,0 +1,38 @@
+BEGIN;
+
+-- A named search request, private to the member who saved it unless shared with the
+-- group. `matched_ids` holds what the search matched when it last ran, so a run can tell
+-- which items started matching since.
+CREATE TABLE public.saved_search (
+    id BIGSERIAL PRIMARY KEY,
+    group_id BIGINT NOT NULL,
+    created_by BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NULL,
+    request JSONB NOT NULL,
+    is_shared BOOLEAN NOT NULL DEFAULT FALSE,
+    notify BOOLEAN NOT NULL DEFAULT FALSE,
+    matched_ids BIGINT[] NOT NULL DEFAULT '{}',
+    last_run_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_saved_search_group_created_by_name
+    ON public.saved_search (group_id, created_by, name);
+
+ALTER TABLE public.saved_search
+    ADD CONSTRAINT saved_search_group_id_fkey
+    FOREIGN KEY (group_id)
+    REFERENCES public."group"(id)
+    ON DELETE CASCADE
+    ON UPDATE RESTRICT;
+
+ALTER TABLE public.saved_search
+    ADD CONSTRAINT saved_search_created_by_fkey
+    FOREIGN KEY (created_by)
+    REFERENCES public.member(id)
+    ON DELETE CASCADE
+    ON UPDATE RESTRICT;
+
+COMMIT;
@@ -0,0 +1,12 @@
+BEGIN;
+
+-- One-time rewrites of existing rows that are done in Rust at startup, so they go through
+-- the same parsers as the API. A row marks a rewrite as done; `report` keeps what it could
+-- not convert.
+CREATE TABLE public.data_migration (
+    name TEXT PRIMARY KEY,
+    ran_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    report JSONB NULL
+);
+
+COMMIT;
@@ -0,0 +1,30 @@
+BEGIN;
+
+-- Last time a blob was written, referenced or released. GC only removes unreferenced
+-- blobs that have not been touched within its grace period, so content that is written
+-- again while it waits for collection is kept.
+ALTER TABLE public.blob ADD COLUMN touched_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE public.blob SET touched_at = created_at;
+
+DROP INDEX IF EXISTS public.idx_blob_unreferenced;
+CREATE INDEX IF NOT EXISTS idx_blob_unreferenced
+    ON public.blob (touched_at)
+    WHERE ref_count = 0;
+
+CREATE OR REPLACE FUNCTION public.validation_file_blob_ref() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') AND OLD.hash ~ '^[0-9a-f]{64}$' THEN
+        UPDATE public.blob SET ref_count = ref_count - 1, touched_at = NOW()
+        WHERE sha256 = OLD.hash;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') AND NEW.hash ~ '^[0-9a-f]{64}$' THEN
+        INSERT INTO public.blob (sha256, size, ref_count)
+        VALUES (NEW.hash, NEW.size, 1)
+        ON CONFLICT (sha256) DO UPDATE
+            SET ref_count = public.blob.ref_count + 1, touched_at = NOW();
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+COMMIT;
@@ -0,0 +1,477 @@
+//! FCS 3.0 and 3.1 list-mode files from flow cytometers: a fixed header with segment
+//! offsets, a TEXT segment of delimited keyword/value pairs and a DATA segment with one
+//! value per channel and event.
+
+use crate::acquisition::{ChannelStats, FlowChannel, FlowMeta};
+use camino::Utf8Path;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+
+const HEADER_LEN: usize = 58;
+/// Larger TEXT segments are not read; real ones are a few kilobytes.
+const MAX_TEXT_BYTES: u64 = 16 * 1024 * 1024;
+/// The median is taken from at most this many evenly spaced events.
+const MEDIAN_SAMPLE: u64 = 10_000;
+/// More parameters than any cytometer records; larger `$PAR` values mean the file is not
+/// FCS and would only make the channel list huge.
+const MAX_PARAMETERS: u64 = 10_000;
+
+/// `None` for files that are not FCS 3.x.
+pub fn read(path: &Utf8Path) -> std::io::Result<Option<FlowMeta>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0; HEADER_LEN];
+    match file.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let Some(header) = Header::parse(&header) else {
+        return Ok(None);
+    };
+    if header.text_end < header.text_start || header.text_end - header.text_start >= MAX_TEXT_BYTES
+    {
+        return Ok(None);
+    }
+
+    file.seek(SeekFrom::Start(header.text_start))?;
+    let mut text = vec![];
+    (&mut file)
+        .take(header.text_end - header.text_start + 1)
+        .read_to_end(&mut text)?;
+    let Some(keywords) = parse_text(&text) else {
+        return Ok(None);
+    };
+    let keyword = |key: &str| keywords.get(key).map(String::as_str);
+    let number = |key: &str| keyword(key).and_then(|value| value.parse::<u64>().ok());
+
+    let parameters = number("$PAR").unwrap_or_default();
+    if parameters > MAX_PARAMETERS {
+        return Ok(None);
+    }
+    let event_count = number("$TOT").unwrap_or_default();
+    let mut channels = (1..=parameters)
+        .map(|n| FlowChannel {
+            name: keyword(&format!("$P{n}N")).map_or_else(|| format!("P{n}"), ToString::to_string),
+            stain: keyword(&format!("$P{n}S"))
+                .filter(|stain| !stain.is_empty())
+                .map(ToString::to_string),
+            stats: None,
+        })
+        .collect::<Vec<_>>();
+
+    // Offsets beyond 99,999,999 do not fit the header and are only in the TEXT segment.
+    let (data_start, data_end) = if header.data_start == 0 && header.data_end == 0 {
+        (
+            number("$BEGINDATA").unwrap_or_default(),
+            number("$ENDDATA").unwrap_or_default(),
+        )
+    } else {
+        (header.data_start, header.data_end)
+    };
+    if let Some(layout) = Layout::new(&keywords, parameters)
+        && data_end > data_start
+    {
+        file.seek(SeekFrom::Start(data_start))?;
+        let available = (data_end - data_start + 1) / layout.event_bytes;
+        let stats = layout.summarize(&mut file, event_count.min(available))?;
+        for (channel, stats) in channels.iter_mut().zip(stats) {
+            channel.stats = stats;
+        }
+    }
+
+    Ok(Some(FlowMeta {
+        format: "fcs".to_string(),
+        version: header.version,
+        acquired_at: acquired_at(keyword("$DATE"), keyword("$BTIM")),
+        cytometer: keyword("$CYT").map(ToString::to_string),
+        event_count,
+        channels,
+    }))
+}
+
+struct Header {
+    version: String,
+    text_start: u64,
+    text_end: u64,
+    data_start: u64,
+    data_end: u64,
+}
+
+impl Header {
+    fn parse(bytes: &[u8; HEADER_LEN]) -> Option<Self> {
+        let version = std::str::from_utf8(&bytes[..6]).ok()?;
+        if version != "FCS3.0" && version != "FCS3.1" {
+            return None;
+        }
+        let offset = |at: usize| -> Option<u64> {
+            let field = std::str::from_utf8(&bytes[at..at + 8]).ok()?.trim();
+            if field.is_empty() {
+                Some(0)
+            } else {
+                field.parse().ok()
+            }
+        };
+        Some(Self {
+            version: version.to_string(),
+            text_start: offset(10)?,
+            text_end: offset(18)?,
+            data_start: offset(26)?,
+            data_end: offset(34)?,
+        })
+    }
+}
+
+/// Keywords, upper-cased, and their values. The first byte is the delimiter; a doubled
+/// delimiter stands for the delimiter itself.
+fn parse_text(text: &[u8]) -> Option<HashMap<String, String>> {
+    let (&delimiter, rest) = text.split_first()?;
+    let mut fields = vec![];
+    let mut field = vec![];
+    let mut at = 0;
+    while let Some(&byte) = rest.get(at) {
+        if byte != delimiter {
+            field.push(byte);
+        } else if rest.get(at + 1) == Some(&delimiter) {
+            field.push(delimiter);
+            at += 1;
+        } else {
+            fields.push(std::mem::take(&mut field));
+        }
+        at += 1;
+    }
+    if !field.is_empty() {
+        fields.push(field);
+    }
+
+    let keywords = fields
+        .chunks_exact(2)
+        .map(|pair| {
+            (
+                String::from_utf8_lossy(&pair[0])
+                    .trim()
+                    .to_ascii_uppercase(),
+                String::from_utf8_lossy(&pair[1]).trim().to_string(),
+            )
+        })
+        .collect();
+    Some(keywords)
+}
+
+/// `$DATE` like `12-MAY-2023` and `$BTIM` like `14:03:22` or `14:03:22.45`.
+fn acquired_at(date: Option<&str>, time: Option<&str>) -> Option<String> {
+    let date = chrono::NaiveDate::parse_from_str(date?, "%d-%b-%Y").ok()?;
+    let time = time
+        .and_then(|time| time.get(..8))
+        .and_then(|time| chrono::NaiveTime::parse_from_str(time, "%H:%M:%S").ok());
+    Some(match time {
+        Some(time) => date.and_time(time).format("%Y-%m-%dT%H:%M:%S").to_string(),
+        None => date.format("%Y-%m-%d").to_string(),
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Value {
+    /// Unsigned integer of this many bytes, masked to the bits its range needs.
+    Int {
+        bytes: usize,
+        mask: u64,
+    },
+    Float,
+    Double,
+}
+
+/// How the values of one event are laid out in the DATA segment.
+struct Layout {
+    values: Vec<Value>,
+    little_endian: bool,
+    event_bytes: u64,
+}
+
+impl Layout {
+    /// `None` for data the summary does not read: ASCII values, non list-mode data or
+    /// integers that are not a whole number of bytes.
+    fn new(keywords: &HashMap<String, String>, parameters: u64) -> Option<Self> {
+        let keyword = |key: &str| keywords.get(key).map(String::as_str);
+        if keyword("$MODE").is_some_and(|mode| mode != "L") || parameters == 0 {
+            return None;
+        }
+        let datatype = keyword("$DATATYPE")?;
+        let mut values = vec![];
+        for n in 1..=parameters {
+            let bits = keyword(&format!("$P{n}B"))?.parse::<usize>().ok()?;
+            let value = match datatype {
+                "I" if matches!(bits, 8 | 16 | 32 | 64) => {
+                    let range = keyword(&format!("$P{n}R"))
+                        .and_then(|range| range.parse::<f64>().ok())
+                        .filter(|range| *range >= 1.0);
+                    let mask = match range {
+                        // Values below the range need ceil(log2(range)) bits.
+                        Some(range) if range.log2().ceil() < 64.0 => {
+                            (1u64 << range.log2().ceil() as u32) - 1
+                        }
+                        _ => u64::MAX,
+                    };
+                    Value::Int {
+                        bytes: bits / 8,
+                        mask,
+                    }
+                }
+                "F" if bits == 32 => Value::Float,
+                "D" if bits == 64 => Value::Double,
+                _ => return None,
+            };
+            values.push(value);
+        }
+        let event_bytes = values
+            .iter()
+            .map(|value| match value {
+                Value::Int { bytes, .. } => *bytes as u64,
+                Value::Float => 4,
+                Value::Double => 8,
+            })
+            .sum();
+        Some(Self {
+            values,
+            little_endian: keyword("$BYTEORD").is_none_or(|order| order.starts_with('1')),
+            event_bytes,
+        })
+    }
+
+    /// Statistics of each channel over `events` events read from `data`.
+    fn summarize(
+        &self,
+        data: &mut impl Read,
+        events: u64,
+    ) -> std::io::Result<Vec<Option<ChannelStats>>> {
+        let stride = events.div_ceil(MEDIAN_SAMPLE).max(1);
+        let mut summaries = vec![Summary::default(); self.values.len()];
+        let mut event = vec![0; usize::try_from(self.event_bytes).unwrap_or_default()];
+        for index in 0..events {
+            data.read_exact(&mut event)?;
+            let mut at = 0;
+            for (value, summary) in self.values.iter().zip(&mut summaries) {
+                let (number, width) = self.decode(*value, &event[at..]);
+                at += width;
+                summary.add(number, index % stride == 0);
+            }
+        }
+        Ok(summaries.into_iter().map(Summary::finish).collect())
+    }
+
+    fn decode(&self, value: Value, bytes: &[u8]) -> (f64, usize) {
+        let mut buf = [0; 8];
+        match value {
+            Value::Int { bytes: width, mask } => {
+                if self.little_endian {
+                    buf[..width].copy_from_slice(&bytes[..width]);
+                    ((u64::from_le_bytes(buf) & mask) as f64, width)
+                } else {
+                    buf[8 - width..].copy_from_slice(&bytes[..width]);
+                    ((u64::from_be_bytes(buf) & mask) as f64, width)
+                }
+            }
+            Value::Float => {
+                let mut buf = [0; 4];
+                buf.copy_from_slice(&bytes[..4]);
+                let number = if self.little_endian {
+                    f32::from_le_bytes(buf)
+                } else {
+                    f32::from_be_bytes(buf)
+                };
+                (f64::from(number), 4)
+            }
+            Value::Double => {
+                buf.copy_from_slice(&bytes[..8]);
+                let number = if self.little_endian {
+                    f64::from_le_bytes(buf)
+                } else {
+                    f64::from_be_bytes(buf)
+                };
+                (number, 8)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Summary {
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sample: Vec<f64>,
+}
+
+impl Summary {
+    fn add(&mut self, value: f64, sampled: bool) {
+        if !value.is_finite() {
+            return;
+        }
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        if sampled {
+            self.sample.push(value);
+        }
+    }
+
+    fn finish(mut self) -> Option<ChannelStats> {
+        if self.count == 0 || self.sample.is_empty() {
+            return None;
+        }
+        self.sample.sort_unstable_by(f64::total_cmp);
+        let middle = self.sample.len() / 2;
+        let median = if self.sample.len().is_multiple_of(2) {
+            f64::midpoint(self.sample[middle - 1], self.sample[middle])
+        } else {
+            self.sample[middle]
+        };
+        Some(ChannelStats {
+            min: self.min,
+            max: self.max,
+            mean: self.sum / self.count as f64,
+            median,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// An FCS 3.1 file with the given TEXT keywords, `/`-delimited, and DATA bytes.
+    fn fcs(keywords: &[(&str, &str)], data: &[u8]) -> Vec<u8> {
+        let mut text = String::from("/");
+        for (key, value) in keywords {
+            text.push_str(&format!("{key}/{}/", value.replace('/', "//")));
+        }
+        let text_start = HEADER_LEN;
+        let data_start = text_start + text.len();
+        let mut bytes = format!(
+            "FCS3.1    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            text_start,
+            data_start - 1,
+            data_start,
+            data_start + data.len() - 1,
+            0,
+            0
+        )
+        .into_bytes();
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn read_bytes(bytes: &[u8]) -> TestResult<Option<FlowMeta>> {
+        let path = std::env::temp_dir().join(format!("airlab-{}.fcs", uuid::Uuid::new_v4()));
+        let path = camino::Utf8PathBuf::from_path_buf(path).map_err(|_| "temp dir is not UTF-8")?;
+        std::fs::write(&path, bytes)?;
+        let meta = read(&path);
+        std::fs::remove_file(&path)?;
+        Ok(meta?)
+    }
+
+    #[test]
+    fn read_extracts_channels_and_float_statistics() -> TestResult {
+        let events: [[f32; 2]; 4] = [[100.0, 1.0], [200.0, 2.0], [300.0, 4.0], [400.0, 8.0]];
+        let data = events
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let bytes = fcs(
+            &[
+                ("$DATATYPE", "F"),
+                ("$BYTEORD", "1,2,3,4"),
+                ("$MODE", "L"),
+                ("$PAR", "2"),
+                ("$TOT", "4"),
+                ("$DATE", "12-May-2023"),
+                ("$BTIM", "14:03:22.45"),
+                ("$CYT", "LSRFortessa"),
+                ("$P1N", "FSC-A"),
+                ("$P1B", "32"),
+                ("$P2N", "FITC-A"),
+                ("$P2S", "CD3/FITC"),
+                ("$P2B", "32"),
+            ],
+            &data,
+        );
+
+        let meta = read_bytes(&bytes)?.ok_or("FCS metadata")?;
+
+        assert_eq!(meta.version, "FCS3.1");
+        assert_eq!(meta.event_count, 4);
+        assert_eq!(meta.cytometer.as_deref(), Some("LSRFortessa"));
+        assert_eq!(meta.acquired_at.as_deref(), Some("2023-05-12T14:03:22"));
+        assert_eq!(meta.channels[0].stain, None);
+        assert_eq!(meta.channels[1].name, "FITC-A");
+        assert_eq!(meta.channels[1].stain.as_deref(), Some("CD3/FITC"));
+        assert_eq!(
+            meta.channels[1].stats,
+            Some(ChannelStats {
+                min: 1.0,
+                max: 8.0,
+                mean: 3.75,
+                median: 3.0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_masks_big_endian_integers_to_their_range() -> TestResult {
+        // The top bits of the 16-bit values are outside of the 1024 range and ignored.
+        let data = [0xf0, 0x10, 0x00, 0x20, 0x03, 0xff];
+        let bytes = fcs(
+            &[
+                ("$DATATYPE", "I"),
+                ("$BYTEORD", "4,3,2,1"),
+                ("$PAR", "1"),
+                ("$TOT", "3"),
+                ("$P1N", "SSC-A"),
+                ("$P1B", "16"),
+                ("$P1R", "1024"),
+            ],
+            &data,
+        );
+
+        let meta = read_bytes(&bytes)?.ok_or("FCS metadata")?;
+        let stats = meta.channels[0].stats.ok_or("statistics")?;
+
+        assert_eq!((stats.min, stats.max, stats.median), (16.0, 1023.0, 32.0));
+        assert_eq!(meta.acquired_at, None);
+        Ok(())
+    }
+
+    #[test]
+    fn read_skips_statistics_it_cannot_decode() -> TestResult {
+        let bytes = fcs(
+            &[
+                ("$DATATYPE", "A"),
+                ("$PAR", "1"),
+                ("$TOT", "1"),
+                ("$P1N", "Time"),
+                ("$P1B", "*"),
+            ],
+            b"12,",
+        );
+
+        let meta = read_bytes(&bytes)?.ok_or("FCS metadata")?;
+
+        assert_eq!(meta.channels[0].name, "Time");
+        assert_eq!(meta.channels[0].stats, None);
+        assert!(read_bytes(b"FCS2.0    ")?.is_none());
+        assert!(read_bytes(&fcs(&[("$PAR", "4294967295"), ("$TOT", "0")], b""))?.is_none());
+        assert!(read_bytes(&[b'x'; HEADER_LEN])?.is_none());
+        Ok(())
+    }
+}
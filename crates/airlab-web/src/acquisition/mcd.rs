@@ -0,0 +1,146 @@
+//! Hyperion `.mcd` files: raw acquisition data followed by the `MCDSchema` XML, encoded as
+//! UTF-16LE, at the end of the file.
+
+use crate::acquisition::xml::Element;
+use crate::acquisition::{Channel, ImagingMeta, Roi, parse_metal};
+use camino::Utf8Path;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Only this much of the end of the file is searched for the schema.
+const TAIL_BYTES: u64 = 64 * 1024 * 1024;
+
+pub fn read(path: &Utf8Path) -> std::io::Result<Option<ImagingMeta>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = vec![];
+    file.read_to_end(&mut tail)?;
+    Ok(parse(&tail))
+}
+
+fn utf16le(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+pub(super) fn parse(tail: &[u8]) -> Option<ImagingMeta> {
+    let needle = utf16le("<MCDSchema");
+    let start = tail
+        .windows(needle.len())
+        .rposition(|window| window == needle.as_slice())?;
+    let units = tail
+        .get(start..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    let xml = String::from_utf16_lossy(&units);
+    let close = "</MCDSchema>";
+    let end = xml.find(close)? + close.len();
+    let root = Element::parse(&xml[..end])?;
+
+    let mut channels: Vec<Channel> = vec![];
+    for channel in root.children("AcquisitionChannel") {
+        // Named like `Er166(Er166Di)`; the X, Y and Z position channels have no metal.
+        let Some(name) = channel.child_text("ChannelName") else {
+            continue;
+        };
+        let metal = name.split('(').next().unwrap_or(name).trim();
+        if parse_metal(metal).is_none() || channels.iter().any(|known| known.metal == metal) {
+            continue;
+        }
+        channels.push(Channel {
+            metal: metal.to_string(),
+            label: channel.child_text("ChannelLabel").map(ToString::to_string),
+        });
+    }
+
+    let acquisitions = root.children("Acquisition").collect::<Vec<_>>();
+    let rois = acquisitions
+        .iter()
+        .map(|acquisition| Roi {
+            description: acquisition
+                .child_text("Description")
+                .map(ToString::to_string),
+            width: acquisition
+                .child_text("MaxX")
+                .and_then(|value| value.parse().ok()),
+            height: acquisition
+                .child_text("MaxY")
+                .and_then(|value| value.parse().ok()),
+        })
+        .collect();
+    let acquired_at = acquisitions
+        .iter()
+        .filter_map(|acquisition| acquisition.child_text("StartTimeStamp"))
+        .filter_map(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+        .min()
+        .map(|date| date.to_rfc3339());
+
+    Some(ImagingMeta {
+        format: "mcd".to_string(),
+        acquired_at,
+        channels,
+        rois,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Raw data followed by a schema with two acquisitions of the same panel.
+    pub(crate) fn sample_mcd() -> Vec<u8> {
+        let mut channels = String::new();
+        for acquisition in [1, 2] {
+            for (name, label) in [
+                ("X", "X"),
+                ("Er166(Er166Di)", "CD45"),
+                ("Ir191(Ir191Di)", "DNA1"),
+            ] {
+                channels.push_str(&format!(
+                    "<AcquisitionChannel><ID>{acquisition}</ID><ChannelName>{name}</ChannelName>\
+                     <AcquisitionID>{acquisition}</AcquisitionID><ChannelLabel>{label}</ChannelLabel>\
+                     </AcquisitionChannel>"
+                ));
+            }
+        }
+        let xml = format!(
+            "<MCDSchema xmlns=\"http://www.fluidigm.com/IMC/MCDSchema_V2_0.xsd\">\
+             <Acquisition><ID>1</ID><Description>ROI_001</Description><MaxX>500</MaxX>\
+             <MaxY>400</MaxY><StartTimeStamp>2023-05-02T10:15:00.1234567+02:00</StartTimeStamp>\
+             </Acquisition>\
+             <Acquisition><ID>2</ID><Description>ROI_002</Description><MaxX>250</MaxX>\
+             <MaxY>250</MaxY><StartTimeStamp>2023-05-02T09:00:00+02:00</StartTimeStamp>\
+             </Acquisition>{channels}</MCDSchema>"
+        );
+        let mut bytes = vec![0x5a; 1001];
+        bytes.extend(utf16le(&xml));
+        bytes
+    }
+
+    #[test]
+    fn parse_reads_channels_rois_and_date() -> Result<(), &'static str> {
+        let meta = parse(&sample_mcd()).ok_or("schema")?;
+
+        let metals = meta
+            .channels
+            .iter()
+            .map(|channel| (channel.metal.as_str(), channel.label.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            metals,
+            vec![("Er166", Some("CD45")), ("Ir191", Some("DNA1"))]
+        );
+        assert_eq!(meta.rois.len(), 2);
+        assert_eq!(
+            (meta.rois[0].width, meta.rois[0].height),
+            (Some(500), Some(400))
+        );
+        assert_eq!(
+            meta.acquired_at.as_deref(),
+            Some("2023-05-02T09:00:00+02:00")
+        );
+        assert!(parse(b"no schema here").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Acquisition metadata read when data files are uploaded as validation files: Hyperion
+//! `.mcd` files and OME-TIFF exports for imaging mass cytometry, FCS files for flow
+//! cytometry. The channel list is checked against the tag of the validated conjugate, so a
+//! validation does not silently point at an acquisition without that conjugate.
+
+mod fcs;
+mod mcd;
+mod ome_tiff;
+mod xml;
+
+#[cfg(test)]
+pub(crate) use mcd::tests::sample_mcd;
+
+use airlab_lib::model::tag::Tag;
+use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
+
+/// Masses of the isotopes used as mass cytometry tags. Keeps antigen labels such as `CD45`
+/// from being read as a metal.
+const MASS_RANGE: std::ops::RangeInclusive<u32> = 75..=210;
+
+/// Both kinds carry a `format` field telling them apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AcquisitionMeta {
+    Imaging(ImagingMeta),
+    Flow(FlowMeta),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagingMeta {
+    pub format: String,
+    pub acquired_at: Option<String>,
+    pub channels: Vec<Channel>,
+    pub rois: Vec<Roi>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Channel {
+    pub metal: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roi {
+    pub description: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowMeta {
+    pub format: String,
+    /// `FCS3.0` or `FCS3.1`.
+    pub version: String,
+    pub acquired_at: Option<String>,
+    pub cytometer: Option<String>,
+    pub event_count: u64,
+    pub channels: Vec<FlowChannel>,
+}
+
+/// A `$PnN` parameter with its `$PnS` stain, if any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlowChannel {
+    pub name: String,
+    pub stain: Option<String>,
+    pub stats: Option<ChannelStats>,
+}
+
+/// Quick-look summary of a channel's values over all events.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+}
+
+/// Whether files with this extension may carry acquisition metadata.
+pub fn supports(extension: &str) -> bool {
+    matches!(
+        extension.to_ascii_lowercase().as_str(),
+        "mcd" | "tif" | "tiff" | "fcs"
+    )
+}
+
+/// Reads the metadata of the file at `path`. `Ok(None)` for files without any, such as
+/// plain TIFFs.
+pub fn extract(path: &Utf8Path, extension: &str) -> std::io::Result<Option<AcquisitionMeta>> {
+    let meta = match extension.to_ascii_lowercase().as_str() {
+        "mcd" => mcd::read(path)?.map(AcquisitionMeta::Imaging),
+        "tif" | "tiff" => ome_tiff::read(path)?.map(AcquisitionMeta::Imaging),
+        "fcs" => fcs::read(path)?.map(AcquisitionMeta::Flow),
+        _ => None,
+    };
+    Ok(meta)
+}
+
+/// Element symbol and mass of a metal written as `Er166`, `166Er` or `Er-166`.
+pub fn parse_metal(value: &str) -> Option<(String, u32)> {
+    let compact = value
+        .trim()
+        .chars()
+        .filter(|ch| !matches!(ch, '-' | '_' | ' '))
+        .collect::<String>();
+    let split = |digits_first: bool| {
+        let at = compact.find(|ch: char| ch.is_ascii_digit() != digits_first)?;
+        let (head, tail) = compact.split_at(at);
+        Some(if digits_first {
+            (tail, head)
+        } else {
+            (head, tail)
+        })
+    };
+    let (symbol, mass) = if compact.starts_with(|ch: char| ch.is_ascii_digit()) {
+        split(true)?
+    } else {
+        split(false)?
+    };
+    if symbol.is_empty()
+        || symbol.len() > 2
+        || !symbol.chars().all(|ch| ch.is_ascii_alphabetic())
+        || !mass.chars().all(|ch| ch.is_ascii_digit())
+    {
+        return None;
+    }
+    let mass = mass.parse().ok().filter(|mass| MASS_RANGE.contains(mass))?;
+    let mut symbol = symbol.to_ascii_lowercase();
+    symbol[..1].make_ascii_uppercase();
+    Some((symbol, mass))
+}
+
+/// Warnings about the conjugate's `tag` missing from the acquired channels.
+pub fn check_conjugate(meta: &AcquisitionMeta, conjugate_id: i64, tag: &Tag) -> Vec<String> {
+    match meta {
+        AcquisitionMeta::Imaging(meta) => check_metal(meta, conjugate_id, tag),
+        AcquisitionMeta::Flow(meta) => check_fluorophore(meta, conjugate_id, tag),
+    }
+}
+
+/// Tags that are no metal, or whose isotope cannot be told from the name and `mw`, are not
+/// checked.
+fn check_metal(meta: &ImagingMeta, conjugate_id: i64, tag: &Tag) -> Vec<String> {
+    if !tag.is_metal {
+        return vec![];
+    }
+    let expected = match (parse_metal(&tag.name), tag.mw) {
+        (Some((symbol, _)), Some(mw)) => u32::try_from(mw).ok().map(|mass| (symbol, mass)),
+        (Some(metal), None) => Some(metal),
+        (None, Some(mw)) => parse_metal(&format!("{}{mw}", tag.name.trim())),
+        (None, None) => None,
+    };
+    let Some((symbol, mass)) = expected else {
+        return vec![];
+    };
+    let found = meta
+        .channels
+        .iter()
+        .filter_map(|channel| parse_metal(&channel.metal))
+        .any(|metal| metal == (symbol.clone(), mass));
+    if found {
+        vec![]
+    } else {
+        vec![format!(
+            "Conjugate {conjugate_id} is tagged with {symbol}{mass}, which is not among the {} acquired channels",
+            meta.channels.len()
+        )]
+    }
+}
+
+/// Only fluorophore tags are checked, against the channels that have a stain.
+fn check_fluorophore(meta: &FlowMeta, conjugate_id: i64, tag: &Tag) -> Vec<String> {
+    let fluorophore = normalize(&tag.name);
+    if !tag.is_fluorophore || fluorophore.is_empty() {
+        return vec![];
+    }
+    let stained = meta
+        .channels
+        .iter()
+        .filter(|channel| channel.stain.is_some())
+        .collect::<Vec<_>>();
+    if stained.iter().any(|channel| detects(channel, &fluorophore)) {
+        vec![]
+    } else {
+        vec![format!(
+            "Conjugate {conjugate_id} is tagged with {}, which is not among the {} stained channels",
+            tag.name,
+            stained.len()
+        )]
+    }
+}
+
+/// A channel detects a fluorophore if it is named after it, like `FITC-A` or
+/// `Comp-PE-Cy7-H`, or the stain mentions it, like `CD3 FITC`.
+fn detects(channel: &FlowChannel, fluorophore: &str) -> bool {
+    let mut name = channel.name.trim();
+    if let Some(prefix) = name.get(..5)
+        && prefix.eq_ignore_ascii_case("comp-")
+    {
+        name = &name[5..];
+    }
+    for suffix in ["-A", "-H", "-W", "-a", "-h", "-w"] {
+        name = name.strip_suffix(suffix).unwrap_or(name);
+    }
+    let stain = channel.stain.as_deref().unwrap_or_default();
+    normalize(name) == fluorophore
+        || normalize(stain) == fluorophore
+        || stain
+            .split(|ch: char| ch.is_whitespace() || "/:,;()_".contains(ch))
+            .any(|word| normalize(word) == fluorophore)
+}
+
+/// Lowercase letters and digits only, so `PE-Cy7` and `pecy7` compare equal.
+fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|ch| ch.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, mw: Option<i64>, is_metal: bool) -> Tag {
+        Tag {
+            id: 1,
+            group_id: 1,
+            name: name.to_string(),
+            description: None,
+            is_metal,
+            is_fluorophore: !is_metal,
+            is_enzyme: false,
+            is_biotin: false,
+            is_other: false,
+            mw,
+            emission: None,
+            excitation: None,
+            status: None,
+            meta: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn parse_metal_accepts_common_spellings() {
+        let er166 = Some(("Er".to_string(), 166));
+        assert_eq!(parse_metal("Er166"), er166);
+        assert_eq!(parse_metal("166Er"), er166);
+        assert_eq!(parse_metal(" er-166 "), er166);
+        assert_eq!(parse_metal("CD45"), None);
+        assert_eq!(parse_metal("Er166Di"), None);
+        assert_eq!(parse_metal("DNA1"), None);
+        assert_eq!(parse_metal("X"), None);
+    }
+
+    #[test]
+    fn check_conjugate_warns_about_missing_metal() {
+        let meta = AcquisitionMeta::Imaging(ImagingMeta {
+            format: "mcd".to_string(),
+            acquired_at: None,
+            channels: vec![Channel {
+                metal: "Er166".to_string(),
+                label: Some("CD45".to_string()),
+            }],
+            rois: vec![],
+        });
+
+        assert!(check_conjugate(&meta, 7, &tag("166Er", None, true)).is_empty());
+        assert!(check_conjugate(&meta, 7, &tag("Er", Some(166), true)).is_empty());
+        assert!(check_conjugate(&meta, 7, &tag("FITC", None, false)).is_empty());
+        assert!(check_conjugate(&meta, 7, &tag("custom", None, true)).is_empty());
+        assert_eq!(
+            check_conjugate(&meta, 7, &tag("Er", Some(168), true)),
+            vec!["Conjugate 7 is tagged with Er168, which is not among the 1 acquired channels"]
+        );
+    }
+
+    #[test]
+    fn check_conjugate_matches_fluorophore_to_stained_channels() {
+        let channel = |name: &str, stain: Option<&str>| FlowChannel {
+            name: name.to_string(),
+            stain: stain.map(ToString::to_string),
+            stats: None,
+        };
+        let meta = AcquisitionMeta::Flow(FlowMeta {
+            format: "fcs".to_string(),
+            version: "FCS3.1".to_string(),
+            acquired_at: None,
+            cytometer: None,
+            event_count: 0,
+            channels: vec![
+                channel("FSC-A", None),
+                channel("Comp-PE-Cy7-A", Some("CD8")),
+                channel("BL1-A", Some("CD3 FITC")),
+                channel("APC-A", None),
+            ],
+        });
+
+        assert!(check_conjugate(&meta, 7, &tag("PE-Cy7", None, false)).is_empty());
+        assert!(check_conjugate(&meta, 7, &tag("fitc", None, false)).is_empty());
+        assert!(check_conjugate(&meta, 7, &tag("Er", Some(166), true)).is_empty());
+        assert_eq!(
+            check_conjugate(&meta, 7, &tag("APC", None, false)),
+            vec!["Conjugate 7 is tagged with APC, which is not among the 2 stained channels"]
+        );
+        assert_eq!(check_conjugate(&meta, 7, &tag("PE", None, false)).len(), 1);
+    }
+}
@@ -0,0 +1,138 @@
+//! OME-TIFF files: OME-XML in the `ImageDescription` tag of the first image.
+
+use crate::acquisition::xml::Element;
+use crate::acquisition::{Channel, ImagingMeta, Roi, parse_metal};
+use camino::Utf8Path;
+use std::fs::File;
+use std::io::BufReader;
+use tiff::TiffError;
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+
+/// `None` for plain TIFFs and TIFFs that cannot be read.
+pub fn read(path: &Utf8Path) -> std::io::Result<Option<ImagingMeta>> {
+    let mut decoder = match Decoder::new(BufReader::new(File::open(path)?)) {
+        Ok(decoder) => decoder,
+        Err(TiffError::IoError(err)) => return Err(err),
+        Err(_) => return Ok(None),
+    };
+    match decoder.get_tag_ascii_string(Tag::ImageDescription) {
+        Ok(description) => Ok(parse(&description)),
+        Err(TiffError::IoError(err)) => Err(err),
+        Err(_) => Ok(None),
+    }
+}
+
+pub(super) fn parse(description: &str) -> Option<ImagingMeta> {
+    let root = Element::parse(description.trim_end_matches('\0'))?;
+    if root.name != "OME" {
+        return None;
+    }
+
+    let mut channels: Vec<Channel> = vec![];
+    let mut rois = vec![];
+    let mut dates = vec![];
+    for image in root.children("Image") {
+        if let Some(date) = image.child_text("AcquisitionDate") {
+            dates.push(date.to_string());
+        }
+        let Some(pixels) = image.child("Pixels") else {
+            continue;
+        };
+        rois.push(Roi {
+            description: image.attribute("Name").map(ToString::to_string),
+            width: pixels
+                .attribute("SizeX")
+                .and_then(|value| value.parse().ok()),
+            height: pixels
+                .attribute("SizeY")
+                .and_then(|value| value.parse().ok()),
+        });
+        for channel in pixels.children("Channel") {
+            // Exporters disagree on whether the metal is the `Fluor` or the `Name`; the
+            // other one is the label.
+            let fluor = channel.attribute("Fluor");
+            let name = channel.attribute("Name");
+            let (metal, label) = match (fluor, name) {
+                (Some(fluor), name) if parse_metal(fluor).is_some() => (fluor, name),
+                (fluor, Some(name)) if parse_metal(name).is_some() => (name, fluor),
+                _ => continue,
+            };
+            if channels.iter().any(|known| known.metal == metal) {
+                continue;
+            }
+            channels.push(Channel {
+                metal: metal.to_string(),
+                label: label
+                    .filter(|label| *label != metal)
+                    .map(ToString::to_string),
+            });
+        }
+    }
+    dates.sort();
+
+    Some(ImagingMeta {
+        format: "ome-tiff".to_string(),
+        acquired_at: dates.into_iter().next(),
+        channels,
+        rois,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    const SAMPLE_OME_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<OME xmlns="http://www.openmicroscopy.org/Schemas/OME/2016-06">
+  <Image ID="Image:0" Name="ROI_001">
+    <AcquisitionDate>2024-01-15T08:30:00</AcquisitionDate>
+    <Pixels ID="Pixels:0" DimensionOrder="XYCZT" Type="float" SizeX="600" SizeY="300" SizeC="3" SizeZ="1" SizeT="1">
+      <Channel ID="Channel:0:0" Name="CD3" Fluor="Sm152"/>
+      <Channel ID="Channel:0:1" Name="Er166" Fluor="CD45"/>
+      <Channel ID="Channel:0:2" Name="Background"/>
+    </Pixels>
+  </Image>
+</OME>"#;
+
+    fn sample_ome_tiff(description: &str) -> Result<Vec<u8>, TiffError> {
+        let mut bytes = Cursor::new(vec![]);
+        let mut encoder = TiffEncoder::new(&mut bytes)?;
+        let mut image = encoder.new_image::<colortype::Gray8>(2, 2)?;
+        image
+            .encoder()
+            .write_tag(Tag::ImageDescription, description)?;
+        image.write_data(&[0, 1, 2, 3])?;
+        Ok(bytes.into_inner())
+    }
+
+    #[test]
+    fn read_finds_ome_xml_in_tiff() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dir = std::env::temp_dir().join(format!("airlab-ome-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        let ome = camino::Utf8PathBuf::from_path_buf(dir.join("a.ome.tiff"))
+            .map_err(|_| "temp dir is not UTF-8")?;
+        let plain = ome.with_file_name("b.tiff");
+        std::fs::write(&ome, sample_ome_tiff(SAMPLE_OME_XML)?)?;
+        std::fs::write(&plain, sample_ome_tiff("ImageJ=1.54")?)?;
+
+        let meta = read(&ome)?.ok_or("OME metadata")?;
+        let channels = meta
+            .channels
+            .iter()
+            .map(|channel| (channel.metal.as_str(), channel.label.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            channels,
+            vec![("Sm152", Some("CD3")), ("Er166", Some("CD45"))]
+        );
+        assert_eq!(meta.rois[0].width, Some(600));
+        assert_eq!(meta.acquired_at.as_deref(), Some("2024-01-15T08:30:00"));
+        assert!(read(&plain)?.is_none());
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
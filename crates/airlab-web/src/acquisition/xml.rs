@@ -0,0 +1,138 @@
+//! Just enough XML for acquisition metadata: elements by local name, attributes and text.
+
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// The root element of `xml`, or `None` if it is not well-formed.
+    pub fn parse(xml: &str) -> Option<Self> {
+        let mut stack: Vec<Self> = vec![];
+        for token in Tokenizer::from(xml) {
+            match token.ok()? {
+                Token::ElementStart { local, .. } => stack.push(Self {
+                    name: local.as_str().to_string(),
+                    ..Self::default()
+                }),
+                Token::Attribute { local, value, .. } => {
+                    stack
+                        .last_mut()?
+                        .attributes
+                        .push((local.as_str().to_string(), unescape(value.as_str())));
+                }
+                Token::Text { text } => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&unescape(text.as_str()));
+                    }
+                }
+                Token::Cdata { text, .. } => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(text.as_str());
+                    }
+                }
+                Token::ElementEnd {
+                    end: ElementEnd::Close(..) | ElementEnd::Empty,
+                    ..
+                } => {
+                    let element = stack.pop()?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Some(element),
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Self> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Trimmed text of the first child called `name`, if it has any.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name)
+            .map(|child| child.text.trim())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(ch) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_builds_tree_with_local_names() -> Result<(), &'static str> {
+        let root = Element::parse(
+            r#"<?xml version="1.0"?><ome:OME xmlns:ome="x"><ome:Image Name="a &amp; b"><Date> 2024 </Date><Empty/></ome:Image></ome:OME>"#,
+        )
+        .ok_or("well-formed")?;
+
+        assert_eq!(root.name, "OME");
+        let image = root.child("Image").ok_or("image")?;
+        assert_eq!(image.attribute("Name"), Some("a & b"));
+        assert_eq!(image.child_text("Date"), Some("2024"));
+        assert_eq!(image.child_text("Empty"), None);
+        assert!(Element::parse("<a><b></a>").is_none());
+        assert_eq!(unescape("&#x45;r&#49;66 &bogus; &"), "Er166 &bogus; &");
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+//! Object storage the blob store writes to. Keys are `/`-separated relative paths such as
+//! `blobs/ab/cd/<sha256>`.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use std::future::Future;
+use std::io::{ErrorKind, SeekFrom};
+use std::pin::Pin;
+use std::time::SystemTime;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = std::io::Result<T>> + Send + 'a>>;
+
+/// Content of an object, read as it arrives.
+pub type ObjectReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Size and age of a stored object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMeta {
+    pub key: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Inclusive byte range of an object, already clamped to its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// What a `Range` request header asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    pub const fn byte_count(self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Reads a single `bytes=` range (`a-b`, `a-` or `-n`) against an object of `size`
+    /// bytes. Anything else, including multiple ranges, is served in full.
+    pub fn parse_header(header: Option<&str>, size: u64) -> RangeRequest {
+        let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+            return RangeRequest::Full;
+        };
+        if spec.contains(',') {
+            return RangeRequest::Full;
+        }
+        let Some((start, end)) = spec.trim().split_once('-') else {
+            return RangeRequest::Full;
+        };
+        let (start, end) = match (start.trim(), end.trim()) {
+            ("", "") => return RangeRequest::Full,
+            ("", suffix) => match suffix.parse::<u64>() {
+                Ok(0) => return RangeRequest::Unsatisfiable,
+                Ok(suffix) => (size.saturating_sub(suffix), size.saturating_sub(1)),
+                Err(_) => return RangeRequest::Full,
+            },
+            (start, "") => match start.parse::<u64>() {
+                Ok(start) => (start, size.saturating_sub(1)),
+                Err(_) => return RangeRequest::Full,
+            },
+            (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+                (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+                _ => return RangeRequest::Full,
+            },
+        };
+        if size == 0 || start >= size {
+            return RangeRequest::Unsatisfiable;
+        }
+        RangeRequest::Partial(Self { start, end })
+    }
+}
+
+pub trait StorageBackend: Send + Sync + std::fmt::Debug {
+    /// Writes the object atomically, replacing an existing one.
+    fn put<'a>(&'a self, key: &'a str, bytes: &'a [u8]) -> BackendFuture<'a, ()>;
+
+    /// Moves a finished local file into the object without loading it into memory. The
+    /// file is gone afterwards.
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Utf8Path) -> BackendFuture<'a, ()>;
+
+    /// Reads the object or a range of it; `None` if it does not exist.
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+        range: Option<ByteRange>,
+    ) -> BackendFuture<'a, Option<Vec<u8>>>;
+
+    /// Opens the object or a range of it without loading it into memory; `None` if it
+    /// does not exist.
+    fn open<'a>(
+        &'a self,
+        key: &'a str,
+        range: Option<ByteRange>,
+    ) -> BackendFuture<'a, Option<ObjectReader>>;
+
+    fn head<'a>(&'a self, key: &'a str) -> BackendFuture<'a, Option<ObjectMeta>>;
+
+    /// Deletes the object; `false` if it was not there.
+    fn delete<'a>(&'a self, key: &'a str) -> BackendFuture<'a, bool>;
+
+    /// All objects whose key starts with `prefix`.
+    fn list<'a>(&'a self, prefix: &'a str) -> BackendFuture<'a, Vec<ObjectMeta>>;
+}
+
+/// Objects as files below a local folder, i.e. `WebConfig.DATA_PATH`.
+#[derive(Debug, Clone)]
+pub struct LocalBackend {
+    root: Utf8PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(root: impl Into<Utf8PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> std::io::Result<Utf8PathBuf> {
+        if key.split('/').any(|part| part.is_empty() || part == "..") {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid object key: {key}"),
+            ));
+        }
+        Ok(self.root.join(key))
+    }
+
+    async fn reader(&self, key: &str, range: Option<ByteRange>) -> std::io::Result<ObjectReader> {
+        let mut file = fs::File::open(self.path(key)?).await?;
+        let Some(range) = range else {
+            return Ok(Box::pin(file));
+        };
+        file.seek(SeekFrom::Start(range.start)).await?;
+        Ok(Box::pin(file.take(range.byte_count())))
+    }
+
+    async fn read(&self, key: &str, range: Option<ByteRange>) -> std::io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        self.reader(key, range)
+            .await?
+            .read_to_end(&mut bytes)
+            .await?;
+        Ok(bytes)
+    }
+}
+
+impl StorageBackend for LocalBackend {
+    fn put<'a>(&'a self, key: &'a str, bytes: &'a [u8]) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            // Readers never see a partial object.
+            let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+            fs::write(&tmp, bytes).await?;
+            if let Err(err) = fs::rename(&tmp, &path).await {
+                let _ = fs::remove_file(&tmp).await;
+                return Err(err);
+            }
+            Ok(())
+        })
+    }
+
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Utf8Path) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let target = self.path(key)?;
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            if fs::rename(path, &target).await.is_ok() {
+                return Ok(());
+            }
+            // On another file system: copy next to the target, then rename as in `put`.
+            let tmp = target.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+            let copied = match fs::copy(path, &tmp).await {
+                Ok(_) => fs::rename(&tmp, &target).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = copied {
+                let _ = fs::remove_file(&tmp).await;
+                return Err(err);
+            }
+            fs::remove_file(path).await
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+        range: Option<ByteRange>,
+    ) -> BackendFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            match self.read(key, range).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn open<'a>(
+        &'a self,
+        key: &'a str,
+        range: Option<ByteRange>,
+    ) -> BackendFuture<'a, Option<ObjectReader>> {
+        Box::pin(async move {
+            match self.reader(key, range).await {
+                Ok(reader) => Ok(Some(reader)),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BackendFuture<'a, Option<ObjectMeta>> {
+        Box::pin(async move {
+            match fs::metadata(self.path(key)?).await {
+                Ok(meta) if meta.is_file() => Ok(Some(ObjectMeta {
+                    key: key.to_string(),
+                    size: meta.len(),
+                    modified: meta.modified()?,
+                })),
+                Ok(_) => Ok(None),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BackendFuture<'a, bool> {
+        Box::pin(async move {
+            match fs::remove_file(self.path(key)?).await {
+                Ok(()) => Ok(true),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> BackendFuture<'a, Vec<ObjectMeta>> {
+        Box::pin(async move {
+            // Walk from the deepest folder the prefix names, then filter on the full key.
+            let dir = prefix.rsplit_once('/').map_or("", |(dir, _)| dir);
+            let mut objects = vec![];
+            let mut dirs = vec![if dir.is_empty() {
+                self.root.clone()
+            } else {
+                self.path(dir)?
+            }];
+            while let Some(dir) = dirs.pop() {
+                let mut entries = match fs::read_dir(&dir).await {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err),
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    let Ok(path) = Utf8PathBuf::from_path_buf(entry.path()) else {
+                        continue;
+                    };
+                    let meta = entry.metadata().await?;
+                    if meta.is_dir() {
+                        dirs.push(path);
+                        continue;
+                    }
+                    let Ok(relative) = path.strip_prefix(&self.root) else {
+                        continue;
+                    };
+                    let key = relative
+                        .components()
+                        .map(|part| part.as_str())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if key.starts_with(prefix) && !key.contains(".tmp-") {
+                        objects.push(ObjectMeta {
+                            key,
+                            size: meta.len(),
+                            modified: meta.modified()?,
+                        });
+                    }
+                }
+            }
+            objects.sort_by(|left, right| left.key.cmp(&right.key));
+            Ok(objects)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[test]
+    fn parse_header_resolves_single_ranges() {
+        let partial = |start, end| RangeRequest::Partial(ByteRange { start, end });
+        assert_eq!(ByteRange::parse_header(None, 10), RangeRequest::Full);
+        assert_eq!(
+            ByteRange::parse_header(Some("bytes=2-4"), 10),
+            partial(2, 4)
+        );
+        assert_eq!(ByteRange::parse_header(Some("bytes=5-"), 10), partial(5, 9));
+        assert_eq!(ByteRange::parse_header(Some("bytes=-3"), 10), partial(7, 9));
+        assert_eq!(
+            ByteRange::parse_header(Some("bytes=8-20"), 10),
+            partial(8, 9)
+        );
+        assert_eq!(
+            ByteRange::parse_header(Some("bytes=10-"), 10),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            ByteRange::parse_header(Some("bytes=0-1,4-5"), 10),
+            RangeRequest::Full
+        );
+        assert_eq!(
+            ByteRange::parse_header(Some("items=0-1"), 10),
+            RangeRequest::Full
+        );
+    }
+
+    #[tokio::test]
+    async fn local_backend_round_trips_objects() -> TestResult {
+        let root =
+            std::env::temp_dir().join(format!("airlab-local-backend-{}", uuid::Uuid::new_v4()));
+        let backend = LocalBackend::new(Utf8PathBuf::from_path_buf(root).unwrap_or_default());
+
+        backend.put("blobs/ab/object", b"0123456789").await?;
+        backend.put("other/object", b"x").await?;
+        assert_eq!(
+            backend
+                .get("blobs/ab/object", Some(ByteRange { start: 2, end: 4 }))
+                .await?,
+            Some(b"234".to_vec())
+        );
+        let meta = backend.head("blobs/ab/object").await?.ok_or("head")?;
+        assert_eq!(meta.size, 10);
+        let mut tail = String::new();
+        backend
+            .open("blobs/ab/object", Some(ByteRange { start: 7, end: 9 }))
+            .await?
+            .ok_or("open")?
+            .read_to_string(&mut tail)
+            .await?;
+        assert_eq!(tail, "789");
+        let keys = backend
+            .list("blobs/")
+            .await?
+            .into_iter()
+            .map(|object| object.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["blobs/ab/object"]);
+
+        assert!(backend.delete("blobs/ab/object").await?);
+        assert!(!backend.delete("blobs/ab/object").await?);
+        assert_eq!(backend.get("blobs/ab/object", None).await?, None);
+        assert!(backend.open("blobs/ab/object", None).await?.is_none());
+        assert!(backend.get("../escape", None).await.is_err());
+
+        Ok(())
+    }
+}
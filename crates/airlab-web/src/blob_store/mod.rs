@@ -0,0 +1,686 @@
+//! Content-addressed store for uploaded validation files.
+//!
+//! Blobs are stored under the key `blobs/<aa>/<bb>/<sha256>` and are shared by every
+//! validation file with the same content. The `blob` table counts the references; files
+//! uploaded before the store existed keep their MD5 name under
+//! `groups/<group>/uploads/validation/<validation>/` until they are imported. Where the
+//! keys live is up to the configured [`StorageBackend`].
+
+pub mod backend;
+pub mod s3;
+pub mod staged;
+
+pub use backend::{ByteRange, LocalBackend, ObjectReader, RangeRequest, StorageBackend};
+pub use staged::StagedUpload;
+
+use crate::blob_store::s3::{S3Backend, S3Config};
+use crate::config::web_config;
+use airlab_lib::ctx::Ctx;
+use airlab_lib::envs;
+use airlab_lib::model::blob::{BlobBmc, is_sha256};
+use airlab_lib::model::{self, ModelManager};
+use camino::{Utf8Path, Utf8PathBuf};
+use derive_more::From;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::AsyncReadExt;
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, From)]
+pub enum Error {
+    #[from]
+    Env(envs::Error),
+    #[from]
+    Model(model::Error),
+    #[from]
+    Io(std::io::Error),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Outcome of re-hashing one stored blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlobCheck {
+    Ok,
+    Missing,
+    Corrupted { actual: String },
+}
+
+/// A blob found in the backend.
+#[derive(Debug, Clone)]
+pub struct StoredBlob {
+    pub sha256: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+const BLOB_PREFIX: &str = "blobs/";
+/// Bytes read at a time when a blob is hashed or copied.
+const CHUNK_LEN: usize = 64 * 1024;
+/// Folder below the data path that holds uploads until they are moved into the store.
+const STAGING_DIR: &str = ".staging";
+
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    backend: Arc<dyn StorageBackend>,
+    staging: Utf8PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(backend: Arc<dyn StorageBackend>, staging: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            backend,
+            staging: staging.into(),
+        }
+    }
+
+    /// Stores below `data_path`; staging shares its file system so uploads move by rename.
+    pub fn local(data_path: &str) -> Self {
+        Self::new(
+            Arc::new(LocalBackend::new(data_path)),
+            Utf8Path::new(data_path).join(STAGING_DIR),
+        )
+    }
+
+    /// Builds the backend selected by `SERVICE_STORAGE_BACKEND`.
+    pub fn from_config() -> Result<Self> {
+        let config = web_config()?;
+        match config.STORAGE_BACKEND.as_str() {
+            "local" => Ok(Self::local(&config.DATA_PATH)),
+            "s3" => {
+                let required = |value: &Option<String>, name: &'static str| {
+                    value.clone().ok_or(envs::Error::MissingEnv(name))
+                };
+                let backend = S3Backend::new(S3Config {
+                    endpoint: required(&config.S3_ENDPOINT, "SERVICE_S3_ENDPOINT")?,
+                    bucket: required(&config.S3_BUCKET, "SERVICE_S3_BUCKET")?,
+                    region: config.S3_REGION.clone(),
+                    access_key: required(&config.S3_ACCESS_KEY, "SERVICE_S3_ACCESS_KEY")?,
+                    secret_key: required(&config.S3_SECRET_KEY, "SERVICE_S3_SECRET_KEY")?,
+                })?;
+                Ok(Self::new(
+                    Arc::new(backend),
+                    Utf8Path::new(&config.DATA_PATH).join(STAGING_DIR),
+                ))
+            }
+            _ => Err(envs::Error::WrongFormat("SERVICE_STORAGE_BACKEND").into()),
+        }
+    }
+
+    pub fn backend(&self) -> &dyn StorageBackend {
+        self.backend.as_ref()
+    }
+
+    #[cfg(test)]
+    pub fn digest(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    pub fn key(sha256: &str) -> String {
+        let fan_out = |range: std::ops::Range<usize>| sha256.get(range).unwrap_or("__");
+        format!("{BLOB_PREFIX}{}/{}/{sha256}", fan_out(0..2), fan_out(2..4))
+    }
+
+    /// Key of a validation file's content: its blob, or the per-validation upload folder
+    /// for files that still carry a legacy hash.
+    pub fn file_key(group_id: i64, validation_id: i64, hash: &str, ext: &str) -> String {
+        if is_sha256(hash) {
+            Self::key(hash)
+        } else {
+            format!("groups/{group_id}/uploads/validation/{validation_id}/{hash}.{ext}")
+        }
+    }
+
+    /// SHA-256 of everything `reader` yields, read in chunks.
+    pub async fn digest_reader(reader: ObjectReader) -> std::io::Result<String> {
+        let mut hasher = Sha256::new();
+        for_each_chunk(reader, |chunk| hasher.update(chunk)).await?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Stores `bytes` unless an equal blob exists and returns its SHA-256. The server
+    /// streams everything it stores; tests seed the store with this.
+    #[cfg(test)]
+    pub async fn put(&self, ctx: &Ctx, mm: &ModelManager, bytes: &[u8]) -> Result<String> {
+        let sha256 = Self::digest(bytes);
+        let size = i64::try_from(bytes.len()).unwrap_or(i64::MAX);
+        // Touched first, so a GC cannot remove an equal blob after it was found here.
+        BlobBmc::touch(ctx, mm, &sha256, size).await?;
+        let key = Self::key(&sha256);
+        if self.backend.head(&key).await?.is_none() {
+            self.backend.put(&key, bytes).await?;
+        }
+        Ok(sha256)
+    }
+
+    /// Starts an upload that is written to the staging folder as it arrives.
+    pub async fn stage(&self) -> std::io::Result<StagedUpload> {
+        StagedUpload::create(&self.staging).await
+    }
+
+    /// Moves a staged upload into the store unless an equal blob exists and returns its
+    /// SHA-256.
+    pub async fn put_staged(
+        &self,
+        ctx: &Ctx,
+        mm: &ModelManager,
+        mut upload: StagedUpload,
+    ) -> Result<String> {
+        let sha256 = upload.finish().await?;
+        let size = i64::try_from(upload.size()).unwrap_or(i64::MAX);
+        BlobBmc::touch(ctx, mm, &sha256, size).await?;
+        let key = Self::key(&sha256);
+        if self.backend.head(&key).await?.is_none() {
+            self.backend.put_file(&key, upload.path()).await?;
+        }
+        Ok(sha256)
+    }
+
+    /// Stages what `reader` yields and stores it as in [`Self::put_staged`]. Returns the
+    /// SHA-256 and the size.
+    pub async fn put_reader(
+        &self,
+        ctx: &Ctx,
+        mm: &ModelManager,
+        mut reader: ObjectReader,
+    ) -> Result<(String, u64)> {
+        let mut upload = self.stage().await?;
+        let mut chunk = vec![0; CHUNK_LEN];
+        loop {
+            let read = reader.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            upload.write(&chunk[..read]).await?;
+        }
+        let size = upload.size();
+        Ok((self.put_staged(ctx, mm, upload).await?, size))
+    }
+
+    /// Re-hashes a stored blob as it is read.
+    pub async fn check(&self, sha256: &str) -> std::io::Result<BlobCheck> {
+        let Some(reader) = self.backend.open(&Self::key(sha256), None).await? else {
+            return Ok(BlobCheck::Missing);
+        };
+        let actual = Self::digest_reader(reader).await?;
+        Ok(if actual == sha256 {
+            BlobCheck::Ok
+        } else {
+            BlobCheck::Corrupted { actual }
+        })
+    }
+
+    /// Key of an object derived from the one at `key`, such as a preview. Derived objects
+    /// are removed together with their blob.
+    pub fn derived_key(key: &str, name: &str) -> String {
+        format!("{key}.{name}")
+    }
+
+    /// Deletes a blob and what was derived from it; `false` if the blob was not there.
+    pub async fn remove(&self, sha256: &str) -> std::io::Result<bool> {
+        let key = Self::key(sha256);
+        for derived in self.backend.list(&Self::derived_key(&key, "")).await? {
+            self.backend.delete(&derived.key).await?;
+        }
+        self.backend.delete(&key).await
+    }
+
+    /// All blobs in the backend; temporary objects of in-flight writes are skipped.
+    pub async fn list(&self) -> std::io::Result<Vec<StoredBlob>> {
+        let objects = self.backend.list(BLOB_PREFIX).await?;
+        Ok(objects
+            .into_iter()
+            .filter_map(|object| {
+                let sha256 = object.key.rsplit('/').next()?.to_string();
+                (is_sha256(&sha256) && object.key == Self::key(&sha256)).then_some(StoredBlob {
+                    sha256,
+                    size: object.size,
+                    modified: object.modified,
+                })
+            })
+            .collect())
+    }
+}
+
+/// State of the routes that read or write validation files. The store is built once at
+/// startup and shared.
+#[derive(Clone)]
+pub struct BlobState {
+    pub mm: ModelManager,
+    pub store: BlobStore,
+}
+
+impl BlobState {
+    pub const fn new(mm: ModelManager, store: BlobStore) -> Self {
+        Self { mm, store }
+    }
+
+    pub fn from_config(mm: ModelManager) -> Result<Self> {
+        Ok(Self::new(mm, BlobStore::from_config()?))
+    }
+}
+
+/// Feeds everything `reader` yields to `consume`, [`CHUNK_LEN`] bytes at a time.
+async fn for_each_chunk(
+    mut reader: ObjectReader,
+    mut consume: impl FnMut(&[u8]),
+) -> std::io::Result<()> {
+    let mut chunk = vec![0; CHUNK_LEN];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        consume(&chunk[..read]);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubFinding {
+    pub hash: String,
+    /// Hash of the content actually found, for corrupted files.
+    pub actual: Option<String>,
+    pub validation_file_ids: Vec<i64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubReport {
+    pub checked: usize,
+    pub verified: usize,
+    pub missing: Vec<ScrubFinding>,
+    pub corrupted: Vec<ScrubFinding>,
+    /// Blob files on disk that no validation file references.
+    pub orphaned: Vec<String>,
+}
+
+/// Re-hashes every referenced blob and every legacy file against the recorded hash.
+pub async fn scrub(ctx: &Ctx, mm: &ModelManager, store: &BlobStore) -> Result<ScrubReport> {
+    let mut report = ScrubReport::default();
+    let mut referenced = HashSet::new();
+    let mut verified = vec![];
+
+    for blob in BlobBmc::list(ctx, mm).await? {
+        if blob.ref_count == 0 {
+            continue;
+        }
+        report.checked += 1;
+        let failed = match store.check(&blob.sha256).await? {
+            BlobCheck::Ok => {
+                verified.push(blob.sha256.clone());
+                None
+            }
+            BlobCheck::Missing => Some((&mut report.missing, None)),
+            BlobCheck::Corrupted { actual } => Some((&mut report.corrupted, Some(actual))),
+        };
+        if let Some((findings, actual)) = failed {
+            findings.push(ScrubFinding {
+                hash: blob.sha256.clone(),
+                actual,
+                validation_file_ids: BlobBmc::references(ctx, mm, &blob.sha256).await?,
+            });
+        }
+        referenced.insert(blob.sha256);
+    }
+    report.verified = verified.len();
+    BlobBmc::mark_verified(ctx, mm, &verified).await?;
+
+    for file in BlobBmc::list_legacy_files(ctx, mm).await? {
+        report.checked += 1;
+        let key = BlobStore::file_key(
+            file.group_id,
+            file.validation_id,
+            &file.hash,
+            &file.extension,
+        );
+        let finding = |actual| ScrubFinding {
+            hash: file.hash.clone(),
+            actual,
+            validation_file_ids: vec![file.id],
+        };
+        match store.backend().open(&key, None).await? {
+            Some(reader) => {
+                let mut context = md5::Context::new();
+                for_each_chunk(reader, |chunk| context.consume(chunk)).await?;
+                let actual = format!("{:x}", context.finalize());
+                if actual == file.hash {
+                    report.verified += 1;
+                } else {
+                    report.corrupted.push(finding(Some(actual)));
+                }
+            }
+            None => report.missing.push(finding(None)),
+        }
+    }
+
+    report.orphaned = store
+        .list()
+        .await?
+        .into_iter()
+        .map(|stored| stored.sha256)
+        .filter(|sha256| !referenced.contains(sha256))
+        .collect();
+    Ok(report)
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// Removes unreferenced blobs not touched within `grace`. The grace period protects uploads
+/// whose row was written after this GC listed the blobs; each blob row stays locked until
+/// its object is gone, so a concurrent write of the same content waits and stores it anew.
+pub async fn gc(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    store: &BlobStore,
+    grace: chrono::Duration,
+) -> Result<GcReport> {
+    let cutoff = chrono::Utc::now() - grace;
+    let mut report = GcReport::default();
+    let mut known = HashSet::new();
+
+    for blob in BlobBmc::list(ctx, mm).await? {
+        if blob.ref_count > 0 {
+            known.insert(blob.sha256);
+            continue;
+        }
+        match BlobBmc::delete_unreferenced(ctx, mm, &blob.sha256, cutoff).await? {
+            Some(removal) => {
+                let removed = store.remove(&removal.blob.sha256).await?;
+                let deleted = removal.commit().await?;
+                if removed {
+                    report.freed_bytes += u64::try_from(deleted.size).unwrap_or_default();
+                }
+                report.removed.push(deleted.sha256);
+            }
+            None => {
+                known.insert(blob.sha256);
+            }
+        }
+    }
+
+    let cutoff = SystemTime::from(cutoff);
+    for stored in store.list().await? {
+        if known.contains(&stored.sha256) || stored.modified >= cutoff {
+            continue;
+        }
+        // A blob row appearing since the listing means an upload is in flight.
+        let size = i64::try_from(stored.size).unwrap_or(i64::MAX);
+        let Some(removal) = BlobBmc::delete_orphan(ctx, mm, &stored.sha256, size).await? else {
+            continue;
+        };
+        let removed = store.remove(&stored.sha256).await?;
+        removal.commit().await?;
+        if removed {
+            report.freed_bytes += stored.size;
+            report.removed.push(stored.sha256);
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub missing: usize,
+}
+
+/// Moves files stored under their legacy MD5 name into the blob store.
+pub async fn import_legacy_files(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    store: &BlobStore,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut imported_keys = HashSet::new();
+
+    for file in BlobBmc::list_legacy_files(ctx, mm).await? {
+        let key = BlobStore::file_key(
+            file.group_id,
+            file.validation_id,
+            &file.hash,
+            &file.extension,
+        );
+        let Some(reader) = store.backend().open(&key, None).await? else {
+            report.missing += 1;
+            continue;
+        };
+        let (sha256, size) = store.put_reader(ctx, mm, reader).await?;
+        let size = i64::try_from(size).unwrap_or(i64::MAX);
+        BlobBmc::set_file_blob(ctx, mm, file.id, &sha256, size).await?;
+        imported_keys.insert(key);
+        report.imported += 1;
+    }
+
+    // Several rows can share one legacy file, so it is only removed once all are moved.
+    for key in imported_keys {
+        if let Err(err) = store.backend().delete(&key).await {
+            warn!("BLOBS - cannot remove imported file {}: {}", key, err);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use airlab_lib::model::validation_file::{ValidationFileBmc, ValidationFileForCreate};
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn temp_store() -> BlobStore {
+        let dir = std::env::temp_dir().join(format!("airlab-blob-store-{}", uuid::Uuid::new_v4()));
+        BlobStore::local(&dir.to_string_lossy())
+    }
+
+    async fn s3_store() -> TestResult<BlobStore> {
+        let (endpoint, _) = s3::test_server::spawn("airlab").await?;
+        let backend = S3Backend::new(S3Config {
+            endpoint,
+            bucket: "airlab".into(),
+            region: "us-east-1".into(),
+            access_key: "test-key".into(),
+            secret_key: "test-secret".into(),
+        })?;
+        let staging = std::env::temp_dir().join(format!("airlab-staging-{}", uuid::Uuid::new_v4()));
+        Ok(BlobStore::new(
+            Arc::new(backend),
+            Utf8PathBuf::from_path_buf(staging).map_err(|_| "temp dir is not UTF-8")?,
+        ))
+    }
+
+    fn file_c(validation_id: i64, hash: &str, size: usize) -> TestResult<ValidationFileForCreate> {
+        Ok(ValidationFileForCreate {
+            validation_id,
+            created_by: 1303,
+            hash: hash.to_string(),
+            size: i64::try_from(size)?,
+            name: Some("scan.txt".into()),
+            extension: "txt".into(),
+            description: None,
+            created_at: chrono::Utc::now(),
+        })
+    }
+
+    #[tokio::test]
+    async fn put_deduplicates_and_check_detects_corruption() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        for store in [temp_store(), s3_store().await?] {
+            let sha256 = store.put(&ctx, &mm, b"stain").await?;
+            let key = BlobStore::key(&sha256);
+            assert_eq!(store.put(&ctx, &mm, b"stain").await?, sha256);
+            assert_eq!(store.list().await?.len(), 1);
+            assert_eq!(
+                store.backend().get(&key, None).await?,
+                Some(b"stain".to_vec())
+            );
+            assert_eq!(store.check(&sha256).await?, BlobCheck::Ok);
+
+            store.backend().put(&key, b"stain!").await?;
+            assert!(matches!(
+                store.check(&sha256).await?,
+                BlobCheck::Corrupted { .. }
+            ));
+            assert!(store.remove(&sha256).await?);
+            assert_eq!(store.check(&sha256).await?, BlobCheck::Missing);
+            assert!(!store.remove(&sha256).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn put_staged_streams_upload_into_store() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        for store in [temp_store(), s3_store().await?] {
+            let mut upload = store.stage().await?;
+            let staged = upload.path().to_owned();
+            upload.write(b"large ").await?;
+            upload.write(b"image").await?;
+            assert_eq!((upload.size(), upload.head()), (11, &b"large image"[..]));
+            assert_eq!(upload.finish().await?, BlobStore::digest(b"large image"));
+
+            let sha256 = store.put_staged(&ctx, &mm, upload).await?;
+            assert!(!staged.exists());
+            assert_eq!(
+                store.backend().get(&BlobStore::key(&sha256), None).await?,
+                Some(b"large image".to_vec())
+            );
+
+            let mut duplicate = store.stage().await?;
+            let staged = duplicate.path().to_owned();
+            duplicate.write(b"large image").await?;
+            assert_eq!(store.put_staged(&ctx, &mm, duplicate).await?, sha256);
+            assert!(!staged.exists());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scrub_reports_and_gc_removes_unreferenced_blobs() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let store = temp_store();
+
+        let kept = store.put(&ctx, &mm, b"kept").await?;
+        ValidationFileBmc::create(&ctx, &mm, file_c(1011, &kept, 4)?).await?;
+        let broken = store.put(&ctx, &mm, b"broken").await?;
+        ValidationFileBmc::create(&ctx, &mm, file_c(1011, &broken, 6)?).await?;
+        store
+            .backend()
+            .put(&BlobStore::key(&broken), b"tampered")
+            .await?;
+        let gone = BlobStore::digest(b"gone");
+        let gone_id = ValidationFileBmc::create(&ctx, &mm, file_c(1024, &gone, 4)?).await?;
+        let dropped = store.put(&ctx, &mm, b"dropped").await?;
+        let dropped_id = ValidationFileBmc::create(&ctx, &mm, file_c(1024, &dropped, 7)?).await?;
+        ValidationFileBmc::delete(&ctx, &mm, dropped_id).await?;
+        let stray = store.put(&ctx, &mm, b"stray").await?;
+
+        let report = scrub(&ctx, &mm, &store).await?;
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].hash, gone);
+        assert_eq!(report.missing[0].validation_file_ids, vec![gone_id]);
+        assert_eq!(report.corrupted.len(), 1);
+        assert_eq!(report.corrupted[0].hash, broken);
+        let mut orphaned = vec![dropped.clone(), stray.clone()];
+        orphaned.sort();
+        assert_eq!(report.orphaned, orphaned);
+        let blob = BlobBmc::get(&ctx, &mm, &kept).await?.ok_or("blob row")?;
+        assert!(blob.verified_at.is_some());
+
+        let report = gc(&ctx, &mm, &store, chrono::Duration::hours(1)).await?;
+        assert!(report.removed.is_empty());
+
+        // Unreferenced rows of other tests go as well, but only this store's objects count.
+        let report = gc(&ctx, &mm, &store, chrono::Duration::seconds(-1)).await?;
+        assert!(
+            orphaned
+                .iter()
+                .all(|sha256| report.removed.contains(sha256))
+        );
+        assert_eq!(report.freed_bytes, 12);
+        assert!(
+            store
+                .list()
+                .await?
+                .iter()
+                .all(|stored| stored.sha256 != stray)
+        );
+        assert!(BlobBmc::get(&ctx, &mm, &dropped).await?.is_none());
+        assert_eq!(store.check(&kept).await?, BlobCheck::Ok);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_unreferenced_blobs_written_again() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let store = temp_store();
+        let sha256 = store.put(&ctx, &mm, b"written again").await?;
+        sqlx::query("UPDATE blob SET touched_at = NOW() - INTERVAL '2 hours' WHERE sha256 = $1")
+            .bind(&sha256)
+            .execute(mm.db())
+            .await?;
+
+        assert_eq!(store.put(&ctx, &mm, b"written again").await?, sha256);
+        let report = gc(&ctx, &mm, &store, chrono::Duration::hours(1)).await?;
+
+        assert!(!report.removed.contains(&sha256));
+        assert_eq!(store.check(&sha256).await?, BlobCheck::Ok);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_moves_legacy_files_into_store() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let store = temp_store();
+        let md5 = format!("{:x}", md5::compute(b"legacy"));
+        let first = ValidationFileBmc::create(&ctx, &mm, file_c(1011, &md5, 6)?).await?;
+        let second = ValidationFileBmc::create(&ctx, &mm, file_c(1011, &md5, 6)?).await?;
+        let legacy = BlobStore::file_key(1000, 1011, &md5, "txt");
+        store.backend().put(&legacy, b"legacy").await?;
+
+        let report = scrub(&ctx, &mm, &store).await?;
+        assert!(report.corrupted.is_empty());
+
+        let report = import_legacy_files(&ctx, &mm, &store).await?;
+        assert_eq!(report.imported, 2);
+        let sha256 = BlobStore::digest(b"legacy");
+        for id in [first, second] {
+            assert_eq!(ValidationFileBmc::get(&ctx, &mm, id).await?.hash, sha256);
+        }
+        let blob = BlobBmc::get(&ctx, &mm, &sha256).await?.ok_or("blob row")?;
+        assert_eq!(blob.ref_count, 2);
+        assert_eq!(
+            store.backend().get(&BlobStore::key(&sha256), None).await?,
+            Some(b"legacy".to_vec())
+        );
+        assert!(store.backend().head(&legacy).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Uploads on their way into the blob store. The bytes are written to a local staging file
+//! and hashed as they arrive, so an upload is never held in memory as a whole.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Bytes kept from the start of an upload for content sniffing.
+pub const SNIFF_LEN: usize = 512;
+
+#[derive(Debug)]
+pub struct StagedUpload {
+    path: Utf8PathBuf,
+    file: Option<BufWriter<fs::File>>,
+    hasher: Sha256,
+    size: u64,
+    head: Vec<u8>,
+    sha256: Option<String>,
+}
+
+impl StagedUpload {
+    pub async fn create(dir: &Utf8Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("upload-{}", uuid::Uuid::new_v4()));
+        let file = fs::File::create(&path).await?;
+        Ok(Self {
+            path,
+            file: Some(BufWriter::new(file)),
+            hasher: Sha256::new(),
+            size: 0,
+            head: vec![],
+            sha256: None,
+        })
+    }
+
+    pub async fn write(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Err(std::io::Error::other("staged upload is already finished"));
+        };
+        file.write_all(chunk).await?;
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+        let missing = SNIFF_LEN.saturating_sub(self.head.len());
+        self.head
+            .extend_from_slice(chunk.get(..missing.min(chunk.len())).unwrap_or_default());
+        Ok(())
+    }
+
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The first [`SNIFF_LEN`] bytes.
+    pub fn head(&self) -> &[u8] {
+        &self.head
+    }
+
+    /// Flushes the staging file to disk and returns the SHA-256 of the upload.
+    pub async fn finish(&mut self) -> std::io::Result<String> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await?;
+            file.get_ref().sync_all().await?;
+            self.sha256 = Some(format!("{:x}", self.hasher.clone().finalize()));
+        }
+        self.sha256
+            .clone()
+            .ok_or_else(|| std::io::Error::other("staged upload has no hash"))
+    }
+
+    pub(crate) fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Drop for StagedUpload {
+    fn drop(&mut self) {
+        // Already moved into the store unless the upload was rejected or failed.
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
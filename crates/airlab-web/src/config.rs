@@ -1,5 +1,6 @@
 #![allow(clippy::module_name_repetitions)]
-use airlab_lib::envs::{get_env, get_env_parse};
+use airlab_lib::envs::{self, get_env, get_env_parse};
+use std::str::FromStr;
 #[cfg(not(test))]
 use std::sync::OnceLock;
 
@@ -36,6 +37,32 @@ pub struct WebConfig {
     pub SUPER_USER: String,
     pub SUPER_USER_PWD: String,
     pub SETUP_DEMO_GROUP: bool,
+    /// `local` (default) keeps uploads under `DATA_PATH`, `s3` in the `S3_*` bucket.
+    pub STORAGE_BACKEND: String,
+    pub S3_ENDPOINT: Option<String>,
+    pub S3_BUCKET: Option<String>,
+    pub S3_REGION: String,
+    pub S3_ACCESS_KEY: Option<String>,
+    pub S3_SECRET_KEY: Option<String>,
+    /// Largest accepted upload per file, in bytes.
+    pub MAX_UPLOAD_BYTES: u64,
+    /// Total bytes of validation files a group may keep; unlimited when unset.
+    pub GROUP_UPLOAD_QUOTA_BYTES: Option<u64>,
+    /// Comma-separated file extensions accepted for uploads; the built-in list when unset.
+    pub UPLOAD_EXTENSIONS: Option<String>,
+    /// Build all search shadows before serving requests, unless a current snapshot has them.
+    pub SEARCH_WARM_UP: bool,
+}
+
+const DEFAULT_MAX_UPLOAD_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// `None` when `name` is unset; an error when it is set but does not parse.
+fn get_env_parse_opt<T: FromStr>(name: &'static str) -> envs::Result<Option<T>> {
+    match get_env_parse(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(envs::Error::MissingEnv(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
 }
 
 impl WebConfig {
@@ -53,6 +80,18 @@ impl WebConfig {
             SUPER_USER: get_env("SUPER_USER")?,
             SUPER_USER_PWD: get_env("SUPER_USER_PWD")?,
             SETUP_DEMO_GROUP: get_env_parse("SETUP_DEMO_GROUP")?,
+            STORAGE_BACKEND: get_env("SERVICE_STORAGE_BACKEND")
+                .unwrap_or_else(|_| "local".to_string()),
+            S3_ENDPOINT: get_env("SERVICE_S3_ENDPOINT").ok(),
+            S3_BUCKET: get_env("SERVICE_S3_BUCKET").ok(),
+            S3_REGION: get_env("SERVICE_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            S3_ACCESS_KEY: get_env("SERVICE_S3_ACCESS_KEY").ok(),
+            S3_SECRET_KEY: get_env("SERVICE_S3_SECRET_KEY").ok(),
+            MAX_UPLOAD_BYTES: get_env_parse_opt("SERVICE_MAX_UPLOAD_BYTES")?
+                .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES),
+            GROUP_UPLOAD_QUOTA_BYTES: get_env_parse_opt("SERVICE_GROUP_UPLOAD_QUOTA_BYTES")?,
+            UPLOAD_EXTENSIONS: get_env("SERVICE_UPLOAD_EXTENSIONS").ok(),
+            SEARCH_WARM_UP: get_env_parse_opt("SERVICE_SEARCH_WARM_UP")?.unwrap_or(false),
         })
     }
 }
@@ -94,6 +133,12 @@ mod tests {
         assert_eq!(config.HOST_PORT, "3000");
         assert_eq!(config.SUPER_USER, "admin@example.test");
         assert!(!config.SETUP_DEMO_GROUP);
+        assert_eq!(config.STORAGE_BACKEND, "local");
+        assert_eq!(config.S3_REGION, "us-east-1");
+        assert!(config.S3_BUCKET.is_none());
+        assert_eq!(config.MAX_UPLOAD_BYTES, DEFAULT_MAX_UPLOAD_BYTES);
+        assert!(!config.SEARCH_WARM_UP);
+        assert!(config.GROUP_UPLOAD_QUOTA_BYTES.is_none());
         Ok(())
     }
 
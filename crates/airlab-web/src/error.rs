@@ -9,6 +9,8 @@ pub enum Error {
     #[from]
     Model(model::Error),
     #[from]
+    BlobStore(crate::blob_store::Error),
+    #[from]
     Env(envs::Error),
     #[from]
     Io(std::io::Error),
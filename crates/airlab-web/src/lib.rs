@@ -1,3 +1,5 @@
+pub mod acquisition;
+pub mod blob_store;
 pub mod config;
 pub mod error;
 pub mod log;
@@ -1,4 +1,6 @@
 #![allow(clippy::missing_errors_doc)]
+mod acquisition;
+mod blob_store;
 mod config;
 mod error;
 mod log;
@@ -6,21 +8,27 @@ mod search_shadow;
 mod util;
 mod web;
 
+use crate::blob_store::{BlobState, BlobStore};
 use crate::search_shadow::SearchState;
 use crate::web::mw_auth::mw_ctx_resolve;
 use crate::web::mw_res_map::{mw_reponse_map, mw_request_track};
 use crate::web::{
-    routes_fallback, routes_group, routes_json, routes_login, routes_search, routes_static,
-    routes_telemetry, routes_user, routes_validation_file, routes_ws,
+    routes_blob_store, routes_conjugation_batch, routes_fallback, routes_group, routes_json,
+    routes_login, routes_report, routes_saved_search, routes_search, routes_static,
+    routes_telemetry, routes_user, routes_validation_file, routes_validation_review, routes_ws,
 };
 use airlab_lib::model::ModelManager;
+use airlab_lib::model::clone::CloneBmc;
+use airlab_lib::model::data_migration::DataMigrationBmc;
+use airlab_lib::model::panel_element::PanelElementBmc;
 use airlab_lib::model::user::{UserBmc, UserForCreate, UserForUpdate};
+use airlab_lib::model::validation::ValidationBmc;
 use axum::{Router, middleware};
 use config::web_config;
 use tokio::net::TcpListener;
 use tower_cookies::CookieManagerLayer;
 #[allow(unused_imports)]
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use tracing_subscriber::EnvFilter;
 
 pub use self::error::{Error, Result};
@@ -52,6 +60,62 @@ async fn setup_admin_user(mm: &ModelManager) -> Result<()> {
     Ok(())
 }
 
+const CONCENTRATION_UNITS_MIGRATION: &str = "validation_concentration_units";
+
+/// Rewrites legacy validation concentrations once, with the parser the API uses on write,
+/// and keeps the values it could not read with the migration.
+async fn migrate_concentration_units(mm: &ModelManager) -> Result<()> {
+    let ctx = airlab_lib::ctx::Ctx::root_ctx();
+    let Some(mut run) = DataMigrationBmc::begin(&ctx, mm, CONCENTRATION_UNITS_MIGRATION).await?
+    else {
+        return Ok(());
+    };
+    let mut report = airlab_lib::units::MigrationReport::default();
+    ValidationBmc::normalize_concentrations(&ctx, run.conn(), &mut report).await?;
+    PanelElementBmc::report_untyped_concentrations(&ctx, run.conn(), &mut report).await?;
+
+    info!(
+        "UNITS - normalized {} concentrations, {} already canonical, {} unparseable",
+        report.normalized,
+        report.unchanged,
+        report.unparseable.len()
+    );
+    for item in &report.unparseable {
+        warn!(
+            "UNITS - unparseable {} {}: {:?} ({})",
+            item.entity, item.id, item.value, item.error
+        );
+    }
+    run.finish(&report).await?;
+    Ok(())
+}
+
+const CLONE_APPLICATIONS_MIGRATION: &str = "clone_application_status";
+
+/// Rewrites legacy clone application shapes once, through the type the API reads them with.
+async fn migrate_clone_applications(mm: &ModelManager) -> Result<()> {
+    let ctx = airlab_lib::ctx::Ctx::root_ctx();
+    let Some(mut run) = DataMigrationBmc::begin(&ctx, mm, CLONE_APPLICATIONS_MIGRATION).await?
+    else {
+        return Ok(());
+    };
+    let normalized = CloneBmc::normalize_applications(&ctx, run.conn()).await?;
+    info!("CLONES - normalized {normalized} application entries");
+    run.finish(&serde_json::json!({ "normalized": normalized }))
+        .await?;
+    Ok(())
+}
+
+async fn migrate_validation_files(mm: &ModelManager, store: &BlobStore) -> Result<()> {
+    let ctx = airlab_lib::ctx::Ctx::root_ctx();
+    let report = blob_store::import_legacy_files(&ctx, mm, store).await?;
+    info!(
+        "BLOBS - imported {} validation files, {} missing on disk",
+        report.imported, report.missing
+    );
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -64,7 +128,19 @@ async fn main() -> Result<()> {
     sqlx::migrate!().run(mm.db()).await?;
 
     setup_admin_user(&mm).await?;
+    migrate_concentration_units(&mm).await?;
+    migrate_clone_applications(&mm).await?;
+    let blob_state = BlobState::from_config(mm.clone())?;
+    migrate_validation_files(&mm, &blob_state.store).await?;
+    let config = web_config()?;
     let search_state = SearchState::new(mm.clone());
+    let snapshot_path = search_shadow::snapshot::path(&config.DATA_PATH);
+    search_shadow::snapshot::restore(&search_state, &snapshot_path).await;
+    if config.SEARCH_WARM_UP {
+        search_state.registry.warm_up(&mm).await?;
+    }
+    search_shadow::notify::spawn_listener(&search_state);
+    search_shadow::snapshot::spawn_writer(&search_state, snapshot_path);
 
     let routes_all = Router::new()
         .merge(routes_login::routes(mm.clone()))
@@ -72,8 +148,13 @@ async fn main() -> Result<()> {
         .merge(routes_group::routes(mm.clone()))
         .merge(routes_fallback::routes(mm.clone()))
         .merge(routes_json::routes(search_state.clone()))
-        .merge(routes_validation_file::routes(mm.clone()))
+        .merge(routes_validation_file::routes(blob_state.clone()))
+        .merge(routes_blob_store::routes(blob_state.clone()))
+        .merge(routes_validation_review::routes(mm.clone()))
+        .merge(routes_conjugation_batch::routes(mm.clone()))
+        .merge(routes_report::routes(blob_state))
         .merge(routes_telemetry::routes(mm.clone()))
+        .merge(routes_saved_search::routes(search_state.clone()))
         .merge(routes_search::routes(search_state))
         .layer(middleware::map_response(mw_reponse_map))
         .layer(middleware::from_fn_with_state(mm.clone(), mw_ctx_resolve))
@@ -82,7 +163,6 @@ async fn main() -> Result<()> {
         .merge(routes_ws::routes())
         .fallback_service(routes_static::serve_dir()?);
 
-    let config = web_config()?;
     let listener =
         TcpListener::bind(&format!("{}:{}", &config.HOST_ADDR, &config.HOST_PORT)).await?;
     info!("LISTENING - {:?}\n", listener.local_addr());
@@ -96,6 +176,7 @@ mod tests {
     use super::*;
     use airlab_lib::ctx::Ctx;
     use airlab_lib::model::user::{User, UserBmc};
+    use serde_json::json;
     use serial_test::serial;
 
     type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -121,6 +202,132 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn validation_review_migration_approves_existing_validations() -> TestResult {
+        crate::web::test_support::init_web_test_env();
+        let mm = airlab_lib::_dev_utils::init_test().await;
+        let mut tx = mm.db().begin().await?;
+
+        let legacy = airlab_lib::_dev_utils::replay_validation_review_migration(
+            &mut tx,
+            "INSERT INTO public.validation (group_id, created_by, clone_id, application, status) \
+             VALUES (1000, 1303, 1016, 1, 0) RETURNING id",
+        )
+        .await?;
+        let created: i64 = sqlx::query_scalar(
+            "INSERT INTO public.validation (group_id, created_by, clone_id, application, status) \
+             VALUES (1000, 1303, 1016, 1, 0) RETURNING id",
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let state = async |tx: &mut sqlx::PgConnection, id: i64| {
+            sqlx::query_scalar::<_, String>("SELECT review_state FROM validation WHERE id = $1")
+                .bind(id)
+                .fetch_one(tx)
+                .await
+        };
+
+        assert_eq!(legacy.len(), 1);
+        assert_eq!(state(&mut tx, legacy[0]).await?, "approved");
+        assert_eq!(state(&mut tx, created).await?, "draft");
+        tx.rollback().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn concentration_units_migration_normalizes_legacy_values() -> TestResult {
+        crate::web::test_support::init_web_test_env();
+        let mm = airlab_lib::_dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let cases = [
+            ("2,5 µg/ml", None, "2.5", Some("ug/mL")),
+            ("700 ng/mL", None, "0.7", Some("ug/mL")),
+            ("1:200", None, "200", Some("dilution")),
+            ("0,5", Some("mg/mL"), "0.5", Some("mg/mL")),
+            ("a lot", Some("ug/mL"), "a lot", Some("ug/mL")),
+        ];
+
+        for (concentration, unit, expected, expected_unit) in cases {
+            sqlx::query("DELETE FROM data_migration WHERE name = $1")
+                .bind(CONCENTRATION_UNITS_MIGRATION)
+                .execute(mm.db())
+                .await?;
+            sqlx::query(
+                "UPDATE validation SET concentration = $1, concentration_unit = $2 WHERE id = 1011",
+            )
+            .bind(concentration)
+            .bind(unit)
+            .execute(mm.db())
+            .await?;
+
+            migrate_concentration_units(&mm).await?;
+
+            let validation = ValidationBmc::get(&ctx, &mm, 1011).await?;
+            assert_eq!(validation.concentration.as_deref(), Some(expected));
+            assert_eq!(validation.concentration_unit.as_deref(), expected_unit);
+        }
+
+        let report = DataMigrationBmc::report(&ctx, &mm, CONCENTRATION_UNITS_MIGRATION)
+            .await?
+            .unwrap_or_default();
+        let unparseable = report["unparseable"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        assert!(unparseable.iter().any(|item| {
+            item["entity"] == "validation" && item["id"] == 1011 && item["value"] == "a lot ug/mL"
+        }));
+
+        sqlx::query("UPDATE validation SET concentration = '2,5 µg/ml' WHERE id = 1011")
+            .execute(mm.db())
+            .await?;
+        migrate_concentration_units(&mm).await?;
+        let validation = ValidationBmc::get(&ctx, &mm, 1011).await?;
+        assert_eq!(validation.concentration.as_deref(), Some("2,5 µg/ml"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn clone_application_migration_rewrites_legacy_shapes() -> TestResult {
+        crate::web::test_support::init_web_test_env();
+        let mm = airlab_lib::_dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let cases = [
+            (
+                json!({"1": true, "2": false, "6": "true", "x": true, "3": null}),
+                json!({"1": {"status": 0}, "2": {"status": 2}, "6": {"status": 0}}),
+            ),
+            (
+                json!(["IMC", {"application": 6, "status": "so-so", "note": " weak band "}]),
+                json!({"1": {"status": 0}, "6": {"status": 1, "note": "weak band"}}),
+            ),
+            (
+                json!({"4": {"status": 2, "note": "high background"}}),
+                json!({"4": {"status": 2, "note": "high background"}}),
+            ),
+        ];
+
+        for (legacy, expected) in cases {
+            sqlx::query("DELETE FROM data_migration WHERE name = $1")
+                .bind(CLONE_APPLICATIONS_MIGRATION)
+                .execute(mm.db())
+                .await?;
+            sqlx::query("UPDATE clone SET application = $1 WHERE id = 1006")
+                .bind(&legacy)
+                .execute(mm.db())
+                .await?;
+
+            migrate_clone_applications(&mm).await?;
+
+            let clone = CloneBmc::get(&ctx, &mm, 1006).await?;
+            assert_eq!(clone.application, Some(expected));
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn setup_admin_user_is_idempotent_when_users_exist() -> TestResult {
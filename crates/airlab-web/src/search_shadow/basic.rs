@@ -1,13 +1,24 @@
+use super::facet::{FacetCounts, FacetTally, SearchFacet};
+use super::filter::{FieldValue, date_epoch, matches, resolve_filter};
+use super::hydrate::Projection;
+use super::query::{
+    Comparison, ParseError, QueryCondition, QueryExpr, QueryTerm, TermValue, TextTerm,
+};
+use super::text::{
+    DEFAULT_BOOST, IDENTIFIER_BOOST, NAME_BOOST, NOTE_BOOST, Snippet, TextIndex, snippet,
+};
 use crate::web::routes_search::{Direction, ReturnType, SearchOrder, allowed_filter_tables};
 use airlab_lib::model::ModelManager;
-use serde_json::Value;
+use airlab_lib::model::clone_application::{Application, ApplicationStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
 use sqlx::FromRow;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::warn;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BasicShadowKind {
     User,
     Member,
@@ -63,6 +74,25 @@ impl BasicShadowKind {
         }
     }
 
+    pub fn return_type(self) -> ReturnType {
+        match self {
+            Self::User => ReturnType::User,
+            Self::Member => ReturnType::Member,
+            Self::Group => ReturnType::Group,
+            Self::Protein => ReturnType::Protein,
+            Self::Provider => ReturnType::Provider,
+            Self::Species => ReturnType::Species,
+            Self::Tag => ReturnType::Tag,
+            Self::Lot => ReturnType::Lot,
+            Self::Conjugate => ReturnType::Conjugate,
+            Self::Panel => ReturnType::Panel,
+            Self::PanelElement => ReturnType::PanelElement,
+            Self::Validation => ReturnType::Validation,
+            Self::Storage => ReturnType::Storage,
+            Self::Collection => ReturnType::Collection,
+        }
+    }
+
     pub fn label(self) -> &'static str {
         match self {
             Self::User => "user",
@@ -87,17 +117,155 @@ impl BasicShadowKind {
 pub struct BasicGroupShadow {
     pub kind: BasicShadowKind,
     pub rows: Arc<Vec<BasicShadowRow>>,
+    /// Over the text fields of `rows`, numbered by position.
+    index: TextIndex,
 }
 
-#[derive(Debug, Clone)]
+impl BasicGroupShadow {
+    pub fn new(kind: BasicShadowKind, rows: Vec<BasicShadowRow>) -> Self {
+        let index = TextIndex::build(rows.iter().map(BasicShadowRow::text_fields));
+        Self {
+            kind,
+            rows: Arc::new(rows),
+            index,
+        }
+    }
+
+    /// The rows of the groups in `group_ids` that match `term`, best first.
+    pub fn rank(&self, term: &TextTerm, group_ids: &HashSet<i64>) -> Vec<(&BasicShadowRow, f64)> {
+        let mut ranked = term
+            .search(&self.index, |doc| self.rows[doc].fulltext.as_str())
+            .into_iter()
+            .map(|(doc, score)| (&self.rows[doc], score))
+            .filter(|(row, _)| {
+                row.int("group_id")
+                    .is_some_and(|id| group_ids.contains(&id))
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(left, left_score), (right, right_score)| {
+            right_score
+                .total_cmp(left_score)
+                .then_with(|| left.id.cmp(&right.id))
+        });
+        ranked
+    }
+
+    /// The rows of `ids` as objects, in order.
+    pub fn objects(&self, ids: &[i64]) -> Vec<Map<String, Value>> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| (row.id, row))
+            .collect::<HashMap<_, _>>();
+        ids.iter()
+            .filter_map(|id| rows.get(id))
+            .map(|row| row.to_object())
+            .collect()
+    }
+
+    /// A copy with the rows of `ids` replaced by `rows`; ids without new rows are dropped.
+    pub fn replace_rows(&self, ids: &HashSet<i64>, rows: Vec<BasicShadowRow>) -> Self {
+        let mut replaced = self
+            .rows
+            .iter()
+            .filter(|row| !ids.contains(&row.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        replaced.extend(rows);
+        Self::new(self.kind, replaced)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BasicShadowRow {
     pub id: i64,
     pub fulltext: String,
     values: HashMap<String, BasicShadowValue>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl BasicShadowRow {
+    /// The integer stored under `key`, such as a foreign key.
+    pub fn int(&self, key: &str) -> Option<i64> {
+        match self.values.get(key) {
+            Some(BasicShadowValue::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The text stored under `key`, unless empty.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.values.get(key) {
+            Some(BasicShadowValue::Text(value)) if !value.is_empty() => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The flag stored under `key`.
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        match self.values.get(key) {
+            Some(BasicShadowValue::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Where the row's text fields contain words of `query`, preferring its own name among
+    /// fields of the same boost.
+    pub fn snippet(&self, query: &str) -> Option<Snippet> {
+        let mut fields = self
+            .values
+            .iter()
+            .filter_map(|(key, value)| match value {
+                BasicShadowValue::Text(text) => Some((key.as_str(), text.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        fields.sort_by_key(|(key, _)| (*key != "name", *key));
+        snippet(
+            fields
+                .into_iter()
+                .map(|(key, text)| (text, field_boost(key))),
+            query,
+        )
+    }
+
+    /// The row's values by key.
+    pub fn to_object(&self) -> Map<String, Value> {
+        let mut object = self
+            .values
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_json()))
+            .collect::<Map<_, _>>();
+        object.insert("id".to_string(), json!(self.id));
+        object
+    }
+
+    fn text_fields(&self) -> Vec<(&str, f64)> {
+        self.values
+            .iter()
+            .filter_map(|(key, value)| match value {
+                BasicShadowValue::Text(text) => Some((text.as_str(), field_boost(key))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Names rank above identifiers, which rank above descriptions.
+fn field_boost(key: &str) -> f64 {
+    match key {
+        "name" | "protein_name" | "clone_name" | "tag_name" | "acronym" => NAME_BOOST,
+        "number" | "lot_number" | "reference" | "custom_id" | "email" | "user_name" => {
+            IDENTIFIER_BOOST
+        }
+        "description" | "conjugate_description" | "group_name" => NOTE_BOOST,
+        _ => DEFAULT_BOOST,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 enum BasicShadowValue {
+    /// A missing number or date.
+    Null,
     Int(i64),
     Float(f64),
     Bool(bool),
@@ -105,8 +273,31 @@ enum BasicShadowValue {
 }
 
 impl BasicShadowValue {
+    fn field_value(&self) -> FieldValue<'_> {
+        match self {
+            Self::Null => FieldValue::Null,
+            Self::Int(value) => FieldValue::Int(*value),
+            Self::Float(value) => FieldValue::Float(*value),
+            Self::Bool(value) => FieldValue::Bool(*value),
+            Self::Text(value) => FieldValue::Text(value),
+        }
+    }
+
+    /// Empty text reads as null, like the missing value it stands for.
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Null => Value::Null,
+            Self::Int(value) => json!(value),
+            Self::Float(value) => json!(value),
+            Self::Bool(value) => json!(value),
+            Self::Text(value) if value.is_empty() => Value::Null,
+            Self::Text(value) => json!(value),
+        }
+    }
+
     fn to_fulltext(&self) -> String {
         match self {
+            Self::Null => String::new(),
             Self::Int(value) => value.to_string(),
             Self::Float(value) => value.to_string(),
             Self::Bool(value) => value.to_string(),
@@ -118,24 +309,45 @@ impl BasicShadowValue {
 #[derive(Debug, Clone)]
 pub struct BasicShadowFilter {
     pub key: String,
+    pub op: Comparison,
     pub value: Value,
 }
 
 #[derive(Debug, Clone)]
 pub struct BasicShadowQuery {
     pub kind: BasicShadowKind,
-    pub filters: Vec<BasicShadowFilter>,
-    pub global_filter: Option<String>,
-    pub order_key: Option<String>,
-    pub direction: Direction,
+    pub filters: Vec<QueryCondition<BasicShadowFilter>>,
+    /// The global filter, resolved against this kind.
+    pub condition: Option<QueryCondition<BasicShadowFilter>>,
+    /// Later orders order ties of earlier ones; by id when empty.
+    pub order: Vec<BasicShadowOrder>,
+    /// Facets paired with the key of their field.
+    pub facets: Vec<(SearchFacet, String)>,
+    /// Return rows instead of ids.
+    pub projection: Option<Projection>,
     pub page: i64,
     pub limit: i64,
 }
 
+#[derive(Debug, Clone)]
+pub enum BasicShadowOrderKey {
+    Key(String),
+    /// How well rows match the global filter.
+    Relevance,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicShadowOrder {
+    pub key: BasicShadowOrderKey,
+    pub direction: Direction,
+}
+
 #[derive(Debug, Clone)]
 pub struct BasicShadowSearchResult {
     pub items: Vec<i64>,
     pub search_total: i64,
+    /// Over all matching rows, not only the page.
+    pub facets: Vec<FacetCounts>,
 }
 
 #[derive(Debug, FromRow)]
@@ -246,6 +458,9 @@ struct LotShadowDbRow {
     number: Option<String>,
     status: i64,
     is_archived: bool,
+    ordered_at_epoch: Option<i64>,
+    received_at_epoch: Option<i64>,
+    created_at_epoch: i64,
     clone_name: Option<String>,
     protein_name: Option<String>,
     provider_name: Option<String>,
@@ -300,6 +515,7 @@ struct ConjugateShadowDbRow {
     species_name: Option<String>,
     tag_name: Option<String>,
     tag_mw: Option<i64>,
+    storage_name: Option<String>,
     collection_id: Option<i64>,
     collection_name: Option<String>,
     group_name: Option<String>,
@@ -344,10 +560,20 @@ pub async fn build_basic_shadow(
     mm: &ModelManager,
     kind: BasicShadowKind,
 ) -> airlab_lib::model::Result<BasicGroupShadow> {
+    let rows = build_basic_rows(mm, kind, None).await?;
+    Ok(BasicGroupShadow::new(kind, rows))
+}
+
+/// The shadow rows of `kind`, or only those of the entities in `ids` when given.
+pub async fn build_basic_rows(
+    mm: &ModelManager,
+    kind: BasicShadowKind,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
     let rows = match kind {
-        BasicShadowKind::User => build_user_rows(mm).await?,
-        BasicShadowKind::Member => build_member_rows(mm).await?,
-        BasicShadowKind::Group => build_group_rows(mm).await?,
+        BasicShadowKind::User => build_user_rows(mm, ids).await?,
+        BasicShadowKind::Member => build_member_rows(mm, ids).await?,
+        BasicShadowKind::Group => build_group_rows(mm, ids).await?,
         BasicShadowKind::Protein => {
             build_named_group_rows(
                 mm,
@@ -362,6 +588,7 @@ pub async fn build_basic_shadow(
             FROM public.protein p
             LEFT JOIN public."group" g ON g.id = p.group_id
             "#,
+                ids,
             )
             .await?
         }
@@ -379,24 +606,21 @@ pub async fn build_basic_shadow(
             FROM public.provider p
             LEFT JOIN public."group" g ON g.id = p.group_id
             "#,
+                ids,
             )
             .await?
         }
-        BasicShadowKind::Species => build_species_rows(mm).await?,
-        BasicShadowKind::Tag => build_tag_rows(mm).await?,
-        BasicShadowKind::Lot => build_lot_rows(mm).await?,
-        BasicShadowKind::Conjugate => build_conjugate_rows(mm).await?,
-        BasicShadowKind::Panel => build_panel_rows(mm).await?,
-        BasicShadowKind::PanelElement => build_panel_element_rows(mm).await?,
-        BasicShadowKind::Validation => build_validation_rows(mm).await?,
-        BasicShadowKind::Storage => build_storage_rows(mm).await?,
-        BasicShadowKind::Collection => build_collection_rows(mm).await?,
+        BasicShadowKind::Species => build_species_rows(mm, ids).await?,
+        BasicShadowKind::Tag => build_tag_rows(mm, ids).await?,
+        BasicShadowKind::Lot => build_lot_rows(mm, ids).await?,
+        BasicShadowKind::Conjugate => build_conjugate_rows(mm, ids).await?,
+        BasicShadowKind::Panel => build_panel_rows(mm, ids).await?,
+        BasicShadowKind::PanelElement => build_panel_element_rows(mm, ids).await?,
+        BasicShadowKind::Validation => build_validation_rows(mm, ids).await?,
+        BasicShadowKind::Storage => build_storage_rows(mm, ids).await?,
+        BasicShadowKind::Collection => build_collection_rows(mm, ids).await?,
     };
-
-    Ok(BasicGroupShadow {
-        kind,
-        rows: Arc::new(rows),
-    })
+    Ok(rows)
 }
 
 pub fn search_basic_shadow(
@@ -407,35 +631,76 @@ pub fn search_basic_shadow(
         kind = shadow.kind.label(),
         row_count = shadow.rows.len(),
         filter_count = query.filters.len(),
-        has_global_filter = query
-            .global_filter
-            .as_ref()
-            .is_some_and(|value| !value.trim().is_empty()),
-        order_key = query.order_key.as_deref().unwrap_or("id"),
-        direction = ?query.direction,
+        has_global_filter = query.condition.is_some(),
+        order = ?query.order,
         page = query.page,
         limit = query.limit,
         "searching basic shadow table"
     );
 
+    let text_scores = query
+        .condition
+        .as_ref()
+        .map_or_else(HashMap::new, |condition| {
+            condition
+                .text_terms()
+                .into_iter()
+                .map(|term| {
+                    let scores =
+                        term.search(&shadow.index, |doc| shadow.rows[doc].fulltext.as_str());
+                    (term, scores)
+                })
+                .collect::<HashMap<_, _>>()
+        });
     let mut matches = shadow
         .rows
         .iter()
-        .filter(|row| {
+        .enumerate()
+        .filter(|(_, row)| {
             query
                 .filters
                 .iter()
-                .all(|filter| row_matches_filter(row, filter))
+                .all(|condition| condition.matches(&|filter| row_matches_filter(row, filter)))
+        })
+        .filter_map(|(doc, row)| match &query.condition {
+            Some(condition) => condition
+                .score(&|filter| row_matches_filter(row, filter), &|term| {
+                    text_scores
+                        .get(term)
+                        .and_then(|scores| scores.get(&doc))
+                        .copied()
+                })
+                .map(|score| (row, score)),
+            None => Some((row, 0.0)),
         })
-        .filter(|row| row_matches_global(row, &query.global_filter))
         .collect::<Vec<_>>();
 
-    let order_key = query.order_key.as_deref().unwrap_or("id");
-    matches.sort_by(|left, right| compare_rows(left, right, order_key, &query.direction));
+    matches.sort_by(|left, right| compare_rows(*left, *right, &query.order));
+
+    let facets = query
+        .facets
+        .iter()
+        .map(|(facet, key)| {
+            let labels = Labels::of(shadow.kind, key);
+            let mut tally = FacetTally::default();
+            for (row, _) in &matches {
+                let value = row
+                    .values
+                    .get(key)
+                    .map_or(Value::Null, BasicShadowValue::to_json);
+                let label = labels
+                    .zip(value.as_i64())
+                    .and_then(|(labels, id)| labels.label(id));
+                tally.add(row.id, value, label);
+            }
+            tally.into_counts(facet)
+        })
+        .collect();
 
     let mut seen_ids = HashSet::new();
     let deduped = matches
         .into_iter()
+        .map(|(row, _)| row)
         .filter(|row| seen_ids.insert(row.id))
         .collect::<Vec<_>>();
 
@@ -453,61 +718,262 @@ pub fn search_basic_shadow(
             .map(|row| row.id)
             .collect(),
         search_total: total,
+        facets,
     }
 }
 
+/// `None` when `req` is not for a basic shadow; an error when `global` names fields the
+/// kind does not have.
 pub fn map_basic_shadow_query(
     req: &crate::web::routes_search::RpcSearchRequest,
-) -> Option<BasicShadowQuery> {
-    let kind = BasicShadowKind::from_return_type(req.return_type)?;
+    global: Option<&QueryExpr>,
+) -> Result<Option<BasicShadowQuery>, ParseError> {
+    let Some(kind) = BasicShadowKind::from_return_type(req.return_type) else {
+        return Ok(None);
+    };
     let allowed = allowed_filter_tables(req.return_type);
-    let filters = req
-        .filters
+    let mut filters = vec![];
+    for (position, filter) in req.filters.iter().enumerate() {
+        if !allowed.contains(&filter.table) {
+            continue;
+        }
+        let Some(key) = field_key(kind, filter.table, &filter.field) else {
+            warn!(
+                kind = kind.label(),
+                table = ?filter.table,
+                field = filter.field,
+                "skipping unsupported shadow filter"
+            );
+            continue;
+        };
+        filters.extend(resolve_filter(position, filter, &mut |op, value| {
+            Some(BasicShadowFilter {
+                key: key.clone(),
+                op,
+                value: value.clone(),
+            })
+        })?);
+    }
+    let condition = global
+        .map(|expr| expr.resolve(&mut |term| resolve_term(kind, req.return_type, &allowed, term)))
+        .transpose()?;
+    let facets = req
+        .facets
         .iter()
-        .filter(|filter| allowed.contains(&filter.table))
-        .filter_map(|filter| {
-            let Some(key) = field_key(kind, filter.table, &filter.field) else {
+        .filter(|facet| allowed.contains(&facet.table))
+        .filter_map(|facet| {
+            let Some(key) = field_key(kind, facet.table, &facet.field) else {
                 warn!(
                     kind = kind.label(),
-                    table = ?filter.table,
-                    field = filter.field,
-                    "skipping unsupported shadow filter"
+                    table = ?facet.table,
+                    field = facet.field,
+                    "skipping unsupported shadow facet"
                 );
                 return None;
             };
-
-            Some(BasicShadowFilter {
-                key,
-                value: filter.value.clone(),
-            })
+            Some((facet.clone(), key))
         })
-        .collect::<Vec<_>>();
+        .collect();
 
-    let (order_key, direction) = match req.order.as_ref() {
-        Some(order) if allowed.contains(&order.table) => match map_order_key(kind, order) {
-            Some(key) => (Some(key), order.direction),
-            None => {
+    let order = req
+        .order
+        .iter()
+        .filter_map(|order| {
+            let key = if order.is_relevance() {
+                BasicShadowOrderKey::Relevance
+            } else if let Some(key) = allowed
+                .contains(&order.table)
+                .then(|| map_order_key(kind, order))
+                .flatten()
+            {
+                BasicShadowOrderKey::Key(key)
+            } else {
                 warn!(
                     kind = kind.label(),
                     table = ?order.table,
                     field = order.field,
                     "skipping unsupported shadow order"
                 );
-                (None, Direction::Asc)
-            }
-        },
-        None => (None, Direction::Asc),
-        Some(_) => (None, Direction::Asc),
-    };
+                return None;
+            };
+            Some(BasicShadowOrder {
+                key,
+                direction: order.direction,
+            })
+        })
+        .collect();
 
-    Some(BasicShadowQuery {
+    Ok(Some(BasicShadowQuery {
         kind,
         filters,
-        global_filter: req.global_filter.clone(),
-        order_key,
-        direction,
+        condition,
+        order,
+        facets,
+        projection: Projection::new(
+            req.return_type,
+            req.fields.as_deref(),
+            &req.expand,
+            |field| field_key(kind, req.return_type, field),
+        ),
         page: req.page.unwrap_or(1),
         limit: req.limit.unwrap_or(crate::web::routes_search::PAGESIZE),
+    }))
+}
+
+/// A bare word naming a flag such as `archived` requires it; other bare words and phrases
+/// search the full text.
+fn resolve_term(
+    kind: BasicShadowKind,
+    return_type: ReturnType,
+    allowed: &std::collections::BTreeSet<ReturnType>,
+    term: &QueryTerm,
+) -> Result<QueryCondition<BasicShadowFilter>, ParseError> {
+    if let Some(text) = term.text() {
+        if !text.phrase
+            && let Some(key) = field_key(
+                kind,
+                return_type,
+                &format!("is_{}", text.text.to_lowercase()),
+            )
+        {
+            return Ok(QueryCondition::Filter(BasicShadowFilter {
+                key,
+                op: Comparison::Eq,
+                value: Value::Bool(true),
+            }));
+        }
+        return Ok(QueryCondition::Text(text));
+    }
+
+    let key = term
+        .targets(return_type, allowed)?
+        .into_iter()
+        .find_map(|(table, field)| field_key(kind, table, &field))
+        .ok_or_else(|| term.unsupported(return_type))?;
+    let filter = |op, raw: &str| -> Result<QueryCondition<BasicShadowFilter>, ParseError> {
+        let value = term_value(kind, &key, raw)
+            .ok_or_else(|| term.invalid_value(format!("unknown {key} `{raw}`")))?;
+        if !matches!(op, Comparison::Match | Comparison::Eq)
+            && !value.is_number()
+            && date_epoch(raw).is_none()
+        {
+            return Err(term.invalid_value(format!("expected a number or date, got `{raw}`")));
+        }
+        Ok(QueryCondition::Filter(BasicShadowFilter {
+            key: key.clone(),
+            op,
+            value,
+        }))
+    };
+    match &term.value {
+        TermValue::Word(raw) => filter(term.comparison, raw),
+        TermValue::Phrase(raw) => match term.comparison {
+            Comparison::Match | Comparison::Eq => Ok(QueryCondition::Filter(BasicShadowFilter {
+                key: key.clone(),
+                op: term.comparison,
+                value: Value::String(raw.clone()),
+            })),
+            _ => Err(term.invalid_value(format!("expected a number, got `{raw}`"))),
+        },
+        TermValue::Range { low, high } => {
+            let mut all = vec![];
+            if let Some(low) = low {
+                all.push(filter(Comparison::Gte, low)?);
+            }
+            if let Some(high) = high {
+                all.push(filter(Comparison::Lte, high)?);
+            }
+            Ok(QueryCondition::All(all))
+        }
+    }
+}
+
+/// Lot statuses by id.
+const LOT_STATUSES: [&str; 7] = [
+    "requested",
+    "approved",
+    "rejected",
+    "ordered",
+    "stock",
+    "low",
+    "finished",
+];
+
+/// Conjugate statuses by id.
+const CONJUGATE_STATUSES: [&str; 3] = ["stock", "low", "finished"];
+
+/// The labels of a field holding ids, such as a status.
+#[derive(Debug, Clone, Copy)]
+enum Labels {
+    /// Labels by id.
+    Names(&'static [&'static str]),
+    ApplicationStatus,
+    Application,
+}
+
+impl Labels {
+    fn of(kind: BasicShadowKind, key: &str) -> Option<Self> {
+        match (kind, key) {
+            (BasicShadowKind::Lot, "status") => Some(Self::Names(&LOT_STATUSES)),
+            (BasicShadowKind::Conjugate, "status") => Some(Self::Names(&CONJUGATE_STATUSES)),
+            (BasicShadowKind::Validation, "status") | (_, "validation_status") => {
+                Some(Self::ApplicationStatus)
+            }
+            (BasicShadowKind::Validation | BasicShadowKind::Panel, "application")
+            | (_, "validation_application") => Some(Self::Application),
+            _ => None,
+        }
+    }
+
+    fn id(self, raw: &str) -> Option<i64> {
+        match self {
+            Self::Names(names) => names
+                .iter()
+                .position(|label| label.eq_ignore_ascii_case(raw))
+                .map(|id| id as i64),
+            Self::ApplicationStatus => {
+                ApplicationStatus::parse(&Value::String(raw.to_string())).map(ApplicationStatus::id)
+            }
+            Self::Application => Application::parse_str(raw).map(Application::id),
+        }
+    }
+
+    fn label(self, id: i64) -> Option<String> {
+        match self {
+            Self::Names(names) => usize::try_from(id)
+                .ok()
+                .and_then(|id| names.get(id))
+                .map(|label| (*label).to_string()),
+            Self::ApplicationStatus => {
+                ApplicationStatus::from_id(id).map(|status| status.label().to_string())
+            }
+            Self::Application => {
+                Application::from_id(id).map(|application| application.label().to_string())
+            }
+        }
+    }
+}
+
+/// The value of `raw` for the field `key`: the id of a status or application label, a
+/// number, a flag or text. `None` for an unknown label.
+fn term_value(kind: BasicShadowKind, key: &str, raw: &str) -> Option<Value> {
+    match Labels::of(kind, key) {
+        // Named statuses also take their id.
+        Some(Labels::Names(_)) if raw.parse::<i64>().is_ok() => {}
+        Some(labels) => return labels.id(raw).map(|id| json!(id)),
+        None => {}
+    }
+
+    if let Ok(value) = raw.parse::<i64>() {
+        return Some(json!(value));
+    }
+    if let Ok(value) = raw.parse::<f64>() {
+        return Some(json!(value));
+    }
+    Some(match raw {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::String(raw.to_string()),
     })
 }
 
@@ -568,6 +1034,7 @@ fn field_key(kind: BasicShadowKind, table: ReturnType, field: &str) -> Option<St
             (ReturnType::Species, "name") => Some("species_name".to_string()),
             (ReturnType::Tag, "name") => Some("tag_name".to_string()),
             (ReturnType::Tag, "mw") => Some("tag_mw".to_string()),
+            (ReturnType::Storage, "name") => Some("storage_name".to_string()),
             (ReturnType::Collection, "id") => Some("collection_id".to_string()),
             (ReturnType::Collection, "name") => Some("collection_name".to_string()),
             _ => None,
@@ -647,6 +1114,9 @@ fn field_key(kind: BasicShadowKind, table: ReturnType, field: &str) -> Option<St
         (ReturnType::Lot, "number") => Some("number".to_string()),
         (ReturnType::Lot, "status") => Some("status".to_string()),
         (ReturnType::Lot, "is_archived") => Some("is_archived".to_string()),
+        (ReturnType::Lot, "ordered_at") => Some("ordered_at".to_string()),
+        (ReturnType::Lot, "received_at") => Some("received_at".to_string()),
+        (ReturnType::Lot, "created_at") => Some("created_at".to_string()),
 
         (ReturnType::Conjugate, "id") => Some("id".to_string()),
         (ReturnType::Conjugate, "group_id") => Some("group_id".to_string()),
@@ -731,48 +1201,44 @@ fn map_order_key(kind: BasicShadowKind, order: &SearchOrder) -> Option<String> {
 }
 
 fn row_matches_filter(row: &BasicShadowRow, filter: &BasicShadowFilter) -> bool {
-    let Some(existing) = row.values.get(&filter.key) else {
-        return false;
-    };
-
-    match (&filter.value, existing) {
-        (Value::String(needle), BasicShadowValue::Text(text)) => contains_ci(text, needle),
-        (Value::Number(number), BasicShadowValue::Int(value)) => number.as_i64() == Some(*value),
-        (Value::Number(number), BasicShadowValue::Float(value)) => number.as_f64() == Some(*value),
-        (Value::Bool(expected), BasicShadowValue::Bool(value)) => *expected == *value,
-        (Value::Array(values), BasicShadowValue::Int(value)) => values
-            .iter()
-            .filter_map(Value::as_i64)
-            .any(|item| item == *value),
-        (Value::Array(values), BasicShadowValue::Text(text)) => values
-            .iter()
-            .filter_map(Value::as_str)
-            .any(|item| item.eq_ignore_ascii_case(text)),
-        _ => false,
-    }
-}
-
-fn row_matches_global(row: &BasicShadowRow, global: &Option<String>) -> bool {
-    match global {
-        Some(value) if !value.trim().is_empty() => {
-            row.fulltext.contains(&value.trim().to_lowercase())
-        }
-        _ => true,
-    }
+    let value = row
+        .values
+        .get(&filter.key)
+        .map_or(FieldValue::Null, BasicShadowValue::field_value);
+    matches(value, filter.op, &filter.value)
 }
 
+/// Rows paired with their relevance score. Ties go by id in the direction of the first
+/// order, best matches first for relevance.
 fn compare_rows(
-    left: &BasicShadowRow,
-    right: &BasicShadowRow,
-    order_key: &str,
-    direction: &Direction,
+    (left, left_score): (&BasicShadowRow, f64),
+    (right, right_score): (&BasicShadowRow, f64),
+    orders: &[BasicShadowOrder],
 ) -> Ordering {
-    let ord = compare_values(left.values.get(order_key), right.values.get(order_key))
-        .then_with(|| left.id.cmp(&right.id));
-    match direction {
+    let directed = |ord: Ordering, direction: Direction| match direction {
         Direction::Asc => ord,
         Direction::Desc => ord.reverse(),
-    }
+    };
+    let ord = orders.iter().fold(Ordering::Equal, |ord, order| {
+        ord.then_with(|| {
+            let ord = match &order.key {
+                BasicShadowOrderKey::Key(key) => {
+                    compare_values(left.values.get(key), right.values.get(key))
+                }
+                BasicShadowOrderKey::Relevance => left_score.total_cmp(&right_score),
+            };
+            directed(ord, order.direction)
+        })
+    });
+    let ties = left.id.cmp(&right.id);
+    ord.then(match orders.first() {
+        Some(BasicShadowOrder {
+            key: BasicShadowOrderKey::Relevance,
+            direction,
+        }) => directed(ties.reverse(), *direction),
+        Some(order) => directed(ties, order.direction),
+        None => ties,
+    })
 }
 
 fn compare_values(left: Option<&BasicShadowValue>, right: Option<&BasicShadowValue>) -> Ordering {
@@ -787,16 +1253,33 @@ fn compare_values(left: Option<&BasicShadowValue>, right: Option<&BasicShadowVal
         (Some(BasicShadowValue::Text(left)), Some(BasicShadowValue::Text(right))) => {
             left.to_lowercase().cmp(&right.to_lowercase())
         }
-        (Some(_), None) => Ordering::Less,
-        (None, Some(_)) => Ordering::Greater,
+        (Some(BasicShadowValue::Null) | None, Some(BasicShadowValue::Null) | None) => {
+            Ordering::Equal
+        }
+        (Some(_), Some(BasicShadowValue::Null) | None) => Ordering::Less,
+        (Some(BasicShadowValue::Null) | None, Some(_)) => Ordering::Greater,
         _ => Ordering::Equal,
     }
 }
 
-fn contains_ci(haystack: &str, needle: &str) -> bool {
-    haystack
-        .to_lowercase()
-        .contains(&needle.trim().to_lowercase())
+/// Runs a shadow query for all rows, or only for the rows whose `id_column` is in `ids`.
+async fn fetch_db_rows<T>(
+    mm: &ModelManager,
+    sql: &str,
+    id_column: &str,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<T>>
+where
+    T: for<'r> FromRow<'r, sqlx::postgres::PgRow> + Send + Unpin,
+{
+    let rows = match ids {
+        None => sqlx::query_as(sql).fetch_all(mm.db()).await,
+        Some(ids) => {
+            let sql = format!("{sql} WHERE {id_column} = ANY($1)");
+            sqlx::query_as(&sql).bind(ids).fetch_all(mm.db()).await
+        }
+    };
+    rows.map_err(airlab_lib::model::Error::from)
 }
 
 fn row_from_pairs(id: i64, pairs: Vec<(&'static str, BasicShadowValue)>) -> BasicShadowRow {
@@ -819,16 +1302,20 @@ fn row_from_pairs(id: i64, pairs: Vec<(&'static str, BasicShadowValue)>) -> Basi
     }
 }
 
-async fn build_user_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<UserShadowDbRow> = sqlx::query_as(
+async fn build_user_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<UserShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT id, email, name
         FROM public."user"
         "#,
+        "id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -848,8 +1335,12 @@ async fn build_user_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Bas
         .collect())
 }
 
-async fn build_member_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<MemberShadowDbRow> = sqlx::query_as(
+async fn build_member_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<MemberShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             m.id AS id,
@@ -864,10 +1355,10 @@ async fn build_member_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<B
         LEFT JOIN public."user" u ON u.id = m.user_id
         LEFT JOIN public."group" g ON g.id = m.group_id
         "#,
+        "m.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -895,16 +1386,20 @@ async fn build_member_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<B
         .collect())
 }
 
-async fn build_group_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<GroupShadowDbRow> = sqlx::query_as(
+async fn build_group_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<GroupShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT id, name, institution, location, description
         FROM public."group"
         "#,
+        "id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -936,11 +1431,9 @@ async fn build_named_group_rows(
     mm: &ModelManager,
     _kind: BasicShadowKind,
     sql: &str,
+    ids: Option<&[i64]>,
 ) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<GroupNamedDbRow> = sqlx::query_as(sql)
-        .fetch_all(mm.db())
-        .await
-        .map_err(airlab_lib::model::Error::from)?;
+    let db_rows: Vec<GroupNamedDbRow> = fetch_db_rows(mm, sql, "p.id", ids).await?;
 
     Ok(db_rows
         .into_iter()
@@ -965,8 +1458,12 @@ async fn build_named_group_rows(
         .collect())
 }
 
-async fn build_tag_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<TagShadowDbRow> = sqlx::query_as(
+async fn build_tag_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<TagShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             t.id AS id,
@@ -986,10 +1483,10 @@ async fn build_tag_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
         FROM public.tag t
         LEFT JOIN public."group" g ON g.id = t.group_id
         "#,
+        "t.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1013,14 +1510,19 @@ async fn build_tag_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
                     ("is_enzyme", BasicShadowValue::Bool(row.is_enzyme)),
                     ("is_biotin", BasicShadowValue::Bool(row.is_biotin)),
                     ("is_other", BasicShadowValue::Bool(row.is_other)),
-                    ("mw", BasicShadowValue::Int(row.mw.unwrap_or_default())),
+                    (
+                        "mw",
+                        row.mw.map_or(BasicShadowValue::Null, BasicShadowValue::Int),
+                    ),
                     (
                         "emission",
-                        BasicShadowValue::Int(row.emission.unwrap_or_default()),
+                        row.emission
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     (
                         "excitation",
-                        BasicShadowValue::Int(row.excitation.unwrap_or_default()),
+                        row.excitation
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     ("status", BasicShadowValue::Int(row.status)),
                 ],
@@ -1029,8 +1531,12 @@ async fn build_tag_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
         .collect())
 }
 
-async fn build_species_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<SpeciesShadowDbRow> = sqlx::query_as(
+async fn build_species_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<SpeciesShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             s.id AS id,
@@ -1041,10 +1547,10 @@ async fn build_species_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<
         FROM public.species s
         LEFT JOIN public."group" g ON g.id = s.group_id
         "#,
+        "s.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1069,8 +1575,12 @@ async fn build_species_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<
         .collect())
 }
 
-async fn build_panel_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<PanelShadowDbRow> = sqlx::query_as(
+async fn build_panel_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<PanelShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             p.id AS id,
@@ -1085,10 +1595,10 @@ async fn build_panel_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Ba
             EXTRACT(EPOCH FROM p.updated_at)::bigint AS updated_at_epoch
         FROM public.panel p
         "#,
+        "p.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1108,7 +1618,8 @@ async fn build_panel_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Ba
                     ("is_locked", BasicShadowValue::Bool(row.is_locked)),
                     (
                         "application",
-                        BasicShadowValue::Int(row.application.unwrap_or_default()),
+                        row.application
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     ("is_archived", BasicShadowValue::Bool(row.is_archived)),
                     ("updated_at", BasicShadowValue::Int(row.updated_at_epoch)),
@@ -1118,8 +1629,12 @@ async fn build_panel_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Ba
         .collect())
 }
 
-async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<LotShadowDbRow> = sqlx::query_as(
+async fn build_lot_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<LotShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             l.id AS id,
@@ -1133,6 +1648,9 @@ async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
             l.number AS number,
             l.status AS status,
             l.is_archived AS is_archived,
+            EXTRACT(EPOCH FROM l.ordered_at)::bigint AS ordered_at_epoch,
+            EXTRACT(EPOCH FROM l.received_at)::bigint AS received_at_epoch,
+            EXTRACT(EPOCH FROM l.created_at)::bigint AS created_at_epoch,
             c.name AS clone_name,
             p.name AS protein_name,
             pr.name AS provider_name,
@@ -1148,10 +1666,10 @@ async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
         LEFT JOIN public."group" g ON g.id = l.group_id
         LEFT JOIN public.validation v ON v.lot_id = l.id
         "#,
+        "l.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1163,11 +1681,13 @@ async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
                 ("clone_id", BasicShadowValue::Int(row.clone_id)),
                 (
                     "provider_id",
-                    BasicShadowValue::Int(row.provider_id.unwrap_or_default()),
+                    row.provider_id
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 (
                     "collection_id",
-                    BasicShadowValue::Int(row.collection_id.unwrap_or_default()),
+                    row.collection_id
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 ("name", BasicShadowValue::Text(row.name.unwrap_or_default())),
                 (
@@ -1180,6 +1700,17 @@ async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
                 ),
                 ("status", BasicShadowValue::Int(row.status)),
                 ("is_archived", BasicShadowValue::Bool(row.is_archived)),
+                (
+                    "ordered_at",
+                    row.ordered_at_epoch
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
+                ),
+                (
+                    "received_at",
+                    row.received_at_epoch
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
+                ),
+                ("created_at", BasicShadowValue::Int(row.created_at_epoch)),
                 (
                     "clone_name",
                     BasicShadowValue::Text(row.clone_name.unwrap_or_default()),
@@ -1223,8 +1754,12 @@ async fn build_lot_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<Basi
         .collect())
 }
 
-async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<ConjugateShadowDbRow> = sqlx::query_as(
+async fn build_conjugate_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<ConjugateShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             c.id AS id,
@@ -1247,6 +1782,7 @@ async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Ve
             s.name AS species_name,
             t.name AS tag_name,
             t.mw AS tag_mw,
+            st.name AS storage_name,
             l.collection_id AS collection_id,
             co.name AS collection_name,
             g.name AS group_name
@@ -1256,13 +1792,14 @@ async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Ve
         LEFT JOIN public.protein p ON p.id = cl.protein_id
         LEFT JOIN public.species s ON s.id = cl.species_id
         LEFT JOIN public.tag t ON t.id = c.tag_id
+        LEFT JOIN public.storage st ON st.id = c.storage_id
         LEFT JOIN public.collection co ON co.id = l.collection_id
         LEFT JOIN public."group" g ON g.id = c.group_id
         "#,
+        "c.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1273,17 +1810,20 @@ async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Ve
                 ("created_by", BasicShadowValue::Int(row.created_by)),
                 (
                     "labeled_by",
-                    BasicShadowValue::Int(row.labeled_by.unwrap_or_default()),
+                    row.labeled_by
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 (
                     "finished_by",
-                    BasicShadowValue::Int(row.finished_by.unwrap_or_default()),
+                    row.finished_by
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 ("lot_id", BasicShadowValue::Int(row.lot_id)),
                 ("tag_id", BasicShadowValue::Int(row.tag_id)),
                 (
                     "storage_id",
-                    BasicShadowValue::Int(row.storage_id.unwrap_or_default()),
+                    row.storage_id
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 ("status", BasicShadowValue::Int(row.status)),
                 ("tube_number", BasicShadowValue::Int(row.tube_number)),
@@ -1318,11 +1858,17 @@ async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Ve
                 ),
                 (
                     "tag_mw",
-                    BasicShadowValue::Int(row.tag_mw.unwrap_or_default()),
+                    row.tag_mw
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
+                ),
+                (
+                    "storage_name",
+                    BasicShadowValue::Text(row.storage_name.unwrap_or_default()),
                 ),
                 (
                     "collection_id",
-                    BasicShadowValue::Int(row.collection_id.unwrap_or_default()),
+                    row.collection_id
+                        .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                 ),
                 (
                     "collection_name",
@@ -1343,8 +1889,10 @@ async fn build_conjugate_rows(mm: &ModelManager) -> airlab_lib::model::Result<Ve
 
 async fn build_panel_element_rows(
     mm: &ModelManager,
+    ids: Option<&[i64]>,
 ) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<PanelElementShadowDbRow> = sqlx::query_as(
+    let db_rows: Vec<PanelElementShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             pe.id AS id,
@@ -1360,10 +1908,10 @@ async fn build_panel_element_rows(
         LEFT JOIN public.conjugate c ON c.id = pe.conjugate_id
         LEFT JOIN public.tag t ON t.id = c.tag_id
         "#,
+        "pe.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1396,8 +1944,10 @@ async fn build_panel_element_rows(
 
 async fn build_validation_rows(
     mm: &ModelManager,
+    ids: Option<&[i64]>,
 ) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<ValidationShadowDbRow> = sqlx::query_as(
+    let db_rows: Vec<ValidationShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT
             v.id AS id,
@@ -1429,10 +1979,10 @@ async fn build_validation_rows(
         LEFT JOIN public."user" u ON u.id = m.user_id
         LEFT JOIN public."group" g ON g.id = v.group_id
         "#,
+        "v.id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1446,15 +1996,18 @@ async fn build_validation_rows(
                     ("clone_id", BasicShadowValue::Int(row.clone_id)),
                     (
                         "lot_id",
-                        BasicShadowValue::Int(row.lot_id.unwrap_or_default()),
+                        row.lot_id
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     (
                         "conjugate_id",
-                        BasicShadowValue::Int(row.conjugate_id.unwrap_or_default()),
+                        row.conjugate_id
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     (
                         "species_id",
-                        BasicShadowValue::Int(row.species_id.unwrap_or_default()),
+                        row.species_id
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     ("application", BasicShadowValue::Int(row.application)),
                     (
@@ -1481,7 +2034,8 @@ async fn build_validation_rows(
                     ),
                     (
                         "tube_number",
-                        BasicShadowValue::Int(row.tube_number.unwrap_or_default()),
+                        row.tube_number
+                            .map_or(BasicShadowValue::Null, BasicShadowValue::Int),
                     ),
                     (
                         "species_name",
@@ -1501,16 +2055,20 @@ async fn build_validation_rows(
         .collect())
 }
 
-async fn build_storage_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<StorageShadowDbRow> = sqlx::query_as(
+async fn build_storage_rows(
+    mm: &ModelManager,
+    ids: Option<&[i64]>,
+) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
+    let db_rows: Vec<StorageShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT id, name, "type", location, temperature_c, active
         FROM public.storage
         "#,
+        "id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
@@ -1538,16 +2096,18 @@ async fn build_storage_rows(mm: &ModelManager) -> airlab_lib::model::Result<Vec<
 
 async fn build_collection_rows(
     mm: &ModelManager,
+    ids: Option<&[i64]>,
 ) -> airlab_lib::model::Result<Vec<BasicShadowRow>> {
-    let db_rows: Vec<CollectionShadowDbRow> = sqlx::query_as(
+    let db_rows: Vec<CollectionShadowDbRow> = fetch_db_rows(
+        mm,
         r#"
         SELECT id, name, description
         FROM public.collection
         "#,
+        "id",
+        ids,
     )
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+    .await?;
 
     Ok(db_rows
         .into_iter()
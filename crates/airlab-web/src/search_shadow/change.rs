@@ -0,0 +1,342 @@
+//! Which shadow rows a single write touches: the written entity's own row and the rows of
+//! entities that show some of its fields, such as a lot's clone name or a clone's
+//! validation status.
+
+use super::basic::BasicShadowKind;
+use BasicShadowKind as K;
+use Before::{Referenced, Referencing, Unknown};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShadowEntity {
+    User,
+    Member,
+    Group,
+    Clone,
+    Protein,
+    Provider,
+    Species,
+    Tag,
+    Lot,
+    Conjugate,
+    Panel,
+    PanelElement,
+    Validation,
+    Storage,
+    Collection,
+}
+
+/// An inserted, updated or deleted entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowChange {
+    pub entity: ShadowEntity,
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Target {
+    Basic(BasicShadowKind),
+    /// Rows of the clone shadows, by clone id.
+    Clone,
+}
+
+/// How to find dependents in the shadow from before the write, so rows that no longer
+/// depend on the entity are refreshed too.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Before {
+    /// The shadow does not keep the link; only the database is asked.
+    Unknown,
+    /// Dependent rows holding the entity's id under this key.
+    Referencing(&'static str),
+    /// The id the entity's own row holds under this key.
+    Referenced(&'static str),
+}
+
+/// Rows of `target` depending on an entity: the ids `sql` selects for the entity's id, plus
+/// what `before` finds in the current shadow.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Dependent {
+    pub target: Target,
+    pub before: Before,
+    pub sql: &'static str,
+}
+
+const fn basic(kind: BasicShadowKind, before: Before, sql: &'static str) -> Dependent {
+    Dependent {
+        target: Target::Basic(kind),
+        before,
+        sql,
+    }
+}
+
+const fn clone(before: Before, sql: &'static str) -> Dependent {
+    Dependent {
+        target: Target::Clone,
+        before,
+        sql,
+    }
+}
+
+const USER: &[Dependent] = &[
+    basic(
+        K::Member,
+        Referencing("user_id"),
+        "SELECT id FROM public.member WHERE user_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Unknown,
+        "SELECT v.id FROM public.validation v JOIN public.member m ON m.id = v.created_by WHERE m.user_id = $1",
+    ),
+];
+
+const MEMBER: &[Dependent] = &[basic(
+    K::Validation,
+    Referencing("created_by"),
+    "SELECT id FROM public.validation WHERE created_by = $1",
+)];
+
+const GROUP: &[Dependent] = &[
+    basic(
+        K::Member,
+        Referencing("group_id"),
+        "SELECT id FROM public.member WHERE group_id = $1",
+    ),
+    basic(
+        K::Protein,
+        Referencing("group_id"),
+        "SELECT id FROM public.protein WHERE group_id = $1",
+    ),
+    basic(
+        K::Provider,
+        Referencing("group_id"),
+        "SELECT id FROM public.provider WHERE group_id = $1",
+    ),
+    basic(
+        K::Species,
+        Referencing("group_id"),
+        "SELECT id FROM public.species WHERE group_id = $1",
+    ),
+    basic(
+        K::Tag,
+        Referencing("group_id"),
+        "SELECT id FROM public.tag WHERE group_id = $1",
+    ),
+    basic(
+        K::Lot,
+        Referencing("group_id"),
+        "SELECT id FROM public.lot WHERE group_id = $1",
+    ),
+    basic(
+        K::Conjugate,
+        Referencing("group_id"),
+        "SELECT id FROM public.conjugate WHERE group_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Referencing("group_id"),
+        "SELECT id FROM public.validation WHERE group_id = $1",
+    ),
+];
+
+const CLONE: &[Dependent] = &[
+    basic(
+        K::Lot,
+        Referencing("clone_id"),
+        "SELECT id FROM public.lot WHERE clone_id = $1",
+    ),
+    basic(
+        K::Conjugate,
+        Unknown,
+        "SELECT c.id FROM public.conjugate c JOIN public.lot l ON l.id = c.lot_id WHERE l.clone_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Referencing("clone_id"),
+        "SELECT id FROM public.validation WHERE clone_id = $1",
+    ),
+];
+
+const PROTEIN: &[Dependent] = &[
+    basic(
+        K::Lot,
+        Unknown,
+        "SELECT l.id FROM public.lot l JOIN public.clone cl ON cl.id = l.clone_id WHERE cl.protein_id = $1",
+    ),
+    basic(
+        K::Conjugate,
+        Unknown,
+        "SELECT c.id FROM public.conjugate c JOIN public.lot l ON l.id = c.lot_id JOIN public.clone cl ON cl.id = l.clone_id WHERE cl.protein_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Unknown,
+        "SELECT v.id FROM public.validation v JOIN public.clone cl ON cl.id = v.clone_id WHERE cl.protein_id = $1",
+    ),
+    clone(Unknown, "SELECT id FROM public.clone WHERE protein_id = $1"),
+];
+
+const PROVIDER: &[Dependent] = &[basic(
+    K::Lot,
+    Referencing("provider_id"),
+    "SELECT id FROM public.lot WHERE provider_id = $1",
+)];
+
+const SPECIES: &[Dependent] = &[
+    basic(
+        K::Conjugate,
+        Unknown,
+        "SELECT c.id FROM public.conjugate c JOIN public.lot l ON l.id = c.lot_id JOIN public.clone cl ON cl.id = l.clone_id WHERE cl.species_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Referencing("species_id"),
+        "SELECT id FROM public.validation WHERE species_id = $1",
+    ),
+    clone(
+        Unknown,
+        "SELECT id FROM public.clone WHERE species_id = $1 OR $1 = ANY(reactivity)",
+    ),
+];
+
+const TAG: &[Dependent] = &[
+    basic(
+        K::Conjugate,
+        Referencing("tag_id"),
+        "SELECT id FROM public.conjugate WHERE tag_id = $1",
+    ),
+    basic(
+        K::PanelElement,
+        Unknown,
+        "SELECT pe.id FROM public.panel_element pe JOIN public.conjugate c ON c.id = pe.conjugate_id WHERE c.tag_id = $1",
+    ),
+];
+
+const LOT: &[Dependent] = &[
+    basic(
+        K::Conjugate,
+        Referencing("lot_id"),
+        "SELECT id FROM public.conjugate WHERE lot_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Referencing("lot_id"),
+        "SELECT id FROM public.validation WHERE lot_id = $1",
+    ),
+];
+
+const CONJUGATE: &[Dependent] = &[
+    basic(
+        K::PanelElement,
+        Referencing("conjugate_id"),
+        "SELECT id FROM public.panel_element WHERE conjugate_id = $1",
+    ),
+    basic(
+        K::Validation,
+        Referencing("conjugate_id"),
+        "SELECT id FROM public.validation WHERE conjugate_id = $1",
+    ),
+];
+
+const PANEL: &[Dependent] = &[basic(
+    K::PanelElement,
+    Referencing("panel_id"),
+    "SELECT id FROM public.panel_element WHERE panel_id = $1",
+)];
+
+/// Lots show the status of their validation and clones one row per validation.
+const VALIDATION: &[Dependent] = &[
+    basic(
+        K::Lot,
+        Referenced("lot_id"),
+        "SELECT lot_id FROM public.validation WHERE id = $1 AND lot_id IS NOT NULL",
+    ),
+    clone(
+        Referenced("clone_id"),
+        "SELECT clone_id FROM public.validation WHERE id = $1",
+    ),
+];
+
+const STORAGE: &[Dependent] = &[basic(
+    K::Conjugate,
+    Referencing("storage_id"),
+    "SELECT id FROM public.conjugate WHERE storage_id = $1",
+)];
+
+const COLLECTION: &[Dependent] = &[
+    basic(
+        K::Lot,
+        Referencing("collection_id"),
+        "SELECT id FROM public.lot WHERE collection_id = $1",
+    ),
+    basic(
+        K::Conjugate,
+        Referencing("collection_id"),
+        "SELECT c.id FROM public.conjugate c JOIN public.lot l ON l.id = c.lot_id WHERE l.collection_id = $1",
+    ),
+];
+
+impl ShadowEntity {
+    /// The entity stored in `table`, a table name without schema.
+    pub fn from_table(table: &str) -> Option<Self> {
+        let entity = match table {
+            "user" => Self::User,
+            "member" => Self::Member,
+            "group" => Self::Group,
+            "clone" => Self::Clone,
+            "protein" => Self::Protein,
+            "provider" => Self::Provider,
+            "species" => Self::Species,
+            "tag" => Self::Tag,
+            "lot" => Self::Lot,
+            "conjugate" => Self::Conjugate,
+            "panel" => Self::Panel,
+            "panel_element" => Self::PanelElement,
+            "validation" => Self::Validation,
+            "storage" => Self::Storage,
+            "collection" => Self::Collection,
+            _ => return None,
+        };
+        Some(entity)
+    }
+
+    /// Where the entity's own row lives.
+    pub(super) fn target(self) -> Target {
+        match self {
+            Self::User => Target::Basic(K::User),
+            Self::Member => Target::Basic(K::Member),
+            Self::Group => Target::Basic(K::Group),
+            Self::Clone => Target::Clone,
+            Self::Protein => Target::Basic(K::Protein),
+            Self::Provider => Target::Basic(K::Provider),
+            Self::Species => Target::Basic(K::Species),
+            Self::Tag => Target::Basic(K::Tag),
+            Self::Lot => Target::Basic(K::Lot),
+            Self::Conjugate => Target::Basic(K::Conjugate),
+            Self::Panel => Target::Basic(K::Panel),
+            Self::PanelElement => Target::Basic(K::PanelElement),
+            Self::Validation => Target::Basic(K::Validation),
+            Self::Storage => Target::Basic(K::Storage),
+            Self::Collection => Target::Basic(K::Collection),
+        }
+    }
+
+    pub(super) fn dependents(self) -> &'static [Dependent] {
+        match self {
+            Self::User => USER,
+            Self::Member => MEMBER,
+            Self::Group => GROUP,
+            Self::Clone => CLONE,
+            Self::Protein => PROTEIN,
+            Self::Provider => PROVIDER,
+            Self::Species => SPECIES,
+            Self::Tag => TAG,
+            Self::Lot => LOT,
+            Self::Conjugate => CONJUGATE,
+            Self::Panel => PANEL,
+            Self::Validation => VALIDATION,
+            Self::Storage => STORAGE,
+            Self::Collection => COLLECTION,
+            Self::PanelElement => &[],
+        }
+    }
+}
@@ -1,5 +1,19 @@
+use super::facet::{FacetCounts, FacetTally, SearchFacet};
+use super::filter::{FieldValue, date_epoch, matches, resolve_filter};
+use super::hydrate::Projection;
+use super::query::{
+    Comparison, ParseError, QueryCondition, QueryExpr, QueryTerm, TermValue, TextTerm,
+};
+use super::text::{DEFAULT_BOOST, NAME_BOOST, Snippet, TextIndex, snippet};
+use crate::web::routes_search::{Filter, ReturnType, allowed_filter_tables};
+use airlab_lib::ctx::Ctx;
 use airlab_lib::model::ModelManager;
-use serde_json::Value;
+use airlab_lib::model::clone_application::{
+    Application, ApplicationFilter, ApplicationStatus, CloneApplications,
+};
+use airlab_lib::model::validation_consensus::ValidationConsensusBmc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
 use sqlx::FromRow;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
@@ -9,20 +23,84 @@ use tracing::warn;
 #[derive(Debug)]
 pub struct CloneGroupShadow {
     pub rows: Arc<Vec<CloneTableShadowRow>>,
+    /// Over the text fields of `rows`, numbered by position.
+    index: TextIndex,
 }
 
-#[derive(Debug, Clone)]
+impl CloneGroupShadow {
+    pub fn new(rows: Vec<CloneTableShadowRow>) -> Self {
+        let index = TextIndex::build(rows.iter().map(clone_text_fields));
+        Self {
+            rows: Arc::new(rows),
+            index,
+        }
+    }
+
+    /// The best matching row of each clone that matches `term`, best first.
+    pub fn rank(&self, term: &TextTerm) -> Vec<(&CloneTableShadowRow, f64)> {
+        let mut best = HashMap::<i64, (&CloneTableShadowRow, f64)>::new();
+        for (doc, score) in term.search(&self.index, |doc| self.rows[doc].fulltext.as_str()) {
+            let row = &self.rows[doc];
+            let entry = best.entry(row.clone_id).or_insert((row, score));
+            if score > entry.1 {
+                *entry = (row, score);
+            }
+        }
+        let mut ranked = best.into_values().collect::<Vec<_>>();
+        ranked.sort_by(|(left, left_score), (right, right_score)| {
+            right_score
+                .total_cmp(left_score)
+                .then_with(|| left.clone_id.cmp(&right.clone_id))
+        });
+        ranked
+    }
+
+    /// The clones of `clone_ids` as objects, in order, each gathered from all of its rows.
+    pub fn objects(&self, clone_ids: &[i64]) -> Vec<Map<String, Value>> {
+        let mut rows_by_clone = HashMap::<i64, Vec<&CloneTableShadowRow>>::new();
+        for row in self.rows.iter() {
+            rows_by_clone.entry(row.clone_id).or_default().push(row);
+        }
+        clone_ids
+            .iter()
+            .filter_map(|clone_id| rows_by_clone.get(clone_id))
+            .map(|rows| clone_object(rows))
+            .collect()
+    }
+
+    /// A copy with the rows of `clone_ids` replaced by `rows`.
+    pub fn replace_clones(
+        &self,
+        clone_ids: &HashSet<i64>,
+        rows: impl IntoIterator<Item = CloneTableShadowRow>,
+    ) -> Self {
+        let mut replaced = self
+            .rows
+            .iter()
+            .filter(|row| !clone_ids.contains(&row.clone_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        replaced.extend(rows);
+        Self::new(replaced)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloneTableShadowRow {
     pub group_id: i64,
     pub clone_id: i64,
     pub clone_name: String,
+    pub protein_id: i64,
     pub protein_name: String,
+    pub species_id: Option<i64>,
     pub species_name: String,
     pub is_phospho: bool,
     pub is_polyclonal: bool,
     pub isotype: String,
     pub epitope: String,
+    pub application_id: Option<i64>,
     pub application_label: Option<String>,
+    pub application_status: Option<i64>,
     pub reactivity_id: Option<i64>,
     pub reactivity_label: Option<String>,
     pub validation_id: Option<i64>,
@@ -30,6 +108,9 @@ pub struct CloneTableShadowRow {
     pub validation_application_label: Option<String>,
     pub validation_status: Option<i64>,
     pub validation_status_label: Option<String>,
+    /// Consensus over all validations of this clone for `validation_application`.
+    pub consensus_score: Option<f64>,
+    pub consensus_status: Option<i64>,
     pub fulltext: String,
 }
 
@@ -40,9 +121,87 @@ pub enum CloneShadowFilter {
     CloneNameContains(String),
     ProteinNameContains(String),
     SpeciesNameContains(String),
+    ApplicationEq(ApplicationFilter),
     ValidationApplicationEq(i64),
     ValidationStatusEq(i64),
+    ConsensusStatusEq(i64),
+    ConsensusScoreGte(f64),
     ReactivityContains(Vec<i64>),
+    /// Any comparison of a field, for operators beyond equality.
+    Field(CloneShadowField, Comparison, Value),
+}
+
+/// A field of clone rows filters can compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneShadowField {
+    Id,
+    Name,
+    ProteinId,
+    ProteinName,
+    SpeciesId,
+    SpeciesName,
+    IsPhospho,
+    IsPolyclonal,
+    Isotype,
+    Epitope,
+    Application,
+    Reactivity,
+    ValidationId,
+    ValidationApplication,
+    ValidationStatus,
+    ConsensusScore,
+    ConsensusStatus,
+}
+
+impl CloneShadowField {
+    pub fn parse(table: ReturnType, field: &str) -> Option<Self> {
+        match (table, field) {
+            (ReturnType::Clone, "id") => Some(Self::Id),
+            (ReturnType::Clone, "name") => Some(Self::Name),
+            (ReturnType::Clone, "protein_id") | (ReturnType::Protein, "id") => {
+                Some(Self::ProteinId)
+            }
+            (ReturnType::Protein, "name") => Some(Self::ProteinName),
+            (ReturnType::Clone, "species_id") | (ReturnType::Species, "id") => {
+                Some(Self::SpeciesId)
+            }
+            (ReturnType::Species, "name") => Some(Self::SpeciesName),
+            (ReturnType::Clone, "is_phospho") => Some(Self::IsPhospho),
+            (ReturnType::Clone, "is_polyclonal") => Some(Self::IsPolyclonal),
+            (ReturnType::Clone, "isotype") => Some(Self::Isotype),
+            (ReturnType::Clone, "epitope") => Some(Self::Epitope),
+            (ReturnType::Clone, "application") => Some(Self::Application),
+            (ReturnType::Clone, "reactivity") => Some(Self::Reactivity),
+            (ReturnType::Validation, "id") => Some(Self::ValidationId),
+            (ReturnType::Validation, "application") => Some(Self::ValidationApplication),
+            (ReturnType::Validation, "status") => Some(Self::ValidationStatus),
+            (ReturnType::Clone, "consensus_score") => Some(Self::ConsensusScore),
+            (ReturnType::Clone, "consensus_status") => Some(Self::ConsensusStatus),
+            _ => None,
+        }
+    }
+
+    fn value(self, row: &CloneTableShadowRow) -> FieldValue<'_> {
+        match self {
+            Self::Id => FieldValue::Int(row.clone_id),
+            Self::Name => FieldValue::Text(&row.clone_name),
+            Self::ProteinId => FieldValue::Int(row.protein_id),
+            Self::ProteinName => FieldValue::Text(&row.protein_name),
+            Self::SpeciesId => row.species_id.into(),
+            Self::SpeciesName => FieldValue::Text(&row.species_name),
+            Self::IsPhospho => FieldValue::Bool(row.is_phospho),
+            Self::IsPolyclonal => FieldValue::Bool(row.is_polyclonal),
+            Self::Isotype => FieldValue::Text(&row.isotype),
+            Self::Epitope => FieldValue::Text(&row.epitope),
+            Self::Application => row.application_id.into(),
+            Self::Reactivity => row.reactivity_id.into(),
+            Self::ValidationId => row.validation_id.into(),
+            Self::ValidationApplication => row.validation_application.into(),
+            Self::ValidationStatus => row.validation_status.into(),
+            Self::ConsensusScore => row.consensus_score.into(),
+            Self::ConsensusStatus => row.consensus_status.into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -55,6 +214,60 @@ pub enum CloneShadowOrderField {
     Application,
     ValidationApplication,
     ValidationStatus,
+    ConsensusScore,
+    /// How well rows match the global filter.
+    Relevance,
+}
+
+/// A field clone searches count results by.
+#[derive(Debug, Clone, Copy)]
+pub enum CloneShadowFacet {
+    ProteinName,
+    SpeciesName,
+    IsPhospho,
+    IsPolyclonal,
+    Isotype,
+    Application,
+    Reactivity,
+    ValidationApplication,
+    ValidationStatus,
+    ConsensusStatus,
+}
+
+impl CloneShadowFacet {
+    /// The value of the field in `row`, with its label when the value is an id.
+    fn value(self, row: &CloneTableShadowRow) -> (Value, Option<String>) {
+        let text = |text: &str| {
+            if text.is_empty() {
+                Value::Null
+            } else {
+                json!(text)
+            }
+        };
+        match self {
+            Self::ProteinName => (text(&row.protein_name), None),
+            Self::SpeciesName => (text(&row.species_name), None),
+            Self::IsPhospho => (json!(row.is_phospho), None),
+            Self::IsPolyclonal => (json!(row.is_polyclonal), None),
+            Self::Isotype => (text(&row.isotype), None),
+            Self::Application => (json!(row.application_id), row.application_label.clone()),
+            Self::Reactivity => (json!(row.reactivity_id), row.reactivity_label.clone()),
+            Self::ValidationApplication => (
+                json!(row.validation_application),
+                row.validation_application_label.clone(),
+            ),
+            Self::ValidationStatus => (
+                json!(row.validation_status),
+                row.validation_status_label.clone(),
+            ),
+            Self::ConsensusStatus => (
+                json!(row.consensus_status),
+                row.consensus_status
+                    .and_then(ApplicationStatus::from_id)
+                    .map(|status| status.label().to_string()),
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -72,9 +285,14 @@ pub struct CloneShadowOrder {
 #[derive(Debug, Clone)]
 pub struct CloneShadowQuery {
     pub group_id: i64,
-    pub filters: Vec<CloneShadowFilter>,
-    pub global_filter: Option<String>,
-    pub order: Option<CloneShadowOrder>,
+    pub filters: Vec<QueryCondition<CloneShadowFilter>>,
+    /// The global filter, resolved against clone shadow filters.
+    pub condition: Option<QueryCondition<CloneShadowFilter>>,
+    /// Later orders order ties of earlier ones.
+    pub order: Vec<CloneShadowOrder>,
+    pub facets: Vec<(SearchFacet, CloneShadowFacet)>,
+    /// Return clones instead of ids.
+    pub projection: Option<Projection>,
     pub page: i64,
     pub limit: i64,
 }
@@ -83,6 +301,8 @@ pub struct CloneShadowQuery {
 pub struct CloneShadowSearchResult {
     pub items: Vec<i64>,
     pub search_total: i64,
+    /// Over all matching clones, not only the page.
+    pub facets: Vec<FacetCounts>,
 }
 
 #[derive(Debug, FromRow)]
@@ -90,7 +310,9 @@ struct CloneShadowDbRow {
     group_id: i64,
     clone_id: i64,
     clone_name: String,
+    protein_id: i64,
     protein_name: Option<String>,
+    species_id: Option<i64>,
     species_name: Option<String>,
     is_phospho: bool,
     is_polyclonal: bool,
@@ -109,17 +331,34 @@ struct SpeciesNameRow {
     name: String,
 }
 
+/// The clones shadow rows are built for.
+#[derive(Debug, Clone, Copy)]
+pub enum CloneRowScope<'a> {
+    Group(i64),
+    Clones(&'a [i64]),
+}
+
 pub async fn build_clone_group_shadow(
     mm: &ModelManager,
     group_id: i64,
 ) -> airlab_lib::model::Result<CloneGroupShadow> {
-    let db_rows: Vec<CloneShadowDbRow> = sqlx::query_as(
+    let rows = build_clone_rows(mm, CloneRowScope::Group(group_id)).await?;
+    Ok(CloneGroupShadow::new(rows))
+}
+
+pub async fn build_clone_rows(
+    mm: &ModelManager,
+    scope: CloneRowScope<'_>,
+) -> airlab_lib::model::Result<Vec<CloneTableShadowRow>> {
+    let sql = format!(
         r#"
         SELECT
             c.group_id AS group_id,
             c.id AS clone_id,
             c.name AS clone_name,
+            c.protein_id AS protein_id,
             p.name AS protein_name,
+            c.species_id AS species_id,
             s.name AS species_name,
             c.is_phospho AS is_phospho,
             c.is_polyclonal AS is_polyclonal,
@@ -134,13 +373,36 @@ pub async fn build_clone_group_shadow(
         LEFT JOIN public.protein p ON p.id = c.protein_id
         LEFT JOIN public.species s ON s.id = c.species_id
         LEFT JOIN public.validation v ON v.clone_id = c.id
-        WHERE c.group_id = $1
+        WHERE {}
         "#,
-    )
-    .bind(group_id)
-    .fetch_all(mm.db())
-    .await
-    .map_err(airlab_lib::model::Error::from)?;
+        match scope {
+            CloneRowScope::Group(_) => "c.group_id = $1",
+            CloneRowScope::Clones(_) => "c.id = ANY($1)",
+        }
+    );
+    let query = sqlx::query_as(&sql);
+    let query = match scope {
+        CloneRowScope::Group(group_id) => query.bind(group_id),
+        CloneRowScope::Clones(clone_ids) => query.bind(clone_ids),
+    };
+    let db_rows: Vec<CloneShadowDbRow> = query
+        .fetch_all(mm.db())
+        .await
+        .map_err(airlab_lib::model::Error::from)?;
+
+    let mut consensus = HashMap::new();
+    let group_ids = db_rows
+        .iter()
+        .map(|row| row.group_id)
+        .collect::<HashSet<_>>();
+    for group_id in group_ids {
+        consensus.extend(
+            ValidationConsensusBmc::list_for_group(&Ctx::root_ctx(), mm, group_id)
+                .await?
+                .into_iter()
+                .map(|consensus| ((consensus.clone_id, consensus.application), consensus)),
+        );
+    }
 
     let reactivity_ids: Vec<i64> = db_rows
         .iter()
@@ -172,7 +434,7 @@ pub async fn build_clone_group_shadow(
         let application_entries = {
             let parsed = parse_clone_application_entries(row.application.as_ref());
             if parsed.is_empty() {
-                vec![(None, None)]
+                vec![(None, None, None)]
             } else {
                 parsed
             }
@@ -193,19 +455,27 @@ pub async fn build_clone_group_shadow(
             }
         };
 
-        for (_application_id, application_label) in &application_entries {
+        let row_consensus = row
+            .validation_application
+            .and_then(|application| consensus.get(&(row.clone_id, application)));
+
+        for (application_id, application_label, application_status) in &application_entries {
             for (reactivity_id, reactivity_label) in &reactivity_entries {
                 let mut shadow = CloneTableShadowRow {
                     group_id: row.group_id,
                     clone_id: row.clone_id,
                     clone_name: row.clone_name.clone(),
+                    protein_id: row.protein_id,
                     protein_name: row.protein_name.clone().unwrap_or_default(),
+                    species_id: row.species_id,
                     species_name: row.species_name.clone().unwrap_or_default(),
                     is_phospho: row.is_phospho,
                     is_polyclonal: row.is_polyclonal,
                     isotype: row.isotype.clone().unwrap_or_default(),
                     epitope: row.epitope.clone().unwrap_or_default(),
+                    application_id: *application_id,
                     application_label: application_label.clone(),
+                    application_status: *application_status,
                     reactivity_id: *reactivity_id,
                     reactivity_label: reactivity_label.clone(),
                     validation_id: row.validation_id,
@@ -215,6 +485,8 @@ pub async fn build_clone_group_shadow(
                         .map(validation_application_to_string),
                     validation_status: row.validation_status,
                     validation_status_label: row.validation_status.map(validation_status_to_string),
+                    consensus_score: row_consensus.map(|consensus| consensus.score),
+                    consensus_status: row_consensus.map(|consensus| consensus.status),
                     fulltext: String::new(),
                 };
                 shadow.fulltext = build_clone_fulltext(&shadow);
@@ -223,9 +495,7 @@ pub async fn build_clone_group_shadow(
         }
     }
 
-    Ok(CloneGroupShadow {
-        rows: Arc::new(rows),
-    })
+    Ok(rows)
 }
 
 pub fn search_clone_shadow(
@@ -239,27 +509,49 @@ pub fn search_clone_shadow(
         row_count = shadow.rows.len(),
         filter_count = query.filters.len(),
         requires_validation_row,
-        has_global_filter = query
-            .global_filter
-            .as_ref()
-            .is_some_and(|value| !value.trim().is_empty()),
+        has_global_filter = query.condition.is_some(),
         page = query.page,
         limit = query.limit,
         "searching clone shadow table"
     );
 
+    let text_scores = query
+        .condition
+        .as_ref()
+        .map_or_else(HashMap::new, |condition| {
+            condition
+                .text_terms()
+                .into_iter()
+                .map(|term| {
+                    let scores =
+                        term.search(&shadow.index, |doc| shadow.rows[doc].fulltext.as_str());
+                    (term, scores)
+                })
+                .collect::<HashMap<_, _>>()
+        });
     let mut matches = shadow
         .rows
         .iter()
-        .filter(|row| row.group_id == query.group_id)
-        .filter(|row| !requires_validation_row || row.validation_id.is_some())
-        .filter(|row| {
+        .enumerate()
+        .filter(|(_, row)| row.group_id == query.group_id)
+        .filter(|(_, row)| !requires_validation_row || row.validation_id.is_some())
+        .filter(|(_, row)| {
             query
                 .filters
                 .iter()
-                .all(|filter| row_matches_filter(row, filter))
+                .all(|condition| condition.matches(&|filter| row_matches_filter(row, filter)))
+        })
+        .filter_map(|(doc, row)| match &query.condition {
+            Some(condition) => condition
+                .score(&|filter| row_matches_filter(row, filter), &|term| {
+                    text_scores
+                        .get(term)
+                        .and_then(|scores| scores.get(&doc))
+                        .copied()
+                })
+                .map(|score| (row, score)),
+            None => Some((row, 0.0)),
         })
-        .filter(|row| row_matches_global(row, &query.global_filter))
         .collect::<Vec<_>>();
 
     warn!(
@@ -268,14 +560,26 @@ pub fn search_clone_shadow(
         "clone shadow filter pass complete"
     );
 
-    if let Some(order) = &query.order {
+    let facets = query
+        .facets
+        .iter()
+        .map(|(facet, field)| {
+            let mut tally = FacetTally::default();
+            for (row, _) in &matches {
+                let (value, label) = field.value(row);
+                tally.add(row.clone_id, value, label);
+            }
+            tally.into_counts(facet)
+        })
+        .collect();
+
+    if !query.order.is_empty() {
         warn!(
             group_id = query.group_id,
-            field = ?order.field,
-            direction = ?order.direction,
+            order = ?query.order,
             "sorting clone shadow search results"
         );
-        matches.sort_by(|left, right| compare_rows(left, right, order));
+        matches.sort_by(|left, right| compare_rows(*left, *right, &query.order));
     } else {
         warn!(
             group_id = query.group_id,
@@ -285,7 +589,7 @@ pub fn search_clone_shadow(
 
     let mut unique_ids = Vec::new();
     let mut seen = HashSet::new();
-    for row in matches {
+    for (row, _) in matches {
         if seen.insert(row.clone_id) {
             unique_ids.push(row.clone_id);
         }
@@ -310,6 +614,7 @@ pub fn search_clone_shadow(
     CloneShadowSearchResult {
         items: unique_ids.get(start..end).unwrap_or(&[]).to_vec(),
         search_total: total,
+        facets,
     }
 }
 
@@ -320,38 +625,66 @@ fn row_matches_filter(row: &CloneTableShadowRow, filter: &CloneShadowFilter) ->
         CloneShadowFilter::CloneNameContains(value) => contains_ci(&row.clone_name, value),
         CloneShadowFilter::ProteinNameContains(value) => contains_ci(&row.protein_name, value),
         CloneShadowFilter::SpeciesNameContains(value) => contains_ci(&row.species_name, value),
+        CloneShadowFilter::ApplicationEq(filter) => {
+            row.application_id == Some(filter.application.id())
+                && filter
+                    .status
+                    .is_none_or(|status| row.application_status == Some(status.id()))
+        }
         CloneShadowFilter::ValidationApplicationEq(value) => {
             row.validation_application == Some(*value)
         }
         CloneShadowFilter::ValidationStatusEq(value) => row.validation_status == Some(*value),
+        CloneShadowFilter::ConsensusStatusEq(value) => row.consensus_status == Some(*value),
+        CloneShadowFilter::ConsensusScoreGte(value) => {
+            row.consensus_score.is_some_and(|score| score >= *value)
+        }
         CloneShadowFilter::ReactivityContains(values) => {
             values.iter().any(|value| row.reactivity_id == Some(*value))
         }
-    }
-}
-
-fn row_matches_global(row: &CloneTableShadowRow, global: &Option<String>) -> bool {
-    match global {
-        Some(value) if !value.trim().is_empty() => {
-            row.fulltext.contains(&value.trim().to_lowercase())
+        CloneShadowFilter::Field(field, comparison, value) => {
+            matches(field.value(row), *comparison, value)
         }
-        _ => true,
     }
 }
 
 fn query_requires_validation_row(query: &CloneShadowQuery) -> bool {
-    query.filters.iter().any(|filter| {
-        matches!(
-            filter,
-            CloneShadowFilter::ValidationApplicationEq(_)
-                | CloneShadowFilter::ValidationStatusEq(_)
-        )
-    })
+    query
+        .filters
+        .iter()
+        .chain(&query.condition)
+        .flat_map(QueryCondition::filters)
+        .any(|filter| {
+            matches!(
+                filter,
+                CloneShadowFilter::ValidationApplicationEq(_)
+                    | CloneShadowFilter::ValidationStatusEq(_)
+                    | CloneShadowFilter::ConsensusStatusEq(_)
+                    | CloneShadowFilter::ConsensusScoreGte(_)
+            )
+        })
 }
 
+/// Rows paired with their relevance score. Ties go by clone id in the direction of the
+/// first order.
 fn compare_rows(
-    left: &CloneTableShadowRow,
-    right: &CloneTableShadowRow,
+    left: (&CloneTableShadowRow, f64),
+    right: (&CloneTableShadowRow, f64),
+    orders: &[CloneShadowOrder],
+) -> Ordering {
+    let ord = orders.iter().fold(Ordering::Equal, |ord, order| {
+        ord.then_with(|| compare_field(left, right, order))
+    });
+    let ties = || match orders.first().map(|order| order.direction) {
+        Some(CloneShadowDirection::Desc) => right.0.clone_id.cmp(&left.0.clone_id),
+        _ => left.0.clone_id.cmp(&right.0.clone_id),
+    };
+    ord.then_with(ties)
+}
+
+fn compare_field(
+    (left, left_score): (&CloneTableShadowRow, f64),
+    (right, right_score): (&CloneTableShadowRow, f64),
     order: &CloneShadowOrder,
 ) -> Ordering {
     let ord = match order.field {
@@ -370,12 +703,11 @@ fn compare_rows(
         CloneShadowOrderField::ValidationStatus => {
             left.validation_status.cmp(&right.validation_status)
         }
-    };
-
-    let ord = if ord == Ordering::Equal {
-        left.clone_id.cmp(&right.clone_id)
-    } else {
-        ord
+        CloneShadowOrderField::ConsensusScore => left
+            .consensus_score
+            .partial_cmp(&right.consensus_score)
+            .unwrap_or(Ordering::Equal),
+        CloneShadowOrderField::Relevance => left_score.total_cmp(&right_score),
     };
 
     match order.direction {
@@ -384,37 +716,54 @@ fn compare_rows(
     }
 }
 
-fn build_clone_fulltext(row: &CloneTableShadowRow) -> String {
-    let mut parts = vec![
-        row.clone_name.clone(),
-        row.protein_name.clone(),
-        row.species_name.clone(),
-        row.isotype.clone(),
-        row.epitope.clone(),
+/// The searchable texts of a row with their boosts; clone and protein names rank first.
+fn clone_text_fields(row: &CloneTableShadowRow) -> Vec<(&str, f64)> {
+    let mut fields = vec![
+        (row.clone_name.as_str(), NAME_BOOST),
+        (row.protein_name.as_str(), NAME_BOOST),
+        (row.species_name.as_str(), DEFAULT_BOOST),
+        (row.isotype.as_str(), DEFAULT_BOOST),
+        (row.epitope.as_str(), DEFAULT_BOOST),
     ];
 
     if row.is_phospho {
-        parts.push("phospho".to_string());
+        fields.push(("phospho", DEFAULT_BOOST));
     }
     if row.is_polyclonal {
-        parts.push("polyclonal".to_string());
+        fields.push(("polyclonal", DEFAULT_BOOST));
     }
-    if let Some(label) = &row.application_label {
-        parts.push(label.clone());
+    if let Some(label) = &row.application_label
+        && row
+            .application_status
+            .and_then(ApplicationStatus::from_id)
+            .is_some_and(ApplicationStatus::is_usable)
+    {
+        fields.push((label, DEFAULT_BOOST));
     }
-    if let Some(label) = &row.validation_application_label {
-        parts.push(label.clone());
+    for label in [
+        &row.validation_application_label,
+        &row.validation_status_label,
+        &row.reactivity_label,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        fields.push((label, DEFAULT_BOOST));
     }
-    if let Some(label) = &row.validation_status_label {
-        parts.push(label.clone());
-    }
-    if let Some(label) = &row.reactivity_label {
-        parts.push(label.clone());
+    fields
+}
+
+impl CloneTableShadowRow {
+    /// Where the row's text fields contain words of `query`.
+    pub fn snippet(&self, query: &str) -> Option<Snippet> {
+        snippet(clone_text_fields(self), query)
     }
+}
 
-    parts
+fn build_clone_fulltext(row: &CloneTableShadowRow) -> String {
+    clone_text_fields(row)
         .into_iter()
-        .map(|part| part.trim().to_lowercase())
+        .map(|(part, _)| part.trim().to_lowercase())
         .filter(|part| !part.is_empty())
         .collect::<Vec<_>>()
         .join(" ")
@@ -439,6 +788,78 @@ fn cmp_optional_text(left: Option<&str>, right: Option<&str>) -> Ordering {
     }
 }
 
+/// The fields of a clone object, which clone searches can select.
+const OBJECT_FIELDS: [&str; 13] = [
+    "id",
+    "group_id",
+    "name",
+    "protein_id",
+    "protein_name",
+    "species_id",
+    "species_name",
+    "is_phospho",
+    "is_polyclonal",
+    "isotype",
+    "epitope",
+    "reactivity",
+    "application",
+];
+
+pub fn object_field(field_name: &str) -> Option<String> {
+    OBJECT_FIELDS
+        .contains(&field_name)
+        .then(|| field_name.to_string())
+}
+
+/// A clone from its shadow rows, which repeat it once per application, reactivity and
+/// validation.
+fn clone_object(rows: &[&CloneTableShadowRow]) -> Map<String, Value> {
+    let Some(first) = rows.first() else {
+        return Map::new();
+    };
+    let mut reactivity = Vec::new();
+    let mut applications = CloneApplications::default();
+    for row in rows {
+        if let Some(id) = row.reactivity_id
+            && !reactivity.contains(&id)
+        {
+            reactivity.push(id);
+        }
+        if let Some(application) = row.application_id.and_then(Application::from_id) {
+            let status = row
+                .application_status
+                .and_then(ApplicationStatus::from_id)
+                .unwrap_or(ApplicationStatus::Undefined);
+            applications.insert(application, status, None);
+        }
+    }
+    let text = |text: &str| {
+        if text.is_empty() {
+            Value::Null
+        } else {
+            json!(text)
+        }
+    };
+    [
+        ("id", json!(first.clone_id)),
+        ("group_id", json!(first.group_id)),
+        ("name", json!(first.clone_name)),
+        ("protein_id", json!(first.protein_id)),
+        ("protein_name", text(&first.protein_name)),
+        ("species_id", json!(first.species_id)),
+        ("species_name", text(&first.species_name)),
+        ("is_phospho", json!(first.is_phospho)),
+        ("is_polyclonal", json!(first.is_polyclonal)),
+        ("isotype", text(&first.isotype)),
+        ("epitope", text(&first.epitope)),
+        ("reactivity", json!(reactivity)),
+        ("application", applications.to_json()),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect()
+}
+
 fn validation_application_to_string(value: i64) -> String {
     match value {
         0 => "SMC".to_string(),
@@ -462,23 +883,23 @@ fn validation_status_to_string(value: i64) -> String {
     }
 }
 
-fn parse_clone_application_entries(value: Option<&Value>) -> Vec<(Option<i64>, Option<String>)> {
-    let Some(Value::Object(map)) = value else {
+fn parse_clone_application_entries(
+    value: Option<&Value>,
+) -> Vec<(Option<i64>, Option<String>, Option<i64>)> {
+    let Some(value) = value else {
         return Vec::new();
     };
 
-    let mut entries = map
+    CloneApplications::from_json(value)
         .iter()
-        .filter_map(|(key, raw_value)| match raw_value {
-            Value::Bool(true) => key.parse::<i64>().ok(),
-            Value::String(v) if v.eq_ignore_ascii_case("true") => key.parse::<i64>().ok(),
-            _ => None,
+        .map(|entry| {
+            (
+                Some(entry.application.id()),
+                Some(entry.application.label().to_string()),
+                Some(entry.status.id()),
+            )
         })
-        .map(|id| (Some(id), Some(validation_application_to_string(id))))
-        .collect::<Vec<_>>();
-
-    entries.sort_by_key(|(id, _)| *id);
-    entries
+        .collect()
 }
 
 pub fn map_filter(field_table: &str, field_name: &str, value: &Value) -> Option<CloneShadowFilter> {
@@ -494,12 +915,21 @@ pub fn map_filter(field_table: &str, field_name: &str, value: &Value) -> Option<
         ("species", "name", Value::String(v)) => {
             Some(CloneShadowFilter::SpeciesNameContains(v.clone()))
         }
+        ("clone", "application", value) => {
+            ApplicationFilter::parse(value).map(CloneShadowFilter::ApplicationEq)
+        }
         ("validation", "application", Value::Number(v)) => {
             v.as_i64().map(CloneShadowFilter::ValidationApplicationEq)
         }
         ("validation", "status", Value::Number(v)) => {
             v.as_i64().map(CloneShadowFilter::ValidationStatusEq)
         }
+        ("clone", "consensus_status", Value::Number(v)) => {
+            v.as_i64().map(CloneShadowFilter::ConsensusStatusEq)
+        }
+        ("clone", "consensus_score", Value::Number(v)) => {
+            v.as_f64().map(CloneShadowFilter::ConsensusScoreGte)
+        }
         ("clone", "reactivity", Value::Number(v)) => v
             .as_i64()
             .map(|id| CloneShadowFilter::ReactivityContains(vec![id])),
@@ -514,3 +944,98 @@ pub fn map_filter(field_table: &str, field_name: &str, value: &Value) -> Option<
         _ => None,
     }
 }
+
+/// The request filter at `position` as a condition on clone rows: `eq` and `contains` map
+/// like [`map_filter`] where it can, and other operators compare [`CloneShadowField`]s.
+pub fn map_request_filter(
+    position: usize,
+    filter: &Filter,
+) -> Result<Option<QueryCondition<CloneShadowFilter>>, ParseError> {
+    let table = filter.table.to_string();
+    let field = CloneShadowField::parse(filter.table, &filter.field);
+    resolve_filter(position, filter, &mut |comparison, value| {
+        let mapped = match comparison {
+            Comparison::Match => map_filter(&table, &filter.field, value),
+            _ => None,
+        };
+        mapped.or_else(|| {
+            field.map(|field| CloneShadowFilter::Field(field, comparison, value.clone()))
+        })
+    })
+}
+
+pub fn map_facet(field_table: &str, field_name: &str) -> Option<CloneShadowFacet> {
+    match (field_table, field_name) {
+        ("protein", "name") => Some(CloneShadowFacet::ProteinName),
+        ("species", "name") => Some(CloneShadowFacet::SpeciesName),
+        ("clone", "is_phospho") => Some(CloneShadowFacet::IsPhospho),
+        ("clone", "is_polyclonal") => Some(CloneShadowFacet::IsPolyclonal),
+        ("clone", "isotype") => Some(CloneShadowFacet::Isotype),
+        ("clone", "application") => Some(CloneShadowFacet::Application),
+        ("clone", "reactivity") => Some(CloneShadowFacet::Reactivity),
+        ("validation", "application") => Some(CloneShadowFacet::ValidationApplication),
+        ("validation", "status") => Some(CloneShadowFacet::ValidationStatus),
+        ("clone", "consensus_status") => Some(CloneShadowFacet::ConsensusStatus),
+        _ => None,
+    }
+}
+
+/// Resolves the global filter: bare words and phrases search the full text and field terms
+/// map like request filters, with `validation.status` also taking labels such as `yes`.
+pub fn resolve_condition(
+    expr: &QueryExpr,
+) -> Result<QueryCondition<CloneShadowFilter>, ParseError> {
+    let allowed = allowed_filter_tables(ReturnType::Clone);
+    expr.resolve(&mut |term| resolve_term(&allowed, term))
+}
+
+fn resolve_term(
+    allowed: &std::collections::BTreeSet<ReturnType>,
+    term: &QueryTerm,
+) -> Result<QueryCondition<CloneShadowFilter>, ParseError> {
+    if let Some(text) = term.text() {
+        return Ok(QueryCondition::Text(text));
+    }
+    for (table, field) in term.targets(ReturnType::Clone, allowed)? {
+        let value = |raw: &String| match (table, field.as_str()) {
+            (ReturnType::Validation, "status") => {
+                ApplicationStatus::parse(&Value::String(raw.clone()))
+                    .map_or_else(|| Value::String(raw.clone()), |status| json!(status.id()))
+            }
+            _ => raw
+                .parse::<i64>()
+                .map(|value| json!(value))
+                .or_else(|_| raw.parse::<f64>().map(|value| json!(value)))
+                .unwrap_or_else(|_| Value::String(raw.clone())),
+        };
+        // Comparisons and ranges compare fields, other terms map like request filters.
+        let compared = |comparison, raw: &String| {
+            let value = value(raw);
+            if !value.is_number() && date_epoch(raw).is_none() {
+                return Err(term.invalid_value(format!("expected a number or date, got `{raw}`")));
+            }
+            Ok(CloneShadowField::parse(table, &field).map(|field| {
+                QueryCondition::Filter(CloneShadowFilter::Field(field, comparison, value))
+            }))
+        };
+        let condition = match (&term.value, term.comparison) {
+            (TermValue::Range { low, high }, _) => {
+                [(Comparison::Gte, low), (Comparison::Lte, high)]
+                    .into_iter()
+                    .filter_map(|(comparison, bound)| Some(compared(comparison, bound.as_ref()?)))
+                    .collect::<Result<Option<Vec<_>>, _>>()?
+                    .map(QueryCondition::All)
+            }
+            (TermValue::Word(raw) | TermValue::Phrase(raw), Comparison::Match | Comparison::Eq) => {
+                map_filter(table.table_name(), &field, &value(raw)).map(QueryCondition::Filter)
+            }
+            (TermValue::Word(raw) | TermValue::Phrase(raw), comparison) => {
+                compared(comparison, raw)?
+            }
+        };
+        if let Some(condition) = condition {
+            return Ok(condition);
+        }
+    }
+    Err(term.unsupported(ReturnType::Clone))
+}
@@ -0,0 +1,103 @@
+//! Per-value counts over a filtered result set, so a client can show how many results fall
+//! under each species or status and narrow the search by one of them.
+
+use crate::web::routes_search::ReturnType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// A field to count results by, named like a request filter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchFacet {
+    pub table: ReturnType,
+    pub field: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FacetCounts {
+    #[serde(flatten)]
+    pub facet: SearchFacet,
+    /// Most frequent first.
+    pub values: Vec<FacetValue>,
+}
+
+impl FacetCounts {
+    pub fn new(facet: &SearchFacet, mut values: Vec<FacetValue>) -> Self {
+        values
+            .sort_by_cached_key(|value| (std::cmp::Reverse(value.count), value.value.to_string()));
+        Self {
+            facet: facet.clone(),
+            values,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FacetValue {
+    /// What a filter on the field takes, such as a status id; null for results without one.
+    pub value: Value,
+    /// How the value reads, such as a status name, when it is an id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Distinct results with the value.
+    pub count: i64,
+}
+
+/// Collects the values of one field, counting each result once per value however many of
+/// its rows carry it.
+#[derive(Debug, Default)]
+pub struct FacetTally {
+    values: HashMap<String, (Value, Option<String>, HashSet<i64>)>,
+}
+
+impl FacetTally {
+    pub fn add(&mut self, id: i64, value: Value, label: Option<String>) {
+        self.values
+            .entry(value.to_string())
+            .or_insert_with(|| (value, label, HashSet::new()))
+            .2
+            .insert(id);
+    }
+
+    pub fn into_counts(self, facet: &SearchFacet) -> FacetCounts {
+        FacetCounts::new(
+            facet,
+            self.values
+                .into_values()
+                .map(|(value, label, ids)| FacetValue {
+                    value,
+                    label,
+                    count: ids.len() as i64,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tally_counts_each_result_once_per_value() {
+        let mut tally = FacetTally::default();
+        tally.add(1, json!(0), Some("Yes".into()));
+        tally.add(1, json!(0), Some("Yes".into()));
+        tally.add(1, json!(2), Some("No".into()));
+        tally.add(2, json!(2), Some("No".into()));
+        tally.add(3, Value::Null, None);
+
+        let counts = tally.into_counts(&SearchFacet {
+            table: ReturnType::Validation,
+            field: "status".into(),
+        });
+        let values = counts
+            .values
+            .iter()
+            .map(|value| (value.value.clone(), value.count))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![(json!(2), 2), (json!(0), 1), (Value::Null, 1)]);
+        assert_eq!(counts.values[0].label.as_deref(), Some("No"));
+    }
+}
@@ -0,0 +1,364 @@
+//! The operators of request filters. Every filter resolves into a [`QueryCondition`] of
+//! single comparisons, like the global filter, so the shadows and the SQL fallback only
+//! compare values and agree on what each operator means:
+//!
+//! - `eq` and `contains` find text containing the value and anything else equal to it; a
+//!   list matches any of its items;
+//! - `ne` is the negation of `eq`;
+//! - `gt`, `gte`, `lt` and `lte` compare numbers and dates;
+//! - `between` takes `[low, high]` and includes both, either being optional as `null`;
+//! - `in` and `not_in` take a list of values to equal, text ignoring case;
+//! - `is_null` and `is_not_null` ignore the value; empty text counts as null;
+//! - `"not": true` negates any filter.
+//!
+//! Dates are `2024-05-31`, RFC 3339 times or relative to now as `now-90d`, with `h`, `d` and
+//! `w` units, and compare with timestamps as seconds since the epoch. A filter with an
+//! unknown operator or a value that does not suit it fails the search.
+
+use super::query::{Comparison, ParseError, QueryCondition};
+use crate::web::routes_search::Filter;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Between,
+    In,
+    NotIn,
+    IsNull,
+    IsNotNull,
+}
+
+impl FilterOp {
+    pub fn parse(op: &str) -> Option<Self> {
+        match op.to_lowercase().as_str() {
+            "eq" | "contains" => Some(Self::Eq),
+            "ne" | "neq" => Some(Self::Ne),
+            "gt" => Some(Self::Gt),
+            "gte" => Some(Self::Gte),
+            "lt" => Some(Self::Lt),
+            "lte" => Some(Self::Lte),
+            "between" => Some(Self::Between),
+            "in" => Some(Self::In),
+            "not_in" => Some(Self::NotIn),
+            "is_null" => Some(Self::IsNull),
+            "is_not_null" => Some(Self::IsNotNull),
+            _ => None,
+        }
+    }
+}
+
+/// `filter`, the request's filter at `position`, as comparisons `leaf` turns into filters of
+/// one search; `None` when `leaf` cannot compare the field so. An error when its operator is
+/// unknown or its value does not suit the operator.
+pub fn resolve_filter<F>(
+    position: usize,
+    filter: &Filter,
+    leaf: &mut impl FnMut(Comparison, &Value) -> Option<F>,
+) -> Result<Option<QueryCondition<F>>, ParseError> {
+    let Some(op) = FilterOp::parse(&filter.op) else {
+        return Err(invalid_filter(
+            position,
+            format!("unknown operator `{}`", filter.op),
+        ));
+    };
+    let value = &filter.value;
+    let ordered =
+        |bound: &Value| bound.is_number() || bound.as_str().and_then(date_epoch).is_some();
+    match op {
+        FilterOp::Gt | FilterOp::Gte | FilterOp::Lt | FilterOp::Lte if !ordered(value) => {
+            return Err(invalid_filter(
+                position,
+                format!("expected a number or date, got `{value}`"),
+            ));
+        }
+        FilterOp::Between => match value.as_array().map(Vec::as_slice) {
+            Some([low, high]) if [low, high].iter().all(|b| b.is_null() || ordered(b)) => {}
+            _ => {
+                return Err(invalid_filter(
+                    position,
+                    format!("expected `[low, high]` of numbers or dates, got `{value}`"),
+                ));
+            }
+        },
+        _ => {}
+    }
+    Ok(resolve_op(op, filter, leaf))
+}
+
+/// A filter error at the filter's position in the request.
+pub fn invalid_filter(position: usize, message: impl std::fmt::Display) -> ParseError {
+    ParseError::new(position, format!("filter {position}: {message}"))
+}
+
+fn resolve_op<F>(
+    op: FilterOp,
+    filter: &Filter,
+    leaf: &mut impl FnMut(Comparison, &Value) -> Option<F>,
+) -> Option<QueryCondition<F>> {
+    let mut one = |comparison, value: &Value| leaf(comparison, value).map(QueryCondition::Filter);
+    let not = |condition| QueryCondition::Not(Box::new(condition));
+    let value = &filter.value;
+    let list = || {
+        value
+            .as_array()
+            .map_or_else(|| vec![value], |items| items.iter().collect())
+    };
+
+    let condition = match op {
+        FilterOp::Eq => one(Comparison::Match, value)?,
+        FilterOp::Ne => not(one(Comparison::Match, value)?),
+        FilterOp::Gt => one(Comparison::Gt, value)?,
+        FilterOp::Gte => one(Comparison::Gte, value)?,
+        FilterOp::Lt => one(Comparison::Lt, value)?,
+        FilterOp::Lte => one(Comparison::Lte, value)?,
+        FilterOp::Between => {
+            let [low, high] = value.as_array()?.as_slice() else {
+                return None;
+            };
+            let bounds = [(Comparison::Gte, low), (Comparison::Lte, high)]
+                .into_iter()
+                .filter(|(_, bound)| !bound.is_null())
+                .map(|(comparison, bound)| one(comparison, bound))
+                .collect::<Option<Vec<_>>>()?;
+            QueryCondition::All(bounds)
+        }
+        // An empty list matches nothing, and nothing is excluded by it.
+        FilterOp::In => QueryCondition::Any(
+            list()
+                .into_iter()
+                .map(|item| one(Comparison::Eq, item))
+                .collect::<Option<_>>()?,
+        ),
+        FilterOp::NotIn => not(QueryCondition::Any(
+            list()
+                .into_iter()
+                .map(|item| one(Comparison::Eq, item))
+                .collect::<Option<_>>()?,
+        )),
+        FilterOp::IsNull => one(Comparison::Eq, &Value::Null)?,
+        FilterOp::IsNotNull => not(one(Comparison::Eq, &Value::Null)?),
+    };
+    Some(if filter.not {
+        not(condition)
+    } else {
+        condition
+    })
+}
+
+/// A value of a shadow row as filters see it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue<'a> {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(&'a str),
+}
+
+impl FieldValue<'_> {
+    fn is_null(self) -> bool {
+        match self {
+            Self::Null => true,
+            Self::Text(text) => text.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    fn number(self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(value as f64),
+            Self::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<Option<&'a str>> for FieldValue<'a> {
+    fn from(value: Option<&'a str>) -> Self {
+        value.map_or(Self::Null, FieldValue::Text)
+    }
+}
+
+impl From<Option<i64>> for FieldValue<'_> {
+    fn from(value: Option<i64>) -> Self {
+        value.map_or(Self::Null, FieldValue::Int)
+    }
+}
+
+impl From<Option<f64>> for FieldValue<'_> {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(Self::Null, FieldValue::Float)
+    }
+}
+
+/// Whether `value` passes `comparison` with `expected`; a `null` expected value asks for
+/// a null.
+pub fn matches(value: FieldValue, comparison: Comparison, expected: &Value) -> bool {
+    if expected.is_null() {
+        return matches!(comparison, Comparison::Match | Comparison::Eq) && value.is_null();
+    }
+    let ordering = || {
+        let expected = match expected {
+            Value::Number(number) => number.as_f64()?,
+            Value::String(raw) => date_epoch(raw)? as f64,
+            _ => return None,
+        };
+        value.number()?.partial_cmp(&expected)
+    };
+    match comparison {
+        Comparison::Match | Comparison::Eq => match (expected, value) {
+            (Value::Array(items), _) => items
+                .iter()
+                .any(|item| !item.is_null() && matches(value, Comparison::Eq, item)),
+            (Value::String(needle), FieldValue::Text(text)) if comparison == Comparison::Match => {
+                text.to_lowercase().contains(&needle.trim().to_lowercase())
+            }
+            (Value::String(expected), FieldValue::Text(text)) => {
+                text.trim().eq_ignore_ascii_case(expected.trim())
+            }
+            (Value::Number(_), _) => ordering() == Some(Ordering::Equal),
+            (Value::Bool(expected), FieldValue::Bool(flag)) => *expected == flag,
+            _ => false,
+        },
+        Comparison::Gt => ordering().is_some_and(Ordering::is_gt),
+        Comparison::Gte => ordering().is_some_and(Ordering::is_ge),
+        Comparison::Lt => ordering().is_some_and(Ordering::is_lt),
+        Comparison::Lte => ordering().is_some_and(Ordering::is_le),
+    }
+}
+
+/// Seconds since the epoch of a date such as `2024-05-31` or `now-90d`.
+pub fn date_epoch(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    if let Some(offset) = raw.strip_prefix("now") {
+        let now = chrono::Utc::now().timestamp();
+        if offset.is_empty() {
+            return Some(now);
+        }
+        let offset = offset.strip_prefix('-')?;
+        let (amount, unit) = offset.split_at(offset.find(|ch: char| !ch.is_ascii_digit())?);
+        let seconds = match unit {
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        return amount
+            .parse::<i64>()
+            .ok()?
+            .checked_mul(seconds)
+            .and_then(|ago| now.checked_sub(ago));
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(time.timestamp());
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+        .map(|time| time.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::routes_search::ReturnType;
+    use serde_json::json;
+
+    fn filter(op: &str, value: Value, not: bool) -> Filter {
+        Filter {
+            table: ReturnType::Tag,
+            field: "mw".into(),
+            op: op.into(),
+            value,
+            not,
+        }
+    }
+
+    fn passes(op: &str, value: Value, not: bool, field: FieldValue) -> bool {
+        resolve_filter(0, &filter(op, value, not), &mut |comparison, value| {
+            Some((comparison, value.clone()))
+        })
+        .ok()
+        .flatten()
+        .is_some_and(|condition| {
+            condition.matches(&|(comparison, expected)| matches(field, *comparison, expected))
+        })
+    }
+
+    #[test]
+    fn operators_compare_numbers_dates_lists_and_nulls() {
+        let mw = FieldValue::Int(153);
+        assert!(passes("between", json!([140, 160]), false, mw));
+        assert!(passes("between", json!([null, 153]), false, mw));
+        assert!(!passes("between", json!([154, null]), false, mw));
+        assert!(passes("gt", json!(152.5), false, mw));
+        assert!(!passes("gt", json!(152.5), true, mw));
+        assert!(passes("in", json!([139, 153]), false, mw));
+        assert!(!passes("in", json!([]), false, mw));
+        assert!(passes("not_in", json!([]), false, mw));
+        assert!(passes("ne", json!(154), false, mw));
+        assert!(passes("is_not_null", Value::Null, false, mw));
+
+        assert!(passes("is_null", Value::Null, false, FieldValue::Null));
+        assert!(passes("is_null", Value::Null, false, FieldValue::Text(" ")));
+        assert!(!passes("gte", json!(0), false, FieldValue::Null));
+        assert!(passes(
+            "eq",
+            json!("DYL"),
+            false,
+            FieldValue::Text("Dylight")
+        ));
+        assert!(!passes(
+            "in",
+            json!(["DYL"]),
+            false,
+            FieldValue::Text("Dylight")
+        ));
+        assert!(passes(
+            "in",
+            json!(["dylight "]),
+            false,
+            FieldValue::Text("Dylight")
+        ));
+
+        let received = FieldValue::Int(date_epoch("2024-05-31").unwrap_or_default());
+        assert!(passes("gte", json!("2024-05-01"), false, received));
+        assert!(passes("lt", json!("2024-05-31T00:00:01Z"), false, received));
+        assert!(!passes("gte", json!("now-90d"), false, received));
+    }
+
+    #[test]
+    fn unknown_operators_and_unsuited_values_are_errors() {
+        let error = |op: &str, value: Value| {
+            resolve_filter(3, &filter(op, value, false), &mut |_, _| Some(()))
+                .err()
+                .map(|err| (err.position, err.message))
+        };
+        assert_eq!(
+            error("like", json!(1)),
+            Some((3, "filter 3: unknown operator `like`".to_string()))
+        );
+        assert_eq!(error("gt", json!("heavy")).map(|err| err.0), Some(3));
+        assert!(error("between", json!([1])).is_some());
+        assert!(error("between", json!([null, "now-1d"])).is_none());
+        assert!(error("in", json!(["heavy"])).is_none());
+    }
+
+    #[test]
+    fn relative_dates_count_back_from_now() {
+        let now = chrono::Utc::now().timestamp();
+        let ago = date_epoch("now-2w").unwrap_or_default();
+        assert!((now - ago - 14 * 24 * 60 * 60).abs() <= 1);
+        assert_eq!(date_epoch("now-2y"), None);
+        assert_eq!(date_epoch("now-d"), None);
+        assert_eq!(date_epoch("May 31"), None);
+        assert_eq!(date_epoch(&format!("now-{}w", i64::MAX / 2)), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Search results as the entities themselves instead of their ids: the selected fields of
+//! each shadow row, with related rows embedded from the shadows of their kinds.
+
+use super::SearchState;
+use super::basic::BasicShadowKind;
+use crate::web::routes_search::ReturnType;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+/// The fields of each result and the related objects to embed in it.
+#[derive(Debug, Clone)]
+pub struct Projection {
+    /// Requested names paired with the shadow keys they read; every key when `None`.
+    pub fields: Option<Vec<(String, String)>>,
+    pub expand: Vec<Expansion>,
+}
+
+/// A related object embedded under `name`, found by the id a result holds under `key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expansion {
+    pub name: &'static str,
+    pub key: &'static str,
+    pub kind: BasicShadowKind,
+}
+
+const fn expansion(name: &'static str, key: &'static str, kind: BasicShadowKind) -> Expansion {
+    Expansion { name, key, kind }
+}
+
+use BasicShadowKind as K;
+
+const CLONE: &[Expansion] = &[
+    expansion("protein", "protein_id", K::Protein),
+    expansion("species", "species_id", K::Species),
+];
+
+const CONJUGATE: &[Expansion] = &[
+    expansion("lot", "lot_id", K::Lot),
+    expansion("tag", "tag_id", K::Tag),
+    expansion("storage", "storage_id", K::Storage),
+];
+
+const LOT: &[Expansion] = &[
+    expansion("provider", "provider_id", K::Provider),
+    expansion("collection", "collection_id", K::Collection),
+];
+
+const VALIDATION: &[Expansion] = &[
+    expansion("lot", "lot_id", K::Lot),
+    expansion("conjugate", "conjugate_id", K::Conjugate),
+    expansion("species", "species_id", K::Species),
+];
+
+const PANEL_ELEMENT: &[Expansion] = &[
+    expansion("panel", "panel_id", K::Panel),
+    expansion("conjugate", "conjugate_id", K::Conjugate),
+];
+
+const MEMBER: &[Expansion] = &[
+    expansion("user", "user_id", K::User),
+    expansion("group", "group_id", K::Group),
+];
+
+/// The related objects each return type can embed.
+fn expansions(return_type: ReturnType) -> &'static [Expansion] {
+    match return_type {
+        ReturnType::Clone => CLONE,
+        ReturnType::Conjugate => CONJUGATE,
+        ReturnType::Lot => LOT,
+        ReturnType::Validation => VALIDATION,
+        ReturnType::PanelElement => PANEL_ELEMENT,
+        ReturnType::Member => MEMBER,
+        _ => &[],
+    }
+}
+
+impl Projection {
+    /// `None` unless `fields` or `expand` asks for more than ids. Fields `key` cannot map and
+    /// relations the return type does not have are skipped.
+    pub fn new(
+        return_type: ReturnType,
+        fields: Option<&[String]>,
+        expand: &[String],
+        key: impl Fn(&str) -> Option<String>,
+    ) -> Option<Self> {
+        if fields.is_none() && expand.is_empty() {
+            return None;
+        }
+        let fields = fields.map(|fields| {
+            fields
+                .iter()
+                .filter_map(|field| match key(field) {
+                    Some(key) => Some((field.clone(), key)),
+                    None => {
+                        warn!(?return_type, field, "skipping unsupported search field");
+                        None
+                    }
+                })
+                .collect()
+        });
+        let expand = expand
+            .iter()
+            .filter_map(|name| {
+                let found = expansions(return_type)
+                    .iter()
+                    .find(|expansion| expansion.name == name)
+                    .copied();
+                if found.is_none() {
+                    warn!(?return_type, name, "skipping unsupported search expansion");
+                }
+                found
+            })
+            .collect();
+        Some(Self { fields, expand })
+    }
+
+    /// The selected fields of `objects`, in order, with the expansions embedded.
+    pub async fn apply(
+        &self,
+        state: &SearchState,
+        objects: Vec<Map<String, Value>>,
+    ) -> airlab_lib::model::Result<Vec<Value>> {
+        let mut embedded = vec![Map::new(); objects.len()];
+        for expansion in &self.expand {
+            let shadow = state
+                .registry
+                .get_or_build_basic_shadow(&state.mm, expansion.kind)
+                .await?;
+            let ids = objects
+                .iter()
+                .filter_map(|object| object.get(expansion.key).and_then(Value::as_i64))
+                .collect::<HashSet<_>>();
+            let related = shadow
+                .rows
+                .iter()
+                .filter(|row| ids.contains(&row.id))
+                .map(|row| (row.id, Value::Object(row.to_object())))
+                .collect::<HashMap<_, _>>();
+            for (object, embedded) in objects.iter().zip(&mut embedded) {
+                let value = object
+                    .get(expansion.key)
+                    .and_then(Value::as_i64)
+                    .and_then(|id| related.get(&id))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                embedded.insert(expansion.name.to_string(), value);
+            }
+        }
+
+        Ok(objects
+            .into_iter()
+            .zip(embedded)
+            .map(|(object, embedded)| {
+                let mut projected = match &self.fields {
+                    None => object,
+                    Some(fields) => {
+                        let mut projected = Map::new();
+                        projected.insert(
+                            "id".to_string(),
+                            object.get("id").cloned().unwrap_or(Value::Null),
+                        );
+                        for (name, key) in fields {
+                            projected.insert(
+                                name.clone(),
+                                object.get(key).cloned().unwrap_or(Value::Null),
+                            );
+                        }
+                        projected
+                    }
+                };
+                projected.extend(embedded);
+                Value::Object(projected)
+            })
+            .collect())
+    }
+}
@@ -1,15 +1,27 @@
 pub mod basic;
+pub mod change;
 pub mod clone;
+pub mod facet;
+pub mod filter;
+pub mod hydrate;
+pub mod notify;
+pub mod omni;
+pub mod query;
 pub mod registry;
+pub mod snapshot;
+pub mod text;
 
+use crate::web::routes_saved_search::MatchCache;
 use airlab_lib::model::ModelManager;
 
+pub use self::change::{ShadowChange, ShadowEntity};
 pub use self::registry::SearchShadowRegistry;
 
 #[derive(Clone)]
 pub struct SearchState {
     pub mm: ModelManager,
     pub registry: SearchShadowRegistry,
+    pub saved_matches: MatchCache,
 }
 
 impl SearchState {
@@ -17,6 +29,7 @@ impl SearchState {
         Self {
             mm,
             registry: SearchShadowRegistry::new(),
+            saved_matches: MatchCache::default(),
         }
     }
 }
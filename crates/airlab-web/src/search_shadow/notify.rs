@@ -0,0 +1,232 @@
+//! Keeps the shadows of several airlab-web instances in step. Database triggers announce
+//! every write to a shadowed table on [`CHANNEL`] with the next value of the
+//! `search_shadow_version` sequence; each instance listens and patches the rows the write
+//! touches.
+//!
+//! Search responses carry the registry version in [`VERSION_HEADER`] and JSON API writes the
+//! database version after the write, so a client can tell that a search from another
+//! instance may not include its write yet. Versions are taken when a row is written, not
+//! when its transaction commits, so notifications can arrive out of version order and a
+//! search carrying a version may still miss a write with a smaller one.
+
+use super::{SearchShadowRegistry, SearchState, ShadowChange, ShadowEntity};
+use airlab_lib::model::ModelManager;
+use serde::Deserialize;
+use sqlx::postgres::{PgListener, PgNotification, PgPoolOptions};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+pub const CHANNEL: &str = "search_shadow";
+
+pub const VERSION_HEADER: &str = "x-search-version";
+
+/// Wait before listening again after the connection failed.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct ShadowNotification {
+    table: String,
+    id: i64,
+    version: i64,
+}
+
+/// The version of the newest write to a shadowed table, 0 before the first one.
+pub async fn db_version(mm: &ModelManager) -> airlab_lib::model::Result<i64> {
+    sqlx::query_scalar(
+        "SELECT CASE WHEN is_called THEN last_value ELSE 0 END FROM public.search_shadow_version",
+    )
+    .fetch_one(mm.db())
+    .await
+    .map_err(airlab_lib::model::Error::from)
+}
+
+/// Listens for writes for as long as the process runs, reconnecting when the connection is
+/// lost.
+pub fn spawn_listener(state: &SearchState) -> JoinHandle<()> {
+    let registry = state.registry.clone();
+    let mm = state.mm.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = listen(&registry, &mm).await {
+                warn!("search shadow listener failed: {err}");
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    })
+}
+
+/// Returns once the connection is lost; writes from then on are missed until the next
+/// call has resynced.
+async fn listen(
+    registry: &SearchShadowRegistry,
+    mm: &ModelManager,
+) -> airlab_lib::model::Result<()> {
+    // The listener holds its connection for good, so it gets a pool of its own.
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect_lazy_with((*mm.db().connect_options()).clone());
+    let mut listener = PgListener::connect_with(&pool)
+        .await
+        .map_err(airlab_lib::model::Error::from)?;
+    listener
+        .listen(CHANNEL)
+        .await
+        .map_err(airlab_lib::model::Error::from)?;
+    registry.resync(mm).await?;
+    debug!("listening for search shadow changes");
+
+    while let Some(first) = listener
+        .try_recv()
+        .await
+        .map_err(airlab_lib::model::Error::from)?
+    {
+        let mut batch = vec![first];
+        while let Some(notification) = listener.next_buffered() {
+            batch.push(notification);
+        }
+        apply(registry, mm, batch.iter().map(PgNotification::payload)).await?;
+    }
+    warn!("search shadow listener lost its connection");
+    Ok(())
+}
+
+/// Applies each changed row once, however often it was written. Every notified write is
+/// applied, including the ones this instance already patched in itself: a change reloads
+/// the row as it is now, so applying it again is harmless, while skipping by version would
+/// drop writes that commit after one with a higher version.
+async fn apply<'a>(
+    registry: &SearchShadowRegistry,
+    mm: &ModelManager,
+    payloads: impl IntoIterator<Item = &'a str>,
+) -> airlab_lib::model::Result<()> {
+    let mut changes: Vec<ShadowChange> = vec![];
+    let mut version = 0;
+    for payload in payloads {
+        let parsed: ShadowNotification = match serde_json::from_str(payload) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                warn!("ignoring search shadow notification: {err}");
+                continue;
+            }
+        };
+        version = version.max(parsed.version);
+        let Some(entity) = ShadowEntity::from_table(&parsed.table) else {
+            continue;
+        };
+        let change = ShadowChange {
+            entity,
+            id: parsed.id,
+        };
+        if !changes.contains(&change) {
+            changes.push(change);
+        }
+    }
+
+    for change in changes {
+        registry.apply_change(mm, change).await?;
+    }
+    registry.observe_version(version);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_shadow::basic::BasicShadowKind;
+    use serde_json::json;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn wait_for(mut done: impl FnMut() -> bool) -> TestResult {
+        for _ in 0..100 {
+            if done() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Err("timed out waiting for the search shadow listener".into())
+    }
+
+    #[tokio::test]
+    async fn listener_patches_shadows_written_elsewhere() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let state = SearchState::new((*mm).clone());
+        let listener = spawn_listener(&state);
+        wait_for(|| state.registry.version() > 0).await?;
+        let seeded = state.registry.version();
+        assert_eq!(seeded, db_version(&mm).await?);
+
+        state
+            .registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Conjugate)
+            .await?;
+        sqlx::query("UPDATE public.tag SET name = 'elsewhere-tag' WHERE id = 211")
+            .execute(mm.db())
+            .await?;
+        let written = db_version(&mm).await?;
+        assert!(written > seeded);
+
+        wait_for(|| state.registry.version() >= written).await?;
+        let conjugates = state
+            .registry
+            .get_basic_shadow(BasicShadowKind::Conjugate)
+            .ok_or("conjugate shadow")?;
+        let row = conjugates
+            .rows
+            .iter()
+            .find(|row| row.id == 4291)
+            .ok_or("conjugate 4291")?;
+        assert!(row.fulltext.contains("elsewhere-tag"));
+
+        listener.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn apply_patches_writes_notified_out_of_version_order() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let registry = SearchShadowRegistry::new();
+        registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Tag)
+            .await?;
+        let insert_tag = |name: &'static str| {
+            sqlx::query_scalar::<_, i64>(
+                "INSERT INTO public.tag (group_id, name, description, is_metal, is_fluorophore, \
+                 is_enzyme, is_biotin, is_other, status, cid, mid) \
+                 VALUES (1, $1, '', true, false, false, false, false, 0, 1, 1) \
+                 RETURNING id",
+            )
+            .bind(name)
+            .fetch_one(mm.db())
+        };
+        let earlier = insert_tag("earlier-tag").await?;
+        let earlier_version = db_version(&mm).await?;
+        let later = insert_tag("later-tag").await?;
+        let later_version = db_version(&mm).await?;
+        let payload = |id: i64, version: i64| {
+            json!({"table": "tag", "id": id, "version": version}).to_string()
+        };
+        let has_row = |id: i64| {
+            registry
+                .get_basic_shadow(BasicShadowKind::Tag)
+                .is_some_and(|shadow| shadow.rows.iter().any(|row| row.id == id))
+        };
+
+        // The later write committed first, e.g. because the earlier transaction ran longer.
+        apply(&registry, &mm, [payload(later, later_version).as_str()]).await?;
+        assert!(has_row(later));
+        assert!(!has_row(earlier));
+
+        apply(&registry, &mm, [payload(earlier, earlier_version).as_str()]).await?;
+        assert!(has_row(earlier));
+        assert_eq!(registry.version(), later_version);
+
+        // A write this instance already patched in is applied again without harm.
+        registry.observe_version(later_version + 1);
+        apply(&registry, &mm, [payload(later, later_version).as_str()]).await?;
+        assert!(has_row(later));
+
+        Ok(())
+    }
+}
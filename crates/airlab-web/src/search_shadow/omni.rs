@@ -0,0 +1,237 @@
+//! One search box over every kind of entity, for a command palette: the best hits of each
+//! kind in the user's groups, each with a short label and the text it matched in.
+
+use super::SearchState;
+use super::basic::{BasicShadowKind, BasicShadowRow};
+use super::query::TextTerm;
+use super::text::Snippet;
+use crate::web::routes_search::ReturnType;
+use airlab_lib::model::clone_application::{Application, ApplicationStatus};
+use futures_util::future::try_join_all;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Hits per kind unless the request asks for fewer or more, up to the maximum.
+pub const OMNI_LIMIT: usize = 5;
+pub const OMNI_MAX_LIMIT: usize = 25;
+
+/// The basic shadows searched besides clones.
+const KINDS: [BasicShadowKind; 5] = [
+    BasicShadowKind::Protein,
+    BasicShadowKind::Lot,
+    BasicShadowKind::Conjugate,
+    BasicShadowKind::Panel,
+    BasicShadowKind::Validation,
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OmniHit {
+    pub id: i64,
+    pub group_id: i64,
+    pub label: String,
+    pub score: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+}
+
+/// The best hits of one return type.
+#[derive(Debug, Clone, Serialize)]
+pub struct OmniGroup {
+    pub return_type: ReturnType,
+    /// All hits, of which `hits` are the best.
+    pub total: i64,
+    pub hits: Vec<OmniHit>,
+}
+
+/// Kinds with hits, the kind of the best hit first.
+pub async fn omni_search(
+    state: &SearchState,
+    user_id: i64,
+    query: &str,
+    limit: usize,
+) -> airlab_lib::model::Result<Vec<OmniGroup>> {
+    if query.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let term = TextTerm {
+        text: query.to_string(),
+        phrase: false,
+    };
+    let memberships = user_memberships(state, user_id).await?;
+    let group_ids = memberships.keys().copied().collect::<HashSet<_>>();
+
+    let basic = |kind| search_basic(state, kind, &term, &memberships, limit);
+    let (proteins, clones, lots, conjugates, panels, validations) = tokio::try_join!(
+        basic(KINDS[0]),
+        search_clones(state, &term, &group_ids, limit),
+        basic(KINDS[1]),
+        basic(KINDS[2]),
+        basic(KINDS[3]),
+        basic(KINDS[4]),
+    )?;
+
+    let mut groups = [proteins, clones, lots, conjugates, panels, validations]
+        .into_iter()
+        .filter(|group| !group.hits.is_empty())
+        .collect::<Vec<_>>();
+    // Stable, so kinds with equally good hits keep the order above.
+    groups.sort_by(|left, right| right.hits[0].score.total_cmp(&left.hits[0].score));
+    Ok(groups)
+}
+
+/// The user's membership of a group.
+struct Membership {
+    member_id: i64,
+    all_panels: bool,
+}
+
+impl Membership {
+    /// Like the panel search, only panels the member created unless they may see all.
+    fn sees(&self, kind: BasicShadowKind, row: &BasicShadowRow) -> bool {
+        kind != BasicShadowKind::Panel
+            || self.all_panels
+            || row.int("created_by") == Some(self.member_id)
+    }
+}
+
+/// The user's memberships by group, of the groups they are an active member of.
+async fn user_memberships(
+    state: &SearchState,
+    user_id: i64,
+) -> airlab_lib::model::Result<HashMap<i64, Membership>> {
+    let members = state
+        .registry
+        .get_or_build_basic_shadow(&state.mm, BasicShadowKind::Member)
+        .await?;
+    Ok(members
+        .rows
+        .iter()
+        .filter(|row| row.int("user_id") == Some(user_id) && row.flag("is_active") == Some(true))
+        .filter_map(|row| {
+            let membership = Membership {
+                member_id: row.id,
+                all_panels: row.flag("all_panels") == Some(true),
+            };
+            Some((row.int("group_id")?, membership))
+        })
+        .collect())
+}
+
+async fn search_basic(
+    state: &SearchState,
+    kind: BasicShadowKind,
+    term: &TextTerm,
+    memberships: &HashMap<i64, Membership>,
+    limit: usize,
+) -> airlab_lib::model::Result<OmniGroup> {
+    let shadow = state
+        .registry
+        .get_or_build_basic_shadow(&state.mm, kind)
+        .await?;
+    let group_ids = memberships.keys().copied().collect::<HashSet<_>>();
+    let ranked = shadow
+        .rank(term, &group_ids)
+        .into_iter()
+        .filter(|(row, _)| {
+            row.int("group_id")
+                .and_then(|group_id| memberships.get(&group_id))
+                .is_some_and(|membership| membership.sees(kind, row))
+        })
+        .collect::<Vec<_>>();
+    Ok(OmniGroup {
+        return_type: kind.return_type(),
+        total: ranked.len() as i64,
+        hits: ranked
+            .into_iter()
+            .take(limit)
+            .map(|(row, score)| OmniHit {
+                id: row.id,
+                group_id: row.int("group_id").unwrap_or_default(),
+                label: basic_label(kind, row),
+                score,
+                snippet: row.snippet(&term.text),
+            })
+            .collect(),
+    })
+}
+
+async fn search_clones(
+    state: &SearchState,
+    term: &TextTerm,
+    group_ids: &HashSet<i64>,
+    limit: usize,
+) -> airlab_lib::model::Result<OmniGroup> {
+    let shadows = try_join_all(group_ids.iter().map(|group_id| {
+        state
+            .registry
+            .get_or_build_clone_shadow(&state.mm, *group_id)
+    }))
+    .await?;
+    let mut hits = vec![];
+    for shadow in shadows {
+        hits.extend(shadow.rank(term).into_iter().map(|(row, score)| OmniHit {
+            id: row.clone_id,
+            group_id: row.group_id,
+            label: label([&row.clone_name, &row.protein_name]),
+            score,
+            snippet: row.snippet(&term.text),
+        }));
+    }
+    hits.sort_by(|left, right| {
+        right
+            .score
+            .total_cmp(&left.score)
+            .then_with(|| left.id.cmp(&right.id))
+    });
+    let total = hits.len() as i64;
+    hits.truncate(limit);
+    Ok(OmniGroup {
+        return_type: ReturnType::Clone,
+        total,
+        hits,
+    })
+}
+
+/// What a palette shows for a row, such as a conjugate's tube number, tag and protein.
+fn basic_label(kind: BasicShadowKind, row: &BasicShadowRow) -> String {
+    let text = |key| row.text(key).unwrap_or_default().to_string();
+    let id_label = |key, label: fn(i64) -> Option<&'static str>| {
+        row.int(key).and_then(label).unwrap_or_default().to_string()
+    };
+    let parts = match kind {
+        BasicShadowKind::Lot => vec![text("name"), text("number")],
+        BasicShadowKind::Conjugate => vec![
+            row.int("tube_number")
+                .map(|tube| format!("#{tube}"))
+                .unwrap_or_default(),
+            text("tag_name"),
+            text("protein_name"),
+        ],
+        BasicShadowKind::Validation => vec![
+            text("clone_name"),
+            id_label("application", |id| {
+                Application::from_id(id).map(Application::label)
+            }),
+            id_label("status", |id| {
+                ApplicationStatus::from_id(id).map(ApplicationStatus::label)
+            }),
+        ],
+        _ => vec![text("name")],
+    };
+    let label = label(&parts);
+    if label.is_empty() {
+        format!("{} {}", kind.label(), row.id)
+    } else {
+        label
+    }
+}
+
+/// The non-empty parts, joined.
+fn label<S: AsRef<str>>(parts: impl IntoIterator<Item = S>) -> String {
+    parts
+        .into_iter()
+        .filter(|part| !part.as_ref().is_empty())
+        .map(|part| part.as_ref().to_string())
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
@@ -0,0 +1,813 @@
+//! The query language of the global search box, such as
+//! `protein:CD3 tag:mw>=150 status:stock -archived application:IMC`.
+//!
+//! Terms are ANDed unless joined by `OR`; `NOT` or a leading `-` negates a term and
+//! parentheses group. A term is a bare word or `"quoted phrase"`, matched against the full
+//! text, or a field term:
+//!
+//! - `field:value` matches a field of the searched table, `table:value` its name and
+//!   `table:field:value` any field of a joined table;
+//! - `>=`, `<=`, `>`, `<` and `=` compare instead of `:`, as in `mw>=150` or `tag:mw>=150`;
+//! - `lo..hi` matches a range, either bound being optional, as in `tag:mw:100..200`.
+//!
+//! `AND`, `OR` and `NOT` are only operators in upper case. Parsing gives a [`QueryExpr`];
+//! each search resolves it into a [`QueryCondition`] over its own filters.
+
+use super::text::TextIndex;
+use crate::web::routes_search::ReturnType;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Why a query could not be parsed or resolved; `position` counts characters from 0.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Character offsets, `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    Term(QueryTerm),
+    Not(Box<QueryExpr>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm {
+    /// `protein` in `protein:CD3` and `tag` in `tag:mw>=150`.
+    pub qualifier: Option<Name>,
+    /// `mw` in `tag:mw>=150` and `mw>=150`.
+    pub field: Option<Name>,
+    pub comparison: Comparison,
+    pub value: TermValue,
+    pub value_span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `:`, containment for text and equality otherwise.
+    Match,
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Match => ":",
+            Self::Eq => "=",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermValue {
+    Word(String),
+    Phrase(String),
+    Range {
+        low: Option<String>,
+        high: Option<String>,
+    },
+}
+
+impl QueryTerm {
+    /// The full-text term of a bare word or phrase.
+    pub fn text(&self) -> Option<TextTerm> {
+        if self.qualifier.is_some() || self.field.is_some() {
+            return None;
+        }
+        match &self.value {
+            TermValue::Word(text) => Some(TextTerm {
+                text: text.clone(),
+                phrase: false,
+            }),
+            TermValue::Phrase(text) => Some(TextTerm {
+                text: text.clone(),
+                phrase: true,
+            }),
+            TermValue::Range { .. } => None,
+        }
+    }
+
+    /// The tables and fields the term may mean, most likely first: `table:field` and
+    /// `table:` name that table, while `field:` may be a field of the searched table or of
+    /// any joined one. Empty for bare words and phrases.
+    pub fn targets(
+        &self,
+        return_type: ReturnType,
+        allowed: &BTreeSet<ReturnType>,
+    ) -> Result<Vec<(ReturnType, String)>, ParseError> {
+        let table = self
+            .qualifier
+            .as_ref()
+            .and_then(|qualifier| ReturnType::from_table_name(&qualifier.text))
+            .filter(|table| allowed.contains(table));
+        match (&self.qualifier, &self.field, table) {
+            (None, None, _) => Ok(vec![]),
+            (Some(_), field, Some(table)) => Ok(vec![(
+                table,
+                field
+                    .as_ref()
+                    .map_or_else(|| "name".to_string(), |field| field.text.clone()),
+            )]),
+            (Some(qualifier), Some(_), None) => Err(ParseError::new(
+                qualifier.span.start,
+                format!(
+                    "unknown table `{}` for {} searches",
+                    qualifier.text,
+                    return_type.table_name()
+                ),
+            )),
+            (Some(name), None, None) | (None, Some(name), _) => {
+                let mut targets = vec![(return_type, name.text.clone())];
+                targets.extend(
+                    allowed
+                        .iter()
+                        .filter(|table| **table != return_type)
+                        .map(|table| (*table, name.text.clone())),
+                );
+                Ok(targets)
+            }
+        }
+    }
+
+    /// The error for a term none of the [`Self::targets`] can filter by.
+    pub fn unsupported(&self, return_type: ReturnType) -> ParseError {
+        let name = self.field.as_ref().or(self.qualifier.as_ref());
+        let (position, text) = name.map_or((self.value_span.start, ""), |name| {
+            (name.span.start, name.text.as_str())
+        });
+        ParseError::new(
+            position,
+            format!(
+                "cannot search {} by `{text}`{}",
+                return_type.table_name(),
+                self.comparison.symbol()
+            ),
+        )
+    }
+
+    /// An error pointing at the value.
+    pub fn invalid_value(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.value_span.start, message)
+    }
+}
+
+/// Parses `input`; `None` when it has no terms at all.
+pub fn parse(input: &str) -> Result<Option<QueryExpr>, ParseError> {
+    let tokens = lex(input)?;
+    let mut parser = Parser {
+        tokens,
+        next: 0,
+        end: input.chars().count(),
+    };
+    if parser.tokens.is_empty() {
+        return Ok(None);
+    }
+    let expr = parser.or()?;
+    if let Some(token) = parser.peek() {
+        return Err(ParseError::new(token.span.start, "unmatched `)`"));
+    }
+    Ok(Some(expr))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Phrase(String),
+    Colon,
+    Compare(Comparison),
+    Minus,
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+    /// Whether whitespace separates the token from the previous one.
+    spaced: bool,
+}
+
+fn is_word_char(ch: char) -> bool {
+    !ch.is_whitespace() && !matches!(ch, '(' | ')' | '"' | ':' | '<' | '>' | '=')
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens: Vec<Token> = vec![];
+    let mut at = 0;
+    let mut spaced = true;
+    while at < chars.len() {
+        let ch = chars[at];
+        let start = at;
+        if ch.is_whitespace() {
+            spaced = true;
+            at += 1;
+            continue;
+        }
+        let kind = match ch {
+            '(' => {
+                at += 1;
+                TokenKind::Open
+            }
+            ')' => {
+                at += 1;
+                TokenKind::Close
+            }
+            ':' => {
+                at += 1;
+                TokenKind::Colon
+            }
+            '=' => {
+                at += 1;
+                TokenKind::Compare(Comparison::Eq)
+            }
+            '<' | '>' => {
+                let or_equal = chars.get(at + 1) == Some(&'=');
+                at += if or_equal { 2 } else { 1 };
+                TokenKind::Compare(match (ch, or_equal) {
+                    ('<', false) => Comparison::Lt,
+                    ('<', true) => Comparison::Lte,
+                    ('>', false) => Comparison::Gt,
+                    _ => Comparison::Gte,
+                })
+            }
+            '"' => {
+                let mut text = String::new();
+                at += 1;
+                loop {
+                    match chars.get(at) {
+                        None => return Err(ParseError::new(start, "unterminated `\"`")),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(at + 1), Some('"' | '\\')) => {
+                            text.push(chars[at + 1]);
+                            at += 2;
+                        }
+                        Some(ch) => {
+                            text.push(*ch);
+                            at += 1;
+                        }
+                    }
+                }
+                at += 1;
+                TokenKind::Phrase(text)
+            }
+            '-' if (spaced
+                || tokens
+                    .last()
+                    .is_some_and(|token| token.kind == TokenKind::Open))
+                && chars.get(at + 1).is_some_and(|next| !next.is_whitespace()) =>
+            {
+                at += 1;
+                TokenKind::Minus
+            }
+            _ => {
+                while at < chars.len() && is_word_char(chars[at]) {
+                    at += 1;
+                }
+                let word = chars[start..at].iter().collect::<String>();
+                match word.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: Span { start, end: at },
+            spaced,
+        });
+        spaced = false;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    /// The length of the input, where errors about a missing term point.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    /// The next token when it directly follows the previous one.
+    fn peek_adjacent(&self) -> Option<&Token> {
+        self.peek().filter(|token| !token.spaced)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Where an error about a missing term after the previous token points.
+    fn missing_position(&self) -> usize {
+        self.peek().map_or(self.end, |token| token.span.start)
+    }
+
+    fn or(&mut self) -> Result<QueryExpr, ParseError> {
+        let mut any = vec![self.and()?];
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.advance();
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            QueryExpr::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<QueryExpr, ParseError> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                None | Some(TokenKind::Or | TokenKind::Close) => break,
+                Some(TokenKind::And) => {
+                    self.advance();
+                }
+                Some(_) => {}
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            QueryExpr::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<QueryExpr, ParseError> {
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Not | TokenKind::Minus) => {
+                self.advance();
+                Ok(QueryExpr::Not(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<QueryExpr, ParseError> {
+        let position = self.missing_position();
+        let Some(token) = self.advance() else {
+            return Err(ParseError::new(position, "expected a search term"));
+        };
+        match token.kind {
+            TokenKind::Open => {
+                let expr = self.or()?;
+                match self.advance() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => Ok(expr),
+                    _ => Err(ParseError::new(token.span.start, "unmatched `(`")),
+                }
+            }
+            TokenKind::Close => Err(ParseError::new(token.span.start, "unmatched `)`")),
+            TokenKind::Phrase(text) => Ok(QueryExpr::Term(QueryTerm {
+                qualifier: None,
+                field: None,
+                comparison: Comparison::Match,
+                value: TermValue::Phrase(text),
+                value_span: token.span,
+            })),
+            TokenKind::Word(word) => self.term(word, token.span),
+            TokenKind::Colon | TokenKind::Compare(_) => Err(ParseError::new(
+                token.span.start,
+                "expected a field name before the operator",
+            )),
+            TokenKind::And | TokenKind::Or | TokenKind::Not | TokenKind::Minus => {
+                Err(ParseError::new(token.span.start, "expected a search term"))
+            }
+        }
+    }
+
+    /// A term starting with `word`.
+    fn term(&mut self, word: String, span: Span) -> Result<QueryExpr, ParseError> {
+        let first = Name { text: word, span };
+        let (qualifier, field, comparison) = match self.peek_adjacent().map(|token| &token.kind) {
+            Some(TokenKind::Colon) => {
+                self.advance();
+                match (
+                    self.peek_adjacent().cloned(),
+                    self.tokens.get(self.next + 1),
+                ) {
+                    (
+                        Some(Token {
+                            kind: TokenKind::Word(field),
+                            span,
+                            ..
+                        }),
+                        Some(Token {
+                            kind: TokenKind::Colon | TokenKind::Compare(_),
+                            spaced: false,
+                            ..
+                        }),
+                    ) => {
+                        self.advance();
+                        let comparison = self.comparison();
+                        (Some(first), Some(Name { text: field, span }), comparison)
+                    }
+                    _ => (Some(first), None, Comparison::Match),
+                }
+            }
+            Some(TokenKind::Compare(_)) => {
+                let comparison = self.comparison();
+                (None, Some(first), comparison)
+            }
+            _ => {
+                return Ok(QueryExpr::Term(QueryTerm {
+                    qualifier: None,
+                    field: None,
+                    comparison: Comparison::Match,
+                    value: TermValue::Word(first.text),
+                    value_span: first.span,
+                }));
+            }
+        };
+
+        let operator_end = self.tokens[self.next - 1].span.end;
+        let (value, value_span) = match self.peek_adjacent().cloned() {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                span,
+                ..
+            }) => {
+                self.advance();
+                (range_or_word(&word, span, comparison)?, span)
+            }
+            Some(Token {
+                kind: TokenKind::Phrase(text),
+                span,
+                ..
+            }) => {
+                self.advance();
+                (TermValue::Phrase(text), span)
+            }
+            _ => {
+                return Err(ParseError::new(
+                    operator_end,
+                    format!("expected a value after `{}`", comparison.symbol()),
+                ));
+            }
+        };
+        Ok(QueryExpr::Term(QueryTerm {
+            qualifier,
+            field,
+            comparison,
+            value,
+            value_span,
+        }))
+    }
+
+    /// Consumes the `:` or comparison operator that is next.
+    fn comparison(&mut self) -> Comparison {
+        match self.advance().map(|token| token.kind) {
+            Some(TokenKind::Compare(comparison)) => comparison,
+            _ => Comparison::Match,
+        }
+    }
+}
+
+fn range_or_word(word: &str, span: Span, comparison: Comparison) -> Result<TermValue, ParseError> {
+    let Some((low, high)) = word.split_once("..") else {
+        return Ok(TermValue::Word(word.to_string()));
+    };
+    if !matches!(comparison, Comparison::Match | Comparison::Eq) {
+        return Err(ParseError::new(
+            span.start,
+            format!("a range cannot follow `{}`", comparison.symbol()),
+        ));
+    }
+    let bound = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    let (low, high) = (bound(low), bound(high));
+    if low.is_none() && high.is_none() {
+        return Err(ParseError::new(
+            span.start,
+            "a range needs at least one bound",
+        ));
+    }
+    Ok(TermValue::Range { low, high })
+}
+
+/// A query resolved against the filters of one kind of search.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryCondition<F> {
+    Text(TextTerm),
+    Filter(F),
+    All(Vec<QueryCondition<F>>),
+    Any(Vec<QueryCondition<F>>),
+    Not(Box<QueryCondition<F>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextTerm {
+    pub text: String,
+    /// Quoted: the words must appear together and in order.
+    pub phrase: bool,
+}
+
+impl TextTerm {
+    /// Scores of the matching documents of `index`.
+    pub fn search<'a>(
+        &self,
+        index: &TextIndex,
+        fulltext: impl Fn(usize) -> &'a str,
+    ) -> HashMap<usize, f64> {
+        let mut scores = index.search(&self.text, &fulltext);
+        if self.phrase {
+            let needle = self.text.trim().to_lowercase();
+            scores.retain(|doc, _| fulltext(*doc).to_lowercase().contains(&needle));
+        }
+        scores
+    }
+}
+
+impl QueryExpr {
+    /// Resolves every term with `leaf`. Neighbouring bare words stay one text term, so
+    /// `CD45 RA` is still searched as a whole.
+    pub fn resolve<F>(
+        &self,
+        leaf: &mut impl FnMut(&QueryTerm) -> Result<QueryCondition<F>, ParseError>,
+    ) -> Result<QueryCondition<F>, ParseError> {
+        Ok(match self {
+            Self::Term(term) => leaf(term)?,
+            Self::Not(expr) => QueryCondition::Not(Box::new(expr.resolve(leaf)?)),
+            Self::Or(exprs) => QueryCondition::Any(
+                exprs
+                    .iter()
+                    .map(|expr| expr.resolve(leaf))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Self::And(exprs) => {
+                let mut all: Vec<QueryCondition<F>> = vec![];
+                for expr in exprs {
+                    let condition = expr.resolve(leaf)?;
+                    match (all.last_mut(), condition) {
+                        (
+                            Some(QueryCondition::Text(last)),
+                            QueryCondition::Text(TextTerm {
+                                text,
+                                phrase: false,
+                            }),
+                        ) if !last.phrase => {
+                            last.text.push(' ');
+                            last.text.push_str(&text);
+                        }
+                        (_, condition) => all.push(condition),
+                    }
+                }
+                if all.len() == 1 {
+                    all.remove(0)
+                } else {
+                    QueryCondition::All(all)
+                }
+            }
+        })
+    }
+}
+
+impl<F> QueryCondition<F> {
+    pub fn text_terms(&self) -> Vec<&TextTerm> {
+        match self {
+            Self::Text(term) => vec![term],
+            Self::Filter(_) => vec![],
+            Self::All(conditions) | Self::Any(conditions) => conditions
+                .iter()
+                .flat_map(QueryCondition::text_terms)
+                .collect(),
+            Self::Not(condition) => condition.text_terms(),
+        }
+    }
+
+    pub fn filters(&self) -> Vec<&F> {
+        match self {
+            Self::Text(_) => vec![],
+            Self::Filter(filter) => vec![filter],
+            Self::All(conditions) | Self::Any(conditions) => conditions
+                .iter()
+                .flat_map(QueryCondition::filters)
+                .collect(),
+            Self::Not(condition) => condition.filters(),
+        }
+    }
+
+    /// The relevance of a row if it matches: the sum of its matching text terms' scores.
+    /// `filter` tells whether the row passes a filter and `text` gives its score for a text
+    /// term it matches.
+    pub fn score(
+        &self,
+        filter: &impl Fn(&F) -> bool,
+        text: &impl Fn(&TextTerm) -> Option<f64>,
+    ) -> Option<f64> {
+        match self {
+            Self::Text(term) => text(term),
+            Self::Filter(value) => filter(value).then_some(0.0),
+            Self::All(conditions) => conditions
+                .iter()
+                .map(|condition| condition.score(filter, text))
+                .sum(),
+            Self::Any(conditions) => conditions
+                .iter()
+                .filter_map(|condition| condition.score(filter, text))
+                .reduce(f64::max),
+            Self::Not(condition) => match condition.score(filter, text) {
+                Some(_) => None,
+                None => Some(0.0),
+            },
+        }
+    }
+
+    /// Whether a row passes a condition of filters only.
+    pub fn matches(&self, filter: &impl Fn(&F) -> bool) -> bool {
+        self.score(filter, &|_| None).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn term(expr: &QueryExpr) -> TestResult<&QueryTerm> {
+        match expr {
+            QueryExpr::Term(term) => Ok(term),
+            other => Err(format!("expected a term, got {other:?}").into()),
+        }
+    }
+
+    fn error(input: &str) -> ParseError {
+        match parse(input) {
+            Err(err) => err,
+            Ok(expr) => panic!("expected {input:?} to fail, got {expr:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_reads_qualifiers_comparisons_and_negation() -> TestResult {
+        let expr = parse("protein:CD3 tag:mw>=150 status:stock -archived application:IMC")?
+            .ok_or("query")?;
+        let QueryExpr::And(terms) = &expr else {
+            return Err(format!("expected AND, got {expr:?}").into());
+        };
+        assert_eq!(terms.len(), 5);
+
+        let protein = term(&terms[0])?;
+        assert_eq!(
+            protein.qualifier.as_ref().map(|name| name.text.as_str()),
+            Some("protein")
+        );
+        assert_eq!(protein.field, None);
+        assert_eq!(protein.value, TermValue::Word("CD3".into()));
+
+        let mw = term(&terms[1])?;
+        assert_eq!(
+            mw.qualifier.as_ref().map(|name| name.text.as_str()),
+            Some("tag")
+        );
+        assert_eq!(mw.field.as_ref().map(|name| name.text.as_str()), Some("mw"));
+        assert_eq!(mw.comparison, Comparison::Gte);
+        assert_eq!(mw.value, TermValue::Word("150".into()));
+        assert_eq!(mw.value_span, Span { start: 20, end: 23 });
+
+        let QueryExpr::Not(archived) = &terms[3] else {
+            return Err(format!("expected NOT, got {:?}", terms[3]).into());
+        };
+        assert_eq!(term(archived)?.value, TermValue::Word("archived".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_groups_or_below_and() -> TestResult {
+        let expr = parse(r#"(CD3 OR CD4) AND NOT "T cell" tag:mw:100.. IHC-F"#)?.ok_or("query")?;
+        let QueryExpr::And(terms) = &expr else {
+            return Err(format!("expected AND, got {expr:?}").into());
+        };
+        assert!(matches!(&terms[0], QueryExpr::Or(any) if any.len() == 2));
+        assert!(matches!(&terms[1], QueryExpr::Not(_)));
+        assert_eq!(
+            term(&terms[2])?.value,
+            TermValue::Range {
+                low: Some("100".into()),
+                high: None
+            }
+        );
+        assert_eq!(term(&terms[3])?.value, TermValue::Word("IHC-F".into()));
+
+        assert_eq!(parse("  ")?, None);
+        assert!(matches!(parse("cd3 or cd4")?, Some(QueryExpr::And(terms)) if terms.len() == 3));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_reports_error_positions() {
+        assert_eq!(
+            error(r#"tag:"Ir 191"#),
+            ParseError::new(4, "unterminated `\"`")
+        );
+        assert_eq!(error("(CD3 OR CD4"), ParseError::new(0, "unmatched `(`"));
+        assert_eq!(error("CD3)"), ParseError::new(3, "unmatched `)`"));
+        assert_eq!(
+            error("CD3 OR"),
+            ParseError::new(6, "expected a search term")
+        );
+        assert_eq!(
+            error("tag:mw>= 150"),
+            ParseError::new(8, "expected a value after `>=`")
+        );
+        assert_eq!(
+            error("protein:"),
+            ParseError::new(8, "expected a value after `:`")
+        );
+        assert_eq!(
+            error("mw>100..200"),
+            ParseError::new(3, "a range cannot follow `>`")
+        );
+        assert_eq!(
+            error("mw:.."),
+            ParseError::new(3, "a range needs at least one bound")
+        );
+        assert_eq!(
+            error(":CD3"),
+            ParseError::new(0, "expected a field name before the operator")
+        );
+    }
+
+    #[test]
+    fn resolve_keeps_neighbouring_words_together() -> TestResult {
+        let expr = parse(r#"CD45 RA -"ki 67" clone:x"#)?.ok_or("query")?;
+        let condition = expr.resolve(&mut |term: &QueryTerm| {
+            Ok(match term.text() {
+                Some(text) => QueryCondition::Text(text),
+                None => QueryCondition::Filter(term.value.clone()),
+            })
+        })?;
+        let QueryCondition::All(all) = &condition else {
+            return Err(format!("expected ALL, got {condition:?}").into());
+        };
+        assert_eq!(
+            all[0],
+            QueryCondition::Text(TextTerm {
+                text: "CD45 RA".into(),
+                phrase: false
+            })
+        );
+        assert_eq!(all.len(), 3);
+
+        let scores = |text: &TextTerm| (text.text == "CD45 RA").then_some(2.0);
+        assert_eq!(condition.score(&|_| true, &scores), Some(2.0));
+        assert_eq!(condition.score(&|_| false, &scores), None);
+        Ok(())
+    }
+}
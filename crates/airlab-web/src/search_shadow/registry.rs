@@ -1,16 +1,40 @@
-use super::basic::{BasicGroupShadow, BasicShadowKind, build_basic_shadow};
-use super::clone::{CloneGroupShadow, build_clone_group_shadow};
+use super::basic::{BasicGroupShadow, BasicShadowKind, build_basic_rows, build_basic_shadow};
+use super::change::{Before, ShadowChange, ShadowEntity, Target};
+use super::clone::{CloneGroupShadow, CloneRowScope, build_clone_group_shadow, build_clone_rows};
+use super::notify::db_version;
 use airlab_lib::ctx::Ctx;
 use airlab_lib::model::ModelManager;
 use airlab_lib::model::group::{Group, GroupBmc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
-use tracing::warn;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
 
+/// Quiet time after the last write before all shadows are rebuilt in the background.
+const REBUILD_DEBOUNCE: Duration = Duration::from_secs(5);
+
+/// Longest a steady stream of writes can put off the background rebuild.
+const MAX_REBUILD_DELAY: Duration = REBUILD_DEBOUNCE.saturating_mul(12);
+
+/// Shadows are replaced, never changed in place: a search keeps the `Arc` it got even while
+/// a newer shadow is swapped in.
 #[derive(Clone, Default)]
 pub struct SearchShadowRegistry {
     basic_by_kind: Arc<RwLock<HashMap<BasicShadowKind, Arc<BasicGroupShadow>>>>,
     clone_by_group: Arc<RwLock<HashMap<i64, Arc<CloneGroupShadow>>>>,
+    /// Held while shadows are patched or swapped, so concurrent writes do not lose updates.
+    swap_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Bumped on every write; a debounced rebuild that sees a newer value waits for it.
+    generation: Arc<AtomicU64>,
+    /// When the oldest write that no rebuild has started since came in.
+    pending_since: Arc<std::sync::Mutex<Option<Instant>>>,
+    /// Changes patched in while rebuilds run, to patch into the rebuilt shadows as well.
+    rebuild_log: Arc<std::sync::Mutex<RebuildLog>>,
+    /// Database version of the newest write the shadows are known to contain.
+    version: Arc<AtomicI64>,
+    /// Bumped whenever a shadow is added, replaced or dropped; see [`super::snapshot`].
+    revision: Arc<AtomicU64>,
 }
 
 impl SearchShadowRegistry {
@@ -18,6 +42,119 @@ impl SearchShadowRegistry {
         Self::default()
     }
 
+    /// The newest write version applied to the shadows. Writes with a smaller version may
+    /// still be missing; see [`super::notify`].
+    pub fn version(&self) -> i64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    pub fn observe_version(&self, version: i64) {
+        self.version.fetch_max(version, Ordering::SeqCst);
+    }
+
+    /// Bumped whenever a cached shadow changes.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    fn bump_revision(&self) {
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether the database has writes the shadows may lack.
+    pub async fn is_behind(&self, mm: &ModelManager) -> airlab_lib::model::Result<bool> {
+        Ok(self.version() < db_version(mm).await?)
+    }
+
+    /// Catches up with writes that may have been missed, such as while no listener was
+    /// connected. Cached shadows keep serving searches until the rebuilt ones replace them.
+    pub(super) async fn resync(&self, mm: &ModelManager) -> airlab_lib::model::Result<()> {
+        if !self.is_behind(mm).await? {
+            return Ok(());
+        }
+        let empty = self
+            .basic_by_kind
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .is_empty()
+            && self
+                .clone_by_group
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .is_empty();
+        if empty {
+            // Shadows built from now on are current.
+            self.observe_version(db_version(mm).await?);
+            return Ok(());
+        }
+        self.rebuild_aside(mm).await
+    }
+
+    /// All cached shadows and the version they include, for [`super::snapshot`].
+    pub(super) fn cached(&self) -> CachedShadows {
+        // The version is read first: shadows are only ever newer than it says.
+        CachedShadows {
+            version: self.version(),
+            basic: self
+                .basic_by_kind
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .values()
+                .cloned()
+                .collect(),
+            clones: self
+                .clone_by_group
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .iter()
+                .map(|(group_id, shadow)| (*group_id, shadow.clone()))
+                .collect(),
+        }
+    }
+
+    /// Replaces all shadows with `cached`, such as when loading a snapshot.
+    pub(super) async fn restore(&self, cached: CachedShadows) {
+        let _guard = self.swap_lock.lock().await;
+        *self
+            .basic_by_kind
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = cached
+            .basic
+            .into_iter()
+            .map(|shadow| (shadow.kind, shadow))
+            .collect();
+        *self
+            .clone_by_group
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) =
+            cached.clones.into_iter().collect();
+        self.version.store(cached.version, Ordering::SeqCst);
+        self.bump_revision();
+    }
+
+    /// Builds every shadow before the first search needs it, unless a current snapshot
+    /// already has them all.
+    pub async fn warm_up(&self, mm: &ModelManager) -> airlab_lib::model::Result<()> {
+        let complete = {
+            let basic_by_kind = self
+                .basic_by_kind
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            BasicShadowKind::ALL
+                .iter()
+                .all(|kind| basic_by_kind.contains_key(kind))
+        };
+        let groups: Vec<Group> = GroupBmc::list(&Ctx::root_ctx(), mm, None, None).await?;
+        let complete = complete
+            && groups
+                .iter()
+                .all(|group| self.get_clone_shadow(group.id).is_some());
+        if complete && !self.is_behind(mm).await? {
+            return Ok(());
+        }
+        self.rebuild_aside(mm).await
+    }
+
     pub fn get_clone_shadow(&self, group_id: i64) -> Option<Arc<CloneGroupShadow>> {
         self.clone_by_group
             .read()
@@ -60,6 +197,7 @@ impl SearchShadowRegistry {
         mm: &ModelManager,
         kind: BasicShadowKind,
     ) -> airlab_lib::model::Result<Arc<BasicGroupShadow>> {
+        let generation = self.generation.load(Ordering::SeqCst);
         warn!(kind = kind.label(), "building basic shadow table");
         let shadow = Arc::new(build_basic_shadow(mm, kind).await?);
         warn!(
@@ -67,11 +205,28 @@ impl SearchShadowRegistry {
             row_count = shadow.rows.len(),
             "basic shadow table loaded"
         );
+        self.cache_basic_shadow(generation, shadow.clone()).await;
+        Ok(shadow)
+    }
+
+    /// Caches a shadow whose build started at `generation`, unless a write came in
+    /// meanwhile: [`Self::apply_change`] skipped the uncached shadow, so the build may lack
+    /// it. Returns whether the shadow was cached; if not, the next search builds it again.
+    async fn cache_basic_shadow(&self, generation: u64, shadow: Arc<BasicGroupShadow>) -> bool {
+        let _guard = self.swap_lock.lock().await;
+        if self.generation.load(Ordering::SeqCst) != generation {
+            debug!(
+                kind = shadow.kind.label(),
+                "basic shadow table built during a write; not caching it"
+            );
+            return false;
+        }
         self.basic_by_kind
             .write()
             .unwrap_or_else(std::sync::PoisonError::into_inner)
-            .insert(kind, shadow.clone());
-        Ok(shadow)
+            .insert(shadow.kind, shadow);
+        self.bump_revision();
+        true
     }
 
     pub async fn get_or_build_clone_shadow(
@@ -100,6 +255,7 @@ impl SearchShadowRegistry {
         mm: &ModelManager,
         group_id: i64,
     ) -> airlab_lib::model::Result<Arc<CloneGroupShadow>> {
+        let generation = self.generation.load(Ordering::SeqCst);
         warn!(group_id, "building clone shadow table");
         let shadow = Arc::new(build_clone_group_shadow(mm, group_id).await?);
         warn!(
@@ -107,24 +263,514 @@ impl SearchShadowRegistry {
             row_count = shadow.rows.len(),
             "clone shadow table loaded"
         );
+        self.cache_clone_shadow(generation, group_id, shadow.clone())
+            .await;
+        Ok(shadow)
+    }
+
+    /// Like [`Self::cache_basic_shadow`], for the clone shadow of `group_id`.
+    async fn cache_clone_shadow(
+        &self,
+        generation: u64,
+        group_id: i64,
+        shadow: Arc<CloneGroupShadow>,
+    ) -> bool {
+        let _guard = self.swap_lock.lock().await;
+        if self.generation.load(Ordering::SeqCst) != generation {
+            debug!(
+                group_id,
+                "clone shadow table built during a write; not caching it"
+            );
+            return false;
+        }
         self.clone_by_group
             .write()
             .unwrap_or_else(std::sync::PoisonError::into_inner)
-            .insert(group_id, shadow.clone());
-        Ok(shadow)
+            .insert(group_id, shadow);
+        self.bump_revision();
+        true
     }
 
-    pub async fn rebuild_all(&self, mm: &ModelManager) -> airlab_lib::model::Result<()> {
-        for kind in BasicShadowKind::ALL {
-            self.rebuild_basic_shadow(mm, kind).await?;
+    /// Refreshes the cached rows of the changed entity and of everything showing its fields,
+    /// then schedules a full rebuild. Shadows that are not cached are left to be built on
+    /// their next search.
+    pub async fn apply_change(
+        &self,
+        mm: &ModelManager,
+        change: ShadowChange,
+    ) -> airlab_lib::model::Result<()> {
+        let guard = self.swap_lock.lock().await;
+        self.patch(mm, change).await?;
+        self.rebuild_log
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .record(change);
+        // Bumped under the lock so a build that started before this patch cannot be cached.
+        let generation = self.next_generation();
+        drop(guard);
+
+        self.spawn_rebuild(mm, generation);
+        Ok(())
+    }
+
+    /// Refreshes the cached rows `change` touches; the caller holds `swap_lock`.
+    async fn patch(
+        &self,
+        mm: &ModelManager,
+        change: ShadowChange,
+    ) -> airlab_lib::model::Result<()> {
+        let touched = self.touched(mm, change).await?;
+
+        for (target, ids) in touched {
+            if ids.is_empty() {
+                continue;
+            }
+            let id_list = ids.iter().copied().collect::<Vec<_>>();
+            match target {
+                Target::Basic(kind) => {
+                    let Some(shadow) = self.get_basic_shadow(kind) else {
+                        continue;
+                    };
+                    let rows = build_basic_rows(mm, kind, Some(&id_list)).await?;
+                    let shadow = Arc::new(shadow.replace_rows(&ids, rows));
+                    self.basic_by_kind
+                        .write()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .insert(kind, shadow);
+                }
+                Target::Clone => {
+                    let cached = self
+                        .clone_by_group
+                        .read()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .clone();
+                    if cached.is_empty() {
+                        continue;
+                    }
+                    let mut rows_by_group: HashMap<i64, Vec<_>> = HashMap::new();
+                    for row in build_clone_rows(mm, CloneRowScope::Clones(&id_list)).await? {
+                        rows_by_group.entry(row.group_id).or_default().push(row);
+                    }
+                    let mut clone_by_group = self
+                        .clone_by_group
+                        .write()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
+                    for (group_id, shadow) in cached {
+                        let rows = rows_by_group.remove(&group_id).unwrap_or_default();
+                        clone_by_group
+                            .insert(group_id, Arc::new(shadow.replace_clones(&ids, rows)));
+                    }
+                }
+            }
         }
 
-        let ctx = Ctx::root_ctx();
-        let groups: Vec<Group> = GroupBmc::list(&ctx, mm, None, None).await?;
+        if change.entity == ShadowEntity::Group {
+            let exists: bool =
+                sqlx::query_scalar(r#"SELECT EXISTS(SELECT 1 FROM public."group" WHERE id = $1)"#)
+                    .bind(change.id)
+                    .fetch_one(mm.db())
+                    .await
+                    .map_err(airlab_lib::model::Error::from)?;
+            if !exists {
+                self.clone_by_group
+                    .write()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .remove(&change.id);
+            }
+        }
+        self.bump_revision();
+        Ok(())
+    }
+
+    /// Ids of the rows to refresh for `change`, as the database has them now and as the
+    /// cached shadows had them before.
+    async fn touched(
+        &self,
+        mm: &ModelManager,
+        change: ShadowChange,
+    ) -> airlab_lib::model::Result<HashMap<Target, HashSet<i64>>> {
+        let mut touched: HashMap<Target, HashSet<i64>> = HashMap::new();
+        touched
+            .entry(change.entity.target())
+            .or_default()
+            .insert(change.id);
+        let own_row = match change.entity.target() {
+            Target::Basic(kind) => self
+                .get_basic_shadow(kind)
+                .and_then(|shadow| shadow.rows.iter().find(|row| row.id == change.id).cloned()),
+            Target::Clone => None,
+        };
+
+        for dependent in change.entity.dependents() {
+            let ids = touched.entry(dependent.target).or_default();
+            let current: Vec<i64> = sqlx::query_scalar(dependent.sql)
+                .bind(change.id)
+                .fetch_all(mm.db())
+                .await
+                .map_err(airlab_lib::model::Error::from)?;
+            ids.extend(current);
+
+            match (dependent.before, dependent.target) {
+                (Before::Unknown, _) | (Before::Referencing(_), Target::Clone) => {}
+                (Before::Referencing(key), Target::Basic(kind)) => {
+                    if let Some(shadow) = self.get_basic_shadow(kind) {
+                        ids.extend(
+                            shadow
+                                .rows
+                                .iter()
+                                .filter(|row| row.int(key) == Some(change.id))
+                                .map(|row| row.id),
+                        );
+                    }
+                }
+                (Before::Referenced(key), _) => {
+                    if let Some(id) = own_row.as_ref().and_then(|row| row.int(key)) {
+                        ids.insert(id);
+                    }
+                }
+            }
+        }
+        Ok(touched)
+    }
+
+    /// Rebuilds all shadows once writes have been quiet for a while. This catches whatever
+    /// the targeted updates of [`Self::apply_change`] do not track.
+    pub fn schedule_rebuild(&self, mm: &ModelManager) {
+        let generation = self.next_generation();
+        self.spawn_rebuild(mm, generation);
+    }
+
+    fn next_generation(&self) -> u64 {
+        self.pending_since
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get_or_insert_with(Instant::now);
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Runs the debounced rebuild for `generation`; see [`Self::rebuild_due`].
+    fn spawn_rebuild(&self, mm: &ModelManager, generation: u64) {
+        let registry = self.clone();
+        let mm = mm.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(REBUILD_DEBOUNCE).await;
+            if !registry.rebuild_due(generation) {
+                return;
+            }
+            if let Err(err) = registry.rebuild_aside(&mm).await {
+                warn!("background search shadow rebuild failed: {err}");
+            }
+        });
+    }
+
+    /// Whether the debounced rebuild for `generation` should run: no later write came in,
+    /// or the oldest pending write has waited [`MAX_REBUILD_DELAY`]. Writes stop being
+    /// pending once a rebuild is due, so only one of the waiting rebuilds runs.
+    fn rebuild_due(&self, generation: u64) -> bool {
+        let mut pending_since = self
+            .pending_since
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let due = self.generation.load(Ordering::SeqCst) == generation
+            || pending_since.is_some_and(|since| since.elapsed() >= MAX_REBUILD_DELAY);
+        if due {
+            *pending_since = None;
+        }
+        due
+    }
+
+    /// Builds every shadow aside and swaps them all in. Changes patched into the old
+    /// shadows during the build are patched into the new ones too, as the build may have
+    /// read their rows before they were written.
+    async fn rebuild_aside(&self, mm: &ModelManager) -> airlab_lib::model::Result<()> {
+        let from = self
+            .rebuild_log
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .begin();
+        let built = self.build_all(mm).await;
+
+        let _guard = self.swap_lock.lock().await;
+        let changes = self
+            .rebuild_log
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .finish(from);
+        let (version, basic_by_kind, clone_by_group) = built?;
+        *self
+            .basic_by_kind
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = basic_by_kind;
+        *self
+            .clone_by_group
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = clone_by_group;
+        for change in changes {
+            self.patch(mm, change).await?;
+        }
+        self.observe_version(version);
+        self.bump_revision();
+        debug!("search shadows rebuilt in the background");
+        Ok(())
+    }
+
+    async fn build_all(&self, mm: &ModelManager) -> airlab_lib::model::Result<BuiltShadows> {
+        let version = db_version(mm).await?;
+        let mut basic_by_kind = HashMap::new();
+        for kind in BasicShadowKind::ALL {
+            basic_by_kind.insert(kind, Arc::new(build_basic_shadow(mm, kind).await?));
+        }
+        let mut clone_by_group = HashMap::new();
+        let groups: Vec<Group> = GroupBmc::list(&Ctx::root_ctx(), mm, None, None).await?;
         for group in groups {
-            self.rebuild_clone_shadow(mm, group.id).await?;
+            clone_by_group.insert(
+                group.id,
+                Arc::new(build_clone_group_shadow(mm, group.id).await?),
+            );
         }
+        Ok((version, basic_by_kind, clone_by_group))
+    }
+}
+
+type BuiltShadows = (
+    i64,
+    HashMap<BasicShadowKind, Arc<BasicGroupShadow>>,
+    HashMap<i64, Arc<CloneGroupShadow>>,
+);
+
+/// Changes patched in since the oldest running rebuild started.
+#[derive(Debug, Default)]
+struct RebuildLog {
+    running: usize,
+    changes: Vec<ShadowChange>,
+}
+
+impl RebuildLog {
+    /// Starts logging for a rebuild; returns where its changes start.
+    fn begin(&mut self) -> usize {
+        self.running += 1;
+        self.changes.len()
+    }
+
+    fn record(&mut self, change: ShadowChange) {
+        if self.running > 0 {
+            self.changes.push(change);
+        }
+    }
+
+    /// Ends the rebuild that began at `from`, returning the changes logged since.
+    fn finish(&mut self, from: usize) -> Vec<ShadowChange> {
+        let mut changes: Vec<ShadowChange> = vec![];
+        for change in &self.changes[from..] {
+            if !changes.contains(change) {
+                changes.push(*change);
+            }
+        }
+        self.running -= 1;
+        if self.running == 0 {
+            self.changes.clear();
+        }
+        changes
+    }
+}
+
+pub(super) struct CachedShadows {
+    pub version: i64,
+    pub basic: Vec<Arc<BasicGroupShadow>>,
+    pub clones: Vec<(i64, Arc<CloneGroupShadow>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[tokio::test]
+    async fn apply_change_refreshes_dependent_rows_and_keeps_old_snapshots() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let registry = SearchShadowRegistry::new();
+        let tags = registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Tag)
+            .await?;
+        let conjugates = registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Conjugate)
+            .await?;
+
+        sqlx::query("UPDATE public.tag SET name = 'renamed-tag' WHERE id = 211")
+            .execute(mm.db())
+            .await?;
+        let generation = registry.generation.load(Ordering::SeqCst);
+        registry
+            .apply_change(
+                &mm,
+                ShadowChange {
+                    entity: ShadowEntity::Tag,
+                    id: 211,
+                },
+            )
+            .await?;
+        // Pending rebuilds started before the patch must not swap in over it.
+        assert_eq!(registry.generation.load(Ordering::SeqCst), generation + 1);
+
+        let row = |shadow: &BasicGroupShadow, id: i64| {
+            shadow.rows.iter().find(|row| row.id == id).cloned()
+        };
+        let new_tags = registry
+            .get_basic_shadow(BasicShadowKind::Tag)
+            .ok_or("tag shadow")?;
+        let new_conjugates = registry
+            .get_basic_shadow(BasicShadowKind::Conjugate)
+            .ok_or("conjugate shadow")?;
+        assert!(
+            row(&new_tags, 211)
+                .ok_or("tag 211")?
+                .fulltext
+                .contains("renamed-tag")
+        );
+        assert!(
+            row(&new_conjugates, 4291)
+                .ok_or("conjugate 4291")?
+                .fulltext
+                .contains("renamed-tag")
+        );
+        assert_eq!(new_tags.rows.len(), tags.rows.len());
+        assert_eq!(new_conjugates.rows.len(), conjugates.rows.len());
+
+        assert!(
+            row(&tags, 211)
+                .ok_or("old tag 211")?
+                .fulltext
+                .contains("primary-tag")
+        );
+        assert!(
+            !row(&conjugates, 4291)
+                .ok_or("old conjugate 4291")?
+                .fulltext
+                .contains("renamed-tag")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn apply_change_drops_deleted_rows_and_skips_unbuilt_shadows() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let registry = SearchShadowRegistry::new();
+        let panel_elements = registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::PanelElement)
+            .await?;
+        let deleted = panel_elements.rows.first().ok_or("panel element")?.id;
+
+        sqlx::query("DELETE FROM public.panel_element WHERE id = $1")
+            .bind(deleted)
+            .execute(mm.db())
+            .await?;
+        registry
+            .apply_change(
+                &mm,
+                ShadowChange {
+                    entity: ShadowEntity::PanelElement,
+                    id: deleted,
+                },
+            )
+            .await?;
+
+        let new_panel_elements = registry
+            .get_basic_shadow(BasicShadowKind::PanelElement)
+            .ok_or("panel element shadow")?;
+        assert_eq!(new_panel_elements.rows.len(), panel_elements.rows.len() - 1);
+        assert!(new_panel_elements.rows.iter().all(|row| row.id != deleted));
+        assert!(
+            registry
+                .get_basic_shadow(BasicShadowKind::Conjugate)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shadows_built_during_a_write_are_not_cached() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let registry = SearchShadowRegistry::new();
+        let generation = registry.generation.load(Ordering::SeqCst);
+        let tags = Arc::new(build_basic_shadow(&mm, BasicShadowKind::Tag).await?);
+        let clones = Arc::new(build_clone_group_shadow(&mm, 1).await?);
+
+        registry.schedule_rebuild(&mm);
+        assert!(!registry.cache_basic_shadow(generation, tags.clone()).await);
+        assert!(
+            !registry
+                .cache_clone_shadow(generation, 1, clones.clone())
+                .await
+        );
+        assert!(registry.get_basic_shadow(BasicShadowKind::Tag).is_none());
+        assert!(registry.get_clone_shadow(1).is_none());
+
+        let generation = registry.generation.load(Ordering::SeqCst);
+        assert!(registry.cache_basic_shadow(generation, tags).await);
+        assert!(registry.cache_clone_shadow(generation, 1, clones).await);
+        assert!(registry.get_basic_shadow(BasicShadowKind::Tag).is_some());
+        assert!(registry.get_clone_shadow(1).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn rebuild_runs_after_the_last_write_or_once_overdue() {
+        let registry = SearchShadowRegistry::new();
+        let first = registry.next_generation();
+        let second = registry.next_generation();
+        assert!(!registry.rebuild_due(first));
+        assert!(registry.rebuild_due(second));
+
+        let third = registry.next_generation();
+        let _fourth = registry.next_generation();
+        assert!(!registry.rebuild_due(third));
+        *registry
+            .pending_since
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) =
+            Instant::now().checked_sub(MAX_REBUILD_DELAY);
+        assert!(registry.rebuild_due(third));
+        // The overdue rebuild covers the writes before it; the others keep waiting.
+        assert!(!registry.rebuild_due(third));
+    }
+
+    #[test]
+    fn rebuild_log_keeps_the_changes_made_during_each_rebuild() {
+        let change = |id| ShadowChange {
+            entity: ShadowEntity::Tag,
+            id,
+        };
+        let mut log = RebuildLog::default();
+        log.record(change(1));
+        let outer = log.begin();
+        log.record(change(2));
+        let inner = log.begin();
+        log.record(change(3));
+        log.record(change(2));
+
+        assert_eq!(log.finish(inner), vec![change(3), change(2)]);
+        assert_eq!(log.finish(outer), vec![change(2), change(3)]);
+        assert!(log.changes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn warm_up_builds_missing_shadows_once() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let registry = SearchShadowRegistry::new();
+        registry.warm_up(&mm).await?;
+
+        assert!(
+            BasicShadowKind::ALL
+                .iter()
+                .all(|kind| registry.get_basic_shadow(*kind).is_some())
+        );
+        assert!(registry.get_clone_shadow(1).is_some());
+        assert!(!registry.is_behind(&mm).await?);
 
+        let revision = registry.revision();
+        registry.warm_up(&mm).await?;
+        assert_eq!(registry.revision(), revision);
         Ok(())
     }
 }
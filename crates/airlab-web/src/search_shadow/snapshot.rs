@@ -0,0 +1,288 @@
+//! Shadows saved under `DATA_PATH`, so a restarted instance answers its first searches
+//! without building them. A snapshot records the database version it includes; one that
+//! is behind still serves searches while the listener catches up (see
+//! [`super::notify`]), and one that is ahead of the database, such as after a restore, is
+//! ignored.
+
+use super::SearchState;
+use super::basic::{BasicGroupShadow, BasicShadowKind, BasicShadowRow};
+use super::clone::{CloneGroupShadow, CloneTableShadowRow};
+use super::notify::db_version;
+use super::registry::{CachedShadows, SearchShadowRegistry};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Bumped whenever the shadow rows change shape; snapshots of another format are ignored.
+const FORMAT: u32 = 4;
+
+/// How often changed shadows are written out.
+const WRITE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Numbers the partial files of this process.
+static PARTIAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    format: u32,
+    version: i64,
+    basic: Vec<(BasicShadowKind, &'a [BasicShadowRow])>,
+    clones: Vec<(i64, &'a [CloneTableShadowRow])>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    format: u32,
+    version: i64,
+    basic: Vec<(BasicShadowKind, Vec<BasicShadowRow>)>,
+    clones: Vec<(i64, Vec<CloneTableShadowRow>)>,
+}
+
+pub fn path(data_path: &str) -> Utf8PathBuf {
+    Utf8Path::new(data_path).join(format!("search_shadow.v{FORMAT}.json"))
+}
+
+/// Writes all cached shadows to `path`, replacing the previous snapshot only once the new
+/// one is complete.
+pub async fn write(registry: &SearchShadowRegistry, path: &Utf8Path) -> std::io::Result<()> {
+    let cached = registry.cached();
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let snapshot = SnapshotRef {
+            format: FORMAT,
+            version: cached.version,
+            basic: cached
+                .basic
+                .iter()
+                .map(|shadow| (shadow.kind, shadow.rows.as_slice()))
+                .collect(),
+            clones: cached
+                .clones
+                .iter()
+                .map(|(group_id, shadow)| (*group_id, shadow.rows.as_slice()))
+                .collect(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Named per process and write, so instances sharing the data path never write
+        // into each other's file before the rename.
+        let partial = path.with_extension(format!(
+            "json.{}-{}.partial",
+            std::process::id(),
+            PARTIAL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written =
+            write_partial(&partial, &snapshot).and_then(|()| std::fs::rename(&partial, &path));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        written
+    })
+    .await?
+}
+
+fn write_partial(partial: &Utf8Path, snapshot: &SnapshotRef<'_>) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(partial)?);
+    serde_json::to_writer(&mut file, snapshot)?;
+    file.flush()
+}
+
+/// Loads the snapshot at `path` into the registry. Returns whether one was loaded; a
+/// missing, unreadable or outdated snapshot leaves the registry as it is.
+pub async fn restore(state: &SearchState, path: &Utf8Path) -> bool {
+    let owned = path.to_owned();
+    let read = tokio::task::spawn_blocking(move || -> std::io::Result<Snapshot> {
+        let file = std::io::BufReader::new(std::fs::File::open(&owned)?);
+        Ok(serde_json::from_reader(file)?)
+    })
+    .await;
+    let snapshot = match read {
+        Ok(Ok(snapshot)) => snapshot,
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => return false,
+        Ok(Err(err)) => {
+            warn!("ignoring search shadow snapshot {path}: {err}");
+            return false;
+        }
+        Err(err) => {
+            warn!("ignoring search shadow snapshot {path}: {err}");
+            return false;
+        }
+    };
+    if snapshot.format != FORMAT {
+        debug!(
+            "ignoring search shadow snapshot of format {}",
+            snapshot.format
+        );
+        return false;
+    }
+    let current = match db_version(&state.mm).await {
+        Ok(current) => current,
+        Err(err) => {
+            warn!("ignoring search shadow snapshot {path}: {err}");
+            return false;
+        }
+    };
+    if snapshot.version > current {
+        warn!(
+            "ignoring search shadow snapshot {path} of version {}, ahead of the database at {current}",
+            snapshot.version
+        );
+        return false;
+    }
+
+    info!(
+        "restoring search shadows of version {} from {path}; the database is at {current}",
+        snapshot.version
+    );
+    state
+        .registry
+        .restore(CachedShadows {
+            version: snapshot.version,
+            basic: snapshot
+                .basic
+                .into_iter()
+                .map(|(kind, rows)| Arc::new(BasicGroupShadow::new(kind, rows)))
+                .collect(),
+            clones: snapshot
+                .clones
+                .into_iter()
+                .map(|(group_id, rows)| (group_id, Arc::new(CloneGroupShadow::new(rows))))
+                .collect(),
+        })
+        .await;
+    true
+}
+
+/// Writes a snapshot whenever the shadows changed since the last one.
+pub fn spawn_writer(state: &SearchState, path: Utf8PathBuf) -> JoinHandle<()> {
+    let registry = state.registry.clone();
+    tokio::spawn(async move {
+        let mut written = registry.revision();
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + WRITE_INTERVAL, WRITE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let revision = registry.revision();
+            if revision == written {
+                continue;
+            }
+            match write(&registry, &path).await {
+                Ok(()) => written = revision,
+                Err(err) => warn!("could not write search shadow snapshot {path}: {err}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn temp_path() -> TestResult<Utf8PathBuf> {
+        let dir = std::env::temp_dir().join(format!("airlab-shadow-{}", uuid::Uuid::new_v4()));
+        let dir = Utf8PathBuf::from_path_buf(dir).map_err(|_| "temp dir is not UTF-8")?;
+        Ok(path(dir.as_str()))
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trips_shadows_and_version() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let written = SearchState::new((*mm).clone());
+        let tags = written
+            .registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Tag)
+            .await?;
+        let clones = written.registry.get_or_build_clone_shadow(&mm, 1).await?;
+        written.registry.observe_version(db_version(&mm).await?);
+        let path = temp_path()?;
+        write(&written.registry, &path).await?;
+
+        let restored = SearchState::new((*mm).clone());
+        assert!(restore(&restored, &path).await);
+        assert_eq!(restored.registry.version(), written.registry.version());
+        assert!(!restored.registry.is_behind(&mm).await?);
+        let restored_tags = restored
+            .registry
+            .get_basic_shadow(BasicShadowKind::Tag)
+            .ok_or("tag shadow")?;
+        assert_eq!(restored_tags.rows.len(), tags.rows.len());
+        assert_eq!(
+            restored
+                .registry
+                .get_clone_shadow(1)
+                .ok_or("clone shadow")?
+                .rows
+                .len(),
+            clones.rows.len()
+        );
+
+        sqlx::query("UPDATE public.tag SET name = 'after-snapshot' WHERE id = 211")
+            .execute(mm.db())
+            .await?;
+        let behind = SearchState::new((*mm).clone());
+        assert!(restore(&behind, &path).await);
+        assert!(behind.registry.is_behind(&mm).await?);
+
+        if let Some(dir) = path.parent() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_writes_use_their_own_partial_files() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let state = SearchState::new((*mm).clone());
+        state
+            .registry
+            .get_or_build_basic_shadow(&mm, BasicShadowKind::Tag)
+            .await?;
+        let path = temp_path()?;
+
+        tokio::try_join!(write(&state.registry, &path), write(&state.registry, &path))?;
+        assert!(restore(&SearchState::new((*mm).clone()), &path).await);
+        let dir = path.parent().ok_or("snapshot dir")?;
+        let files = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(files, vec![path.file_name().unwrap_or_default()]);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_ignores_missing_and_foreign_snapshots() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let state = SearchState::new((*mm).clone());
+        let path = temp_path()?;
+        assert!(!restore(&state, &path).await);
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, r#"{"format":0,"version":1,"basic":[],"clones":[]}"#)?;
+        assert!(!restore(&state, &path).await);
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"format":{FORMAT},"version":9223372036854775807,"basic":[],"clones":[]}}"#
+            ),
+        )?;
+        assert!(!restore(&state, &path).await);
+        assert_eq!(state.registry.version(), 0);
+
+        if let Some(dir) = path.parent() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
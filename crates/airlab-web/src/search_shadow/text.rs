@@ -0,0 +1,428 @@
+//! Inverted index over the text fields of shadow rows, for the global search box. Matches
+//! words exactly, by prefix or with a typo or two, and ranks rows with BM25, weighting
+//! each field by its boost.
+//!
+//! Words are runs of letters and digits. Neighbouring words and the words of a
+//! whitespace-delimited token are also indexed joined, so `CD45 RA` is found as `cd45ra`
+//! and `seed-collection-b` as `seedcollectionb`. A query token such as `Ki-67` is matched
+//! joined too, and the whole query is tried joined as well, so `CD45 RA` finds `CD45RA`.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Score factors of words that only start with, or are close to, a query word.
+const PREFIX_WEIGHT: f64 = 0.7;
+const FUZZY_WEIGHT: f64 = 0.5;
+
+/// Score of rows that contain the query only as a substring, like `45` in `CD45`.
+const SUBSTRING_SCORE: f64 = 0.01;
+
+/// The boost of primary names, such as a protein name.
+pub const NAME_BOOST: f64 = 3.0;
+/// The boost of identifiers, such as a lot number.
+pub const IDENTIFIER_BOOST: f64 = 1.5;
+pub const DEFAULT_BOOST: f64 = 1.0;
+/// The boost of free text, such as a description.
+pub const NOTE_BOOST: f64 = 0.5;
+
+/// Characters of field text a snippet shows at most, and before its first match.
+const SNIPPET_LENGTH: usize = 80;
+const SNIPPET_LEAD: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+struct Posting {
+    doc: u32,
+    boost: f32,
+    count: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct TextIndex {
+    terms: BTreeMap<String, Vec<Posting>>,
+    doc_lengths: Vec<u32>,
+    average_length: f64,
+}
+
+impl TextIndex {
+    /// Indexes one document per item, each a list of field texts with their boosts.
+    /// Documents are numbered in order.
+    pub fn build<I, F, S>(docs: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let mut terms: BTreeMap<String, Vec<Posting>> = BTreeMap::new();
+        let mut doc_lengths = vec![];
+        for (doc, fields) in docs.into_iter().enumerate() {
+            let doc = u32::try_from(doc).unwrap_or(u32::MAX);
+            let mut length = 0;
+            for (text, boost) in fields {
+                let text = text.as_ref();
+                length += words(text).len();
+                let mut counts: HashMap<String, u32> = HashMap::new();
+                for term in terms_of(text) {
+                    *counts.entry(term).or_default() += 1;
+                }
+                for (term, count) in counts {
+                    terms.entry(term).or_default().push(Posting {
+                        doc,
+                        boost: boost as f32,
+                        count,
+                    });
+                }
+            }
+            doc_lengths.push(u32::try_from(length).unwrap_or(u32::MAX));
+        }
+        let average_length = if doc_lengths.is_empty() {
+            0.0
+        } else {
+            doc_lengths
+                .iter()
+                .map(|length| f64::from(*length))
+                .sum::<f64>()
+                / doc_lengths.len() as f64
+        };
+        Self {
+            terms,
+            doc_lengths,
+            average_length,
+        }
+    }
+
+    /// Scores of the documents matching every token of `query`, or `query` with its words
+    /// joined. Documents whose `fulltext` merely contains the query still match, with the
+    /// lowest score.
+    pub fn search<'a>(
+        &self,
+        query: &str,
+        fulltext: impl Fn(usize) -> &'a str,
+    ) -> HashMap<usize, f64> {
+        let query_tokens = tokens(query);
+        let mut scores: Option<HashMap<usize, f64>> = None;
+        for token in &query_tokens {
+            let word_scores = self.word_scores(token);
+            scores = Some(match scores {
+                None => word_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(doc, score)| {
+                        word_scores
+                            .get(&doc)
+                            .map(|word_score| (doc, score + word_score))
+                    })
+                    .collect(),
+            });
+        }
+        let mut scores = scores.unwrap_or_default();
+
+        if query_tokens.len() > 1 {
+            for (doc, score) in self.word_scores(&query_tokens.concat()) {
+                let best = scores.entry(doc).or_default();
+                *best = best.max(score);
+            }
+        }
+
+        let needle = query.trim().to_lowercase();
+        if !needle.is_empty() {
+            for doc in 0..self.doc_lengths.len() {
+                if !scores.contains_key(&doc) && fulltext(doc).contains(&needle) {
+                    scores.insert(doc, SUBSTRING_SCORE);
+                }
+            }
+        }
+        scores
+    }
+
+    /// BM25 scores for one query word, using the best matching indexed term of each
+    /// document.
+    fn word_scores(&self, word: &str) -> HashMap<usize, f64> {
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for (term, postings) in self
+            .terms
+            .range::<str, _>((Bound::Included(word), Bound::Unbounded))
+        {
+            if !term.starts_with(word) {
+                break;
+            }
+            let weight = if term == word { 1.0 } else { PREFIX_WEIGHT };
+            self.add_term(&mut scores, postings, weight);
+        }
+        let max_distance = max_typos(word);
+        if max_distance > 0 {
+            let chars = word.chars().collect::<Vec<_>>();
+            for (term, postings) in &self.terms {
+                if term.starts_with(word) {
+                    continue;
+                }
+                let term_chars = term.chars().collect::<Vec<_>>();
+                if term_chars.len().abs_diff(chars.len()) > max_distance {
+                    continue;
+                }
+                if edit_distance(&chars, &term_chars) <= max_distance {
+                    self.add_term(&mut scores, postings, FUZZY_WEIGHT);
+                }
+            }
+        }
+        scores
+    }
+
+    fn add_term(&self, scores: &mut HashMap<usize, f64>, postings: &[Posting], weight: f64) {
+        let docs = self.doc_lengths.len() as f64;
+        let mut doc_count = postings
+            .iter()
+            .map(|posting| posting.doc)
+            .collect::<Vec<_>>();
+        doc_count.dedup();
+        let frequency = doc_count.len() as f64;
+        let idf = (1.0 + (docs - frequency + 0.5) / (frequency + 0.5)).ln();
+
+        let mut term_scores: HashMap<usize, f64> = HashMap::new();
+        for posting in postings {
+            let doc = posting.doc as usize;
+            let length = self.doc_lengths.get(doc).copied().unwrap_or_default();
+            let count = f64::from(posting.count);
+            let norm = if self.average_length > 0.0 {
+                1.0 - B + B * f64::from(length) / self.average_length
+            } else {
+                1.0
+            };
+            *term_scores.entry(doc).or_default() +=
+                f64::from(posting.boost) * idf * count * (K1 + 1.0) / (count + K1 * norm);
+        }
+        for (doc, score) in term_scores {
+            let best = scores.entry(doc).or_default();
+            *best = best.max(weight * score);
+        }
+    }
+}
+
+/// Part of a field showing where the query matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Character ranges of `text`, end exclusive, holding query words.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// The part of the first field, by boost, that contains a word of `query`, with each such
+/// word highlighted. `None` when no field contains one, as when only a typo matched.
+pub fn snippet<'a>(
+    fields: impl IntoIterator<Item = (&'a str, f64)>,
+    query: &str,
+) -> Option<Snippet> {
+    let query_words = words(query)
+        .into_iter()
+        .map(|word| word.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut fields = fields.into_iter().collect::<Vec<_>>();
+    fields.sort_by(|left, right| right.1.total_cmp(&left.1));
+
+    fields.into_iter().find_map(|(text, _)| {
+        let chars = text.chars().collect::<Vec<_>>();
+        let lower = chars
+            .iter()
+            .map(|ch| ch.to_lowercase().next().unwrap_or(*ch))
+            .collect::<Vec<_>>();
+        let mut ranges = query_words
+            .iter()
+            .filter(|word| !word.is_empty() && word.len() <= lower.len())
+            .flat_map(|word| {
+                (0..=lower.len() - word.len())
+                    .filter(|start| lower[*start..].starts_with(word))
+                    .map(|start| (start, start + word.len()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = vec![];
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        let first = merged.first()?.0;
+
+        let start = first
+            .saturating_sub(SNIPPET_LEAD)
+            .min(chars.len().saturating_sub(SNIPPET_LENGTH));
+        let end = (start + SNIPPET_LENGTH).min(chars.len());
+        let prefix = usize::from(start > 0);
+        let mut text = String::new();
+        if start > 0 {
+            text.push('…');
+        }
+        text.extend(&chars[start..end]);
+        if end < chars.len() {
+            text.push('…');
+        }
+        let highlights = merged
+            .into_iter()
+            .filter(|(from, _)| *from < end)
+            .map(|(from, to)| (from - start + prefix, to.min(end) - start + prefix))
+            .collect();
+        Some(Snippet { text, highlights })
+    })
+}
+
+/// Lowercase runs of letters and digits.
+fn words(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The words of each whitespace-delimited token, joined.
+fn tokens(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|token| words(token).concat())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+/// The words, each pair of neighbouring words joined and each token made of several words.
+fn terms_of(text: &str) -> Vec<String> {
+    let words = words(text);
+    let mut terms = words.clone();
+    terms.extend(words.windows(2).map(|pair| pair.concat()));
+    for token in tokens(text) {
+        if !terms.contains(&token) {
+            terms.push(token);
+        }
+    }
+    terms
+}
+
+/// Short words must match exactly; longer ones may have one or two typos.
+fn max_typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Levenshtein distance counting a swap of neighbouring characters as one edit.
+fn edit_distance(left: &[char], right: &[char]) -> usize {
+    let width = right.len() + 1;
+    let mut rows = vec![vec![0; width]; left.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=left.len() {
+        for j in 1..=right.len() {
+            let cost = usize::from(left[i - 1] != right[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && left[i - 1] == right[j - 2] && left[i - 2] == right[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[left.len()][right.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_highlight_query_words_in_the_best_field() {
+        let fields = [
+            ("used for CD8 T cells, see also cd8a", NOTE_BOOST),
+            ("Anti-CD8", NAME_BOOST),
+        ];
+        assert_eq!(
+            snippet(fields, "cd8"),
+            Some(Snippet {
+                text: "Anti-CD8".to_string(),
+                highlights: vec![(5, 8)],
+            })
+        );
+        assert_eq!(
+            snippet([fields[0]], "cd8 cells").map(|snippet| snippet.highlights),
+            Some(vec![(9, 12), (15, 20), (31, 34)])
+        );
+
+        let long = format!("{} CD8 {}", "a".repeat(60), "b".repeat(60));
+        let Some(long) = snippet([(long.as_str(), DEFAULT_BOOST)], "cd8") else {
+            panic!("expected a snippet");
+        };
+        assert!(long.text.starts_with('…') && long.text.ends_with('…'));
+        let highlighted = long
+            .text
+            .chars()
+            .skip(long.highlights[0].0)
+            .take(long.highlights[0].1 - long.highlights[0].0)
+            .collect::<String>();
+        assert_eq!(highlighted, "CD8");
+
+        assert_eq!(snippet([("CD45", NAME_BOOST)], "cd8"), None);
+    }
+
+    fn index(docs: &[&[(&str, f64)]]) -> TextIndex {
+        TextIndex::build(docs.iter().map(|fields| fields.iter().copied()))
+    }
+
+    fn ranked(index: &TextIndex, fulltexts: &[&str], query: &str) -> Vec<usize> {
+        let scores = index.search(query, |doc| fulltexts[doc]);
+        let mut docs = scores.into_iter().collect::<Vec<_>>();
+        docs.sort_by(|left, right| right.1.total_cmp(&left.1).then(left.0.cmp(&right.0)));
+        docs.into_iter().map(|(doc, _)| doc).collect()
+    }
+
+    #[test]
+    fn search_matches_split_joined_and_misspelled_words() {
+        let docs: &[&[(&str, f64)]] = &[
+            &[("CD45RA", NAME_BOOST)],
+            &[("CD45 RA", NAME_BOOST)],
+            &[("Ki-67", NAME_BOOST)],
+            &[("Vimentin", NAME_BOOST)],
+        ];
+        let fulltexts = ["cd45ra", "cd45 ra", "ki-67", "vimentin"];
+        let index = index(docs);
+
+        assert_eq!(ranked(&index, &fulltexts, "CD45RA"), vec![0, 1]);
+        assert_eq!(ranked(&index, &fulltexts, "cd45 ra"), vec![1, 0]);
+        assert_eq!(ranked(&index, &fulltexts, "Ki67"), vec![2]);
+        assert_eq!(ranked(&index, &fulltexts, "ki-67"), vec![2]);
+        assert_eq!(ranked(&index, &fulltexts, "ki 67"), vec![2]);
+        assert_eq!(ranked(&index, &fulltexts, "vimentni"), vec![3]);
+        assert_eq!(ranked(&index, &fulltexts, "vim"), vec![3]);
+        assert_eq!(ranked(&index, &fulltexts, "45"), vec![0, 1]);
+        assert!(ranked(&index, &fulltexts, "cd8").is_empty());
+    }
+
+    #[test]
+    fn search_ranks_boosted_fields_first() {
+        let docs: &[&[(&str, f64)]] = &[
+            &[("Vimentin", NAME_BOOST), ("stains CD3 cells", NOTE_BOOST)],
+            &[("CD3", NAME_BOOST), ("T cells", NOTE_BOOST)],
+            &[("CD4", NAME_BOOST), ("not CD3", NOTE_BOOST)],
+        ];
+        let fulltexts = ["", "", ""];
+        let index = index(docs);
+
+        assert_eq!(ranked(&index, &fulltexts, "cd3")[0], 1);
+        assert_eq!(ranked(&index, &fulltexts, "cd3").len(), 3);
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        let chars = |value: &str| value.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("vimentin"), &chars("vimentni")), 1);
+        assert_eq!(edit_distance(&chars("cd45"), &chars("cd54")), 1);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+    }
+}
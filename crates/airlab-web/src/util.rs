@@ -315,6 +315,7 @@ async fn create_validation(ctx: &Ctx, mm: &ModelManager, entity: &Entity) -> Res
         is_archived: None,
         created_at: None,
         updated_at: None,
+        protocol_template_id: None,
     };
     let id = ValidationBmc::create(ctx, mm, fc).await?;
 
@@ -0,0 +1,365 @@
+use crate::web::pdf::{PdfBuilder, PdfImage};
+use airlab_lib::b64::b64_encode;
+use airlab_lib::model::clone_application::{Application, ApplicationStatus};
+use airlab_lib::model::clone_dossier::{CloneDossier, DossierValidation};
+use airlab_lib::model::validation::Validation;
+use airlab_lib::model::validation_file::ValidationFile;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Contents of the validation files that could be read, by file id.
+pub type FileContents = HashMap<i64, Vec<u8>>;
+
+fn yes_no(value: Option<bool>) -> Option<String> {
+    value.map(|value| if value { "yes" } else { "no" }.to_string())
+}
+
+fn with_detail(flag: Option<bool>, detail: Option<&str>) -> Option<String> {
+    match (
+        yes_no(flag),
+        detail.filter(|detail| !detail.trim().is_empty()),
+    ) {
+        (Some(flag), Some(detail)) => Some(format!("{flag} ({detail})")),
+        (flag, detail) => flag.or_else(|| detail.map(str::to_string)),
+    }
+}
+
+fn title(dossier: &CloneDossier) -> String {
+    format!(
+        "Validation report: {} / {}",
+        dossier.protein.name, dossier.clone.name
+    )
+}
+
+fn clone_fields(dossier: &CloneDossier) -> Vec<(&'static str, String)> {
+    let clone = &dossier.clone;
+    let applications = clone
+        .applications()
+        .iter()
+        .map(|entry| format!("{}: {}", entry.application.label(), entry.status.label()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let fields = [
+        ("Protein", Some(dossier.protein.name.clone())),
+        ("Clone", Some(clone.name.clone())),
+        ("Host", dossier.host_species_name.clone()),
+        ("Isotype", clone.isotype.clone()),
+        ("Epitope", clone.epitope.clone()),
+        ("Phospho", yes_no(Some(clone.is_phospho))),
+        ("Polyclonal", yes_no(Some(clone.is_polyclonal))),
+        ("Reactivity", Some(dossier.reactivity_names.join(", "))),
+        ("Applications", Some(applications)),
+        (
+            "Report scope",
+            Some(
+                dossier
+                    .application
+                    .map_or("all applications", Application::label)
+                    .to_string(),
+            ),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value.filter(|value| !value.is_empty())?)))
+        .collect()
+}
+
+fn validation_title(validation: &Validation) -> String {
+    let application =
+        Application::from_id(validation.application).map_or("Unknown application", |a| a.label());
+    let status = ApplicationStatus::from_id(validation.status).map_or("Unknown", |s| s.label());
+    format!("Validation {} - {application}: {status}", validation.id)
+}
+
+/// Protocol and outcome fields of a validation that are filled in, in report order.
+fn validation_fields(
+    item: &DossierValidation,
+    dossier: &CloneDossier,
+) -> Vec<(&'static str, String)> {
+    let validation = &item.validation;
+    let lot = validation.lot_id.and_then(|lot_id| {
+        dossier
+            .lots
+            .iter()
+            .find(|lot| lot.id == lot_id)
+            .map(|lot| lot.name.clone())
+    });
+    let conjugate = validation.conjugate_id.and_then(|conjugate_id| {
+        dossier
+            .conjugates
+            .iter()
+            .find(|item| item.conjugate.id == conjugate_id)
+            .map(|item| format!("#{} ({})", item.conjugate.tube_number, item.tag_name))
+    });
+    let concentration = validation
+        .concentration_quantity()
+        .map(|quantity| quantity.to_string())
+        .or_else(|| validation.concentration.clone());
+    let antigen_retrieval = [
+        validation.antigen_retrieval_type.as_deref(),
+        validation.antigen_retrieval_time.as_deref(),
+        validation.antigen_retrieval_temperature.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|part| !part.trim().is_empty())
+    .collect::<Vec<_>>()
+    .join(", ");
+    let fixation = match (validation.fixation, validation.fixation_notes.as_deref()) {
+        (Some(fixation), Some(notes)) => Some(format!("{fixation} ({notes})")),
+        (Some(fixation), None) => Some(fixation.to_string()),
+        (None, notes) => notes.map(str::to_string),
+    };
+
+    let fields = [
+        ("Species", item.species_name.clone()),
+        ("Lot", lot),
+        ("Conjugate", conjugate),
+        ("Tissue", validation.tissue.clone()),
+        ("Positive control", validation.positive_control.clone()),
+        ("Negative control", validation.negative_control.clone()),
+        ("Concentration", concentration),
+        ("Incubation", validation.incubation_conditions.clone()),
+        ("Fixation", fixation),
+        ("Antigen retrieval", Some(antigen_retrieval)),
+        (
+            "Saponin",
+            with_detail(
+                validation.saponin,
+                validation.saponin_concentration.as_deref(),
+            ),
+        ),
+        (
+            "Methanol treatment",
+            with_detail(
+                validation.methanol_treatment,
+                validation.methanol_treatment_concentration.as_deref(),
+            ),
+        ),
+        (
+            "Surface staining",
+            with_detail(
+                validation.surface_staining,
+                validation.surface_staining_concentration.as_deref(),
+            ),
+        ),
+        ("Notes", validation.notes.clone()),
+        ("Review", Some(validation.review_state.clone())),
+        (
+            "Last updated",
+            Some(validation.updated_at.format("%Y-%m-%d").to_string()),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value.filter(|value| !value.trim().is_empty())?)))
+        .collect()
+}
+
+fn file_label(file: &ValidationFile) -> String {
+    let name = file
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{}.{}", file.hash, file.extension));
+    match file.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(description) => format!("{name}: {description}"),
+        None => name,
+    }
+}
+
+fn image_mime(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+pub fn is_image(file: &ValidationFile) -> bool {
+    image_mime(&file.extension).is_some()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the dossier as a single HTML page with the images inlined as data URIs.
+pub fn render_html(dossier: &CloneDossier, files: &FileContents) -> String {
+    let title = escape_html(&title(dossier));
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>body{{font-family:sans-serif;margin:2em;max-width:60em}}\
+         table{{border-collapse:collapse;width:100%;margin-bottom:1em}}\
+         td,th{{border:1px solid #999;padding:4px 8px;text-align:left;vertical-align:top}}\
+         th{{width:12em}}figure{{margin:0 0 1em}}img{{max-width:100%}}\
+         section{{page-break-inside:avoid}}</style></head><body><h1>{title}</h1><table>"
+    );
+    for (label, value) in clone_fields(dossier) {
+        let _ = write!(
+            html,
+            "<tr><th>{label}</th><td>{}</td></tr>",
+            escape_html(&value)
+        );
+    }
+    html.push_str("</table>");
+
+    html.push_str("<h2>Lots</h2><table><tr><th>Lot</th><th>Number</th><th>Reference</th><th>Received</th></tr>");
+    for lot in &dossier.lots {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&lot.name),
+            escape_html(lot.number.as_deref().unwrap_or("-")),
+            escape_html(lot.reference.as_deref().unwrap_or("-")),
+            lot.received_at
+                .map_or_else(|| "-".to_string(), |at| at.format("%Y-%m-%d").to_string())
+        );
+    }
+    html.push_str("</table>");
+
+    html.push_str("<h2>Conjugates</h2><table><tr><th>Tube</th><th>Tag</th><th>Lot</th><th>Concentration</th></tr>");
+    for item in &dossier.conjugates {
+        let lot = dossier
+            .lots
+            .iter()
+            .find(|lot| lot.id == item.conjugate.lot_id)
+            .map_or("-", |lot| lot.name.as_str());
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            item.conjugate.tube_number,
+            escape_html(&item.tag_name),
+            escape_html(lot),
+            item.conjugate
+                .concentration
+                .map_or_else(|| "-".to_string(), |c| format!("{c:.1} µg/mL"))
+        );
+    }
+    html.push_str("</table>");
+
+    html.push_str("<h2>Validations</h2>");
+    if dossier.validations.is_empty() {
+        html.push_str("<p>No validations recorded.</p>");
+    }
+    for item in &dossier.validations {
+        let _ = write!(
+            html,
+            "<section><h3>{}</h3><table>",
+            escape_html(&validation_title(&item.validation))
+        );
+        for (label, value) in validation_fields(item, dossier) {
+            let _ = write!(
+                html,
+                "<tr><th>{label}</th><td>{}</td></tr>",
+                escape_html(&value)
+            );
+        }
+        html.push_str("</table>");
+        for file in &item.files {
+            let label = escape_html(&file_label(file));
+            match (image_mime(&file.extension), files.get(&file.id)) {
+                (Some(mime), Some(bytes)) => {
+                    let _ = write!(
+                        html,
+                        "<figure><img src=\"data:{mime};base64,{}\" alt=\"{label}\"><figcaption>{label}</figcaption></figure>",
+                        b64_encode(bytes)
+                    );
+                }
+                _ => {
+                    let _ = write!(html, "<p>Attachment: {label}</p>");
+                }
+            }
+        }
+        html.push_str("</section>");
+    }
+
+    let _ = write!(
+        html,
+        "<footer><p>Generated {}</p></footer></body></html>",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+    html
+}
+
+/// Renders the dossier as PDF. JPEG and PNG images are embedded; other attachments are
+/// listed by name.
+pub fn render_pdf(dossier: &CloneDossier, files: &FileContents) -> Vec<u8> {
+    let mut pdf = PdfBuilder::new();
+    pdf.heading(&title(dossier));
+    for (label, value) in clone_fields(dossier) {
+        pdf.field(label, &value);
+    }
+
+    pdf.subheading("Lots");
+    if dossier.lots.is_empty() {
+        pdf.paragraph("No lots recorded.");
+    }
+    for lot in &dossier.lots {
+        let received = lot
+            .received_at
+            .map(|at| format!(", received {}", at.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        pdf.paragraph(&format!(
+            "{} (number {}, reference {}{received})",
+            lot.name,
+            lot.number.as_deref().unwrap_or("-"),
+            lot.reference.as_deref().unwrap_or("-")
+        ));
+    }
+
+    pdf.subheading("Conjugates");
+    if dossier.conjugates.is_empty() {
+        pdf.paragraph("No conjugates recorded.");
+    }
+    for item in &dossier.conjugates {
+        let concentration = item
+            .conjugate
+            .concentration
+            .map(|c| format!(", {c:.1} µg/mL"))
+            .unwrap_or_default();
+        pdf.paragraph(&format!(
+            "Tube #{}: {}{concentration}",
+            item.conjugate.tube_number, item.tag_name
+        ));
+    }
+
+    pdf.subheading("Validations");
+    if dossier.validations.is_empty() {
+        pdf.paragraph("No validations recorded.");
+    }
+    for item in &dossier.validations {
+        pdf.subheading(&validation_title(&item.validation));
+        for (label, value) in validation_fields(item, dossier) {
+            pdf.field(label, &value);
+        }
+        for file in &item.files {
+            let image = files
+                .get(&file.id)
+                .and_then(|bytes| PdfImage::from_bytes(bytes, &file.extension));
+            match image {
+                Some(image) => {
+                    pdf.space(4.0);
+                    pdf.image(image);
+                    pdf.paragraph(&file_label(file));
+                }
+                None => pdf.paragraph(&format!("Attachment: {}", file_label(file))),
+            }
+        }
+    }
+
+    pdf.space(8.0);
+    pdf.paragraph(&format!(
+        "Generated {}",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+    ));
+    pdf.finish()
+}
@@ -26,6 +26,19 @@ pub enum Error {
     },
     BadRequest(String),
     UnsupportedQueryValue(String),
+    AdminRequired,
+    UploadTooLarge {
+        file_name: String,
+        limit: u64,
+    },
+    GroupQuotaExceeded {
+        group_id: i64,
+        quota: u64,
+    },
+    UnsupportedMediaType {
+        file_name: String,
+        reason: String,
+    },
     #[from]
     CtxExt(web::mw_auth::CtxExtError),
     #[from]
@@ -38,6 +51,11 @@ pub enum Error {
     Token(token::Error),
     #[from]
     Io(#[serde_as(as = "DisplayFromStr")] std::io::Error),
+    #[from]
+    BlobStore(#[serde_as(as = "DisplayFromStr")] crate::blob_store::Error),
+
+    #[from]
+    SearchQuery(crate::search_shadow::query::ParseError),
 
     #[from]
     SerdeJson(#[serde_as(as = "DisplayFromStr")] serde_json::Error),
@@ -61,10 +79,11 @@ impl core::fmt::Display for Error {
 impl std::error::Error for Error {}
 
 impl Error {
-    pub const fn client_status_and_error(&self) -> (StatusCode, ClientError) {
+    pub fn client_status_and_error(&self) -> (StatusCode, ClientError) {
         use web::Error::{
-            BadRequest, CtxExt, LoginFailPwdNotMatching, LoginFailUserHasNoPwd,
-            LoginFailUsernameNotFound, Model,
+            AdminRequired, BadRequest, CtxExt, GroupQuotaExceeded, LoginFailPwdNotMatching,
+            LoginFailUserHasNoPwd, LoginFailUsernameNotFound, Model, SearchQuery,
+            UnsupportedMediaType, UploadTooLarge,
         };
 
         #[allow(unreachable_patterns)]
@@ -73,13 +92,58 @@ impl Error {
             | LoginFailUserHasNoPwd { .. }
             | LoginFailPwdNotMatching { .. } => (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL),
 
-            CtxExt(_) => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
+            CtxExt(_) | AdminRequired => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
 
             Model(model::Error::EntityNotFound { entity, id }) => (
                 StatusCode::BAD_REQUEST,
                 ClientError::ENTITY_NOT_FOUND { entity, id: *id },
             ),
 
+            Model(model::Error::ValidationLocked { id }) => (
+                StatusCode::CONFLICT,
+                ClientError::VALIDATION_LOCKED { id: *id },
+            ),
+            Model(model::Error::ReviewTransitionNotAllowed { from, action, .. }) => (
+                StatusCode::CONFLICT,
+                ClientError::REVIEW_TRANSITION_NOT_ALLOWED { from, action },
+            ),
+            Model(model::Error::ReviewNotPermitted { reason, .. }) => (
+                StatusCode::FORBIDDEN,
+                ClientError::REVIEW_NOT_PERMITTED { reason },
+            ),
+            Model(model::Error::ReviewCommentRequired { .. }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::REVIEW_COMMENT_REQUIRED,
+            ),
+            Model(model::Error::InvalidTitrationPoints { index, reason }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::INVALID_TITRATION_POINTS {
+                    index: *index,
+                    reason,
+                },
+            ),
+
+            UploadTooLarge { limit, .. } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ClientError::UPLOAD_TOO_LARGE { limit: *limit },
+            ),
+            GroupQuotaExceeded { quota, .. } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ClientError::GROUP_QUOTA_EXCEEDED { quota: *quota },
+            ),
+            UnsupportedMediaType { .. } => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ClientError::UNSUPPORTED_MEDIA_TYPE,
+            ),
+
+            SearchQuery(err) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::SEARCH_QUERY_INVALID {
+                    position: err.position,
+                    message: err.message.clone(),
+                },
+            ),
+
             BadRequest(_) => (StatusCode::BAD_REQUEST, ClientError::SERVICE_ERROR),
 
             _ => (
@@ -96,7 +160,36 @@ impl Error {
 pub enum ClientError {
     LOGIN_FAIL,
     NO_AUTH,
-    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+    ENTITY_NOT_FOUND {
+        entity: &'static str,
+        id: i64,
+    },
+    VALIDATION_LOCKED {
+        id: i64,
+    },
+    REVIEW_TRANSITION_NOT_ALLOWED {
+        from: &'static str,
+        action: &'static str,
+    },
+    REVIEW_NOT_PERMITTED {
+        reason: &'static str,
+    },
+    REVIEW_COMMENT_REQUIRED,
+    INVALID_TITRATION_POINTS {
+        index: Option<usize>,
+        reason: &'static str,
+    },
+    UPLOAD_TOO_LARGE {
+        limit: u64,
+    },
+    GROUP_QUOTA_EXCEEDED {
+        quota: u64,
+    },
+    UNSUPPORTED_MEDIA_TYPE,
+    SEARCH_QUERY_INVALID {
+        position: usize,
+        message: String,
+    },
 
     SERVICE_ERROR,
 }
@@ -124,6 +217,52 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn validation_review_errors_map_to_client_errors() {
+        let (status, client_error) =
+            Error::from(model::Error::ValidationLocked { id: 3 }).client_status_and_error();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(matches!(
+            client_error,
+            ClientError::VALIDATION_LOCKED { id: 3 }
+        ));
+
+        let (status, _) = Error::from(model::Error::ReviewNotPermitted {
+            id: 3,
+            reason: "reviewer role required",
+        })
+        .client_status_and_error();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn upload_errors_map_to_413_and_415() {
+        let (status, client_error) = Error::UploadTooLarge {
+            file_name: "scan.tiff".into(),
+            limit: 10,
+        }
+        .client_status_and_error();
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(matches!(
+            client_error,
+            ClientError::UPLOAD_TOO_LARGE { limit: 10 }
+        ));
+
+        let (status, _) = Error::GroupQuotaExceeded {
+            group_id: 1,
+            quota: 10,
+        }
+        .client_status_and_error();
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let (status, _) = Error::UnsupportedMediaType {
+            file_name: "run.exe".into(),
+            reason: "extension .exe is not accepted".into(),
+        }
+        .client_status_and_error();
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
     #[test]
     fn login_fail_maps_to_forbidden() {
         let (status, client_error) = Error::LoginFailUsernameNotFound.client_status_and_error();
@@ -1,16 +1,25 @@
+pub mod dossier;
 mod error;
 pub mod mw_auth;
 pub mod mw_res_map;
+pub mod pdf;
+pub mod preview;
+pub mod routes_blob_store;
+pub mod routes_conjugation_batch;
 pub mod routes_fallback;
 pub mod routes_group;
 pub mod routes_json;
 pub mod routes_login;
+pub mod routes_report;
+pub mod routes_saved_search;
 pub mod routes_search;
 pub mod routes_static;
 pub mod routes_telemetry;
 pub mod routes_user;
 pub mod routes_validation_file;
+pub mod routes_validation_review;
 pub mod routes_ws;
+pub mod upload_policy;
 
 pub use self::error::ClientError;
 pub use self::error::{Error, Result};
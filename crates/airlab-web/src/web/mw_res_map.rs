@@ -60,13 +60,15 @@ pub async fn mw_reponse_map(
     let web_error = res.extensions().get::<Arc<web::Error>>();
     let client_status_error = web_error.map(|se| se.client_status_and_error());
 
-    let error_response = client_status_error.as_ref().map(|(status_code, _)| {
-        let client_error_body = json!({});
+    let error_response = client_status_error
+        .as_ref()
+        .map(|(status_code, client_error)| {
+            let client_error_body = json!({ "error": client_error });
 
-        debug!("CLIENT ERROR BODY:\n{client_error_body}");
+            debug!("CLIENT ERROR BODY:\n{client_error_body}");
 
-        (*status_code, Json(client_error_body)).into_response()
-    });
+            (*status_code, Json(client_error_body)).into_response()
+        });
     let client_error = client_status_error.unzip().1;
     let final_status = error_response
         .as_ref()
@@ -132,4 +134,35 @@ mod tests {
 
         assert_eq!(mapped.status(), StatusCode::FORBIDDEN);
     }
+
+    #[tokio::test]
+    async fn response_map_returns_search_query_errors_in_the_body() -> TestResult {
+        let error = crate::search_shadow::query::parse("tag:(")
+            .err()
+            .ok_or("expected a parse error")?;
+        let mapped = mw_reponse_map(
+            Err(Error::CtxExt(
+                crate::web::mw_auth::CtxExtError::CtxNotInRequestExt,
+            )),
+            None,
+            Uri::from_static("/api/v1/search"),
+            Method::POST,
+            Error::from(error).into_response(),
+        )
+        .await;
+
+        assert_eq!(mapped.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_str(&crate::web::test_support::response_body_string(mapped).await?)?;
+        assert_eq!(
+            body,
+            json!({
+                "error": {
+                    "message": "SEARCH_QUERY_INVALID",
+                    "detail": {"position": 4, "message": "expected a value after `:`"}
+                }
+            })
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,470 @@
+//! Minimal PDF writer for generated reports: A4 pages, the standard Helvetica fonts and
+//! embedded JPEG or PNG images. Text is laid out top to bottom and wrapped on an estimated
+//! glyph width, which is good enough for plain reports.
+
+use std::fmt::Write;
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 50.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+/// Average Helvetica glyph width relative to the font size, used for wrapping.
+const GLYPH_WIDTH: f64 = 0.52;
+const BODY_SIZE: f64 = 10.0;
+const MAX_IMAGE_HEIGHT: f64 = 320.0;
+
+/// An image ready to be embedded without re-encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    filter: &'static str,
+    decode_parms: Option<String>,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    /// Reads a JPEG or PNG by extension; other formats, interlaced PNGs and PNGs with an
+    /// alpha channel or palette are not supported.
+    pub fn from_bytes(bytes: &[u8], extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Self::from_jpeg(bytes),
+            "png" => Self::from_png(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn from_jpeg(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(..2)? != [0xFF, 0xD8] {
+            return None;
+        }
+        let mut pos = 2;
+        while pos + 4 <= bytes.len() {
+            if bytes[pos] != 0xFF {
+                return None;
+            }
+            let marker = bytes[pos + 1];
+            let length = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+            let is_frame = (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_frame {
+                let frame = bytes.get(pos + 4..pos + 2 + length)?;
+                let height = u32::from(u16::from_be_bytes([*frame.get(1)?, *frame.get(2)?]));
+                let width = u32::from(u16::from_be_bytes([*frame.get(3)?, *frame.get(4)?]));
+                let color_space = match frame.get(5)? {
+                    1 => "DeviceGray",
+                    3 => "DeviceRGB",
+                    4 => "DeviceCMYK",
+                    _ => return None,
+                };
+                return Some(Self {
+                    width,
+                    height,
+                    color_space,
+                    filter: "DCTDecode",
+                    decode_parms: None,
+                    data: bytes.to_vec(),
+                });
+            }
+            if marker == 0xDA {
+                return None;
+            }
+            pos += 2 + length;
+        }
+        None
+    }
+
+    /// PNG image data is a zlib stream with per-row predictors, which PDF decodes natively.
+    pub fn from_png(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(..8)? != b"\x89PNG\r\n\x1a\n" {
+            return None;
+        }
+        let mut pos = 8;
+        let mut header = None;
+        let mut data = vec![];
+        while pos + 8 <= bytes.len() {
+            let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+            let kind = &bytes[pos + 4..pos + 8];
+            let chunk = bytes.get(pos + 8..pos + 8 + length)?;
+            match kind {
+                b"IHDR" if length >= 13 => header = Some(chunk.to_vec()),
+                b"IDAT" => data.extend_from_slice(chunk),
+                b"IEND" => break,
+                _ => {}
+            }
+            pos += 12 + length;
+        }
+
+        let header = header?;
+        let width = u32::from_be_bytes(header[0..4].try_into().ok()?);
+        let height = u32::from_be_bytes(header[4..8].try_into().ok()?);
+        let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+        let (color_space, colors) = match color_type {
+            0 => ("DeviceGray", 1),
+            2 => ("DeviceRGB", 3),
+            _ => return None,
+        };
+        if bit_depth != 8 || interlace != 0 || data.is_empty() {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            color_space,
+            filter: "FlateDecode",
+            decode_parms: Some(format!(
+                "<< /Predictor 15 /Colors {colors} /BitsPerComponent 8 /Columns {width} >>"
+            )),
+            data,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    const fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+        }
+    }
+}
+
+/// Lays out a document page by page.
+#[derive(Debug)]
+pub struct PdfBuilder {
+    pages: Vec<Vec<u8>>,
+    images: Vec<PdfImage>,
+    y: f64,
+}
+
+impl Default for PdfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        Self {
+            pages: vec![vec![]],
+            images: vec![],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        self.space(8.0);
+        self.lines(text, Font::Bold, 16.0, 0.0);
+        self.space(4.0);
+    }
+
+    pub fn subheading(&mut self, text: &str) {
+        self.space(6.0);
+        self.lines(text, Font::Bold, 12.0, 0.0);
+        self.space(2.0);
+    }
+
+    pub fn paragraph(&mut self, text: &str) {
+        self.lines(text, Font::Regular, BODY_SIZE, 0.0);
+    }
+
+    /// A `label: value` line; long values wrap under the value column.
+    pub fn field(&mut self, label: &str, value: &str) {
+        let indent = 150.0;
+        self.ensure(BODY_SIZE * 1.4);
+        self.text_at(MARGIN, label, Font::Bold, BODY_SIZE);
+        self.lines(value, Font::Regular, BODY_SIZE, indent);
+    }
+
+    pub fn space(&mut self, height: f64) {
+        self.y -= height;
+    }
+
+    /// Adds an image scaled to the content width and at most `MAX_IMAGE_HEIGHT` high.
+    pub fn image(&mut self, image: PdfImage) {
+        let (width, height) = (f64::from(image.width), f64::from(image.height));
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let scale = (CONTENT_WIDTH / width)
+            .min(MAX_IMAGE_HEIGHT / height)
+            .min(1.0);
+        let (width, height) = (width * scale, height * scale);
+        self.ensure(height + 4.0);
+        self.y -= height;
+        let index = self.images.len();
+        self.images.push(image);
+        let op = format!(
+            "q {width:.2} 0 0 {height:.2} {MARGIN:.2} {:.2} cm /Im{index} Do Q\n",
+            self.y
+        );
+        self.push_op(op.as_bytes());
+        self.y -= 4.0;
+    }
+
+    fn push_op(&mut self, op: &[u8]) {
+        if let Some(page) = self.pages.last_mut() {
+            page.extend_from_slice(op);
+        }
+    }
+
+    fn ensure(&mut self, height: f64) {
+        if self.y - height < MARGIN {
+            self.pages.push(vec![]);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn lines(&mut self, text: &str, font: Font, size: f64, indent: f64) {
+        let max_chars = ((CONTENT_WIDTH - indent) / (size * GLYPH_WIDTH)).floor() as usize;
+        for line in wrap(text, max_chars.max(1)) {
+            self.ensure(size * 1.4);
+            self.text_at(MARGIN + indent, &line, font, size);
+            self.y -= size * 1.4;
+        }
+    }
+
+    fn text_at(&mut self, x: f64, text: &str, font: Font, size: f64) {
+        let baseline = self.y - size;
+        let mut op = format!(
+            "BT /{} {size} Tf {x:.2} {baseline:.2} Td (",
+            font.resource()
+        )
+        .into_bytes();
+        op.extend_from_slice(&pdf_string(text));
+        op.extend_from_slice(b") Tj ET\n");
+        self.push_op(&op);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut objects: Vec<Vec<u8>> = vec![];
+        let image_base = 6;
+        let page_base = image_base + self.images.len();
+        let kids = (0..self.pages.len())
+            .map(|index| format!("{} 0 R", page_base + 2 * index))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{kids}] /Count {} >>",
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        for font in ["Helvetica", "Helvetica-Bold"] {
+            objects.push(
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>"
+                )
+                .into_bytes(),
+            );
+        }
+        let xobjects = (0..self.images.len())
+            .map(|index| format!("/Im{index} {} 0 R", image_base + index))
+            .collect::<String>();
+        objects.push(
+            format!("<< /Font << /F1 3 0 R /F2 4 0 R >> /XObject << {xobjects} >> >>").into_bytes(),
+        );
+
+        for image in self.images {
+            let mut dict = format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /{}",
+                image.width, image.height, image.color_space, image.filter
+            );
+            if let Some(parms) = &image.decode_parms {
+                let _ = write!(dict, " /DecodeParms {parms}");
+            }
+            objects.push(stream_object(&dict, &image.data));
+        }
+
+        for (index, content) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] /Resources 5 0 R /Contents {} 0 R >>",
+                    page_base + 2 * index + 1
+                )
+                .into_bytes(),
+            );
+            objects.push(stream_object("", content));
+        }
+
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = vec![];
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+fn stream_object(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+/// Encodes text as a WinAnsi literal string body, replacing what the encoding lacks.
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        let byte = match ch {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                ch as u8
+            }
+            '\u{2013}' | '\u{2014}' => b'-',
+            '\u{2018}' | '\u{2019}' => b'\'',
+            '\u{201C}' | '\u{201D}' => b'"',
+            ' '..='~' | '\u{A0}'..='\u{FF}' => ch as u32 as u8,
+            _ => b'?',
+        };
+        out.push(byte);
+    }
+    out
+}
+
+/// Greedy word wrap on character counts; overlong words are split.
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word.to_string();
+            while word.chars().count() > max_chars {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word
+                    .char_indices()
+                    .nth(max_chars)
+                    .map_or(word.len(), |(index, _)| index);
+                let rest = word.split_off(split);
+                lines.push(word);
+                word = rest;
+            }
+            let needed =
+                line.chars().count() + usize::from(!line.is_empty()) + word.chars().count();
+            if needed > max_chars && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    #[test]
+    fn finish_writes_consistent_cross_reference_table() -> TestResult {
+        let mut pdf = PdfBuilder::new();
+        pdf.heading("Report (draft)");
+        for index in 0..60 {
+            pdf.field("Line", &format!("value {index}"));
+        }
+        let bytes = pdf.finish();
+
+        assert!(bytes.starts_with(b"%PDF-1.4"));
+        assert!(bytes.ends_with(b"%%EOF\n"));
+        assert!(find(&bytes, b"(Report \\(draft\\)) Tj").is_some());
+        assert!(find(&bytes, b"/Count 2").is_some());
+
+        let xref = find(&bytes, b"xref\n").ok_or("xref table should be written")?;
+        let table = String::from_utf8(bytes[xref..].to_vec())?;
+        let startxref = table
+            .lines()
+            .skip_while(|line| *line != "startxref")
+            .nth(1)
+            .ok_or("startxref should be written")?;
+        assert_eq!(startxref.parse::<usize>()?, xref);
+        for (index, line) in table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = line[..10].parse()?;
+            let expected = format!("{} 0 obj", index + 1);
+            assert!(bytes[offset..].starts_with(expected.as_bytes()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn images_are_read_from_jpeg_and_png_headers() -> TestResult {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00,
+            0x02, 0x00, 0x03, 0x03, 0x00, 0x00, 0x00,
+        ];
+        let image = PdfImage::from_bytes(&jpeg, "JPG").ok_or("jpeg should be read")?;
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.color_space, "DeviceRGB");
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [
+            (&b"IHDR"[..], &[0, 0, 0, 4, 0, 0, 0, 5, 8, 0, 0, 0, 0][..]),
+            (b"IDAT", &[0x78, 0x9C]),
+            (b"IEND", &[]),
+        ] {
+            png.extend_from_slice(&u32::try_from(data.len())?.to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            png.extend_from_slice(&[0; 4]);
+        }
+        let image = PdfImage::from_bytes(&png, "png").ok_or("png should be read")?;
+        assert_eq!((image.width, image.height), (4, 5));
+        assert_eq!(image.color_space, "DeviceGray");
+        assert_eq!(image.data, vec![0x78, 0x9C]);
+
+        assert!(PdfImage::from_bytes(&png, "gif").is_none());
+        assert!(PdfImage::from_bytes(b"not an image", "png").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_breaks_on_words_and_splits_long_words() {
+        assert_eq!(wrap("one two three", 7), vec!["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 10), vec![String::new()]);
+    }
+}
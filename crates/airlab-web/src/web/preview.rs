@@ -0,0 +1,207 @@
+//! Downscaled PNG previews of validation files. Images with more than 8 bits per sample,
+//! typically 16-bit microscopy TIFFs, are auto-contrasted so they are not rendered black.
+//! PDFs get the first embedded JPEG, which for scans and exported figures is the first
+//! page; vector pages would need a full PDF renderer and get no preview.
+
+use image::{DynamicImage, ImageFormat, Rgba32FImage};
+use std::io::Cursor;
+
+/// Fraction of the darkest and brightest samples clipped by auto-contrast.
+const CLIP_FRACTION: f64 = 0.005;
+
+/// Formats a preview can be rendered from, by file extension.
+pub fn supports(extension: &str) -> bool {
+    image_format(extension).is_some() || extension.eq_ignore_ascii_case("pdf")
+}
+
+fn image_format(extension: &str) -> Option<ImageFormat> {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "gif" => Some(ImageFormat::Gif),
+        "webp" => Some(ImageFormat::WebP),
+        "tif" | "tiff" => Some(ImageFormat::Tiff),
+        _ => None,
+    }
+}
+
+/// Renders a PNG that fits into `width` x `height`, keeping the aspect ratio. `Ok(None)`
+/// if the file has nothing to preview.
+pub fn render(
+    bytes: &[u8],
+    extension: &str,
+    width: u32,
+    height: u32,
+) -> image::ImageResult<Option<Vec<u8>>> {
+    let image = if extension.eq_ignore_ascii_case("pdf") {
+        match first_pdf_jpeg(bytes) {
+            Some(jpeg) => image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg)?,
+            None => return Ok(None),
+        }
+    } else {
+        match image_format(extension) {
+            Some(format) => image::load_from_memory_with_format(bytes, format)?,
+            None => return Ok(None),
+        }
+    };
+
+    let preview = to_8bit(image.thumbnail(width, height));
+    let mut png = vec![];
+    preview.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(Some(png))
+}
+
+/// 8-bit images pass through; deeper ones are stretched between their clip percentiles.
+fn to_8bit(image: DynamicImage) -> DynamicImage {
+    let has_alpha = image.color().has_alpha();
+    let grey = image.color().channel_count() <= 2;
+    let deep = !matches!(
+        image,
+        DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_)
+    );
+    if !deep {
+        return image;
+    }
+
+    let mut pixels: Rgba32FImage = image.into_rgba32f();
+    let mut samples = pixels
+        .pixels()
+        .flat_map(|pixel| [pixel.0[0], pixel.0[1], pixel.0[2]])
+        .filter(|sample| sample.is_finite())
+        .collect::<Vec<_>>();
+    let (low, high) = clip_range(&mut samples);
+    let scale = if high > low { 1.0 / (high - low) } else { 0.0 };
+    for pixel in pixels.pixels_mut() {
+        for sample in &mut pixel.0[..3] {
+            *sample = if sample.is_finite() {
+                ((*sample - low) * scale).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+
+    let stretched = DynamicImage::ImageRgba32F(pixels);
+    match (grey, has_alpha) {
+        (true, false) => DynamicImage::ImageLuma8(stretched.into_luma8()),
+        (true, true) => DynamicImage::ImageLumaA8(stretched.into_luma_alpha8()),
+        (false, false) => DynamicImage::ImageRgb8(stretched.into_rgb8()),
+        (false, true) => DynamicImage::ImageRgba8(stretched.into_rgba8()),
+    }
+}
+
+/// Sample values at the lower and upper clip percentile.
+fn clip_range(samples: &mut [f32]) -> (f32, f32) {
+    if samples.is_empty() {
+        return (0.0, 1.0);
+    }
+    samples.sort_unstable_by(f32::total_cmp);
+    let last = samples.len() - 1;
+    let clipped = (last as f64 * CLIP_FRACTION).round() as usize;
+    let low = samples.get(clipped).copied().unwrap_or_default();
+    let high = samples.get(last - clipped).copied().unwrap_or(low);
+    (low, high)
+}
+
+/// The first stream that is stored as a plain JPEG (`/DCTDecode` as the only filter).
+fn first_pdf_jpeg(pdf: &[u8]) -> Option<&[u8]> {
+    let mut from = 0;
+    while let Some(found) = find(pdf.get(from..)?, b"/DCTDecode") {
+        let at = from + found;
+        from = at + 1;
+        let Some(stream) = find(pdf.get(at..)?, b"stream") else {
+            break;
+        };
+        let mut start = at + stream + b"stream".len();
+        if pdf.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if pdf.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let Some(end) = find(pdf.get(start..)?, b"endstream") else {
+            break;
+        };
+        let data = pdf.get(start..start + end)?;
+        if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            return Some(data);
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma, Rgb, RgbImage};
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> TestResult<Vec<u8>> {
+        let mut bytes = vec![];
+        image.write_to(&mut Cursor::new(&mut bytes), format)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn render_fits_preview_into_box() -> TestResult {
+        let png = encode(
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 200, Rgb([10, 200, 30]))),
+            ImageFormat::Png,
+        )?;
+
+        let preview = render(&png, "PNG", 100, 100)?.ok_or("preview")?;
+        let preview = image::load_from_memory(&preview)?;
+
+        assert_eq!((preview.width(), preview.height()), (100, 50));
+        assert_eq!(preview.to_rgb8().get_pixel(50, 25), &Rgb([10, 200, 30]));
+        assert!(render(&png, "txt", 100, 100)?.is_none());
+        assert!(render(b"not a png", "png", 100, 100).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn render_auto_contrasts_16bit_tiff() -> TestResult {
+        // A dim gradient that would be almost black when scaled down linearly.
+        let dim = ImageBuffer::from_fn(64, 64, |x, _| {
+            Luma([1000 + u16::try_from(x).unwrap_or_default() * 10])
+        });
+        let tiff = encode(DynamicImage::ImageLuma16(dim), ImageFormat::Tiff)?;
+
+        let preview = render(&tiff, "tiff", 64, 64)?.ok_or("preview")?;
+        let preview = image::load_from_memory(&preview)?.into_luma8();
+        let darkest = preview.pixels().map(|pixel| pixel.0[0]).min();
+        let brightest = preview.pixels().map(|pixel| pixel.0[0]).max();
+
+        assert_eq!(darkest, Some(0));
+        assert_eq!(brightest, Some(255));
+        Ok(())
+    }
+
+    #[test]
+    fn render_uses_first_jpeg_of_pdf() -> TestResult {
+        let jpeg = encode(
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(80, 40, Rgb([200, 200, 200]))),
+            ImageFormat::Jpeg,
+        )?;
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Length 3 /Filter /FlateDecode >>\nstream\nabc\nendstream\nendobj\n2 0 obj\n<< /Type /XObject /Subtype /Image /Filter /DCTDecode >>\nstream\n".to_vec();
+        pdf.extend_from_slice(&jpeg);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF\n");
+
+        let preview = render(&pdf, "pdf", 40, 40)?.ok_or("preview")?;
+        let preview = image::load_from_memory(&preview)?;
+
+        assert_eq!((preview.width(), preview.height()), (40, 20));
+        assert!(render(b"%PDF-1.4\n%%EOF\n", "pdf", 40, 40)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use crate::blob_store::{self, BlobState};
+use crate::web::mw_auth::CtxW;
+use crate::web::{Error, Result};
+use airlab_lib::ctx::Ctx;
+use airlab_lib::model::ModelManager;
+use airlab_lib::model::user::{User, UserBmc};
+use axum::extract::{Query, State};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+#[allow(unused_imports)]
+use tracing::{debug, warn};
+
+/// Unreferenced blobs younger than this are kept by default, see `blob_store::gc`.
+const DEFAULT_GC_GRACE_MINUTES: i64 = 60;
+
+pub fn routes(state: BlobState) -> Router {
+    Router::new()
+        .route("/api/v1/admin/blobs/scrub", post(api_blob_scrub_handler))
+        .route("/api/v1/admin/blobs/gc", post(api_blob_gc_handler))
+        .with_state(state)
+}
+
+async fn api_blob_scrub_handler(
+    State(BlobState { mm, store }): State<BlobState>,
+    ctx: CtxW,
+) -> Result<Json<Value>> {
+    debug!("HANDLER - api_blob_scrub_handler");
+
+    let ctx = ctx.0;
+    ensure_admin(&ctx, &mm).await?;
+    let report = blob_store::scrub(&ctx, &mm, &store).await?;
+    if !report.missing.is_empty() || !report.corrupted.is_empty() {
+        warn!(
+            "BLOBS - scrub found {} missing and {} corrupted files",
+            report.missing.len(),
+            report.corrupted.len()
+        );
+    }
+
+    Ok(Json(json!(report)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcParams {
+    grace_minutes: Option<i64>,
+}
+
+async fn api_blob_gc_handler(
+    State(BlobState { mm, store }): State<BlobState>,
+    ctx: CtxW,
+    Query(params): Query<GcParams>,
+) -> Result<Json<Value>> {
+    debug!("HANDLER - api_blob_gc_handler: {:?}", params);
+
+    let ctx = ctx.0;
+    ensure_admin(&ctx, &mm).await?;
+    let grace = params.grace_minutes.unwrap_or(DEFAULT_GC_GRACE_MINUTES);
+    if grace < 0 {
+        return Err(Error::BadRequest(format!(
+            "graceMinutes must not be negative: {grace}"
+        )));
+    }
+    let report = blob_store::gc(&ctx, &mm, &store, chrono::Duration::minutes(grace)).await?;
+
+    Ok(Json(json!(report)))
+}
+
+async fn ensure_admin(ctx: &Ctx, mm: &ModelManager) -> Result<()> {
+    let user: User = UserBmc::get(ctx, mm, ctx.user_id()).await?;
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err(Error::AdminRequired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn post_request(uri: &str) -> TestResult<Request<Body>> {
+        Ok(Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(Body::empty())?)
+    }
+
+    #[tokio::test]
+    async fn blob_routes_require_admin_and_report() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = app
+            .clone()
+            .oneshot(post_request("/api/v1/admin/blobs/scrub")?)
+            .await?;
+        assert!(!response.status().is_success());
+
+        sqlx::query(r#"UPDATE "user" SET is_admin = true WHERE id = 1"#)
+            .execute(mm.db())
+            .await?;
+        let response = app
+            .clone()
+            .oneshot(post_request("/api/v1/admin/blobs/scrub")?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let report: Value = serde_json::from_str(&body)?;
+        assert!(report["missing"].is_array());
+        assert!(report["orphaned"].is_array());
+
+        let response = app
+            .clone()
+            .oneshot(post_request("/api/v1/admin/blobs/gc?graceMinutes=-5")?)
+            .await?;
+        assert!(!response.status().is_success());
+
+        let response = app.oneshot(post_request("/api/v1/admin/blobs/gc")?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use crate::web::Result;
+use crate::web::mw_auth::CtxW;
+use airlab_lib::model::ModelManager;
+use airlab_lib::model::clone::CloneBmc;
+use airlab_lib::model::conjugate::ConjugateBmc;
+use airlab_lib::model::conjugation_batch::{ConjugationBatch, ConjugationBatchBmc};
+use airlab_lib::model::lot::LotBmc;
+use airlab_lib::model::tag::TagBmc;
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::http::{HeaderValue, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use std::fmt::Write;
+#[allow(unused_imports)]
+use tracing::{debug, warn};
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/api/v1/conjugation_batches/{batch_id}/protocol",
+            get(api_conjugation_batch_protocol_handler),
+        )
+        .with_state(mm)
+}
+
+struct ProtocolContext {
+    lot_name: String,
+    clone_name: String,
+    tag_name: String,
+    tube_number: Option<i64>,
+}
+
+async fn api_conjugation_batch_protocol_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Path(batch_id): Path<i64>,
+) -> Result<Response> {
+    debug!(
+        "HANDLER - api_conjugation_batch_protocol_handler: {}",
+        batch_id
+    );
+
+    let ctx = ctx.0;
+    let batch = ConjugationBatchBmc::get(&ctx, &mm, batch_id).await?;
+    let lot = LotBmc::get(&ctx, &mm, batch.lot_id).await?;
+    let clone = CloneBmc::get(&ctx, &mm, lot.clone_id).await?;
+    let tag = TagBmc::get(&ctx, &mm, batch.tag_id).await?;
+    let tube_number = match batch.conjugate_id {
+        Some(conjugate_id) => Some(
+            ConjugateBmc::get(&ctx, &mm, conjugate_id)
+                .await?
+                .tube_number,
+        ),
+        None => None,
+    };
+
+    let html = render_protocol_sheet(
+        &batch,
+        &ProtocolContext {
+            lot_name: lot.name,
+            clone_name: clone.name,
+            tag_name: tag.name,
+            tube_number,
+        },
+    );
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        )],
+        html,
+    )
+        .into_response())
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_optional(value: Option<f64>, unit: &str) -> String {
+    value.map_or_else(|| "-".to_string(), |value| format!("{value:.1} {unit}"))
+}
+
+fn render_protocol_sheet(batch: &ConjugationBatch, context: &ProtocolContext) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Conjugation batch {id}</title>\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse;width:100%}}\
+         td,th{{border:1px solid #999;padding:4px 8px;text-align:left}}\
+         @media print{{button{{display:none}}}}</style></head><body>\
+         <h1>Conjugation batch {id}</h1><table>",
+        id = batch.id
+    );
+
+    let tube = context
+        .tube_number
+        .map_or_else(|| "-".to_string(), |tube| tube.to_string());
+    let rows = [
+        ("Clone", escape_html(&context.clone_name)),
+        ("Lot", escape_html(&context.lot_name)),
+        ("Tag", escape_html(&context.tag_name)),
+        ("Conjugate tube", tube),
+        (
+            "Labeling kit",
+            escape_html(batch.kit.as_deref().unwrap_or("-")),
+        ),
+        (
+            "Buffer",
+            escape_html(batch.buffer.as_deref().unwrap_or("-")),
+        ),
+        (
+            "Antibody in",
+            format_optional(Some(batch.antibody_amount_ug), "µg"),
+        ),
+        (
+            "Measured concentration",
+            format_optional(batch.measured_concentration, "µg/mL"),
+        ),
+        ("Final volume", format_optional(batch.final_volume_ul, "µL")),
+        (
+            "Recovered",
+            format_optional(batch.recovered_amount_ug(), "µg"),
+        ),
+        ("Yield", format_optional(batch.yield_percent(), "%")),
+    ];
+    for (label, value) in rows {
+        let _ = write!(html, "<tr><th>{label}</th><td>{value}</td></tr>");
+    }
+    html.push_str("</table><h2>Steps</h2><table><tr><th>Step</th><th>Started</th><th>Finished</th><th>Note</th><th>Initials</th></tr>");
+
+    for step in batch.parsed_steps() {
+        let time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+            at.map_or_else(String::new, |at| at.format("%Y-%m-%d %H:%M").to_string())
+        };
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td></td></tr>",
+            escape_html(&step.name),
+            time(step.started_at),
+            time(step.finished_at),
+            escape_html(step.note.as_deref().unwrap_or_default())
+        );
+    }
+    html.push_str("</table>");
+
+    if let Some(note) = batch.note.as_deref() {
+        let _ = write!(html, "<h2>Notes</h2><p>{}</p>", escape_html(note));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use airlab_lib::ctx::Ctx;
+    use airlab_lib::model::conjugation_batch::ConjugationBatchForCreate;
+    use axum::http::StatusCode;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[test]
+    fn escape_html_escapes_markup() {
+        assert_eq!(
+            escape_html("<b>\"A&B\"</b>"),
+            "&lt;b&gt;&quot;A&amp;B&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[tokio::test]
+    async fn protocol_route_renders_batch_sheet() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let batch_id = ConjugationBatchBmc::create(
+            &ctx,
+            &mm,
+            ConjugationBatchForCreate {
+                group_id: 1000,
+                lot_id: 1007,
+                tag_id: 1005,
+                conjugate_id: Some(1008),
+                created_by: 1303,
+                labeled_by: None,
+                kit: Some("MaxPar <X8>".into()),
+                buffer: None,
+                antibody_amount_ug: 100.0,
+                measured_concentration: Some(300.0),
+                final_volume_ul: Some(200.0),
+                steps: json!([{"name": "buffer exchange"}]),
+                note: None,
+            },
+        )
+        .await?;
+        let app = crate::web::test_support::authed_router(routes((*mm).clone()));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(format!("/api/v1/conjugation_batches/{batch_id}/protocol"))
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        assert!(body.contains("seed-clone"));
+        assert!(body.contains("seed-tag"));
+        assert!(body.contains("MaxPar &lt;X8&gt;"));
+        assert!(body.contains("buffer exchange"));
+        assert!(body.contains("60.0 %"));
+
+        Ok(())
+    }
+}
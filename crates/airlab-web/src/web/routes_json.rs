@@ -1,5 +1,6 @@
 use crate::config::web_config;
-use crate::search_shadow::SearchState;
+use crate::search_shadow::notify::{VERSION_HEADER, db_version};
+use crate::search_shadow::{SearchState, ShadowChange, ShadowEntity};
 use crate::web::Result;
 use crate::web::mw_auth::CtxW;
 use Operation as Op;
@@ -13,6 +14,10 @@ use airlab_lib::model::collection::{
 use airlab_lib::model::conjugate::{
     Conjugate, ConjugateBmc, ConjugateFilter, ConjugateForCreate, ConjugateForUpdate,
 };
+use airlab_lib::model::conjugation_batch::{
+    ConjugationBatch, ConjugationBatchBmc, ConjugationBatchFilter, ConjugationBatchForCreate,
+    ConjugationBatchForUpdate,
+};
 use airlab_lib::model::group::{Group, GroupBmc, GroupFilter, GroupForCreate, GroupForUpdate};
 use airlab_lib::model::lot::{Lot, LotBmc, LotFilter, LotForCreate, LotForUpdate};
 use airlab_lib::model::member::{
@@ -28,6 +33,10 @@ use airlab_lib::model::panel_element::{
 use airlab_lib::model::protein::{
     Protein, ProteinBmc, ProteinFilter, ProteinForCreate, ProteinForUpdate,
 };
+use airlab_lib::model::protocol_template::{
+    ProtocolTemplate, ProtocolTemplateBmc, ProtocolTemplateFilter, ProtocolTemplateForCreate,
+    ProtocolTemplateForUpdate,
+};
 use airlab_lib::model::provider::{
     Provider, ProviderBmc, ProviderFilter, ProviderForCreate, ProviderForUpdate,
 };
@@ -38,6 +47,9 @@ use airlab_lib::model::storage::{
     Storage, StorageBmc, StorageFilter, StorageForCreate, StorageForUpdate,
 };
 use airlab_lib::model::tag::{Tag, TagBmc, TagFilter, TagForCreate, TagForUpdate};
+use airlab_lib::model::titration::{
+    Titration, TitrationBmc, TitrationFilter, TitrationForCreate, TitrationForUpdate,
+};
 use airlab_lib::model::user::{User, UserBmc, UserFilter, UserForCreate, UserForUpdate};
 use airlab_lib::model::validation::{
     Validation, ValidationBmc, ValidationFilter, ValidationForCreate, ValidationForUpdate,
@@ -46,7 +58,10 @@ use airlab_lib::model::validation_file::{
     ValidationFile, ValidationFileBmc, ValidationFileFilter, ValidationFileForCreate,
     ValidationFileForUpdate,
 };
+use airlab_lib::units::{ConcentrationUnit, ConversionContext, DILUTION_TYPE_DILUTION, Quantity};
 use axum::extract::{Json as eJson, State};
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
 use axum::routing::post;
 use axum::{Json, Router};
 use camino::Utf8PathBuf;
@@ -70,7 +85,7 @@ async fn api_post_json_handler(
     State(state): State<SearchState>,
     ctx: CtxW,
     eJson(req): eJson<RpcRequest>,
-) -> Result<Json<Value>> {
+) -> Result<Response> {
     let mm = state.mm.clone();
     info!("HANDLER - api_post_json_handler: {:?}", req);
     let ctx = ctx.0;
@@ -89,6 +104,9 @@ async fn api_post_json_handler(
         req.operation,
         Op::Insert | Op::Update | Op::Delete | Op::Reorder
     );
+    let shadow_entity = req.return_type.shadow_entity();
+    let creates = matches!(req.operation, Op::Insert);
+    let reorders = matches!(req.operation, Op::Reorder);
 
     let result = match (req.return_type, req.operation) {
         (RT::Clone, Op::Get) => get_clones(&ctx, &mm, filter_json, lo).await?,
@@ -107,6 +125,26 @@ async fn api_post_json_handler(
         (RT::Conjugate, Op::Update) => update_conjugate(&ctx, &mm, req.id, req.payload).await?,
         (RT::Conjugate, Op::Insert) => insert_conjugate(&ctx, &mm, req.payload).await?,
         (RT::Conjugate, Op::Delete) => delete_conjugate(&ctx, &mm, req.id).await?,
+        (RT::ConjugationBatch, Op::Get) => {
+            get_conjugation_batches(&ctx, &mm, filter_json, lo).await?
+        }
+        (RT::ConjugationBatch, Op::Update) => {
+            update_conjugation_batch(&ctx, &mm, req.id, req.payload).await?
+        }
+        (RT::ConjugationBatch, Op::Insert) => {
+            insert_conjugation_batch(&ctx, &mm, req.payload).await?
+        }
+        (RT::ConjugationBatch, Op::Delete) => delete_conjugation_batch(&ctx, &mm, req.id).await?,
+        (RT::ProtocolTemplate, Op::Get) => {
+            get_protocol_templates(&ctx, &mm, filter_json, lo).await?
+        }
+        (RT::ProtocolTemplate, Op::Update) => {
+            update_protocol_template(&ctx, &mm, req.id, req.payload).await?
+        }
+        (RT::ProtocolTemplate, Op::Insert) => {
+            insert_protocol_template(&ctx, &mm, req.payload).await?
+        }
+        (RT::ProtocolTemplate, Op::Delete) => delete_protocol_template(&ctx, &mm, req.id).await?,
         (RT::Tag, Op::Get) => get_tags(&ctx, &mm, filter_json, lo).await?,
         (RT::Tag, Op::Update) => update_tag(&ctx, &mm, req.id, req.payload).await?,
         (RT::Tag, Op::Insert) => insert_tag(&ctx, &mm, req.payload).await?,
@@ -138,6 +176,10 @@ async fn api_post_json_handler(
         (RT::Collection, Op::Update) => update_collection(&ctx, &mm, req.id, req.payload).await?,
         (RT::Collection, Op::Insert) => insert_collection(&ctx, &mm, req.payload).await?,
         (RT::Collection, Op::Delete) => delete_collection(&ctx, &mm, req.id).await?,
+        (RT::Titration, Op::Get) => get_titrations(&ctx, &mm, filter_json, lo).await?,
+        (RT::Titration, Op::Update) => update_titration(&ctx, &mm, req.id, req.payload).await?,
+        (RT::Titration, Op::Insert) => insert_titration(&ctx, &mm, req.payload).await?,
+        (RT::Titration, Op::Delete) => delete_titration(&ctx, &mm, req.id).await?,
         (RT::Panel, Op::Get) => get_panels(&ctx, &mm, filter_json, lo).await?,
         (RT::Panel, Op::Update) => update_panel(&ctx, &mm, req.id, req.payload).await?,
         (RT::Panel, Op::Insert) => insert_panel(&ctx, &mm, req.payload).await?,
@@ -166,9 +208,38 @@ async fn api_post_json_handler(
         }
     };
     if should_rebuild_shadows {
-        state.registry.rebuild_all(&mm).await?;
+        // Inserts answer with the new row and the others act on `req.id`; a reorder copies
+        // the lot `req.id` into the new one it answers with.
+        let ids = if creates {
+            result["id"].as_i64().into_iter().collect::<Vec<_>>()
+        } else if reorders {
+            req.id.into_iter().chain(result["id"].as_i64()).collect()
+        } else {
+            req.id.into_iter().collect()
+        };
+        // Lets clients see whether searches on other instances include this write yet.
+        let version = db_version(&mm).await?;
+        match shadow_entity {
+            Some(entity) if !ids.is_empty() => {
+                for id in ids {
+                    state
+                        .registry
+                        .apply_change(&mm, ShadowChange { entity, id })
+                        .await?;
+                }
+                // The registry version moves past this write once the listener has applied
+                // its notifications.
+            }
+            // Nothing to patch in place; the debounced rebuild runs off the request.
+            _ => state.registry.schedule_rebuild(&mm),
+        }
+        let mut response = Json(result).into_response();
+        response
+            .headers_mut()
+            .insert(VERSION_HEADER, HeaderValue::from(version));
+        return Ok(response);
     }
-    Ok(Json(result))
+    Ok(Json(result).into_response())
 }
 
 async fn delete_clone(ctx: &Ctx, mm: &MM, id: Option<i64>) -> Result<serde_json::Value> {
@@ -249,8 +320,14 @@ async fn get_validations(
 ) -> Result<serde_json::Value> {
     let filters: Vec<ValidationFilter> = serde_json::from_value(filter_json)?;
     let total = ValidationBmc::count(ctx, mm, Some(filters.clone())).await?;
-    let mut ret = PaginatedResponse::<Validation>::from_lo(&lo, total);
-    ret.items = ValidationBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    let mut ret = PaginatedResponse::<Value>::from_lo(&lo, total);
+    let items = ValidationBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    ret.items = with_concentration(items, |validation: &Validation| {
+        Some((
+            validation.concentration_quantity()?,
+            ConversionContext::default(),
+        ))
+    })?;
     Ok(json!(ret))
 }
 
@@ -328,6 +405,7 @@ async fn insert_validation_file(
     if let Some(payload) = payload {
         let fc: ValidationFileForCreate = serde_json::from_value(payload)?;
         warn!("UPDATE: {fc:?}");
+        ValidationBmc::ensure_editable(mm, fc.validation_id).await?;
         id = ValidationFileBmc::create(ctx, mm, fc).await?;
     }
     Ok(json!({"id": id}))
@@ -336,6 +414,7 @@ async fn insert_validation_file(
 async fn delete_validation_file(ctx: &Ctx, mm: &MM, id: Option<i64>) -> Result<serde_json::Value> {
     if let Some(id) = id {
         let validation_file = ValidationFileBmc::get(ctx, mm, id).await?;
+        ValidationBmc::ensure_editable(mm, validation_file.validation_id).await?;
         let validation = ValidationBmc::get(ctx, mm, validation_file.validation_id).await?;
         let data_path = web_config()?.DATA_PATH.clone();
         let file_path = Utf8PathBuf::from(format!(
@@ -362,8 +441,19 @@ async fn get_panel_elements(
 ) -> Result<serde_json::Value> {
     let filters: Vec<PanelElementFilter> = serde_json::from_value(filter_json)?;
     let total = PanelElementBmc::count(ctx, mm, Some(filters.clone())).await?;
-    let mut ret = PaginatedResponse::<PanelElement>::from_lo(&lo, total);
-    ret.items = PanelElementBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    let mut ret = PaginatedResponse::<Value>::from_lo(&lo, total);
+    let items = PanelElementBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    let conjugate_ids: Vec<i64> = items.iter().map(|item| item.conjugate_id).collect();
+    let stocks = conjugate_stocks(ctx, mm, &conjugate_ids).await?;
+    ret.items = with_concentration(items, |element: &PanelElement| {
+        Some((
+            element.concentration_quantity()?,
+            ConversionContext {
+                stock_ug_per_ml: stocks.get(&element.conjugate_id).copied(),
+                ..Default::default()
+            },
+        ))
+    })?;
     Ok(json!(ret))
 }
 
@@ -395,13 +485,84 @@ async fn insert_panel_element(
 ) -> Result<serde_json::Value> {
     let mut id = 0;
     if let Some(payload) = payload {
-        let fc: PanelElementForCreate = payload.into();
+        let mut fc: PanelElementForCreate = payload.into();
+        if fc.concentration.is_none() {
+            fc.concentration =
+                suggested_panel_concentration(ctx, mm, fc.conjugate_id, fc.dilution_type).await?;
+        }
         warn!("UPDATE: {fc:?}");
         id = PanelElementBmc::create(ctx, mm, fc).await?;
     }
     Ok(json!({"id": id}))
 }
 
+/// Titration optimum expressed in the element's `dilution_type`.
+async fn suggested_panel_concentration(
+    ctx: &Ctx,
+    mm: &MM,
+    conjugate_id: i64,
+    dilution_type: i64,
+) -> Result<Option<f32>> {
+    let Some(optimum) = TitrationBmc::suggested_concentration(ctx, mm, conjugate_id).await? else {
+        return Ok(None);
+    };
+    let target = if dilution_type == DILUTION_TYPE_DILUTION {
+        ConcentrationUnit::Dilution
+    } else {
+        ConcentrationUnit::UgPerMl
+    };
+    if optimum.unit == target {
+        return Ok(Some(optimum.value as f32));
+    }
+
+    let conjugate = ConjugateBmc::get(ctx, mm, conjugate_id).await?;
+    let context = ConversionContext {
+        stock_ug_per_ml: conjugate.concentration,
+        ..Default::default()
+    };
+    Ok(optimum
+        .convert(target, &context)
+        .map(|quantity| quantity.value as f32))
+}
+
+async fn conjugate_stocks(ctx: &Ctx, mm: &MM, conjugate_ids: &[i64]) -> Result<HashMap<i64, f64>> {
+    if conjugate_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let filters: Vec<ConjugateFilter> = serde_json::from_value(json!([
+        {
+            "id": {"$in": conjugate_ids}
+        }
+    ]))?;
+    let conjugates = ConjugateBmc::list(ctx, mm, Some(filters), None).await?;
+    Ok(conjugates
+        .into_iter()
+        .filter_map(|conjugate| Some((conjugate.id, conjugate.concentration?)))
+        .collect())
+}
+
+/// Serializes items and adds the typed concentration next to the legacy fields.
+fn with_concentration<T: Serialize>(
+    items: Vec<T>,
+    quantity: impl Fn(&T) -> Option<(Quantity, ConversionContext)>,
+) -> Result<Vec<Value>> {
+    items
+        .into_iter()
+        .map(|item| {
+            let extra = quantity(&item);
+            let mut value = serde_json::to_value(item)?;
+            if let (Some((quantity, context)), Some(map)) = (extra, value.as_object_mut()) {
+                map.insert("concentrationQuantity".into(), json!(quantity));
+                map.insert("concentrationLabel".into(), json!(quantity.to_string()));
+                if let Some(ug_per_ml) = quantity.to_ug_per_ml(&context) {
+                    map.insert("concentrationUgPerMl".into(), json!(ug_per_ml));
+                }
+            }
+            Ok(value)
+        })
+        .collect()
+}
+
 async fn update_panel_element_values(
     _ctx: &Ctx,
     mm: &MM,
@@ -504,11 +665,23 @@ async fn update_panel(
                         )
                         .await?;
                     } else {
+                        let concentration = match element.concentration {
+                            Some(concentration) => Some(concentration as f32),
+                            None => {
+                                suggested_panel_concentration(
+                                    ctx,
+                                    mm,
+                                    *conjugate_id,
+                                    element.dilution_type,
+                                )
+                                .await?
+                            }
+                        };
                         let create = PanelElementForCreate {
                             panel_id: id,
                             conjugate_id: *conjugate_id,
                             dilution_type: element.dilution_type,
-                            concentration: element.concentration.map(|c| c as f32),
+                            concentration,
                         };
                         PanelElementBmc::create(ctx, mm, create).await?;
                     }
@@ -683,6 +856,53 @@ async fn insert_collection(
     Ok(json!({"id": id}))
 }
 
+async fn get_titrations(
+    ctx: &Ctx,
+    mm: &MM,
+    filter_json: Value,
+    lo: LO,
+) -> Result<serde_json::Value> {
+    let filters: Vec<TitrationFilter> = serde_json::from_value(filter_json)?;
+    let total = TitrationBmc::count(ctx, mm, Some(filters.clone())).await?;
+    let mut ret = PaginatedResponse::<Titration>::from_lo(&lo, total);
+    ret.items = TitrationBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    Ok(json!(ret))
+}
+
+async fn delete_titration(ctx: &Ctx, mm: &MM, id: Option<i64>) -> Result<serde_json::Value> {
+    if let Some(id) = id {
+        TitrationBmc::delete(ctx, mm, id).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn update_titration(
+    ctx: &Ctx,
+    mm: &MM,
+    id: Option<i64>,
+    payload: Option<Value>,
+) -> Result<serde_json::Value> {
+    if let (Some(id), Some(payload)) = (id, payload) {
+        let fu: TitrationForUpdate = payload.into();
+        warn!("UPDATE: {fu:?}");
+        TitrationBmc::update(ctx, mm, id, fu).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn insert_titration(ctx: &Ctx, mm: &MM, payload: Option<Value>) -> Result<serde_json::Value> {
+    let mut id = 0;
+    if let Some(payload) = payload {
+        let mut fc: TitrationForCreate = payload.into();
+        if fc.created_by == 0 {
+            fc.created_by = get_member_id(ctx, mm, fc.group_id, ctx.user_id()).await?;
+        }
+        warn!("UPDATE: {fc:?}");
+        id = TitrationBmc::create(ctx, mm, fc).await?;
+    }
+    Ok(json!({"id": id}))
+}
+
 async fn get_tags(ctx: &Ctx, mm: &MM, filter_json: Value, lo: LO) -> Result<serde_json::Value> {
     let filters: Vec<TagFilter> = serde_json::from_value(filter_json)?;
     let total = TagBmc::count(ctx, mm, Some(filters.clone())).await?;
@@ -730,8 +950,14 @@ async fn get_conjugates(
 ) -> Result<serde_json::Value> {
     let filters: Vec<ConjugateFilter> = serde_json::from_value(filter_json)?;
     let total = ConjugateBmc::count(ctx, mm, Some(filters.clone())).await?;
-    let mut ret = PaginatedResponse::<Conjugate>::from_lo(&lo, total);
-    ret.items = ConjugateBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    let mut ret = PaginatedResponse::<Value>::from_lo(&lo, total);
+    let items = ConjugateBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    ret.items = with_concentration(items, |conjugate: &Conjugate| {
+        Some((
+            Quantity::ug_per_ml(conjugate.concentration?),
+            ConversionContext::default(),
+        ))
+    })?;
     Ok(json!(ret))
 }
 
@@ -766,6 +992,116 @@ async fn insert_conjugate(ctx: &Ctx, mm: &MM, payload: Option<Value>) -> Result<
     Ok(json!({"id": id}))
 }
 
+async fn get_conjugation_batches(
+    ctx: &Ctx,
+    mm: &MM,
+    filter_json: Value,
+    lo: LO,
+) -> Result<serde_json::Value> {
+    let filters: Vec<ConjugationBatchFilter> = serde_json::from_value(filter_json)?;
+    let total = ConjugationBatchBmc::count(ctx, mm, Some(filters.clone())).await?;
+    let mut ret = PaginatedResponse::<ConjugationBatch>::from_lo(&lo, total);
+    ret.items = ConjugationBatchBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    Ok(json!(ret))
+}
+
+async fn delete_conjugation_batch(
+    ctx: &Ctx,
+    mm: &MM,
+    id: Option<i64>,
+) -> Result<serde_json::Value> {
+    if let Some(id) = id {
+        ConjugationBatchBmc::delete(ctx, mm, id).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn update_conjugation_batch(
+    ctx: &Ctx,
+    mm: &MM,
+    id: Option<i64>,
+    payload: Option<Value>,
+) -> Result<serde_json::Value> {
+    if let (Some(id), Some(payload)) = (id, payload) {
+        let fu: ConjugationBatchForUpdate = payload.into();
+        warn!("UPDATE: {fu:?}");
+        ConjugationBatchBmc::update(ctx, mm, id, fu).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn insert_conjugation_batch(
+    ctx: &Ctx,
+    mm: &MM,
+    payload: Option<Value>,
+) -> Result<serde_json::Value> {
+    let mut id = 0;
+    if let Some(payload) = payload {
+        let mut fc: ConjugationBatchForCreate = payload.into();
+        if fc.created_by == 0 {
+            fc.created_by = get_member_id(ctx, mm, fc.group_id, ctx.user_id()).await?;
+        }
+        warn!("UPDATE: {fc:?}");
+        id = ConjugationBatchBmc::create(ctx, mm, fc).await?;
+    }
+    Ok(json!({"id": id}))
+}
+
+async fn get_protocol_templates(
+    ctx: &Ctx,
+    mm: &MM,
+    filter_json: Value,
+    lo: LO,
+) -> Result<serde_json::Value> {
+    let filters: Vec<ProtocolTemplateFilter> = serde_json::from_value(filter_json)?;
+    let total = ProtocolTemplateBmc::count(ctx, mm, Some(filters.clone())).await?;
+    let mut ret = PaginatedResponse::<ProtocolTemplate>::from_lo(&lo, total);
+    ret.items = ProtocolTemplateBmc::list(ctx, mm, Some(filters), Some(lo)).await?;
+    Ok(json!(ret))
+}
+
+async fn delete_protocol_template(
+    ctx: &Ctx,
+    mm: &MM,
+    id: Option<i64>,
+) -> Result<serde_json::Value> {
+    if let Some(id) = id {
+        ProtocolTemplateBmc::delete(ctx, mm, id).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn update_protocol_template(
+    ctx: &Ctx,
+    mm: &MM,
+    id: Option<i64>,
+    payload: Option<Value>,
+) -> Result<serde_json::Value> {
+    if let (Some(id), Some(payload)) = (id, payload) {
+        let fu: ProtocolTemplateForUpdate = payload.into();
+        warn!("UPDATE: {fu:?}");
+        ProtocolTemplateBmc::update(ctx, mm, id, fu).await?;
+    }
+    Ok(json!({}))
+}
+
+async fn insert_protocol_template(
+    ctx: &Ctx,
+    mm: &MM,
+    payload: Option<Value>,
+) -> Result<serde_json::Value> {
+    let mut id = 0;
+    if let Some(payload) = payload {
+        let mut fc: ProtocolTemplateForCreate = payload.into();
+        if fc.created_by == 0 {
+            fc.created_by = get_member_id(ctx, mm, fc.group_id, ctx.user_id()).await?;
+        }
+        warn!("UPDATE: {fc:?}");
+        id = ProtocolTemplateBmc::create(ctx, mm, fc).await?;
+    }
+    Ok(json!({"id": id}))
+}
+
 async fn get_lots(ctx: &Ctx, mm: &MM, filter_json: Value, lo: LO) -> Result<serde_json::Value> {
     let filters: Vec<LotFilter> = serde_json::from_value(filter_json)?;
     let total = LotBmc::count(ctx, mm, Some(filters.clone())).await?;
@@ -1114,13 +1450,44 @@ pub enum ReturnType {
     Lot,
     Tag,
     Conjugate,
+    ConjugationBatch,
     Panel,
     PanelElement,
+    ProtocolTemplate,
     Validation,
     ValidationFile,
     Provider,
     Storage,
     Collection,
+    Titration,
+}
+
+impl ReturnType {
+    /// The entity whose search shadow rows a write of this type changes, if any.
+    fn shadow_entity(&self) -> Option<ShadowEntity> {
+        let entity = match self {
+            Self::User => ShadowEntity::User,
+            Self::Member => ShadowEntity::Member,
+            Self::Group => ShadowEntity::Group,
+            Self::Clone => ShadowEntity::Clone,
+            Self::Species => ShadowEntity::Species,
+            Self::Protein => ShadowEntity::Protein,
+            Self::Lot => ShadowEntity::Lot,
+            Self::Tag => ShadowEntity::Tag,
+            Self::Conjugate => ShadowEntity::Conjugate,
+            Self::Panel => ShadowEntity::Panel,
+            Self::PanelElement => ShadowEntity::PanelElement,
+            Self::Validation => ShadowEntity::Validation,
+            Self::Provider => ShadowEntity::Provider,
+            Self::Storage => ShadowEntity::Storage,
+            Self::Collection => ShadowEntity::Collection,
+            Self::ConjugationBatch
+            | Self::ProtocolTemplate
+            | Self::ValidationFile
+            | Self::Titration => return None,
+        };
+        Some(entity)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -1136,6 +1503,7 @@ mod tests {
     use airlab_lib::ctx::Ctx;
     use airlab_lib::model::collection::CollectionBmc;
     use airlab_lib::model::storage::{StorageBmc, StorageForCreate};
+    use airlab_lib::model::titration::{TitrationBmc, TitrationForCreate};
     use tower::ServiceExt;
 
     type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -1253,6 +1621,7 @@ mod tests {
             },
         )
         .await?;
+        let created = db_version(&mm).await?;
         let app = crate::web::test_support::authed_router(routes(
             crate::search_shadow::SearchState::new((*mm).clone()),
         ));
@@ -1280,6 +1649,13 @@ mod tests {
             .await?;
 
         assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let version: i64 = response
+            .headers()
+            .get(VERSION_HEADER)
+            .ok_or("search version header")?
+            .to_str()?
+            .parse()?;
+        assert!(version > created);
         let storage = StorageBmc::get(&ctx, &mm, id).await?;
         assert_eq!(storage.name, "json-storage-after");
         assert_eq!(storage.location, "Room B");
@@ -1288,4 +1664,171 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn json_route_prefills_panel_element_concentration_from_titration() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        TitrationBmc::create(
+            &ctx,
+            &mm,
+            TitrationForCreate {
+                group_id: 1000,
+                conjugate_id: 1008,
+                validation_id: None,
+                created_by: 1303,
+                points: json!([
+                    {"concentration": 0.5, "signal": 4.0, "noise": 1.0},
+                    {"concentration": 2.0, "signal": 10.0, "noise": 1.0}
+                ]),
+                optimal_concentration: Some(1.5),
+                note: None,
+            },
+        )
+        .await?;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "operation": "Insert",
+            "return_type": "PanelElement",
+            "payload": {
+                "panelId": 1009,
+                "conjugateId": 1008,
+                "dilutionType": 0
+            }
+        });
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/json")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from(request.to_string()))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let value: serde_json::Value = serde_json::from_str(&body)?;
+        let id = value["id"]
+            .as_i64()
+            .ok_or_else(|| std::io::Error::other("insert should return id"))?;
+        let element = PanelElementBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(element.concentration, Some(1.5));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json_route_creates_validation_from_protocol_template() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let post = |request: Value| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/api/v1/json")
+                .header(axum::http::header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(request.to_string()))
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post(json!({
+                "operation": "Insert",
+                "return_type": "ProtocolTemplate",
+                "filters": [],
+                "payload": {
+                    "groupId": 1,
+                    "name": "FC surface",
+                    "application": 2,
+                    "surfaceStaining": true,
+                    "incubationConditions": "30 min on ice"
+                }
+            }))?)
+            .await?;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let template_id = serde_json::from_str::<Value>(&body)?["id"].clone();
+
+        let response = app
+            .clone()
+            .oneshot(post(json!({
+                "operation": "Insert",
+                "return_type": "Validation",
+                "filters": [],
+                "payload": {
+                    "groupId": 1,
+                    "createdBy": 1,
+                    "cloneId": 3124,
+                    "protocolTemplateId": template_id
+                }
+            }))?)
+            .await?;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+        let response = app
+            .oneshot(post(json!({
+                "operation": "Get",
+                "return_type": "Validation",
+                "filters": [
+                    { "field": "protocol_template_id", "op": "eq", "value": template_id }
+                ],
+                "page": 1,
+                "limit": 10
+            }))?)
+            .await?;
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let value: Value = serde_json::from_str(&body)?;
+        assert_eq!(value["total"], 1);
+        let item = &value["items"][0];
+        assert_eq!(item["application"], 2);
+        assert_eq!(item["methanolStaining"], true);
+        assert_eq!(item["incubationConditions"], "30 min on ice");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json_route_annotates_validation_concentration_quantity() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        ValidationBmc::update(&ctx, &mm, 1011, json!({"concentration": "1:200"}).into()).await?;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "operation": "Get",
+            "return_type": "Validation",
+            "filters": [
+                { "field": "id", "op": "eq", "value": 1011 }
+            ],
+            "page": 1,
+            "limit": 10
+        });
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/json")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from(request.to_string()))?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let value: serde_json::Value = serde_json::from_str(&body)?;
+        let item = &value["items"][0];
+        assert_eq!(item["concentration"], "200");
+        assert_eq!(item["concentrationUnit"], "dilution");
+        assert_eq!(item["concentrationLabel"], "1:200");
+        assert_eq!(item["concentrationQuantity"]["unit"], "dilution");
+
+        Ok(())
+    }
 }
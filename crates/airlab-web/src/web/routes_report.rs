@@ -0,0 +1,370 @@
+use crate::blob_store::BlobState;
+use crate::web::dossier::{self, FileContents};
+use crate::web::mw_auth::CtxW;
+use crate::web::routes_validation_file::validation_file_key;
+use crate::web::{Error, Result};
+use airlab_lib::model::clone_application::Application;
+use airlab_lib::model::clone_dossier::CloneDossierBmc;
+use airlab_lib::model::cross_reactivity::CrossReactivityBmc;
+use airlab_lib::model::validation_consensus::ValidationConsensusBmc;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderValue, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+#[allow(unused_imports)]
+use tracing::{debug, warn};
+
+pub fn routes(state: BlobState) -> Router {
+    Router::new()
+        .route(
+            "/api/v1/groups/{group_id}/reports/cross_reactivity",
+            get(api_cross_reactivity_handler),
+        )
+        .route(
+            "/api/v1/groups/{group_id}/reports/validation_consensus",
+            get(api_validation_consensus_handler),
+        )
+        .route(
+            "/api/v1/clones/{clone_id}/dossier",
+            get(api_clone_dossier_handler),
+        )
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossReactivityParams {
+    /// Application id or label, e.g. `1` or `IMC`.
+    application: Option<String>,
+    /// `json` (default) or `csv`.
+    format: Option<String>,
+}
+
+async fn api_cross_reactivity_handler(
+    State(BlobState { mm, .. }): State<BlobState>,
+    ctx: CtxW,
+    Path(group_id): Path<i64>,
+    Query(params): Query<CrossReactivityParams>,
+) -> Result<Response> {
+    debug!(
+        "HANDLER - api_cross_reactivity_handler: {} {:?}",
+        group_id, params
+    );
+
+    let application = parse_application(params.application.as_deref())?;
+    let matrix = CrossReactivityBmc::matrix(&ctx.0, &mm, group_id, application).await?;
+
+    match params.format.as_deref() {
+        None | Some("json") => Ok(Json(json!(matrix)).into_response()),
+        Some("csv") => Ok((
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/csv; charset=utf-8"),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_static("attachment; filename=\"cross_reactivity.csv\""),
+                ),
+            ],
+            matrix.to_csv(),
+        )
+            .into_response()),
+        Some(other) => Err(Error::BadRequest(format!("unsupported format: {other}"))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsensusParams {
+    application: Option<String>,
+}
+
+async fn api_validation_consensus_handler(
+    State(BlobState { mm, .. }): State<BlobState>,
+    ctx: CtxW,
+    Path(group_id): Path<i64>,
+    Query(params): Query<ConsensusParams>,
+) -> Result<Json<Value>> {
+    debug!(
+        "HANDLER - api_validation_consensus_handler: {} {:?}",
+        group_id, params
+    );
+
+    let application = parse_application(params.application.as_deref())?;
+    let mut consensus = ValidationConsensusBmc::list_for_group(&ctx.0, &mm, group_id).await?;
+    if let Some(application) = application {
+        consensus.retain(|item| item.application == application.id());
+    }
+    consensus.sort_by(|left, right| {
+        left.application
+            .cmp(&right.application)
+            .then(right.score.total_cmp(&left.score))
+            .then(left.clone_id.cmp(&right.clone_id))
+    });
+
+    Ok(Json(json!(consensus)))
+}
+
+/// Images larger than this are listed by name instead of being embedded in a dossier.
+const MAX_DOSSIER_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct DossierParams {
+    application: Option<String>,
+    /// `html` (default) or `pdf`.
+    format: Option<String>,
+}
+
+async fn api_clone_dossier_handler(
+    State(BlobState { mm, store }): State<BlobState>,
+    ctx: CtxW,
+    Path(clone_id): Path<i64>,
+    Query(params): Query<DossierParams>,
+) -> Result<Response> {
+    debug!(
+        "HANDLER - api_clone_dossier_handler: {} {:?}",
+        clone_id, params
+    );
+
+    let application = parse_application(params.application.as_deref())?;
+    let format = params.format.as_deref().unwrap_or("html");
+    if !matches!(format, "html" | "pdf") {
+        return Err(Error::BadRequest(format!("unsupported format: {format}")));
+    }
+    let dossier = CloneDossierBmc::load(&ctx.0, &mm, clone_id, application).await?;
+
+    let mut files = FileContents::new();
+    for item in &dossier.validations {
+        for file in item.files.iter().filter(|file| dossier::is_image(file)) {
+            let key = validation_file_key(&item.validation, file);
+            let readable = store
+                .backend()
+                .head(&key)
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|meta| meta.size <= MAX_DOSSIER_IMAGE_BYTES);
+            if !readable {
+                warn!("Dossier image not embedded: {}", key);
+                continue;
+            }
+            match store.backend().get(&key, None).await {
+                Ok(Some(bytes)) => {
+                    files.insert(file.id, bytes);
+                }
+                Ok(None) => warn!("Dossier image vanished: {}", key),
+                Err(err) => warn!("Cannot read dossier image {}: {}", key, err),
+            }
+        }
+    }
+
+    if format == "pdf" {
+        let disposition = format!("attachment; filename=\"clone_{clone_id}_dossier.pdf\"");
+        let disposition = HeaderValue::from_str(&disposition)
+            .map_err(|err| Error::BadRequest(err.to_string()))?;
+        return Ok((
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/pdf"),
+                ),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            dossier::render_pdf(&dossier, &files),
+        )
+            .into_response());
+    }
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        )],
+        dossier::render_html(&dossier, &files),
+    )
+        .into_response())
+}
+
+fn parse_application(raw: Option<&str>) -> Result<Option<Application>> {
+    match raw.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(raw) => Application::parse_str(raw)
+            .map(Some)
+            .ok_or_else(|| Error::BadRequest(format!("unknown application: {raw}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use airlab_lib::ctx::Ctx;
+    use airlab_lib::model::ModelManager;
+    use airlab_lib::model::validation::ValidationBmc;
+    use axum::http::StatusCode;
+    use tower::ServiceExt;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Creates a validation in group 1000 and approves it, as only approved ones count.
+    async fn approved_validation(
+        mm: &ModelManager,
+        clone_id: i64,
+        species_id: i64,
+        application: i64,
+        status: i64,
+    ) -> TestResult {
+        let ctx = Ctx::root_ctx();
+        let id = ValidationBmc::create(
+            &ctx,
+            mm,
+            json!({
+                "groupId": 1000,
+                "createdBy": 1303,
+                "cloneId": clone_id,
+                "speciesId": species_id,
+                "application": application,
+                "status": status
+            })
+            .into(),
+        )
+        .await?;
+        sqlx::query(
+            "UPDATE validation SET review_state = 'approved', reviewed_by = 1304 WHERE id = $1",
+        )
+        .bind(id)
+        .execute(mm.db())
+        .await?;
+        Ok(())
+    }
+
+    async fn get(app: Router, uri: &str) -> TestResult<Response> {
+        Ok(app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())?,
+            )
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn cross_reactivity_route_returns_json_matrix() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        approved_validation(&mm, 1006, 1004, 1, 1).await?;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = get(app, "/api/v1/groups/1000/reports/cross_reactivity").await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let value: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(value["species"][0]["name"], "Mouse");
+        let seed = value["rows"]
+            .as_array()
+            .and_then(|rows| rows.iter().find(|row| row["cloneId"] == 1006))
+            .ok_or("seed clone should be listed")?;
+        assert_eq!(seed["cells"][0], "validated_positive");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cross_reactivity_route_exports_csv_for_application() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        approved_validation(&mm, 1016, 1014, 2, 2).await?;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = get(
+            app.clone(),
+            "/api/v1/groups/1000/reports/cross_reactivity?application=FC&format=csv",
+        )
+        .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/csv; charset=utf-8"))
+        );
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "protein,clone,clone_id,Mouse,Rat");
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(",1016,untested,validated_negative"));
+
+        let response = get(
+            app,
+            "/api/v1/groups/1000/reports/cross_reactivity?application=XYZ",
+        )
+        .await?;
+        assert!(!response.status().is_success());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validation_consensus_route_lists_scores_for_application() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        approved_validation(&mm, 1006, 1004, 1, 1).await?;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = get(
+            app,
+            "/api/v1/groups/1000/reports/validation_consensus?application=IMC",
+        )
+        .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let value: serde_json::Value = serde_json::from_str(&body)?;
+        let scores = value.as_array().ok_or("scores should be a list")?;
+        assert!(scores.iter().all(|score| score["application"] == 1));
+        let seed = scores
+            .iter()
+            .find(|score| score["cloneId"] == 1006)
+            .ok_or("seed clone should be scored")?;
+        assert_eq!(seed["score"], 0.5);
+        assert_eq!(seed["status"], 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn clone_dossier_route_renders_html_and_pdf() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = get(app.clone(), "/api/v1/clones/1006/dossier").await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/html; charset=utf-8"))
+        );
+        let body = crate::web::test_support::response_body_string(response).await?;
+        assert!(body.starts_with("<!DOCTYPE html>"));
+        assert!(body.contains("Validation 1011"));
+        assert!(body.contains("<th>Host</th><td>Mouse</td>"));
+
+        let response = get(app.clone(), "/api/v1/clones/1006/dossier?format=pdf").await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/pdf"))
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        assert!(bytes.starts_with(b"%PDF-"));
+
+        let response = get(app.clone(), "/api/v1/clones/1006/dossier?application=WB").await?;
+        let body = crate::web::test_support::response_body_string(response).await?;
+        assert!(body.contains("No validations recorded."));
+
+        let response = get(app, "/api/v1/clones/1006/dossier?format=docx").await?;
+        assert!(!response.status().is_success());
+
+        Ok(())
+    }
+}
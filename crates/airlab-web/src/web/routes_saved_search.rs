@@ -0,0 +1,569 @@
+use crate::search_shadow::SearchState;
+use crate::search_shadow::notify::db_version;
+use crate::search_shadow::query;
+use crate::web::mw_auth::CtxW;
+use crate::web::routes_search::{
+    Filter, RpcSearchRequest, get_member_id, mw_search_version, search,
+};
+use crate::web::{Error, Result};
+use airlab_lib::ctx::Ctx;
+use airlab_lib::model::saved_search::{
+    SavedSearch, SavedSearchBmc, SavedSearchForCreate, SavedSearchForUpdate,
+};
+use axum::extract::{Json as eJson, Path, Query, State};
+use axum::middleware;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+/// Page size that takes every match of a saved request at once.
+const ALL_MATCHES: i64 = u32::MAX as i64;
+
+/// Matches of saved searches by id, each as of the database version and shadow revision it
+/// was searched at, so checking for notifications only searches again after a write.
+#[derive(Clone, Default)]
+pub struct MatchCache(Arc<Mutex<HashMap<i64, CachedMatches>>>);
+
+struct CachedMatches {
+    seen_at: (i64, u64),
+    request: Value,
+    ids: Arc<Vec<i64>>,
+}
+
+impl MatchCache {
+    fn get(&self, saved: &SavedSearch, seen_at: (i64, u64)) -> Option<Arc<Vec<i64>>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(&saved.id)
+            .filter(|cached| cached.seen_at == seen_at && cached.request == saved.request)
+            .map(|cached| cached.ids.clone())
+    }
+
+    fn insert(&self, saved: &SavedSearch, seen_at: (i64, u64), ids: Arc<Vec<i64>>) {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(
+                saved.id,
+                CachedMatches {
+                    seen_at,
+                    request: saved.request.clone(),
+                    ids,
+                },
+            );
+    }
+
+    fn remove(&self, search_id: i64) {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .remove(&search_id);
+    }
+}
+
+pub fn routes(state: SearchState) -> Router {
+    Router::new()
+        .route(
+            "/api/v1/search/saved",
+            get(api_saved_searches_handler).post(api_post_saved_search_handler),
+        )
+        .route(
+            "/api/v1/search/saved/notifications",
+            get(api_saved_search_notifications_handler),
+        )
+        .route(
+            "/api/v1/search/saved/{search_id}",
+            get(api_saved_search_handler)
+                .patch(api_patch_saved_search_handler)
+                .delete(api_delete_saved_search_handler),
+        )
+        .route(
+            "/api/v1/search/saved/{search_id}/run",
+            post(api_post_saved_search_run_handler),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            mw_search_version,
+        ))
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct SavedSearchesParams {
+    group_id: Option<i64>,
+}
+
+/// Overrides of the saved request's pagination.
+#[derive(Debug, Deserialize)]
+struct RunParams {
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// The user's own saved searches and those shared with the user's groups.
+async fn api_saved_searches_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    Query(params): Query<SavedSearchesParams>,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    let searches =
+        SavedSearchBmc::list_visible(&ctx, &state.mm, ctx.user_id(), params.group_id).await?;
+    Ok(Json(json!(searches)))
+}
+
+async fn api_saved_search_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    Path(search_id): Path<i64>,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    let saved = SavedSearchBmc::get_visible(&ctx, &state.mm, search_id, ctx.user_id()).await?;
+    Ok(Json(json!(saved)))
+}
+
+async fn api_post_saved_search_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    eJson(payload): eJson<Value>,
+) -> Result<Json<Value>> {
+    debug!("HANDLER - api_post_saved_search_handler: {:?}", payload);
+    let ctx = ctx.0;
+    let mut search_c = SavedSearchForCreate::from(payload);
+    if search_c.name.trim().is_empty() {
+        return Err(Error::BadRequest("saved search needs a name".to_string()));
+    }
+    parse_request(&search_c.request, search_c.group_id)?;
+    search_c.created_by = member_id(&ctx, &state, search_c.group_id).await?;
+    let id = SavedSearchBmc::create(&ctx, &state.mm, search_c).await?;
+    Ok(Json(json!({"id": id})))
+}
+
+async fn api_patch_saved_search_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    Path(search_id): Path<i64>,
+    eJson(payload): eJson<Value>,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    let saved = own_saved_search(&ctx, &state, search_id).await?;
+    let search_u = SavedSearchForUpdate::from(payload);
+    if let Some(request) = &search_u.request {
+        parse_request(request, saved.group_id)?;
+    }
+    SavedSearchBmc::update(&ctx, &state.mm, search_id, search_u).await?;
+    Ok(Json(json!({})))
+}
+
+async fn api_delete_saved_search_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    Path(search_id): Path<i64>,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    own_saved_search(&ctx, &state, search_id).await?;
+    SavedSearchBmc::delete(&ctx, &state.mm, search_id).await?;
+    state.saved_matches.remove(search_id);
+    Ok(Json(json!({})))
+}
+
+/// The search response of the saved request. Runs of a notifying search by its creator also
+/// list the items that started matching since the previous such run under `new_ids`.
+async fn api_post_saved_search_run_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    Path(search_id): Path<i64>,
+    Query(params): Query<RunParams>,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    let saved = SavedSearchBmc::get_visible(&ctx, &state.mm, search_id, ctx.user_id()).await?;
+    let mut req = parse_request(&saved.request, saved.group_id)?;
+    req.page = params.page.or(req.page);
+    req.limit = params.limit.or(req.limit);
+    let Json(mut response) = search(&state, &ctx, req).await?;
+
+    if saved.notify && member_id(&ctx, &state, saved.group_id).await? == saved.created_by {
+        let ids = matching_ids(&state, &ctx, &saved).await?;
+        let new_ids = SavedSearchBmc::record_run(&ctx, &state.mm, saved.id, &ids).await?;
+        response["new_ids"] = json!(new_ids);
+    }
+    Ok(Json(response))
+}
+
+/// The user's notifying searches that match items they did not when last run. Checking
+/// does not count as a run, so a search stays listed until it is run. Matches are only
+/// searched again after a write; see [`MatchCache`].
+async fn api_saved_search_notifications_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+) -> Result<Json<Value>> {
+    let ctx = ctx.0;
+    let searches = SavedSearchBmc::list_visible(&ctx, &state.mm, ctx.user_id(), None).await?;
+    let mut member_ids = HashMap::new();
+    let mut notifications = vec![];
+    for saved in searches.into_iter().filter(|saved| saved.notify) {
+        let member_id = match member_ids.get(&saved.group_id) {
+            Some(member_id) => *member_id,
+            None => {
+                let member_id = member_id(&ctx, &state, saved.group_id).await?;
+                member_ids.insert(saved.group_id, member_id);
+                member_id
+            }
+        };
+        if member_id != saved.created_by {
+            continue;
+        }
+        let new_ids = saved.new_matches(&matching_ids(&state, &ctx, &saved).await?);
+        if !new_ids.is_empty() {
+            notifications.push(json!({
+                "id": saved.id,
+                "group_id": saved.group_id,
+                "name": saved.name,
+                "new_ids": new_ids,
+            }));
+        }
+    }
+    Ok(Json(json!(notifications)))
+}
+
+/// A stored request as the search API takes it, limited to the saved search's group;
+/// rejected when it would not run or filters on another group.
+fn parse_request(request: &Value, group_id: i64) -> Result<RpcSearchRequest> {
+    let mut req: RpcSearchRequest = serde_json::from_value(request.clone())
+        .map_err(|err| Error::BadRequest(format!("invalid search request: {err}")))?;
+    query::parse(req.global_filter.as_deref().unwrap_or_default())?;
+
+    let group_filters = req
+        .filters
+        .iter()
+        .filter(|filter| filter.field == "group_id")
+        .collect::<Vec<_>>();
+    if group_filters
+        .iter()
+        .any(|filter| filter.not || filter.op != "eq" || filter.value.as_i64() != Some(group_id))
+    {
+        return Err(Error::BadRequest(format!(
+            "saved search of group {group_id} may only filter on its own group"
+        )));
+    }
+    if group_filters.is_empty() {
+        req.filters.push(Filter {
+            table: req.return_type,
+            field: "group_id".to_string(),
+            op: "eq".to_string(),
+            value: json!(group_id),
+            not: false,
+        });
+    }
+    Ok(req)
+}
+
+/// The ids of all items the saved request matches, sorted by id whatever its order.
+async fn matching_ids(
+    state: &SearchState,
+    ctx: &Ctx,
+    saved: &SavedSearch,
+) -> Result<Arc<Vec<i64>>> {
+    // Read before searching, so a write during the search is searched for again.
+    let seen_at = (db_version(&state.mm).await?, state.registry.revision());
+    if let Some(ids) = state.saved_matches.get(saved, seen_at) {
+        return Ok(ids);
+    }
+
+    let mut req = parse_request(&saved.request, saved.group_id)?;
+    req.page = Some(1);
+    req.limit = Some(ALL_MATCHES);
+    req.fields = None;
+    req.expand.clear();
+    req.facets.clear();
+    let Json(response) = search(state, ctx, req).await?;
+    let mut ids: Vec<i64> = response["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(Value::as_i64).collect())
+        .unwrap_or_default();
+    ids.sort_unstable();
+    let ids = Arc::new(ids);
+    state.saved_matches.insert(saved, seen_at, ids.clone());
+    Ok(ids)
+}
+
+async fn member_id(ctx: &Ctx, state: &SearchState, group_id: i64) -> Result<i64> {
+    match get_member_id(ctx, &state.mm, group_id, ctx.user_id()).await? {
+        0 => Err(Error::BadRequest(format!(
+            "not a member of group {group_id}"
+        ))),
+        member_id => Ok(member_id),
+    }
+}
+
+/// Only the member who saved a search may change or delete it.
+async fn own_saved_search(ctx: &Ctx, state: &SearchState, search_id: i64) -> Result<SavedSearch> {
+    let saved = SavedSearchBmc::get_visible(ctx, &state.mm, search_id, ctx.user_id()).await?;
+    if member_id(ctx, state, saved.group_id).await? != saved.created_by {
+        return Err(Error::BadRequest(format!(
+            "saved search {search_id} belongs to another member"
+        )));
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> TestResult<Value> {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(axum::http::header::CONTENT_TYPE, "application/json");
+        let response = app
+            .clone()
+            .oneshot(request.body(axum::body::Body::from(
+                body.map(|body| body.to_string()).unwrap_or_default(),
+            ))?)
+            .await?;
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::OK,
+            "{method} {uri}"
+        );
+        let body = crate::web::test_support::response_body_string(response).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    fn ids(value: &Value) -> Vec<i64> {
+        value
+            .as_array()
+            .map(|items| items.iter().filter_map(Value::as_i64).collect())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn saved_search_route_runs_and_reports_new_matches() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let state = SearchState::new((*mm).clone());
+        let app = crate::web::test_support::authed_router(
+            routes(state.clone()).merge(crate::web::routes_json::routes(state)),
+        );
+        let insert_lot = |name: &str| {
+            json!({
+                "operation": "Insert",
+                "return_type": "Lot",
+                "filters": [],
+                "payload": {
+                    "name": name,
+                    "groupId": 1,
+                    "cloneId": 3123,
+                    "createdBy": 1,
+                    "status": 0,
+                    "isArchived": false
+                }
+            })
+        };
+        let first = send(
+            &app,
+            "POST",
+            "/api/v1/json",
+            Some(insert_lot("watched-lot first")),
+        )
+        .await?["id"]
+            .as_i64()
+            .unwrap_or_default();
+
+        let created = send(
+            &app,
+            "POST",
+            "/api/v1/search/saved",
+            Some(json!({
+                "groupId": 1,
+                "name": "watched lots",
+                "notify": true,
+                "request": {
+                    "return_type": "Lot",
+                    "filters": [
+                        {"table": "Lot", "field": "name", "op": "contains", "value": "watched-lot"}
+                    ],
+                    "order": {"table": "Lot", "field": "id", "direction": "asc"},
+                    "fields": ["name"],
+                    "page": 1,
+                    "limit": 10
+                }
+            })),
+        )
+        .await?;
+        let run_uri = format!("/api/v1/search/saved/{}/run", created["id"]);
+
+        let response = send(&app, "POST", &run_uri, None).await?;
+        assert_eq!(
+            response["items"],
+            json!([{"id": first, "name": "watched-lot first"}])
+        );
+        assert_eq!(response["new_ids"], json!([]));
+
+        let second = send(
+            &app,
+            "POST",
+            "/api/v1/json",
+            Some(insert_lot("watched-lot second")),
+        )
+        .await?["id"]
+            .as_i64()
+            .unwrap_or_default();
+        let notifications = send(&app, "GET", "/api/v1/search/saved/notifications", None).await?;
+        assert_eq!(notifications[0]["id"], created["id"]);
+        assert_eq!(ids(&notifications[0]["new_ids"]), vec![second]);
+
+        let response = send(&app, "POST", &format!("{run_uri}?limit=1"), None).await?;
+        assert_eq!(response["search_total"], 2);
+        assert_eq!(response["items"].as_array().map(Vec::len), Some(1));
+        assert_eq!(ids(&response["new_ids"]), vec![second]);
+        let notifications = send(&app, "GET", "/api/v1/search/saved/notifications", None).await?;
+        assert_eq!(notifications, json!([]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn saved_search_matches_are_sorted_by_id_and_cached_until_a_write() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let state = SearchState::new((*mm).clone());
+        let app = crate::web::test_support::authed_router(
+            routes(state.clone()).merge(crate::web::routes_json::routes(state.clone())),
+        );
+        let mut lots = vec![];
+        for name in ["sorted-lot a", "sorted-lot b"] {
+            let insert = json!({
+                "operation": "Insert",
+                "return_type": "Lot",
+                "filters": [],
+                "payload": {
+                    "name": name,
+                    "groupId": 1,
+                    "cloneId": 3123,
+                    "createdBy": 1,
+                    "status": 0,
+                    "isArchived": false
+                }
+            });
+            let created = send(&app, "POST", "/api/v1/json", Some(insert)).await?;
+            lots.push(created["id"].as_i64().unwrap_or_default());
+        }
+        let created = send(
+            &app,
+            "POST",
+            "/api/v1/search/saved",
+            Some(json!({
+                "groupId": 1,
+                "name": "sorted lots",
+                "notify": true,
+                "request": {
+                    "return_type": "Lot",
+                    "filters": [
+                        {"table": "Lot", "field": "name", "op": "contains", "value": "sorted-lot"}
+                    ],
+                    "order": {"table": "Lot", "field": "id", "direction": "desc"},
+                    "limit": 1
+                }
+            })),
+        )
+        .await?;
+        let search_id = created["id"].as_i64().unwrap_or_default();
+        let run_uri = format!("/api/v1/search/saved/{search_id}/run");
+
+        let response = send(&app, "POST", &run_uri, None).await?;
+        assert_eq!(ids(&response["items"]), vec![lots[1]]);
+        let saved = SavedSearchBmc::get(&ctx, &mm, search_id).await?;
+        assert_eq!(saved.matched_ids, lots);
+
+        let cached = matching_ids(&state, &ctx, &saved).await?;
+        assert!(Arc::ptr_eq(
+            &cached,
+            &matching_ids(&state, &ctx, &saved).await?
+        ));
+        sqlx::query("UPDATE public.lot SET name = 'sorted-lot c' WHERE id = $1")
+            .bind(lots[0])
+            .execute(mm.db())
+            .await?;
+        assert!(!Arc::ptr_eq(
+            &cached,
+            &matching_ids(&state, &ctx, &saved).await?
+        ));
+
+        send(
+            &app,
+            "DELETE",
+            &format!("/api/v1/search/saved/{search_id}"),
+            None,
+        )
+        .await?;
+        assert!(state.saved_matches.get(&saved, (i64::MAX, 0)).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_request_limits_requests_to_the_group() -> TestResult {
+        let req = parse_request(&json!({"return_type": "Lot"}), 1)?;
+        assert_eq!(req.filters.len(), 1);
+        assert_eq!(
+            req.filters[0].table,
+            crate::web::routes_search::ReturnType::Lot
+        );
+        assert_eq!(req.filters[0].field, "group_id");
+        assert_eq!(req.filters[0].value, json!(1));
+
+        let own = json!({"table": "Lot", "field": "group_id", "op": "eq", "value": 1});
+        let req = parse_request(&json!({"return_type": "Lot", "filters": [own]}), 1)?;
+        assert_eq!(req.filters.len(), 1);
+        assert!(parse_request(&json!({"return_type": "Lot", "filters": [own]}), 1000).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn saved_search_route_rejects_invalid_requests() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(SearchState::new((*mm).clone())));
+
+        for request in [
+            json!({"filters": []}),
+            json!({"return_type": "Lot", "global_filter": "name:("}),
+            json!({
+                "return_type": "Lot",
+                "filters": [{"table": "Lot", "field": "group_id", "op": "eq", "value": 1000}]
+            }),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    axum::http::Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/search/saved")
+                        .header(axum::http::header::CONTENT_TYPE, "application/json")
+                        .body(axum::body::Body::from(
+                            json!({"groupId": 1, "name": "broken", "request": request}).to_string(),
+                        ))?,
+                )
+                .await?;
+            assert_ne!(response.status(), axum::http::StatusCode::OK);
+        }
+
+        let searches = send(&app, "GET", "/api/v1/search/saved?group_id=1", None).await?;
+        assert!(
+            searches
+                .as_array()
+                .is_some_and(|searches| searches.iter().all(|search| search["name"] != "broken"))
+        );
+
+        Ok(())
+    }
+}
@@ -3,7 +3,17 @@ use crate::search_shadow::SearchState;
 use crate::search_shadow::basic::{map_basic_shadow_query, search_basic_shadow};
 use crate::search_shadow::clone::{
     CloneShadowDirection, CloneShadowOrder, CloneShadowOrderField, CloneShadowQuery,
-    map_filter as map_clone_shadow_filter, search_clone_shadow,
+    map_facet as map_clone_shadow_facet, map_request_filter as map_clone_shadow_filter,
+    object_field as clone_object_field, resolve_condition as resolve_clone_condition,
+    search_clone_shadow,
+};
+use crate::search_shadow::facet::{FacetCounts, FacetValue, SearchFacet};
+use crate::search_shadow::filter::{date_epoch, invalid_filter, resolve_filter};
+use crate::search_shadow::hydrate::Projection;
+use crate::search_shadow::notify::VERSION_HEADER;
+use crate::search_shadow::omni::{OMNI_LIMIT, OMNI_MAX_LIMIT, omni_search};
+use crate::search_shadow::query::{
+    self, Comparison, ParseError, QueryCondition, QueryExpr, QueryTerm, TermValue,
 };
 use crate::web::mw_auth::CtxW;
 use crate::web::{Error, Result};
@@ -11,6 +21,7 @@ use ReturnType as RT;
 use airlab_lib::ctx::Ctx;
 use airlab_lib::model::ModelManager as MM;
 use airlab_lib::model::clone::{Clone, CloneBmc, CloneFilter, CloneForUpdate, CloneId};
+use airlab_lib::model::clone_application::ApplicationFilter;
 use airlab_lib::model::conjugate::{Conjugate, ConjugateBmc, ConjugateFilter};
 use airlab_lib::model::lot::{Lot, LotBmc, LotFilter};
 use airlab_lib::model::member::{Member, MemberBmc, MemberFilter};
@@ -21,7 +32,11 @@ use airlab_lib::model::provider::{Provider, ProviderBmc, ProviderFilter};
 use airlab_lib::model::species::{Species, SpeciesBmc, SpeciesFilter};
 use airlab_lib::model::tag::{Tag, TagBmc, TagFilter};
 use airlab_lib::model::validation::{Validation, ValidationBmc, ValidationFilter};
+use axum::body::Body;
 use axum::extract::{Json as eJson, State};
+use axum::http::{HeaderValue, Request};
+use axum::middleware::{self, Next};
+use axum::response::Response;
 use axum::routing::post;
 use axum::{Json, Router};
 use modql::filter::IntoFilterNodes;
@@ -32,8 +47,8 @@ use sea_query::IntoIden;
 use sea_query::QueryBuilder;
 use sea_query::extension::postgres::PgExpr;
 use sea_query::{
-    Alias, ColumnRef, Condition, Order, PostgresQueryBuilder, Query, SqlWriter, Value as SeaValue,
-    Values,
+    Alias, ColumnRef, Condition, Func, Order, PostgresQueryBuilder, Query, SimpleExpr, SqlWriter,
+    Value as SeaValue, Values,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value, json};
@@ -51,29 +66,59 @@ pub(crate) const PAGESIZE: i64 = 50;
 pub fn routes(state: SearchState) -> Router {
     Router::new()
         .route("/api/v1/search", post(api_post_search_handler))
+        .route("/api/v1/search/omni", post(api_post_omni_search_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            mw_search_version,
+        ))
         .with_state(state)
 }
 
+/// Read before searching: the shadows a search uses include at least this version.
+pub(crate) async fn mw_search_version(
+    State(state): State<SearchState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let version = state.registry.version();
+    let mut response = next.run(req).await;
+    response
+        .headers_mut()
+        .insert(VERSION_HEADER, HeaderValue::from(version));
+    response
+}
+
 async fn api_post_search_handler(
     State(state): State<SearchState>,
     ctx: CtxW,
-    eJson(mut req): eJson<RpcSearchRequest>,
+    eJson(req): eJson<RpcSearchRequest>,
+) -> Result<Json<Value>> {
+    search(&state, &ctx.0, req).await
+}
+
+/// Runs `req` for the user of `ctx`, on a shadow when one covers the return type.
+pub(crate) async fn search(
+    state: &SearchState,
+    ctx: &Ctx,
+    mut req: RpcSearchRequest,
 ) -> Result<Json<Value>> {
     if req.return_type == ReturnType::Panel && !req.show_all.unwrap_or(false) {
-        inject_panel_owner_filter(&ctx.0, &state.mm, &mut req).await?;
+        inject_panel_owner_filter(ctx, &state.mm, &mut req).await?;
     }
 
+    let global = query::parse(req.global_filter.as_deref().unwrap_or_default())?;
+
     if req.return_type == ReturnType::Clone {
-        let Some(query) = map_clone_shadow_query(&req) else {
+        let Some(query) = map_clone_shadow_query(&req, global.as_ref())? else {
             return Err(Error::UnsupportedQueryValue(
                 "No clone shadow query available".to_string(),
             ));
         };
-        return clone_shadow_search_handler(&state, &query).await;
+        return clone_shadow_search_handler(state, &query).await;
     }
 
-    if let Some(query) = map_basic_shadow_query(&req) {
-        return basic_shadow_search_handler(&state, &query).await;
+    if let Some(query) = map_basic_shadow_query(&req, global.as_ref())? {
+        return basic_shadow_search_handler(state, &query).await;
     }
 
     if crate::search_shadow::basic::BasicShadowKind::from_return_type(req.return_type).is_some() {
@@ -83,7 +128,22 @@ async fn api_post_search_handler(
         )));
     }
 
-    sql_search_handler(&state.mm, req).await
+    sql_search_handler(&state.mm, req, global.as_ref()).await
+}
+
+/// The best matches of `query` among the proteins, clones, lots, conjugates, panels and
+/// validations of the user's groups, grouped by return type.
+async fn api_post_omni_search_handler(
+    State(state): State<SearchState>,
+    ctx: CtxW,
+    eJson(req): eJson<OmniSearchRequest>,
+) -> Result<Json<Value>> {
+    let limit = req.limit.unwrap_or(OMNI_LIMIT).clamp(1, OMNI_MAX_LIMIT);
+    let groups = omni_search(&state, ctx.0.user_id(), &req.query, limit).await?;
+    Ok(Json(json!({
+        "query": req.query,
+        "groups": groups,
+    })))
 }
 
 async fn clone_shadow_search_handler(
@@ -102,12 +162,20 @@ async fn clone_shadow_search_handler(
         ..Default::default()
     };
 
-    let mut response = PaginatedResponse::<i64>::from_lo(&lo, result.search_total);
+    let mut response = PaginatedResponse::<Value>::from_lo(&lo, result.search_total);
     response.page = query.page.max(1) as u32;
     response.limit = query.limit.max(1) as u32;
     response.has_previous = query.page.max(1) > 1;
     response.has_next = (query.page.max(1) * query.limit.max(1)) < result.search_total;
-    response.items = result.items;
+    response.items = match &query.projection {
+        Some(projection) => {
+            projection
+                .apply(state, shadow.objects(&result.items))
+                .await?
+        }
+        None => result.items.into_iter().map(Value::from).collect(),
+    };
+    response.facets = result.facets;
 
     Ok(Json(json!(response)))
 }
@@ -128,71 +196,113 @@ async fn basic_shadow_search_handler(
         ..Default::default()
     };
 
-    let mut response = PaginatedResponse::<i64>::from_lo(&lo, result.search_total);
+    let mut response = PaginatedResponse::<Value>::from_lo(&lo, result.search_total);
     response.page = query.page.max(1) as u32;
     response.limit = query.limit.max(1) as u32;
     response.has_previous = query.page.max(1) > 1;
     response.has_next = (query.page.max(1) * query.limit.max(1)) < result.search_total;
-    response.items = result.items;
+    response.items = match &query.projection {
+        Some(projection) => {
+            projection
+                .apply(state, shadow.objects(&result.items))
+                .await?
+        }
+        None => result.items.into_iter().map(Value::from).collect(),
+    };
+    response.facets = result.facets;
 
     Ok(Json(json!(response)))
 }
 
-async fn sql_search_handler(mm: &MM, req: RpcSearchRequest) -> Result<Json<Value>> {
+async fn sql_search_handler(
+    mm: &MM,
+    req: RpcSearchRequest,
+    global: Option<&QueryExpr>,
+) -> Result<Json<Value>> {
     info!("HANDLER - api_post_search_handler: {:?}", req);
+    if req.fields.is_some() || !req.expand.is_empty() {
+        warn!("Skipping fields and expand (only shadow searches return entities)");
+    }
     let graph = get_graph();
     let allowed = allowed_filter_tables(req.return_type);
-    let global_filter = req
-        .global_filter
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(str::to_string);
-    let mut filters: Vec<Filter> = req
-        .filters
+    let global = global
+        .map(|expr| expr.resolve(&mut |term| resolve_sql_term(req.return_type, &allowed, term)))
+        .transpose()?;
+    let mut filters: Vec<(Filter, Condition)> = vec![];
+    for (position, filter) in req.filters.into_iter().enumerate() {
+        if !allowed.contains(&filter.table) {
+            warn!(
+                "Skipping filter on {:?}.{} (not allowed for return_type {:?})",
+                filter.table, filter.field, req.return_type
+            );
+            continue;
+        }
+        match sql_request_condition(req.return_type, position, &filter)? {
+            Some(condition) => filters.push((filter, condition)),
+            None => warn!(
+                "Skipping unsupported filter {:?}.{} {} {}",
+                filter.table, filter.field, filter.op, filter.value
+            ),
+        }
+    }
+
+    let orders: Vec<SearchOrder> = req
+        .order
         .into_iter()
-        .filter(|filter| {
-            if allowed.contains(&filter.table) {
+        .filter(|order| {
+            if order.is_relevance() {
+                warn!("Skipping relevance order (only shadow searches rank by relevance)");
+                false
+            } else if allowed.contains(&order.table) {
                 true
             } else {
                 warn!(
-                    "Skipping filter on {:?}.{} (not allowed for return_type {:?})",
-                    filter.table, filter.field, req.return_type
+                    "Skipping order on {:?}.{} (not allowed for return_type {:?})",
+                    order.table, order.field, req.return_type
                 );
                 false
             }
         })
         .collect();
 
-    let order = req.order.and_then(|order| {
-        if allowed.contains(&order.table) {
-            Some(order)
-        } else {
-            warn!(
-                "Skipping order on {:?}.{} (not allowed for return_type {:?})",
-                order.table, order.field, req.return_type
+    let mut facets: Vec<SearchFacet> = req
+        .facets
+        .into_iter()
+        .filter(|facet| {
+            if allowed.contains(&facet.table) && is_sql_identifier(&facet.field) {
+                true
+            } else {
+                warn!(
+                    "Skipping facet on {:?}.{} (not allowed for return_type {:?})",
+                    facet.table, facet.field, req.return_type
+                );
+                false
+            }
+        })
+        .collect();
+
+    let mut required = get_required_tables(
+        req.return_type,
+        filters.iter().map(|(filter, _)| filter.table),
+        &orders,
+    );
+    required.extend(facets.iter().map(|facet| facet.table));
+    if let Some(global) = &global {
+        required.extend(global.filters().into_iter().map(|filter| filter.table));
+        if !global.text_terms().is_empty() {
+            required.extend(
+                sql_text_columns(req.return_type)
+                    .iter()
+                    .map(|(table, _)| *table),
             );
-            None
         }
-    });
-
-    let mut required = get_required_tables(req.return_type, &filters, order.as_ref());
-    if global_filter.is_some() && req.return_type == ReturnType::Lot {
-        required.extend([
-            ReturnType::Lot,
-            ReturnType::Clone,
-            ReturnType::Protein,
-            ReturnType::Provider,
-            ReturnType::Collection,
-            ReturnType::Group,
-        ]);
     }
     debug!("required tables: {required:#?}");
     let join_plan = find_join_plan(&graph, req.return_type, &required);
     debug!("join plan: {join_plan:#?}");
     let joined_tables = get_joined_tables(req.return_type, &join_plan);
 
-    filters.retain(|filter| {
+    filters.retain(|(filter, _)| {
         if joined_tables.contains(&filter.table) {
             true
         } else {
@@ -203,6 +313,17 @@ async fn sql_search_handler(mm: &MM, req: RpcSearchRequest) -> Result<Json<Value
             false
         }
     });
+    facets.retain(|facet| {
+        if joined_tables.contains(&facet.table) {
+            true
+        } else {
+            warn!(
+                "Skipping facet on {:?}.{} (not joined for return_type {:?})",
+                facet.table, facet.field, req.return_type
+            );
+            false
+        }
+    });
     let offset = req
         .limit
         .zip(req.page)
@@ -226,144 +347,49 @@ async fn sql_search_handler(mm: &MM, req: RpcSearchRequest) -> Result<Json<Value
         );
         query.inner_join(to, Expr::col(frm_colref).equals(to_colref));
     }
-    for filter in filters {
-        let table_name = filter.table.table_name();
-        let field_name = filter.field.to_string().to_lowercase();
-
-        // Clone.reactivity is a bigint[] column. "contains" must use array operators.
-        if filter.table == ReturnType::Clone
-            && field_name == "reactivity"
-            && filter.op == "contains"
-        {
-            match filter.value {
-                Value::Number(n) => {
-                    if let Some(v) = n.as_i64() {
-                        let sql = format!("{table_name}.{field_name} @> ARRAY[{v}]::bigint[]");
-                        query.and_where(Expr::cust(sql));
-                    }
-                    continue;
-                }
-                Value::Array(arr) => {
-                    let vals: Vec<i64> = arr.iter().filter_map(|v| v.as_i64()).collect();
-                    if !vals.is_empty() {
-                        let csv = vals
-                            .iter()
-                            .map(std::string::ToString::to_string)
-                            .collect::<Vec<_>>()
-                            .join(",");
-                        let sql = format!("{table_name}.{field_name} && ARRAY[{csv}]::bigint[]");
-                        query.and_where(Expr::cust(sql));
-                    }
-                    continue;
-                }
-                _ => {
-                    warn!(
-                        "Skipping unsupported reactivity filter value: {:?}",
-                        filter.value
-                    );
-                    continue;
-                }
-            }
-        }
-
-        let cref = ColumnRef::TableColumn(
-            Alias::new(table_name).into_iden(),
-            Alias::new(field_name).into_iden(),
-        );
-        match filter.value {
-            Value::String(s) => {
-                if !s.is_empty() {
-                    let st = format!("%{}%", s);
-                    query.and_where(Expr::col(cref).ilike(st));
-                }
-            }
-            Value::Number(n) => {
-                query.and_where(Expr::col(cref).eq(n.as_i64()));
-            }
-            Value::Bool(b) => {
-                query.and_where(Expr::col(cref).eq(b));
-            }
-            Value::Array(arr) => {
-                // accept Vec<i64>
-                let vals: Vec<i64> = arr.iter().filter_map(|v| v.as_i64()).collect();
-
-                if !vals.is_empty() {
-                    query.and_where(Expr::col(cref).is_in(vals));
-                }
-            }
-            _ => {
-                warn!("Skipping {}", filter.value);
-            }
-        }
+    let mut condition = Condition::all();
+    for (_, filter_condition) in filters {
+        condition = condition.add(filter_condition);
     }
+    if let Some(global) = &global {
+        condition = condition.add(sql_condition(req.return_type, global));
+    }
+    query.cond_where(condition);
 
-    if let Some(value) = &global_filter
-        && req.return_type == ReturnType::Lot
-    {
-        let pattern = format!("%{value}%");
-        query.and_where(
-            Condition::any()
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("lot").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("lot").into_iden(),
-                        Alias::new("reference").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("lot").into_iden(),
-                        Alias::new("number").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("clone").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("protein").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("provider").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("collection").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern.clone()),
-                )
-                .add(
-                    Expr::col(ColumnRef::TableColumn(
-                        Alias::new("group").into_iden(),
-                        Alias::new("name").into_iden(),
-                    ))
-                    .ilike(pattern),
-                )
-                .into(),
-        );
+    let mut facet_counts = Vec::new();
+    for facet in &facets {
+        let column = format!(r#""{}"."{}""#, facet.table.table_name(), facet.field);
+        let (sql, values) = query
+            .clone()
+            .clear_selects()
+            .expr_as(Expr::cust(format!("{column}::text")), Alias::new("value"))
+            .expr_as(
+                Expr::cust(format!(
+                    r#"COUNT(DISTINCT "{}"."id")"#,
+                    req.return_type.table_name()
+                )),
+                Alias::new("count"),
+            )
+            .add_group_by([Expr::cust(column)])
+            .build(PostgresQueryBuilder);
+        let rows = bind_sql_values(sqlx::query(&sql), values)?
+            .fetch_all(mm.db())
+            .await
+            .map_err(airlab_lib::model::Error::from)?;
+        let values = rows
+            .iter()
+            .map(|row| FacetValue {
+                value: row
+                    .get::<Option<String>, _>("value")
+                    .map_or(Value::Null, |raw| sql_value(&raw)),
+                label: None,
+                count: row.get("count"),
+            })
+            .collect();
+        facet_counts.push(FacetCounts::new(facet, values));
     }
 
-    if let Some(order) = order {
+    for order in orders {
         debug!(
             "ORDER SET: {:?} {} {:?}",
             order.table, order.field, order.direction
@@ -380,7 +406,39 @@ async fn sql_search_handler(mm: &MM, req: RpcSearchRequest) -> Result<Json<Value
         query.order_by_expr(col_ref.into(), ordr);
     }
     let (sql, values) = query.build(PostgresQueryBuilder);
-    let mut q = sqlx::query(&sql);
+    let rows = bind_sql_values(sqlx::query(&sql), values)?
+        .fetch_all(mm.db())
+        .await
+        .map_err(airlab_lib::model::Error::from)?;
+    let mut seen_ids: HashSet<i64> = HashSet::new();
+    let mut ids: Vec<i64> = Vec::new();
+    for row in rows {
+        let id: i64 = row.get("id");
+        if seen_ids.insert(id) {
+            ids.push(id);
+        }
+    }
+    let limit = req.limit.unwrap_or(PAGESIZE) as usize;
+    let page = req.page.unwrap_or(1) as usize;
+
+    let lo = LO {
+        limit: req.limit,
+        offset,
+        ..Default::default()
+    };
+
+    let mut ret2 = PaginatedResponse::<i64>::from_lo(&lo, ids.len() as i64);
+    let start = (page.saturating_sub(1)) * limit;
+    let end = (start + limit).min(ids.len());
+    ret2.items = ids.get(start..end).unwrap_or(&[]).to_vec();
+    ret2.facets = facet_counts;
+    Ok(Json(json!(ret2)))
+}
+
+fn bind_sql_values<'q>(
+    mut q: sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    values: Values,
+) -> Result<sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>> {
     for v in values {
         q = match v {
             SeaValue::Bool(b) => q.bind(b),
@@ -403,32 +461,271 @@ async fn sql_search_handler(mm: &MM, req: RpcSearchRequest) -> Result<Json<Value
             }
         }
     }
-    let rows = q
-        .fetch_all(mm.db())
-        .await
-        .map_err(airlab_lib::model::Error::from)?;
-    let mut seen_ids: HashSet<i64> = HashSet::new();
-    let mut ids: Vec<i64> = Vec::new();
-    for row in rows {
-        let id: i64 = row.get("id");
-        if seen_ids.insert(id) {
-            ids.push(id);
+    Ok(q)
+}
+
+fn is_sql_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_')
+}
+
+/// A number or flag when `raw` reads as one, otherwise text.
+fn sql_value(raw: &str) -> Value {
+    raw.parse::<i64>()
+        .map(|value| json!(value))
+        .or_else(|_| raw.parse::<f64>().map(|value| json!(value)))
+        .unwrap_or_else(|_| match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        })
+}
+
+/// The columns bare words and phrases of the global filter search in the SQL fallback.
+fn sql_text_columns(return_type: ReturnType) -> &'static [(ReturnType, &'static str)] {
+    match return_type {
+        ReturnType::Lot => &[
+            (ReturnType::Lot, "name"),
+            (ReturnType::Lot, "reference"),
+            (ReturnType::Lot, "number"),
+            (ReturnType::Clone, "name"),
+            (ReturnType::Protein, "name"),
+            (ReturnType::Provider, "name"),
+            (ReturnType::Collection, "name"),
+            (ReturnType::Group, "name"),
+        ],
+        _ => &[],
+    }
+}
+
+/// The request filter at `position` as a condition of the SQL fallback; `None` when it
+/// cannot be compared in SQL.
+fn sql_request_condition(
+    return_type: ReturnType,
+    position: usize,
+    filter: &Filter,
+) -> std::result::Result<Option<Condition>, ParseError> {
+    let table_name = filter.table.table_name();
+    let field_name = filter.field.to_lowercase();
+    let matching = matches!(filter.op.as_str(), "eq" | "contains" | "in");
+    let negated = matches!(filter.op.as_str(), "ne" | "not_in");
+    let custom = |sql: String| {
+        let condition = Condition::all().add(Expr::cust(sql));
+        Ok(Some(if filter.not != negated {
+            condition.not()
+        } else {
+            condition
+        }))
+    };
+
+    // Clone.reactivity is a bigint[] column, so species match with array operators.
+    if filter.table == ReturnType::Clone && field_name == "reactivity" && (matching || negated) {
+        let species = match &filter.value {
+            Value::Array(items) => items.iter().map(Value::as_i64).collect(),
+            value => value.as_i64().map(|id| vec![id]),
         }
+        .ok_or_else(|| invalid_filter(position, "expected species ids"))?;
+        let csv = species
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        return custom(format!(
+            "{table_name}.{field_name} && ARRAY[{csv}]::bigint[]"
+        ));
     }
-    let limit = req.limit.unwrap_or(PAGESIZE) as usize;
-    let page = req.page.unwrap_or(1) as usize;
 
-    let lo = LO {
-        limit: req.limit,
-        offset,
-        ..Default::default()
+    // Clone.application is canonical jsonb, so application/status pairs use containment.
+    if filter.table == ReturnType::Clone
+        && field_name == "application"
+        && matches!(filter.op.as_str(), "eq" | "contains")
+    {
+        let application = ApplicationFilter::parse(&filter.value)
+            .ok_or_else(|| invalid_filter(position, "expected an application and status"))?;
+        return custom(format!(
+            "{table_name}.{field_name} @> '{}'::jsonb",
+            application.containment()
+        ));
+    }
+
+    Ok(resolve_filter(position, filter, &mut |comparison, value| {
+        Some(SqlFilter {
+            table: filter.table,
+            column: field_name.clone(),
+            comparison,
+            value: value.clone(),
+        })
+    })?
+    .map(|condition| sql_condition(return_type, &condition)))
+}
+
+/// A comparison of a request filter or a field term of the global filter in the SQL
+/// fallback.
+#[derive(Debug, Clone)]
+struct SqlFilter {
+    table: ReturnType,
+    column: String,
+    comparison: Comparison,
+    value: Value,
+}
+
+fn resolve_sql_term(
+    return_type: ReturnType,
+    allowed: &BTreeSet<ReturnType>,
+    term: &QueryTerm,
+) -> std::result::Result<QueryCondition<SqlFilter>, ParseError> {
+    if let Some(text) = term.text() {
+        return Ok(QueryCondition::Text(text));
+    }
+    let (table, column) = term
+        .targets(return_type, allowed)?
+        .into_iter()
+        .next()
+        .filter(|(_, column)| is_sql_identifier(column))
+        .ok_or_else(|| term.unsupported(return_type))?;
+    let filter = |comparison, raw: &str| {
+        let value = sql_value(raw);
+        if !matches!(comparison, Comparison::Match | Comparison::Eq)
+            && !value.is_number()
+            && date_epoch(raw).is_none()
+        {
+            return Err(term.invalid_value(format!("expected a number or date, got `{raw}`")));
+        }
+        Ok(QueryCondition::Filter(SqlFilter {
+            table,
+            column: column.clone(),
+            comparison,
+            value,
+        }))
     };
+    match &term.value {
+        TermValue::Word(raw) => filter(term.comparison, raw),
+        TermValue::Phrase(raw) => match term.comparison {
+            Comparison::Match | Comparison::Eq => Ok(QueryCondition::Filter(SqlFilter {
+                table,
+                column: column.clone(),
+                comparison: term.comparison,
+                value: Value::String(raw.clone()),
+            })),
+            _ => Err(term.invalid_value(format!("expected a number, got `{raw}`"))),
+        },
+        TermValue::Range { low, high } => {
+            let mut all = vec![];
+            if let Some(low) = low {
+                all.push(filter(Comparison::Gte, low)?);
+            }
+            if let Some(high) = high {
+                all.push(filter(Comparison::Lte, high)?);
+            }
+            Ok(QueryCondition::All(all))
+        }
+    }
+}
 
-    let mut ret2 = PaginatedResponse::<i64>::from_lo(&lo, ids.len() as i64);
-    let start = (page.saturating_sub(1)) * limit;
-    let end = (start + limit).min(ids.len());
-    ret2.items = ids.get(start..end).unwrap_or(&[]).to_vec();
-    Ok(Json(json!(ret2)))
+fn sql_condition(return_type: ReturnType, condition: &QueryCondition<SqlFilter>) -> Condition {
+    match condition {
+        QueryCondition::Text(term) => {
+            let columns = sql_text_columns(return_type);
+            if columns.is_empty() {
+                warn!(
+                    "Skipping global text {:?} (no text columns for return_type {:?})",
+                    term.text, return_type
+                );
+                return Condition::all().add(Expr::cust("TRUE"));
+            }
+            let pattern = format!("%{}%", term.text.trim());
+            columns
+                .iter()
+                .fold(Condition::any(), |any, (table, column)| {
+                    // Empty rather than NULL, so that NOT finds rows without the text.
+                    let text = sea_query::Func::coalesce([
+                        Expr::col(ColumnRef::TableColumn(
+                            Alias::new(table.table_name()).into_iden(),
+                            Alias::new(*column).into_iden(),
+                        ))
+                        .into(),
+                        Expr::val("").into(),
+                    ]);
+                    any.add(Expr::expr(text).ilike(pattern.clone()))
+                })
+        }
+        QueryCondition::Filter(filter) => sql_filter_condition(filter),
+        QueryCondition::All(conditions) => {
+            conditions.iter().fold(Condition::all(), |all, condition| {
+                all.add(sql_condition(return_type, condition))
+            })
+        }
+        QueryCondition::Any(conditions) if conditions.is_empty() => {
+            Condition::all().add(Expr::cust("FALSE"))
+        }
+        QueryCondition::Any(conditions) => {
+            conditions.iter().fold(Condition::any(), |any, condition| {
+                any.add(sql_condition(return_type, condition))
+            })
+        }
+        QueryCondition::Not(condition) => sql_condition(return_type, condition).not(),
+    }
+}
+
+/// `filter` in SQL, with the meaning [`crate::search_shadow::filter::matches`] gives it in
+/// the shadows: dates compare as timestamps, text equals ignoring case and a list matches
+/// any of its items.
+fn sql_filter_condition(filter: &SqlFilter) -> Condition {
+    let column = Expr::col(ColumnRef::TableColumn(
+        Alias::new(filter.table.table_name()).into_iden(),
+        Alias::new(&filter.column).into_iden(),
+    ));
+    let equality = matches!(filter.comparison, Comparison::Match | Comparison::Eq);
+    let value: SimpleExpr = match &filter.value {
+        Value::Null if equality => return Condition::all().add(column.is_null()),
+        Value::Array(items) if equality => {
+            let items = items
+                .iter()
+                .filter(|item| !item.is_null() && !item.is_array())
+                .map(|item| SqlFilter {
+                    comparison: Comparison::Eq,
+                    value: item.clone(),
+                    ..filter.clone()
+                })
+                .collect::<Vec<_>>();
+            if items.is_empty() {
+                return Condition::all().add(Expr::cust("FALSE"));
+            }
+            return items.iter().fold(Condition::any(), |any, item| {
+                any.add(sql_filter_condition(item))
+            });
+        }
+        // An empty text used to mean no filter rather than rows with any text.
+        Value::String(text) if filter.comparison == Comparison::Match && text.is_empty() => {
+            return Condition::all();
+        }
+        Value::String(text) if filter.comparison == Comparison::Match => {
+            return Condition::all().add(column.ilike(format!("%{text}%")));
+        }
+        Value::String(text) if equality => {
+            return Condition::all()
+                .add(Expr::expr(Func::lower(column)).eq(text.trim().to_lowercase()));
+        }
+        Value::String(raw) => match date_epoch(raw) {
+            Some(epoch) => Expr::cust_with_values("to_timestamp($1)", [epoch]),
+            None => return Condition::all().add(Expr::cust("FALSE")),
+        },
+        Value::Bool(flag) => Expr::val(*flag).into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => Expr::val(number).into(),
+            None => Expr::val(number.as_f64()).into(),
+        },
+        _ => return Condition::all().add(Expr::cust("FALSE")),
+    };
+    Condition::all().add(match filter.comparison {
+        Comparison::Match | Comparison::Eq => column.eq(value),
+        Comparison::Gt => column.gt(value),
+        Comparison::Gte => column.gte(value),
+        Comparison::Lt => column.lt(value),
+        Comparison::Lte => column.lte(value),
+    })
 }
 
 #[derive(Serialize, Default)]
@@ -439,6 +736,8 @@ struct PaginatedResponse<T> {
     limit: u32,
     has_next: bool,
     has_previous: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    facets: Vec<FacetCounts>,
 }
 
 impl<T> PaginatedResponse<T> {
@@ -453,6 +752,7 @@ impl<T> PaginatedResponse<T> {
             limit: limit as u32,
             has_next: offset + limit < search_total,
             has_previous: page > 0,
+            facets: vec![],
         }
     }
 }
@@ -464,12 +764,24 @@ pub struct RpcSearchRequest {
     pub limit: Option<i64>,
     #[serde(default)]
     pub filters: Vec<Filter>,
-    pub order: Option<SearchOrder>,
+    /// One order or a list of them, the later ones ordering ties of the earlier.
+    #[serde(default, deserialize_with = "one_or_many_orders")]
+    pub order: Vec<SearchOrder>,
     pub global_filter: Option<String>,
     pub show_all: Option<bool>,
+    /// Fields to count the filtered results by.
+    #[serde(default)]
+    pub facets: Vec<SearchFacet>,
+    /// Return entities with these fields of the return type instead of ids.
+    pub fields: Option<Vec<String>>,
+    /// Related objects to embed in returned entities, such as a conjugate's `lot`.
+    #[serde(default)]
+    pub expand: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, PartialOrd, Eq, Ord, Display, Hash, Copy)]
+#[derive(
+    Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd, Eq, Ord, Display, Hash, Copy,
+)]
 #[strum(serialize_all = "lowercase")]
 pub enum ReturnType {
     User,
@@ -490,7 +802,7 @@ pub enum ReturnType {
 }
 
 impl ReturnType {
-    fn table_name(self) -> &'static str {
+    pub(crate) fn table_name(self) -> &'static str {
         match self {
             ReturnType::User => "user",
             ReturnType::Member => "member",
@@ -509,6 +821,34 @@ impl ReturnType {
             ReturnType::Collection => "collection",
         }
     }
+
+    pub(crate) fn from_table_name(name: &str) -> Option<Self> {
+        match name {
+            "user" => Some(ReturnType::User),
+            "member" => Some(ReturnType::Member),
+            "group" => Some(ReturnType::Group),
+            "clone" => Some(ReturnType::Clone),
+            "species" => Some(ReturnType::Species),
+            "protein" => Some(ReturnType::Protein),
+            "lot" => Some(ReturnType::Lot),
+            "tag" => Some(ReturnType::Tag),
+            "conjugate" => Some(ReturnType::Conjugate),
+            "panel" => Some(ReturnType::Panel),
+            "panel_element" => Some(ReturnType::PanelElement),
+            "validation" => Some(ReturnType::Validation),
+            "provider" => Some(ReturnType::Provider),
+            "storage" => Some(ReturnType::Storage),
+            "collection" => Some(ReturnType::Collection),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OmniSearchRequest {
+    pub query: String,
+    /// Hits per return type.
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Clone, Copy)]
@@ -526,13 +866,43 @@ pub struct SearchOrder {
     pub direction: Direction,
 }
 
+impl SearchOrder {
+    /// `relevance` orders shadow searches by how well rows match `global_filter`, whatever
+    /// the table; `desc` puts the best matches first.
+    pub fn is_relevance(&self) -> bool {
+        self.field == "relevance"
+    }
+}
+
+fn one_or_many_orders<'de, D>(deserializer: D) -> std::result::Result<Vec<SearchOrder>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(SearchOrder),
+        Many(Vec<SearchOrder>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        None => vec![],
+        Some(OneOrMany::One(order)) => vec![order],
+        Some(OneOrMany::Many(orders)) => orders,
+    })
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct Filter {
     pub table: ReturnType,
     pub field: String,
     pub op: String,
+    #[serde(default)]
     pub value: Value,
+    /// Negates the filter.
+    #[serde(default)]
+    pub not: bool,
 }
 
 #[allow(dead_code)]
@@ -552,16 +922,17 @@ fn format_value_for_sql(value: &Value, wildcard: bool) -> String {
     }
 }
 
-fn map_clone_shadow_query(req: &RpcSearchRequest) -> Option<CloneShadowQuery> {
-    let group_id = extract_clone_group_id(&req.filters)?;
+fn map_clone_shadow_query(
+    req: &RpcSearchRequest,
+    global: Option<&QueryExpr>,
+) -> std::result::Result<Option<CloneShadowQuery>, ParseError> {
+    let Some(group_id) = extract_clone_group_id(&req.filters) else {
+        return Ok(None);
+    };
     let mut filters = Vec::new();
 
-    for filter in &req.filters {
-        let Some(mapped) = map_clone_shadow_filter(
-            &filter.table.to_string(),
-            filter.field.as_str(),
-            &filter.value,
-        ) else {
+    for (position, filter) in req.filters.iter().enumerate() {
+        let Some(mapped) = map_clone_shadow_filter(position, filter)? else {
             warn!(
                 table = ?filter.table,
                 field = filter.field,
@@ -572,25 +943,59 @@ fn map_clone_shadow_query(req: &RpcSearchRequest) -> Option<CloneShadowQuery> {
         filters.push(mapped);
     }
 
-    let order = match req.order.as_ref() {
-        Some(order) => map_clone_shadow_order_field(order).map(|field| CloneShadowOrder {
-            field,
-            direction: match order.direction {
-                Direction::Asc => CloneShadowDirection::Asc,
-                Direction::Desc => CloneShadowDirection::Desc,
-            },
-        }),
-        None => None,
-    };
+    let order = req
+        .order
+        .iter()
+        .filter_map(|order| {
+            let Some(field) = map_clone_shadow_order_field(order) else {
+                warn!(
+                    table = ?order.table,
+                    field = order.field,
+                    "skipping unsupported clone shadow order"
+                );
+                return None;
+            };
+            Some(CloneShadowOrder {
+                field,
+                direction: match order.direction {
+                    Direction::Asc => CloneShadowDirection::Asc,
+                    Direction::Desc => CloneShadowDirection::Desc,
+                },
+            })
+        })
+        .collect();
+
+    let facets = req
+        .facets
+        .iter()
+        .filter_map(|facet| {
+            let Some(field) = map_clone_shadow_facet(&facet.table.to_string(), &facet.field) else {
+                warn!(
+                    table = ?facet.table,
+                    field = facet.field,
+                    "skipping unsupported clone shadow facet"
+                );
+                return None;
+            };
+            Some((facet.clone(), field))
+        })
+        .collect();
 
-    Some(CloneShadowQuery {
+    Ok(Some(CloneShadowQuery {
         group_id,
         filters,
-        global_filter: req.global_filter.clone(),
+        condition: global.map(resolve_clone_condition).transpose()?,
         order,
+        facets,
+        projection: Projection::new(
+            req.return_type,
+            req.fields.as_deref(),
+            &req.expand,
+            clone_object_field,
+        ),
         page: req.page.unwrap_or(1),
         limit: req.limit.unwrap_or(PAGESIZE),
-    })
+    }))
 }
 
 fn extract_clone_group_id(filters: &[Filter]) -> Option<i64> {
@@ -632,11 +1037,12 @@ async fn inject_panel_owner_filter(ctx: &Ctx, mm: &MM, req: &mut RpcSearchReques
         field: "created_by".to_string(),
         op: "eq".to_string(),
         value: json!(member_id),
+        not: false,
     });
     Ok(())
 }
 
-async fn get_member_id(ctx: &Ctx, mm: &MM, group_id: i64, user_id: i64) -> Result<i64> {
+pub(crate) async fn get_member_id(ctx: &Ctx, mm: &MM, group_id: i64, user_id: i64) -> Result<i64> {
     let filters: Vec<MemberFilter> = serde_json::from_value(json!([
         {
             "group_id": {"$eq": group_id},
@@ -648,6 +1054,9 @@ async fn get_member_id(ctx: &Ctx, mm: &MM, group_id: i64, user_id: i64) -> Resul
 }
 
 fn map_clone_shadow_order_field(order: &SearchOrder) -> Option<CloneShadowOrderField> {
+    if order.is_relevance() {
+        return Some(CloneShadowOrderField::Relevance);
+    }
     match (order.table, order.field.as_str()) {
         (ReturnType::Clone, "id") => Some(CloneShadowOrderField::Id),
         (ReturnType::Clone, "name") => Some(CloneShadowOrderField::CloneName),
@@ -659,23 +1068,21 @@ fn map_clone_shadow_order_field(order: &SearchOrder) -> Option<CloneShadowOrderF
             Some(CloneShadowOrderField::ValidationApplication)
         }
         (ReturnType::Validation, "status") => Some(CloneShadowOrderField::ValidationStatus),
+        (ReturnType::Clone, "consensus_score") => Some(CloneShadowOrderField::ConsensusScore),
         _ => None,
     }
 }
 
 fn get_required_tables(
     return_type: ReturnType,
-    filters: &[Filter],
-    order: Option<&SearchOrder>,
+    filter_tables: impl IntoIterator<Item = ReturnType>,
+    orders: &[SearchOrder],
 ) -> BTreeSet<ReturnType> {
     let mut tables_used: BTreeSet<ReturnType> = BTreeSet::new();
     tables_used.insert(return_type);
+    tables_used.extend(filter_tables);
 
-    for filter in filters {
-        tables_used.insert(filter.table);
-    }
-
-    if let Some(order) = order {
+    for order in orders {
         tables_used.insert(order.table);
     }
 
@@ -718,6 +1125,7 @@ pub(crate) fn allowed_filter_tables(return_type: ReturnType) -> BTreeSet<ReturnT
             RT::Protein,
             RT::Species,
             RT::Tag,
+            RT::Storage,
             RT::Collection,
             RT::Group,
         ]
@@ -847,6 +1255,13 @@ fn get_graph() -> Graph {
         ("conjugate", "tag_id"),
         ("tag", "id"),
     );
+    add_edge(
+        &mut graph,
+        ReturnType::Conjugate,
+        ReturnType::Storage,
+        ("conjugate", "storage_id"),
+        ("storage", "id"),
+    );
     add_edge(
         &mut graph,
         ReturnType::Conjugate,
@@ -1063,6 +1478,27 @@ mod tests {
             .collect()
     }
 
+    /// The values and counts of the response's facet on `table.field`.
+    fn facet_counts(response: &Value, table: &str, field: &str) -> TestResult<Vec<(Value, i64)>> {
+        let facet = response["facets"]
+            .as_array()
+            .ok_or_else(|| std::io::Error::other("facets should be an array"))?
+            .iter()
+            .find(|facet| facet["table"] == table && facet["field"] == field)
+            .ok_or_else(|| std::io::Error::other(format!("missing facet {table}.{field}")))?;
+        Ok(facet["values"]
+            .as_array()
+            .ok_or_else(|| std::io::Error::other("facet values should be an array"))?
+            .iter()
+            .map(|value| {
+                (
+                    value["value"].clone(),
+                    value["count"].as_i64().unwrap_or(-1),
+                )
+            })
+            .collect())
+    }
+
     #[test]
     fn conjugate_to_protein_uses_lot_clone_path() {
         let graph = get_graph();
@@ -1324,10 +1760,477 @@ mod tests {
         Ok(())
     }
 
+    /// Clone reactivity must reference species of the clone's group.
+    async fn seed_group_species(mm: &MM, ids: &[i64]) -> TestResult {
+        for id in ids {
+            sqlx::query(
+                "INSERT INTO species (id, group_id, name, acronym, cid, ctime, mid, mtime, created_at) \
+                 VALUES ($1, 1000, $2, $2, 1, NOW(), 1, NOW(), NOW())",
+            )
+            .bind(id)
+            .bind(format!("species-{id}"))
+            .execute(mm.db())
+            .await?;
+        }
+        Ok(())
+    }
+
     #[tokio::test]
-    async fn search_route_supports_clone_reactivity_scalar_filter() -> TestResult {
+    async fn search_route_applies_global_query_language() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        sqlx::query("UPDATE public.tag SET mw = CASE id WHEN 1005 THEN 191 ELSE 89 END")
+            .execute(mm.db())
+            .await?;
+        sqlx::query("UPDATE public.conjugate SET is_archived = true WHERE id = 4292")
+            .execute(mm.db())
+            .await?;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let search = |global_filter: &str| {
+            json!({
+                "return_type": "Conjugate",
+                "global_filter": global_filter,
+                "order": {"table": "Conjugate", "field": "id", "direction": "asc"}
+            })
+        };
+
+        let response = post_search(&app, search("protein:seed tag:mw>=150 status:stock")).await?;
+        assert_eq!(item_ids(&response)?, vec![1008]);
+        let response = post_search(&app, search("tag:mw:100.. OR (tag:mw<100 -archived)")).await?;
+        assert_eq!(item_ids(&response)?, vec![1008, 1019, 4291]);
+        let response = post_search(&app, search(r#""primary-conjugate" -archived"#)).await?;
+        assert_eq!(item_ids(&response)?, vec![4291]);
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/search")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from(
+                        search("tag:mw>=heavy status:stock").to_string(),
+                    ))?,
+            )
+            .await?;
+        let error = response
+            .extensions()
+            .get::<std::sync::Arc<Error>>()
+            .ok_or("search error")?;
+        let (status, client_error) = error.client_status_and_error();
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert!(matches!(
+            client_error,
+            crate::web::error::ClientError::SEARCH_QUERY_INVALID { position: 8, .. }
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_applies_global_query_language_to_clones() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "return_type": "Clone",
+            "filters": [
+                {"table": "Clone", "field": "group_id", "op": "eq", "value": 1}
+            ],
+            "global_filter": r#"clone:"primary-clone-1" OR clone:id=3124"#,
+            "order": {"table": "Clone", "field": "id", "direction": "asc"}
+        });
+
+        let response = post_search(&app, request).await?;
+        assert_eq!(item_ids(&response)?, vec![3123, 3124]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_counts_conjugate_facets_over_all_matches() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |global_filter: &str| {
+            json!({
+                "return_type": "Conjugate",
+                "limit": 1,
+                "filters": [
+                    {"table": "Conjugate", "field": "group_id", "op": "eq", "value": 1000}
+                ],
+                "global_filter": global_filter,
+                "order": {"table": "Conjugate", "field": "id", "direction": "asc"},
+                "facets": [
+                    {"table": "Tag", "field": "name"},
+                    {"table": "Storage", "field": "name"},
+                    {"table": "Conjugate", "field": "status"}
+                ]
+            })
+        };
+
+        let response = post_search(&app, request("")).await?;
+        assert_eq!(item_ids(&response)?, vec![1008]);
+        assert_eq!(
+            facet_counts(&response, "Tag", "name")?,
+            vec![(json!("backup-tag"), 1), (json!("seed-tag"), 1)]
+        );
+        assert_eq!(
+            facet_counts(&response, "Storage", "name")?,
+            vec![(json!("seed-storage-a"), 1), (json!("seed-storage-b"), 1)]
+        );
+        let statuses = response["facets"][2]["values"]
+            .as_array()
+            .ok_or_else(|| std::io::Error::other("facet values should be an array"))?
+            .iter()
+            .map(|value| (value["value"].clone(), value["label"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![(json!(0), json!("stock")), (json!(1), json!("low"))]
+        );
+
+        let response = post_search(&app, request("status:low")).await?;
+        assert_eq!(item_ids(&response)?, vec![1019]);
+        assert_eq!(
+            facet_counts(&response, "Storage", "name")?,
+            vec![(json!("seed-storage-b"), 1)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_counts_clone_facets_once_per_clone() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |global_filter: &str| {
+            json!({
+                "return_type": "Clone",
+                "filters": [
+                    {"table": "Clone", "field": "group_id", "op": "eq", "value": 1000}
+                ],
+                "global_filter": global_filter,
+                "facets": [
+                    {"table": "Species", "field": "name"},
+                    {"table": "Validation", "field": "status"},
+                    {"table": "Clone", "field": "is_phospho"}
+                ]
+            })
+        };
+
+        // Each clone has a row per reactivity, but counts once.
+        let response = post_search(&app, request("")).await?;
+        assert_eq!(
+            facet_counts(&response, "Species", "name")?,
+            vec![(json!("Mouse"), 1), (json!("Rat"), 1)]
+        );
+        assert_eq!(
+            facet_counts(&response, "Validation", "status")?,
+            vec![(json!(1), 1), (json!(2), 1)]
+        );
+        assert_eq!(response["facets"][1]["values"][0]["label"], "So-So");
+        assert_eq!(
+            facet_counts(&response, "Clone", "is_phospho")?,
+            vec![(json!(false), 2)]
+        );
+
+        let response = post_search(&app, request("validation:status=no")).await?;
+        assert_eq!(
+            facet_counts(&response, "Species", "name")?,
+            vec![(json!("Rat"), 1)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_returns_projected_conjugates_with_related_objects() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "return_type": "Conjugate",
+            "filters": [
+                {"table": "Conjugate", "field": "group_id", "op": "eq", "value": 1000}
+            ],
+            "order": {"table": "Conjugate", "field": "id", "direction": "asc"},
+            "fields": ["status", "tube_number", "description", "unknown"],
+            "expand": ["lot", "tag", "unknown"]
+        });
+
+        let response = post_search(&app, request).await?;
+        let items = response["items"]
+            .as_array()
+            .ok_or_else(|| std::io::Error::other("items should be an array"))?;
+        assert_eq!(items.len(), 2);
+        let first = items[0]
+            .as_object()
+            .ok_or_else(|| std::io::Error::other("items should be objects"))?;
+        assert_eq!(
+            first.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["description", "id", "lot", "status", "tag", "tube_number"]
+        );
+        assert_eq!(first["id"], 1008);
+        assert_eq!(first["status"], 0);
+        assert_eq!(first["description"], "seed-conjugate");
+        assert_eq!(first["lot"]["id"], 1007);
+        assert_eq!(first["lot"]["name"], "seed-lot");
+        assert_eq!(first["tag"]["name"], "seed-tag");
+        assert_eq!(items[1]["tag"]["name"], "backup-tag");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_returns_clones_with_related_objects() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "return_type": "Clone",
+            "filters": [
+                {"table": "Clone", "field": "group_id", "op": "eq", "value": 1000}
+            ],
+            "order": {"table": "Clone", "field": "id", "direction": "asc"},
+            "expand": ["protein", "species"]
+        });
+
+        let response = post_search(&app, request).await?;
+        let first = &response["items"][0];
+        assert_eq!(first["id"], 1006);
+        assert_eq!(first["name"], "seed-clone");
+        assert_eq!(first["reactivity"], json!([7001, 7002]));
+        assert_eq!(first["protein"]["name"], "seed-protein");
+        assert_eq!(first["species"]["name"], "Mouse");
+        assert_eq!(response["items"][1]["species"]["name"], "Rat");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn omni_search_route_groups_hits_from_the_users_groups() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        // The user may only see the panels they created, unlike `primary-panel`.
+        let own_panel: i64 = sqlx::query_scalar(
+            "INSERT INTO public.panel (group_id, created_by, name, is_fluorophore, is_locked, \
+             is_archived, cid, mid) VALUES (1, 1, 'primary-own-panel', false, false, false, 1, 1) \
+             RETURNING id",
+        )
+        .fetch_one(mm.db())
+        .await?;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let omni = |query: &str| {
+            let app = app.clone();
+            let request = json!({"query": query, "limit": 1});
+            async move {
+                let response = app
+                    .oneshot(
+                        axum::http::Request::builder()
+                            .method("POST")
+                            .uri("/api/v1/search/omni")
+                            .header(axum::http::header::CONTENT_TYPE, "application/json")
+                            .body(axum::body::Body::from(request.to_string()))?,
+                    )
+                    .await?;
+                assert_eq!(response.status(), axum::http::StatusCode::OK);
+                let body = crate::web::test_support::response_body_string(response).await?;
+                TestResult::<Value>::Ok(serde_json::from_str(&body)?)
+            }
+        };
+
+        let response = omni("primary").await?;
+        let groups = response["groups"]
+            .as_array()
+            .ok_or_else(|| std::io::Error::other("groups should be an array"))?;
+        let mut return_types = groups
+            .iter()
+            .filter_map(|group| group["return_type"].as_str())
+            .collect::<Vec<_>>();
+        return_types.sort_unstable();
+        assert_eq!(
+            return_types,
+            vec!["Clone", "Conjugate", "Lot", "Panel", "Validation"]
+        );
+        for group in groups {
+            let hits = group["hits"]
+                .as_array()
+                .ok_or_else(|| std::io::Error::other("hits should be an array"))?;
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0]["group_id"], 1);
+        }
+        let group = |return_type: &str| {
+            groups
+                .iter()
+                .find(|group| group["return_type"] == return_type)
+                .cloned()
+                .unwrap_or_default()
+        };
+        assert_eq!(group("Clone")["total"], 2);
+        assert_eq!(group("Conjugate")["total"], 2);
+        assert_eq!(group("Panel")["total"], 1);
+        assert_eq!(group("Panel")["hits"][0]["id"], own_panel);
+        // Other tests rename the tag and add conjugates.
+        let label = group("Conjugate")["hits"][0]["label"].to_string();
+        assert!(
+            label.starts_with("\"#") && label.ends_with(" · seed-protein\""),
+            "{label}"
+        );
+        let lot = &group("Lot")["hits"][0];
+        assert_eq!(lot["id"], 5495);
+        assert_eq!(lot["label"], "primary-lot");
+        assert_eq!(
+            lot["snippet"],
+            json!({"text": "primary-lot", "highlights": [[0, 7]]})
+        );
+
+        // The backup entities belong to a group the user is not in.
+        assert_eq!(omni("backup").await?["groups"], json!([]));
+        assert_eq!(omni(" ").await?["groups"], json!([]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sql_search_counts_facets() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let request = RpcSearchRequest {
+            return_type: ReturnType::Lot,
+            page: Some(1),
+            limit: Some(1),
+            filters: vec![],
+            order: vec![],
+            global_filter: None,
+            show_all: None,
+            facets: vec![
+                SearchFacet {
+                    table: ReturnType::Lot,
+                    field: "status".to_string(),
+                },
+                SearchFacet {
+                    table: ReturnType::Collection,
+                    field: "name".to_string(),
+                },
+                SearchFacet {
+                    table: ReturnType::Lot,
+                    field: "name; DROP TABLE lot".to_string(),
+                },
+            ],
+            fields: None,
+            expand: vec![],
+        };
+        let global = query::parse("group:id=1000")?;
+
+        let Json(response) = sql_search_handler(&mm, request, global.as_ref()).await?;
+        assert_eq!(response["search_total"], 2);
+        assert_eq!(
+            facet_counts(&response, "Lot", "status")?,
+            vec![(json!(0), 1), (json!(1), 1)]
+        );
+        assert_eq!(
+            facet_counts(&response, "Collection", "name")?,
+            vec![
+                (json!("seed-collection-a"), 1),
+                (json!("seed-collection-b"), 1)
+            ]
+        );
+        assert_eq!(response["facets"].as_array().map(Vec::len), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sql_search_maps_global_query_language() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let request = |global_filter: &str| RpcSearchRequest {
+            return_type: ReturnType::Lot,
+            page: None,
+            limit: None,
+            filters: vec![],
+            order: vec![SearchOrder {
+                table: ReturnType::Lot,
+                field: "id".to_string(),
+                direction: Direction::Asc,
+            }],
+            global_filter: Some(global_filter.to_string()),
+            show_all: None,
+            facets: vec![],
+            fields: None,
+            expand: vec![],
+        };
+
+        for (global_filter, expected) in [
+            ("name:seed-lot OR provider:backup", vec![1007, 1018]),
+            ("lot:status>=1 OR seed-collection", vec![1007, 1018]),
+            ("group:id=1000 NOT backup", vec![1007]),
+        ] {
+            let request = request(global_filter);
+            let global = query::parse(global_filter)?;
+            let Json(response) = sql_search_handler(&mm, request, global.as_ref()).await?;
+            assert_eq!(item_ids(&response)?, expected, "{global_filter}");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_orders_global_matches_by_relevance() -> TestResult {
         let mm = crate::web::test_support::init_test_db().await;
         let ctx = Ctx::root_ctx();
+        let mut ids = vec![];
+        for (name, description) in [
+            ("Acme Reagents", Some("resells Zebrafluor kits")),
+            ("Zebrafluor Supply", None),
+            ("Zebraflour Store", None),
+        ] {
+            ids.push(
+                ProviderBmc::create(
+                    &ctx,
+                    &mm,
+                    ProviderForCreate {
+                        name: name.to_string(),
+                        group_id: 1000,
+                        description: description.map(ToString::to_string),
+                        url: None,
+                    },
+                )
+                .await?,
+            );
+        }
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = json!({
+            "return_type": "Provider",
+            "global_filter": "zebrafluor",
+            "order": {
+                "table": "Provider",
+                "field": "relevance",
+                "direction": "desc"
+            }
+        });
+
+        let response = post_search(&app, request).await?;
+        assert_eq!(response["search_total"], 3);
+        assert_eq!(item_ids(&response)?, vec![ids[1], ids[2], ids[0]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_supports_clone_reactivity_scalar_filter() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        seed_group_species(&mm, &[77001, 77002]).await?;
         let clone_id = CloneBmc::create(
             &ctx,
             &mm,
@@ -1386,6 +2289,7 @@ mod tests {
     async fn search_route_supports_clone_reactivity_array_overlap_filter() -> TestResult {
         let mm = crate::web::test_support::init_test_db().await;
         let ctx = Ctx::root_ctx();
+        seed_group_species(&mm, &[77100, 77200, 77300]).await?;
         let matching_clone_id = CloneBmc::create(
             &ctx,
             &mm,
@@ -1458,4 +2362,293 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn search_route_supports_clone_application_status_filter() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let mut ids = Vec::new();
+        for (name, application) in [
+            ("search-application-imc-yes", json!({"IMC": true})),
+            (
+                "search-application-imc-no",
+                json!([{"application": 1, "status": "No"}]),
+            ),
+        ] {
+            let id = CloneBmc::create(
+                &ctx,
+                &mm,
+                CloneForCreate {
+                    group_id: 1000,
+                    created_by: Some(1303),
+                    protein_id: 1002,
+                    species_id: Some(1004),
+                    name: name.into(),
+                    isotype: String::new(),
+                    epitope: String::new(),
+                    is_phospho: false,
+                    is_polyclonal: false,
+                    is_archived: None,
+                    reactivity: None,
+                    application: Some(application),
+                },
+            )
+            .await?;
+            ids.push(id);
+        }
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |status: Value| {
+            json!({
+                "return_type": "Clone",
+                "filters": [
+                    {"table": "Clone", "field": "group_id", "op": "eq", "value": 1000},
+                    {
+                        "table": "Clone",
+                        "field": "application",
+                        "op": "eq",
+                        "value": {"application": "IMC", "status": status}
+                    }
+                ],
+                "order": {"table": "Clone", "field": "id", "direction": "asc"},
+                "page": 1,
+                "limit": 10
+            })
+        };
+
+        let response = post_search(&app, request(json!(0))).await?;
+        assert_eq!(item_ids(&response)?, vec![ids[0]]);
+        let response = post_search(&app, request(json!("No"))).await?;
+        assert_eq!(item_ids(&response)?, vec![ids[1]]);
+        let response = post_search(&app, request(Value::Null)).await?;
+        assert_eq!(item_ids(&response)?, ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_orders_clones_by_validation_consensus() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let id = ValidationBmc::create(
+            &ctx,
+            &mm,
+            json!({
+                "groupId": 1000,
+                "createdBy": 1304,
+                "cloneId": 1016,
+                "lotId": 1018,
+                "speciesId": 1014,
+                "application": 1,
+                "status": 0
+            })
+            .into(),
+        )
+        .await?;
+        // Only approved validations count toward the consensus.
+        sqlx::query(
+            "UPDATE validation SET review_state = 'approved', reviewed_by = 1303 WHERE id = $1",
+        )
+        .bind(id)
+        .execute(mm.db())
+        .await?;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |extra: Value| {
+            let mut filters = vec![
+                json!({"table": "Clone", "field": "group_id", "op": "eq", "value": 1000}),
+                json!({"table": "Validation", "field": "application", "op": "eq", "value": 1}),
+            ];
+            if !extra.is_null() {
+                filters.push(extra);
+            }
+            json!({
+                "return_type": "Clone",
+                "filters": filters,
+                "order": {"table": "Clone", "field": "consensus_score", "direction": "desc"},
+                "page": 1,
+                "limit": 10
+            })
+        };
+
+        let response = post_search(&app, request(Value::Null)).await?;
+        assert_eq!(item_ids(&response)?, vec![1016, 1006]);
+        let response = post_search(
+            &app,
+            request(json!({"table": "Clone", "field": "consensus_status", "op": "eq", "value": 0})),
+        )
+        .await?;
+        assert_eq!(item_ids(&response)?, vec![1016]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_filter_operators_agree_between_shadow_and_sql() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let mut lots = vec![];
+        for (name, status, received) in [
+            ("filter-ops-lot-a", 1, Some(10)),
+            ("filter-ops-lot-b", 0, Some(200)),
+            ("filter-ops-lot-c", 1, None),
+        ] {
+            let id = LotBmc::create(
+                &ctx,
+                &mm,
+                json!({
+                    "groupId": 1000,
+                    "createdBy": 1000,
+                    "cloneId": 1006,
+                    "name": name,
+                    "status": status,
+                    "isArchived": false
+                })
+                .into(),
+            )
+            .await?;
+            sqlx::query("UPDATE public.lot SET received_at = NOW() - make_interval(days => $1) WHERE id = $2")
+                .bind(received)
+                .bind(id)
+                .execute(mm.db())
+                .await?;
+            lots.push(id);
+        }
+        let [a, b, c] = lots[..] else {
+            return Err("three lots".into());
+        };
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |filter: Value| {
+            json!({
+                "return_type": "Lot",
+                "filters": [
+                    {"table": "Lot", "field": "name", "op": "contains", "value": "filter-ops-lot"},
+                    filter
+                ],
+                "order": [
+                    {"table": "Lot", "field": "status", "direction": "asc"},
+                    {"table": "Lot", "field": "id", "direction": "desc"}
+                ]
+            })
+        };
+
+        for (filter, expected) in [
+            (
+                json!({"field": "received_at", "op": "gte", "value": "now-90d"}),
+                vec![a],
+            ),
+            (
+                json!({"field": "received_at", "op": "between", "value": ["now-365d", "now-90d"]}),
+                vec![b],
+            ),
+            (json!({"field": "received_at", "op": "is_null"}), vec![c]),
+            (
+                json!({"field": "received_at", "op": "is_not_null", "not": true}),
+                vec![c],
+            ),
+            (
+                json!({"field": "name", "op": "not_in", "value": ["FILTER-OPS-LOT-B"]}),
+                vec![c, a],
+            ),
+            (
+                json!({"field": "id", "op": "in", "value": [a, b]}),
+                vec![b, a],
+            ),
+            (
+                json!({"field": "status", "op": "ne", "value": 0}),
+                vec![c, a],
+            ),
+        ] {
+            let mut filter = filter;
+            filter["table"] = json!("Lot");
+            let request = request(filter.clone());
+            let response = post_search(&app, request.clone()).await?;
+            assert_eq!(item_ids(&response)?, expected, "shadow {filter}");
+            let Json(response) =
+                sql_search_handler(&mm, serde_json::from_value(request)?, None).await?;
+            assert_eq!(item_ids(&response)?, expected, "sql {filter}");
+        }
+
+        for filter in [
+            json!({"table": "Lot", "field": "status", "op": "like", "value": 0}),
+            json!({"table": "Lot", "field": "received_at", "op": "gt", "value": "lately"}),
+            json!({"table": "Lot", "field": "received_at", "op": "between", "value": "now-1d"}),
+        ] {
+            let request = request(filter.clone());
+            let response = app
+                .clone()
+                .oneshot(
+                    axum::http::Request::builder()
+                        .method("POST")
+                        .uri("/api/v1/search")
+                        .header(axum::http::header::CONTENT_TYPE, "application/json")
+                        .body(axum::body::Body::from(request.to_string()))?,
+                )
+                .await?;
+            let error = response
+                .extensions()
+                .get::<std::sync::Arc<Error>>()
+                .ok_or("search error")?;
+            let (status, client_error) = error.client_status_and_error();
+            assert_eq!(
+                status,
+                axum::http::StatusCode::BAD_REQUEST,
+                "shadow {filter}"
+            );
+            assert!(matches!(
+                client_error,
+                crate::web::error::ClientError::SEARCH_QUERY_INVALID { position: 1, .. }
+            ));
+            let error = sql_search_handler(&mm, serde_json::from_value(request)?, None)
+                .await
+                .err()
+                .ok_or("sql search error")?;
+            assert!(
+                matches!(&error, Error::SearchQuery(err) if err.position == 1),
+                "sql {filter}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_route_compares_clone_fields() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app = crate::web::test_support::authed_router(routes(
+            crate::search_shadow::SearchState::new((*mm).clone()),
+        ));
+        let request = |filter: Value, global_filter: Option<&str>| {
+            json!({
+                "return_type": "Clone",
+                "filters": [
+                    {"table": "Clone", "field": "group_id", "op": "eq", "value": 1},
+                    {"table": "Clone", "field": "id", "op": "in", "value": [3123, 3124]},
+                    filter
+                ],
+                "global_filter": global_filter,
+                "order": [{"table": "Clone", "field": "id", "direction": "desc"}]
+            })
+        };
+
+        let all = json!({"table": "Clone", "field": "id", "op": "gt", "value": 0});
+        let response = post_search(&app, request(all.clone(), None)).await?;
+        assert_eq!(item_ids(&response)?, vec![3124, 3123]);
+        let response = post_search(&app, request(all, Some("clone:id:3124.."))).await?;
+        assert_eq!(item_ids(&response)?, vec![3124]);
+        let not_first =
+            json!({"table": "Clone", "field": "id", "op": "eq", "value": 3123, "not": true});
+        let response = post_search(&app, request(not_first, None)).await?;
+        assert_eq!(item_ids(&response)?, vec![3124]);
+        let below =
+            json!({"table": "Clone", "field": "id", "op": "between", "value": [null, 3123]});
+        let response = post_search(&app, request(below, None)).await?;
+        assert_eq!(item_ids(&response)?, vec![3123]);
+
+        Ok(())
+    }
 }
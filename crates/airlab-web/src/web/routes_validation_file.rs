@@ -1,29 +1,39 @@
-use crate::config::web_config;
+use crate::acquisition::{self, AcquisitionMeta};
+use crate::blob_store::{BlobState, BlobStore, ByteRange, RangeRequest, StagedUpload};
 use crate::web::mw_auth::CtxW;
+use crate::web::preview;
+use crate::web::upload_policy::{UploadPolicy, file_extension};
 use crate::web::{Error, Result};
 use airlab_lib::ctx::Ctx;
 use airlab_lib::model::ModelManager;
+use airlab_lib::model::conjugate::ConjugateBmc;
 use airlab_lib::model::member::{Member, MemberBmc, MemberFilter};
+use airlab_lib::model::tag::{Tag, TagBmc};
 use airlab_lib::model::validation::{Validation, ValidationBmc};
 use airlab_lib::model::validation_file::{
     ValidationFile, ValidationFileBmc, ValidationFileForCreate,
 };
 use axum::Router;
 use axum::body::Body;
-use axum::extract::{Multipart, Path, State};
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
 use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use camino::Utf8PathBuf;
+use serde::Deserialize;
 use serde_json::json;
-use tokio::fs;
-use tokio::fs::File;
-use tokio::io::BufReader;
 use tokio_util::io::ReaderStream;
 #[allow(unused_imports)]
 use tracing::{debug, warn};
 
-pub fn routes(mm: ModelManager) -> Router {
+const MAX_FILES_PER_UPLOAD: usize = 20;
+const DEFAULT_PREVIEW_SIZE: u32 = 256;
+const PREVIEW_SIZES: std::ops::RangeInclusive<u32> = 16..=2048;
+/// Edge lengths previews are rendered and cached at; requests snap up to the next one.
+const PREVIEW_BUCKETS: [u32; 6] = [64, 128, 256, 512, 1024, 2048];
+/// Larger files are not decoded for a preview, as they are decoded in memory.
+const MAX_PREVIEW_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+pub fn routes(state: BlobState) -> Router {
     Router::new()
         .route(
             "/api/v1/validationFiles/{file_id}/serve",
@@ -33,17 +43,27 @@ pub fn routes(mm: ModelManager) -> Router {
             "/api/v1/validation_files/{file_id}/serve",
             get(api_serve_validation_handler),
         )
+        .route(
+            "/api/v1/validationFiles/{file_id}/thumbnail",
+            get(api_validation_file_thumbnail_handler),
+        )
+        .route(
+            "/api/v1/validation_files/{file_id}/thumbnail",
+            get(api_validation_file_thumbnail_handler),
+        )
         .route(
             "/api/v1/validations/{validation_id}/validation_files",
-            post(api_upload_validation_file_handler),
+            // Sizes are enforced per file and group while streaming, see `UploadPolicy`.
+            post(api_upload_validation_file_handler).layer(DefaultBodyLimit::disable()),
         )
-        .with_state(mm)
+        .with_state(state)
 }
 
 async fn api_serve_validation_handler(
-    State(mm): State<ModelManager>,
+    State(BlobState { mm, store }): State<BlobState>,
     ctx: CtxW,
     Path(file_id): Path<i64>,
+    request_headers: HeaderMap,
 ) -> Result<Response> {
     debug!("HANDLER - api_serve_validation_handler: {}", file_id);
 
@@ -51,35 +71,11 @@ async fn api_serve_validation_handler(
     let validation_file: ValidationFile = ValidationFileBmc::get(&ctx, &mm, file_id).await?;
     let validation: Validation =
         ValidationBmc::get(&ctx, &mm, validation_file.validation_id).await?;
-    let data_path = web_config()?.DATA_PATH.clone();
+    let key = validation_file_key(&validation, &validation_file);
 
-    let file_path = Utf8PathBuf::from(format!(
-        "{}/groups/{}/uploads/validation/{}/{}.{}",
-        data_path,
-        validation.group_id,
-        validation.id,
-        validation_file.hash,
-        validation_file.extension
-    ));
-
-    if !file_path.is_file() {
-        warn!("Cannot find the file: {}", file_path);
-        return Ok((
-            StatusCode::NOT_FOUND,
-            format!("File not found: {file_path}"),
-        )
-            .into_response());
-    }
-
-    let file = match File::open(&file_path).await {
-        Ok(file) => file,
-        Err(err) => {
-            return Ok((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error reading file: {err}"),
-            )
-                .into_response());
-        }
+    let Some(meta) = store.backend().head(&key).await? else {
+        warn!("Cannot find the file: {}", key);
+        return Ok((StatusCode::NOT_FOUND, format!("File not found: {key}")).into_response());
     };
 
     let mut headers = HeaderMap::new();
@@ -87,6 +83,7 @@ async fn api_serve_validation_handler(
         header::CONTENT_TYPE,
         HeaderValue::from_static(content_type_for_extension(&validation_file.extension)),
     );
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
     if let Some(name) = validation_file.name.clone() {
         let disposition = format!("inline; filename=\"{}\"", name.replace('"', ""));
         if let Ok(value) = HeaderValue::from_str(&disposition) {
@@ -94,27 +91,165 @@ async fn api_serve_validation_handler(
         }
     }
 
-    let reader = BufReader::new(file);
-    let stream = ReaderStream::new(reader);
-    Ok((headers, Body::from_stream(stream)).into_response())
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let (status, range) = match ByteRange::parse_header(range, meta.size) {
+        RangeRequest::Full => (StatusCode::OK, None),
+        RangeRequest::Partial(range) => (StatusCode::PARTIAL_CONTENT, Some(range)),
+        RangeRequest::Unsatisfiable => {
+            let content_range = format!("bytes */{}", meta.size);
+            if let Ok(value) = HeaderValue::from_str(&content_range) {
+                headers.insert(header::CONTENT_RANGE, value);
+            }
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response());
+        }
+    };
+    if let Some(range) = range {
+        let content_range = format!("bytes {}-{}/{}", range.start, range.end, meta.size);
+        if let Ok(value) = HeaderValue::from_str(&content_range) {
+            headers.insert(header::CONTENT_RANGE, value);
+        }
+    }
+    let content_length = range.map_or(meta.size, ByteRange::byte_count);
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content_length));
+
+    // Streamed from the backend, so large files are never held in memory.
+    let Some(reader) = store.backend().open(&key, range).await? else {
+        warn!("File vanished while serving: {}", key);
+        return Ok((StatusCode::NOT_FOUND, format!("File not found: {key}")).into_response());
+    };
+    let body = Body::from_stream(ReaderStream::new(reader));
+    Ok((status, headers, body).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct ThumbnailParams {
+    width: Option<u32>,
+    height: Option<u32>,
 }
 
+async fn api_validation_file_thumbnail_handler(
+    State(BlobState { mm, store }): State<BlobState>,
+    ctx: CtxW,
+    Path(file_id): Path<i64>,
+    Query(params): Query<ThumbnailParams>,
+) -> Result<Response> {
+    debug!(
+        "HANDLER - api_validation_file_thumbnail_handler: {} {:?}",
+        file_id, params
+    );
+
+    let width = params.width.unwrap_or(DEFAULT_PREVIEW_SIZE);
+    let height = params.height.unwrap_or(width);
+    if !PREVIEW_SIZES.contains(&width) || !PREVIEW_SIZES.contains(&height) {
+        return Err(Error::BadRequest(format!(
+            "width and height must be between {} and {}",
+            PREVIEW_SIZES.start(),
+            PREVIEW_SIZES.end()
+        )));
+    }
+
+    let ctx = ctx.0;
+    let validation_file: ValidationFile = ValidationFileBmc::get(&ctx, &mm, file_id).await?;
+    let validation: Validation =
+        ValidationBmc::get(&ctx, &mm, validation_file.validation_id).await?;
+    let no_preview = || {
+        (
+            StatusCode::NOT_FOUND,
+            format!("No preview for file {file_id}"),
+        )
+            .into_response()
+    };
+    if !preview::supports(&validation_file.extension) {
+        return Ok(no_preview());
+    }
+
+    let size = preview_bucket(width.max(height));
+    let key = validation_file_key(&validation, &validation_file);
+    let cache_key = BlobStore::derived_key(&key, &format!("preview-{size}.png"));
+    let png = if let Some(png) = store.backend().get(&cache_key, None).await? {
+        png
+    } else {
+        let Some(meta) = store.backend().head(&key).await? else {
+            warn!("Cannot find the file: {}", key);
+            return Ok((StatusCode::NOT_FOUND, format!("File not found: {key}")).into_response());
+        };
+        if meta.size > MAX_PREVIEW_SOURCE_BYTES {
+            return Ok(no_preview());
+        }
+        let Some(bytes) = store.backend().get(&key, None).await? else {
+            return Ok((StatusCode::NOT_FOUND, format!("File not found: {key}")).into_response());
+        };
+        let extension = validation_file.extension.clone();
+        let rendered =
+            tokio::task::spawn_blocking(move || preview::render(&bytes, &extension, size, size))
+                .await
+                .map_err(std::io::Error::other)?;
+        let png = match rendered {
+            Ok(Some(png)) => png,
+            Ok(None) => return Ok(no_preview()),
+            Err(err) => {
+                warn!("Cannot render a preview of {}: {}", key, err);
+                return Ok(no_preview());
+            }
+        };
+        // Blobs never change, so a cached preview stays valid until the blob is removed.
+        if let Err(err) = store.backend().put(&cache_key, &png).await {
+            warn!("Cannot cache the preview {}: {}", cache_key, err);
+        }
+        png
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("image/png")),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_static("private, max-age=86400"),
+            ),
+        ],
+        png,
+    )
+        .into_response())
+}
+
+/// Smallest preview bucket that holds `size`, so only a handful of previews are cached
+/// per file.
+fn preview_bucket(size: u32) -> u32 {
+    PREVIEW_BUCKETS
+        .into_iter()
+        .find(|bucket| *bucket >= size)
+        .unwrap_or(*PREVIEW_SIZES.end())
+}
+
+/// Accepts one or more `file` fields and an optional `description` for all of them. Each
+/// file is streamed to disk and hashed on the way; the answer is the created file, or the
+/// list of them when several were sent. Acquisition metadata of MCD, OME-TIFF and FCS files
+/// goes into the file's `meta`, with warnings when the conjugate's tag was not acquired.
 async fn api_upload_validation_file_handler(
-    State(mm): State<ModelManager>,
+    State(BlobState { mm, store }): State<BlobState>,
     ctx: CtxW,
     Path(validation_id): Path<i64>,
     mut multipart: Multipart,
 ) -> Result<Response> {
     let ctx = ctx.0;
     let validation = ValidationBmc::get(&ctx, &mm, validation_id).await?;
+    ValidationBmc::ensure_editable(&mm, validation.id).await?;
     let member_id = get_member_id(&ctx, &mm, validation.group_id, ctx.user_id()).await?;
+    let policy = UploadPolicy::from_config()?;
 
-    let mut uploaded_name: Option<String> = None;
-    let mut uploaded_extension: Option<String> = None;
-    let mut uploaded_bytes: Option<Vec<u8>> = None;
+    let mut remaining_quota = match policy.group_quota_bytes {
+        Some(quota) => {
+            let usage = ValidationFileBmc::group_usage(&ctx, &mm, validation.group_id).await?;
+            Some(quota.saturating_sub(u64::try_from(usage).unwrap_or_default()))
+        }
+        None => None,
+    };
+    let mut uploads: Vec<(String, String, StagedUpload, Option<AcquisitionMeta>)> = vec![];
     let mut description: Option<String> = None;
 
-    while let Some(field) = multipart
+    while let Some(mut field) = multipart
         .next_field()
         .await
         .map_err(|err| Error::BadRequest(format!("Unable to read multipart field: {err}")))?
@@ -127,59 +262,183 @@ async fn api_upload_validation_file_handler(
             continue;
         }
 
-        if field_name != "file" {
+        if field_name != "file" && field_name != "files" {
             continue;
         }
+        if uploads.len() == MAX_FILES_PER_UPLOAD {
+            return Err(Error::BadRequest(format!(
+                "At most {MAX_FILES_PER_UPLOAD} files per upload"
+            )));
+        }
 
-        uploaded_name = field.file_name().map(ToOwned::to_owned);
-        uploaded_extension = uploaded_name
-            .as_deref()
-            .and_then(|name| name.rsplit('.').next())
-            .map(|ext| ext.trim().to_ascii_lowercase())
-            .filter(|ext| !ext.is_empty());
-        uploaded_bytes = Some(
-            field
-                .bytes()
-                .await
-                .map_err(|err| Error::BadRequest(format!("Unable to read uploaded file: {err}")))?
-                .to_vec(),
-        );
+        let file_name = field
+            .file_name()
+            .map_or_else(|| "validation-file".to_string(), ToOwned::to_owned);
+        let extension = file_extension(&file_name);
+        policy.check_declared(&file_name, &extension, field.content_type())?;
+
+        let limit = policy.limit(remaining_quota);
+        let mut upload = store.stage().await?;
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|err| Error::BadRequest(format!("Unable to read uploaded file: {err}")))?
+        {
+            limit.check(
+                &file_name,
+                validation.group_id,
+                upload.size() + chunk.len() as u64,
+            )?;
+            upload.write(&chunk).await?;
+        }
+        policy.check_content(&file_name, &extension, upload.head())?;
+        if let Some(remaining) = remaining_quota.as_mut() {
+            *remaining -= upload.size();
+        }
+        let acquisition = read_acquisition(&file_name, &extension, &mut upload).await?;
+        uploads.push((file_name, extension, upload, acquisition));
+    }
+
+    if uploads.is_empty() {
+        return Err(Error::BadRequest(
+            "Missing multipart file field".to_string(),
+        ));
     }
+    let sent_several = uploads.len() > 1;
+    let conjugate_tag = if uploads
+        .iter()
+        .any(|(.., acquisition)| acquisition.is_some())
+    {
+        conjugate_tag(&ctx, &mm, &validation).await?
+    } else {
+        None
+    };
 
-    let file_bytes = uploaded_bytes
-        .ok_or_else(|| Error::BadRequest("Missing multipart file field".to_string()))?;
-    let file_name = uploaded_name.unwrap_or_else(|| "validation-file".to_string());
-    let extension = uploaded_extension.unwrap_or_else(|| "bin".to_string());
-    let hash = format!("{:x}", md5::compute(&file_bytes));
-
-    let data_path = web_config()?.DATA_PATH.clone();
-    let directory = Utf8PathBuf::from(format!(
-        "{}/groups/{}/uploads/validation/{}",
-        data_path, validation.group_id, validation.id
-    ));
-    fs::create_dir_all(directory.as_std_path()).await?;
-
-    let file_path = directory.join(format!("{hash}.{extension}"));
-    fs::write(file_path.as_std_path(), &file_bytes).await?;
-
-    let file_id = ValidationFileBmc::create(
-        &ctx,
-        &mm,
-        ValidationFileForCreate {
+    let mut file_ids = vec![];
+    for (file_name, extension, upload, acquisition) in uploads {
+        let meta = acquisition.map(|acquisition| {
+            let warnings = conjugate_tag
+                .as_ref()
+                .map(|(conjugate_id, tag)| {
+                    acquisition::check_conjugate(&acquisition, *conjugate_id, tag)
+                })
+                .unwrap_or_default();
+            for warning in &warnings {
+                warn!(
+                    "Validation {} file {}: {}",
+                    validation.id, file_name, warning
+                );
+            }
+            json!({"acquisition": acquisition, "warnings": warnings})
+        });
+        let file_c = ValidationFileForCreate {
             validation_id: validation.id,
             created_by: member_id,
-            hash,
-            size: file_bytes.len() as i64,
+            hash: String::new(),
+            size: i64::try_from(upload.size()).unwrap_or(i64::MAX),
             name: Some(file_name),
             extension,
-            description,
+            description: description.clone(),
             created_at: chrono::Utc::now(),
-        },
-    )
-    .await?;
+        };
+        match store_upload(&ctx, &mm, &store, file_c, upload, meta).await {
+            Ok(file_id) => file_ids.push(file_id),
+            Err(err) => {
+                for file_id in file_ids {
+                    ValidationFileBmc::delete(&ctx, &mm, file_id).await?;
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let mut files = vec![];
+    for file_id in file_ids {
+        files.push(ValidationFileBmc::get(&ctx, &mm, file_id).await?);
+    }
+    let body = if sent_several {
+        json!(files)
+    } else {
+        json!(files.into_iter().next())
+    };
+    Ok((StatusCode::CREATED, axum::Json(body)).into_response())
+}
+
+/// Creates the row for a staged upload and moves the upload into the store.
+async fn store_upload(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    store: &BlobStore,
+    mut file_c: ValidationFileForCreate,
+    mut upload: StagedUpload,
+    meta: Option<serde_json::Value>,
+) -> Result<i64> {
+    file_c.hash = upload.finish().await?;
+    // The row goes in first so a concurrent GC sees the blob as referenced.
+    let file_id = ValidationFileBmc::create(ctx, mm, file_c).await?;
+    if let Some(meta) = meta
+        && let Err(err) = ValidationFileBmc::set_meta(ctx, mm, file_id, meta).await
+    {
+        ValidationFileBmc::delete(ctx, mm, file_id).await?;
+        return Err(err.into());
+    }
+    if let Err(err) = store.put_staged(ctx, mm, upload).await {
+        ValidationFileBmc::delete(ctx, mm, file_id).await?;
+        return Err(err.into());
+    }
+    Ok(file_id)
+}
+
+/// Acquisition metadata of an MCD, OME-TIFF or FCS upload. Files it cannot be read from are
+/// still accepted, just without metadata.
+async fn read_acquisition(
+    file_name: &str,
+    extension: &str,
+    upload: &mut StagedUpload,
+) -> Result<Option<AcquisitionMeta>> {
+    if !acquisition::supports(extension) {
+        return Ok(None);
+    }
+    upload.finish().await?;
+    let path = upload.path().to_owned();
+    let extension = extension.to_string();
+    let read = tokio::task::spawn_blocking(move || acquisition::extract(&path, &extension))
+        .await
+        .map_err(std::io::Error::other)?;
+    match read {
+        Ok(meta) => Ok(meta),
+        Err(err) => {
+            warn!("Cannot read acquisition metadata of {}: {}", file_name, err);
+            Ok(None)
+        }
+    }
+}
 
-    let file = ValidationFileBmc::get(&ctx, &mm, file_id).await?;
-    Ok((StatusCode::CREATED, axum::Json(json!(file))).into_response())
+/// The conjugate a validation is about, with its tag.
+async fn conjugate_tag(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    validation: &Validation,
+) -> Result<Option<(i64, Tag)>> {
+    let Some(conjugate_id) = validation.conjugate_id else {
+        return Ok(None);
+    };
+    let conjugate = ConjugateBmc::get(ctx, mm, conjugate_id).await?;
+    let tag = TagBmc::get(ctx, mm, conjugate.tag_id).await?;
+    Ok(Some((conjugate_id, tag)))
+}
+
+/// Storage key of an uploaded validation file.
+pub(crate) fn validation_file_key(
+    validation: &Validation,
+    validation_file: &ValidationFile,
+) -> String {
+    BlobStore::file_key(
+        validation.group_id,
+        validation.id,
+        &validation_file.hash,
+        &validation_file.extension,
+    )
 }
 
 fn content_type_for_extension(ext: &str) -> &'static str {
@@ -218,7 +477,10 @@ async fn get_member_id(ctx: &Ctx, mm: &ModelManager, group_id: i64, user_id: i64
 mod tests {
     use super::*;
     use airlab_lib::ctx::Ctx;
-    use airlab_lib::model::validation_file::{ValidationFileBmc, ValidationFileForCreate};
+    use airlab_lib::model::validation::ValidationForCreate;
+    use airlab_lib::model::validation_file::{
+        ValidationFileBmc, ValidationFileFilter, ValidationFileForCreate,
+    };
     use tower::ServiceExt;
 
     type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -252,7 +514,8 @@ mod tests {
             },
         )
         .await?;
-        let app = crate::web::test_support::authed_router(routes((*mm).clone()));
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
 
         let response = app
             .oneshot(
@@ -268,4 +531,316 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn validation_file_route_serves_byte_ranges() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+        let content = format!("range-{}", uuid::Uuid::new_v4());
+        let hash = BlobStore::from_config()?
+            .put(&ctx, &mm, content.as_bytes())
+            .await?;
+        let file_id = ValidationFileBmc::create(
+            &ctx,
+            &mm,
+            ValidationFileForCreate {
+                validation_id: 1011,
+                created_by: 1303,
+                hash,
+                size: content.len() as i64,
+                name: Some("range.txt".into()),
+                extension: "txt".into(),
+                description: None,
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .await?;
+        let request = |range: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/api/v1/validation_files/{file_id}/serve"))
+                .header(header::RANGE, range)
+                .body(axum::body::Body::empty())
+        };
+
+        let response = app.clone().oneshot(request("bytes=1-3")?).await?;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE),
+            Some(&HeaderValue::from_str(&format!(
+                "bytes 1-3/{}",
+                content.len()
+            ))?)
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_LENGTH),
+            Some(&HeaderValue::from(3))
+        );
+        let body = crate::web::test_support::response_body_string(response).await?;
+        assert_eq!(body, "ang");
+
+        let response = app.clone().oneshot(request("bytes=999-")?).await?;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let response = app.oneshot(request("")?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        assert_eq!(body, content);
+
+        ValidationFileBmc::delete(&ctx, &mm, file_id).await?;
+        Ok(())
+    }
+
+    fn upload_request(files: &[(&str, &str, &[u8])]) -> TestResult<axum::http::Request<Body>> {
+        upload_request_to(2221, files)
+    }
+
+    fn upload_request_to(
+        validation_id: i64,
+        files: &[(&str, &str, &[u8])],
+    ) -> TestResult<axum::http::Request<Body>> {
+        let boundary = "airlab-test-boundary";
+        let mut body = vec![];
+        for (file_name, content_type, bytes) in files {
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; \
+                     filename=\"{file_name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(bytes);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"description\"\r\n\r\n\
+                 batch upload\r\n--{boundary}--\r\n"
+            )
+            .as_bytes(),
+        );
+        Ok(axum::http::Request::builder()
+            .method("POST")
+            .uri(format!(
+                "/api/v1/validations/{validation_id}/validation_files"
+            ))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))?)
+    }
+
+    #[tokio::test]
+    async fn upload_route_streams_several_files() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+        let csv = format!("clone,score\n{},1\n", uuid::Uuid::new_v4());
+
+        let response = app
+            .oneshot(upload_request(&[
+                ("report.pdf", "application/pdf", b"%PDF-1.4\n%%EOF"),
+                ("scores.csv", "text/csv", csv.as_bytes()),
+            ])?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let files: Vec<ValidationFile> = serde_json::from_str(&body)?;
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].extension, "csv");
+        assert_eq!(files[1].hash, BlobStore::digest(csv.as_bytes()));
+        assert_eq!(files[1].size, csv.len() as i64);
+        assert_eq!(files[0].description.as_deref(), Some("batch upload"));
+        let stored = BlobStore::from_config()?
+            .backend()
+            .get(&BlobStore::key(&files[1].hash), None)
+            .await?;
+        assert_eq!(stored, Some(csv.into_bytes()));
+
+        for file in files {
+            ValidationFileBmc::delete(&ctx, &mm, file.id).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_route_rejects_unsupported_files_with_415() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        for file in [
+            ("setup.exe", "application/octet-stream", &b"MZ"[..]),
+            ("image.png", "image/png", &b"%PDF-1.4"[..]),
+            ("image.png", "text/html", &b"\x89PNG\r\n\x1a\n"[..]),
+        ] {
+            let response = app.clone().oneshot(upload_request(&[file])?).await?;
+            let error = response
+                .extensions()
+                .get::<std::sync::Arc<Error>>()
+                .ok_or("error extension")?;
+            assert_eq!(
+                error.client_status_and_error().0,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_route_rejects_approved_validations() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let seed = ValidationBmc::get(&ctx, &mm, 2221).await?;
+        let id = ValidationBmc::create(
+            &ctx,
+            &mm,
+            ValidationForCreate::from(json!({
+                "groupId": seed.group_id,
+                "createdBy": seed.created_by,
+                "cloneId": seed.clone_id,
+                "application": seed.application
+            })),
+        )
+        .await?;
+        sqlx::query("UPDATE validation SET review_state = 'approved' WHERE id = $1")
+            .bind(id)
+            .execute(mm.db())
+            .await?;
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+
+        let response = app
+            .oneshot(upload_request_to(
+                id,
+                &[("report.pdf", "application/pdf", b"%PDF-1.4\n%%EOF")],
+            )?)
+            .await?;
+
+        let error = response
+            .extensions()
+            .get::<std::sync::Arc<Error>>()
+            .ok_or("error extension")?;
+        assert_eq!(error.client_status_and_error().0, StatusCode::CONFLICT);
+        let files: Vec<ValidationFileFilter> = serde_json::from_value(json!([
+            {"validation_id": {"$eq": id}}
+        ]))?;
+        assert!(
+            ValidationFileBmc::list(&ctx, &mm, Some(files), None)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_route_extracts_acquisition_metadata() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+        let mut mcd = crate::acquisition::sample_mcd();
+        mcd.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+
+        let response = app
+            .oneshot(upload_request(&[(
+                "slide.mcd",
+                "application/octet-stream",
+                &mcd,
+            )])?)
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let file: ValidationFile = serde_json::from_str(&body)?;
+        let meta = file.meta.ok_or("meta")?;
+        assert_eq!(meta["acquisition"]["format"], "mcd");
+        assert_eq!(meta["acquisition"]["channels"][0]["metal"], "Er166");
+        assert_eq!(meta["acquisition"]["rois"][1]["description"], "ROI_002");
+        // The seeded conjugate's tag names no isotope, so nothing can be checked.
+        assert_eq!(meta["warnings"], json!([]));
+
+        ValidationFileBmc::delete(&ctx, &mm, file.id).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn thumbnail_route_renders_and_caches_preview() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        let app =
+            crate::web::test_support::authed_router(routes(BlobState::from_config((*mm).clone())?));
+        // A unique colour keeps the blob apart from other tests' uploads.
+        let shade = u8::try_from(uuid::Uuid::new_v4().as_u128() % 256)?;
+        let mut png = vec![];
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            120,
+            60,
+            image::Rgb([shade, 17, 99]),
+        ))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+        let store = BlobStore::from_config()?;
+        let hash = store.put(&ctx, &mm, &png).await?;
+        let file_id = ValidationFileBmc::create(
+            &ctx,
+            &mm,
+            ValidationFileForCreate {
+                validation_id: 1011,
+                created_by: 1303,
+                hash: hash.clone(),
+                size: png.len() as i64,
+                name: Some("stain.png".into()),
+                extension: "png".into(),
+                description: None,
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .await?;
+        let request = |query: &str| {
+            axum::http::Request::builder()
+                .uri(format!(
+                    "/api/v1/validation_files/{file_id}/thumbnail{query}"
+                ))
+                .body(Body::empty())
+        };
+
+        let response = app.clone().oneshot(request("?width=30")?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("image/png"))
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let preview = image::load_from_memory(&body)?;
+        assert_eq!((preview.width(), preview.height()), (64, 32));
+        let cache_key = BlobStore::derived_key(&BlobStore::key(&hash), "preview-64.png");
+        assert!(store.backend().head(&cache_key).await?.is_some());
+
+        let response = app.clone().oneshot(request("?width=50&height=20")?).await?;
+        let cached = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        assert_eq!(cached, body);
+        let derived = store
+            .backend()
+            .list(&BlobStore::derived_key(&BlobStore::key(&hash), ""))
+            .await?;
+        assert_eq!(derived.len(), 1);
+
+        let response = app.oneshot(request("?width=0")?).await?;
+        assert!(
+            response
+                .extensions()
+                .get::<std::sync::Arc<Error>>()
+                .is_some()
+        );
+
+        ValidationFileBmc::delete(&ctx, &mm, file_id).await?;
+        assert!(store.remove(&hash).await?);
+        assert!(store.backend().head(&cache_key).await?.is_none());
+        Ok(())
+    }
 }
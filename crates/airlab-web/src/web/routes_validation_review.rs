@@ -0,0 +1,209 @@
+use crate::web::mw_auth::CtxW;
+use crate::web::{Error, Result};
+use airlab_lib::ctx::Ctx;
+use airlab_lib::model::ModelManager;
+use airlab_lib::model::member::{Member, MemberBmc, MemberFilter};
+use airlab_lib::model::validation::{ReviewAction, ValidationBmc};
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+#[allow(unused_imports)]
+use tracing::{debug, warn};
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/api/v1/groups/{group_id}/validations/review_queue",
+            get(api_review_queue_handler),
+        )
+        .route(
+            "/api/v1/validations/{validation_id}/review",
+            post(api_review_validation_handler),
+        )
+        .route(
+            "/api/v1/validations/{validation_id}/review_history",
+            get(api_review_history_handler),
+        )
+        .with_state(mm)
+}
+
+/// Submitted validations the caller may review, i.e. not authored or submitted by them.
+async fn api_review_queue_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Path(group_id): Path<i64>,
+) -> Result<Json<Value>> {
+    debug!("HANDLER - api_review_queue_handler: {}", group_id);
+
+    let ctx = ctx.0;
+    let member_id = get_member_id(&ctx, &mm, group_id, ctx.user_id()).await?;
+    let mut queue = ValidationBmc::review_queue(&ctx, &mm, group_id).await?;
+    queue.retain(|validation| {
+        validation.created_by != member_id && validation.submitted_by != Some(member_id)
+    });
+
+    Ok(Json(json!(queue)))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewPayload {
+    action: ReviewAction,
+    comment: Option<String>,
+}
+
+async fn api_review_validation_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Path(validation_id): Path<i64>,
+    Json(payload): Json<ReviewPayload>,
+) -> Result<Json<Value>> {
+    debug!(
+        "HANDLER - api_review_validation_handler: {} {:?}",
+        validation_id, payload
+    );
+
+    let ctx = ctx.0;
+    let validation = ValidationBmc::get(&ctx, &mm, validation_id).await?;
+    let member_id = get_member_id(&ctx, &mm, validation.group_id, ctx.user_id()).await?;
+    let validation = ValidationBmc::review(
+        &ctx,
+        &mm,
+        validation_id,
+        payload.action,
+        member_id,
+        payload.comment,
+    )
+    .await?;
+
+    Ok(Json(json!(validation)))
+}
+
+async fn api_review_history_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Path(validation_id): Path<i64>,
+) -> Result<Json<Value>> {
+    debug!("HANDLER - api_review_history_handler: {}", validation_id);
+
+    let ctx = ctx.0;
+    ValidationBmc::get(&ctx, &mm, validation_id).await?;
+    let history = ValidationBmc::review_history(&ctx, &mm, validation_id).await?;
+
+    Ok(Json(json!(history)))
+}
+
+async fn get_member_id(ctx: &Ctx, mm: &ModelManager, group_id: i64, user_id: i64) -> Result<i64> {
+    let filters: Vec<MemberFilter> = serde_json::from_value(json!([
+        {
+            "group_id": {"$eq": group_id},
+            "user_id": {"$eq": user_id}
+        }
+    ]))?;
+
+    let members: Vec<Member> = MemberBmc::list(ctx, mm, Some(filters), None).await?;
+    members
+        .into_iter()
+        .next()
+        .map(|member| member.id)
+        .ok_or_else(|| {
+            Error::BadRequest(format!(
+                "No member found for group {group_id} and user {user_id}"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use airlab_lib::model::validation::{REVIEWER_ROLE, ValidationForCreate};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn post_review(validation_id: i64, body: &Value) -> TestResult<Request<Body>> {
+        Ok(Request::builder()
+            .method("POST")
+            .uri(format!("/api/v1/validations/{validation_id}/review"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))?)
+    }
+
+    #[tokio::test]
+    async fn review_route_approves_submission_from_queue() -> TestResult {
+        let mm = crate::web::test_support::init_test_db().await;
+        let ctx = Ctx::root_ctx();
+        // Test requests run as user 1, member 1 of group 1; the validation is by member 261.
+        sqlx::query("UPDATE member SET role = $1 WHERE id = 1")
+            .bind(REVIEWER_ROLE)
+            .execute(mm.db())
+            .await?;
+        let seed = ValidationBmc::get(&ctx, &mm, 2221).await?;
+        let id = ValidationBmc::create(
+            &ctx,
+            &mm,
+            ValidationForCreate::from(json!({
+                "groupId": seed.group_id,
+                "createdBy": 261,
+                "cloneId": seed.clone_id,
+                "application": seed.application
+            })),
+        )
+        .await?;
+        ValidationBmc::review(&ctx, &mm, id, ReviewAction::Submit, 261, None).await?;
+        let app = crate::web::test_support::authed_router(routes((*mm).clone()));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/groups/1/validations/review_queue")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let queue: Value = serde_json::from_str(&body)?;
+        let queued = queue
+            .as_array()
+            .and_then(|queue| queue.iter().find(|item| item["id"] == id))
+            .ok_or("validation should be queued")?;
+        assert_eq!(queued["reviewState"], "submitted");
+
+        let response = app
+            .clone()
+            .oneshot(post_review(id, &json!({"action": "reject"}))?)
+            .await?;
+        assert!(!response.status().is_success());
+
+        let response = app
+            .clone()
+            .oneshot(post_review(
+                id,
+                &json!({"action": "approve", "comment": "controls ok"}),
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let validation: Value = serde_json::from_str(&body)?;
+        assert_eq!(validation["reviewState"], "approved");
+        assert_eq!(validation["reviewedBy"], 1);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/v1/validations/{id}/review_history"))
+                    .body(Body::empty())?,
+            )
+            .await?;
+        let body = crate::web::test_support::response_body_string(response).await?;
+        let history: Value = serde_json::from_str(&body)?;
+        assert_eq!(history[1]["action"], "approve");
+        assert_eq!(history[1]["comment"], "controls ok");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! What the validation file upload accepts: file kinds by extension, declared MIME type and
+//! leading bytes, and how large files may get per file and per group.
+
+use crate::config::web_config;
+use crate::web::{Error, Result};
+
+/// A file kind the upload knows how to recognise.
+struct FileKind {
+    extensions: &'static [&'static str],
+    /// Declared MIME types that fit, besides `application/octet-stream`.
+    mime_types: &'static [&'static str],
+    sniff: fn(&[u8]) -> bool,
+}
+
+const FILE_KINDS: &[FileKind] = &[
+    FileKind {
+        extensions: &["pdf"],
+        mime_types: &["application/pdf"],
+        sniff: |head| head.starts_with(b"%PDF-"),
+    },
+    FileKind {
+        extensions: &["png"],
+        mime_types: &["image/png"],
+        sniff: |head| head.starts_with(b"\x89PNG\r\n\x1a\n"),
+    },
+    FileKind {
+        extensions: &["jpg", "jpeg"],
+        mime_types: &["image/jpeg", "image/pjpeg"],
+        sniff: |head| head.starts_with(&[0xff, 0xd8, 0xff]),
+    },
+    FileKind {
+        extensions: &["gif"],
+        mime_types: &["image/gif"],
+        sniff: |head| head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a"),
+    },
+    FileKind {
+        extensions: &["webp"],
+        mime_types: &["image/webp"],
+        sniff: |head| head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP"),
+    },
+    FileKind {
+        extensions: &["tif", "tiff"],
+        mime_types: &["image/tiff"],
+        // Classic and BigTIFF, both byte orders.
+        sniff: |head| {
+            [b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"]
+                .iter()
+                .any(|magic| head.starts_with(*magic))
+        },
+    },
+    FileKind {
+        extensions: &["txt", "csv", "tsv"],
+        mime_types: &[
+            "text/plain",
+            "text/csv",
+            "text/tab-separated-values",
+            "application/csv",
+            "application/vnd.ms-excel",
+        ],
+        sniff: is_text,
+    },
+    FileKind {
+        extensions: &["fcs"],
+        mime_types: &["application/vnd.isac.fcs"],
+        sniff: |head| head.starts_with(b"FCS"),
+    },
+    FileKind {
+        // Fluidigm MCD keeps its schema at the end of the file; nothing to check up front.
+        extensions: &["mcd"],
+        mime_types: &[],
+        sniff: |_| true,
+    },
+    FileKind {
+        extensions: &["xlsx", "docx"],
+        mime_types: &[
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        ],
+        sniff: |head| head.starts_with(b"PK\x03\x04"),
+    },
+];
+
+/// UTF-8 without NUL bytes; a character cut off at the end of the sniffed head is fine.
+fn is_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+fn file_kind(extension: &str) -> Option<&'static FileKind> {
+    FILE_KINDS
+        .iter()
+        .find(|kind| kind.extensions.contains(&extension))
+}
+
+/// Lowercase extension of an uploaded file name, `bin` if it has none.
+pub fn file_extension(file_name: &str) -> String {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.trim().to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    pub max_file_bytes: u64,
+    pub group_quota_bytes: Option<u64>,
+    extensions: Vec<String>,
+}
+
+impl UploadPolicy {
+    pub fn from_config() -> Result<Self> {
+        let config = web_config()?;
+        let extensions = match &config.UPLOAD_EXTENSIONS {
+            Some(list) => list
+                .split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+            None => FILE_KINDS
+                .iter()
+                .flat_map(|kind| kind.extensions.iter().map(ToString::to_string))
+                .collect(),
+        };
+        Ok(Self {
+            max_file_bytes: config.MAX_UPLOAD_BYTES,
+            group_quota_bytes: config.GROUP_UPLOAD_QUOTA_BYTES,
+            extensions,
+        })
+    }
+
+    /// Checks the extension against the allowlist and the declared MIME type against the
+    /// extension, before any bytes are read.
+    pub fn check_declared(
+        &self,
+        file_name: &str,
+        extension: &str,
+        mime_type: Option<&str>,
+    ) -> Result<()> {
+        if !self.extensions.iter().any(|allowed| allowed == extension) {
+            return Err(unsupported(
+                file_name,
+                format!("extension .{extension} is not accepted"),
+            ));
+        }
+        let Some(kind) = file_kind(extension) else {
+            return Ok(());
+        };
+        let mime_type = mime_type
+            .and_then(|mime| mime.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase())
+            .filter(|mime| !mime.is_empty() && mime != "application/octet-stream");
+        match mime_type {
+            Some(mime)
+                if !kind.mime_types.is_empty() && !kind.mime_types.contains(&mime.as_str()) =>
+            {
+                Err(unsupported(
+                    file_name,
+                    format!("{mime} does not match .{extension}"),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the first bytes of an upload against its extension.
+    pub fn check_content(&self, file_name: &str, extension: &str, head: &[u8]) -> Result<()> {
+        match file_kind(extension) {
+            Some(kind) if !(kind.sniff)(head) => Err(unsupported(
+                file_name,
+                format!("content is not a .{extension} file"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The largest size the next file may reach, given what is left of the group quota.
+    pub fn limit(&self, remaining_quota: Option<u64>) -> UploadLimit {
+        match remaining_quota {
+            Some(remaining) if remaining < self.max_file_bytes => UploadLimit::GroupQuota {
+                remaining,
+                quota: self.group_quota_bytes.unwrap_or(remaining),
+            },
+            _ => UploadLimit::File(self.max_file_bytes),
+        }
+    }
+}
+
+/// Which bound applies to a file, so the rejection names the right one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadLimit {
+    File(u64),
+    GroupQuota { remaining: u64, quota: u64 },
+}
+
+impl UploadLimit {
+    pub const fn bytes(self) -> u64 {
+        match self {
+            Self::File(limit) => limit,
+            Self::GroupQuota { remaining, .. } => remaining,
+        }
+    }
+
+    /// Fails once a file grows past the limit.
+    pub fn check(self, file_name: &str, group_id: i64, size: u64) -> Result<()> {
+        if size <= self.bytes() {
+            return Ok(());
+        }
+        Err(match self {
+            Self::File(limit) => Error::UploadTooLarge {
+                file_name: file_name.to_string(),
+                limit,
+            },
+            Self::GroupQuota { quota, .. } => Error::GroupQuotaExceeded { group_id, quota },
+        })
+    }
+}
+
+fn unsupported(file_name: &str, reason: String) -> Error {
+    Error::UnsupportedMediaType {
+        file_name: file_name.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(extensions: &[&str]) -> UploadPolicy {
+        UploadPolicy {
+            max_file_bytes: 100,
+            group_quota_bytes: Some(1000),
+            extensions: extensions.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn file_extension_is_lowercase_with_fallback() {
+        assert_eq!(file_extension("Scan.TIFF"), "tiff");
+        assert_eq!(file_extension("archive.tar.gz"), "gz");
+        assert_eq!(file_extension("README"), "bin");
+        assert_eq!(file_extension("trailing."), "bin");
+    }
+
+    #[test]
+    fn check_declared_applies_allowlist_and_mime_types() {
+        let policy = policy(&["pdf", "png", "mcd", "dat"]);
+        assert!(policy.check_declared("a.pdf", "pdf", None).is_ok());
+        assert!(
+            policy
+                .check_declared("a.pdf", "pdf", Some("application/octet-stream"))
+                .is_ok()
+        );
+        assert!(policy.check_declared("a.dat", "dat", Some("x/y")).is_ok());
+        assert!(policy.check_declared("a.mcd", "mcd", Some("x/y")).is_ok());
+        assert!(matches!(
+            policy.check_declared("a.exe", "exe", None),
+            Err(Error::UnsupportedMediaType { .. })
+        ));
+        assert!(matches!(
+            policy.check_declared("a.png", "png", Some("text/html")),
+            Err(Error::UnsupportedMediaType { .. })
+        ));
+    }
+
+    #[test]
+    fn check_content_sniffs_known_kinds() {
+        let policy = policy(&[]);
+        assert!(policy.check_content("a.pdf", "pdf", b"%PDF-1.7\n").is_ok());
+        assert!(policy.check_content("a.png", "png", b"%PDF-1.7\n").is_err());
+        assert!(policy.check_content("a.tif", "tif", b"II*\0\x08\0").is_ok());
+        assert!(
+            policy
+                .check_content("a.csv", "csv", "a,b\nä".as_bytes())
+                .is_ok()
+        );
+        assert!(policy.check_content("a.csv", "csv", b"a,b\0").is_err());
+        assert!(policy.check_content("a.txt", "txt", &[b'a', 0xc3]).is_ok());
+        assert!(policy.check_content("a.dat", "dat", b"\0\0").is_ok());
+    }
+
+    #[test]
+    fn limit_names_the_bound_that_applies() {
+        let policy = policy(&[]);
+        assert_eq!(policy.limit(None), UploadLimit::File(100));
+        assert_eq!(policy.limit(Some(500)), UploadLimit::File(100));
+
+        let limit = policy.limit(Some(40));
+        assert_eq!(
+            limit,
+            UploadLimit::GroupQuota {
+                remaining: 40,
+                quota: 1000
+            }
+        );
+        assert!(limit.check("a.pdf", 7, 40).is_ok());
+        assert!(matches!(
+            limit.check("a.pdf", 7, 41),
+            Err(Error::GroupQuotaExceeded {
+                group_id: 7,
+                quota: 1000
+            })
+        ));
+        assert!(matches!(
+            UploadLimit::File(100).check("a.pdf", 7, 101),
+            Err(Error::UploadTooLarge { limit: 100, .. })
+        ));
+    }
+}
@@ -2,6 +2,8 @@ import { describe, it, expect } from "vitest";
 
 import {
   applicationToString,
+  applicationUsable,
+  applicationEntry,
   stringToUTCString,
   validationStatusToString,
   roleToString,
@@ -22,6 +24,24 @@ describe("utils/converters", () => {
     expect(applicationToString(0)).toBeTypeOf("string");
   });
 
+  it("applicationUsable reads status entries and legacy booleans", () => {
+    expect(applicationUsable({ status: 0 })).toBe(true);
+    expect(applicationUsable({ status: 1, note: "weak" })).toBe(true);
+    expect(applicationUsable({ status: 2 })).toBe(false);
+    expect(applicationUsable({ status: 3 })).toBeNull();
+    expect(applicationUsable(undefined)).toBeNull();
+    expect(applicationUsable(false)).toBe(false);
+  });
+
+  it("applicationEntry keeps the previous entry while the toggle agrees", () => {
+    const soSo = { status: 1, note: "weak" };
+    expect(applicationEntry("true", soSo)).toBe(soSo);
+    expect(applicationEntry("false", soSo)).toEqual({ status: 2 });
+    expect(applicationEntry(null, { status: 3 })).toEqual({ status: 3 });
+    expect(applicationEntry(null, soSo)).toBeNull();
+    expect(applicationEntry("true")).toEqual({ status: 0 });
+  });
+
   it("stringToUTCString converts to UTC string", () => {
     const out = stringToUTCString("2024-01-01T00:00:00Z");
     expect(out).toContain("GMT");
@@ -36,6 +56,7 @@ describe("utils/converters", () => {
 
   it("roleToString maps roles correctly", () => {
     expect(roleToString(100)).toBe("Admin");
+    expect(roleToString(50)).toBe("Reviewer");
     expect(roleToString(10)).toBe("Standard");
     expect(roleToString(0)).toBe("Guest");
     expect(roleToString(-1)).toBe("");
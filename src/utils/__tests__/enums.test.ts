@@ -41,6 +41,7 @@ describe("enums", () => {
   it("role enum is stable", () => {
     expect(roleEnum).toEqual([
       { value: 100, text: "Admin" },
+      { value: 50, text: "Reviewer" },
       { value: 10, text: "Standard" },
       { value: 0, text: "Guest" },
     ]);
@@ -8,6 +8,39 @@ export function applicationToString(value: number): string {
   return applicationIdToName[value];
 }
 
+/**
+ * Whether a clone is usable for an application, from its entry in `clone.application`:
+ * `true` for Yes and So-So, `false` for No, `null` when undefined or unset. Entries are
+ * `{status, note}` objects; bare booleans of older data are still read.
+ */
+export function applicationUsable(entry: unknown): boolean | null {
+  if (typeof entry === "boolean") {
+    return entry;
+  }
+  switch ((entry as { status?: number } | null | undefined)?.status) {
+    case 0:
+    case 1:
+      return true;
+    case 2:
+      return false;
+    default:
+      return null;
+  }
+}
+
+/**
+ * The `clone.application` entry for a Yes/No toggle (`"true"`, `"false"` or `null`). The
+ * previous entry is kept while the toggle agrees with it, so its So-So status and note
+ * survive a save.
+ */
+export function applicationEntry(toggle: string | null, previous?: unknown): object | null {
+  const usable = toggle === null ? null : toggle === "true";
+  if (previous && typeof previous === "object" && applicationUsable(previous) === usable) {
+    return previous;
+  }
+  return usable === null ? null : { status: usable ? 0 : 2 };
+}
+
 export function stringToUTCString(value: string): string {
   return new Date(value).toUTCString();
 }
@@ -25,6 +58,7 @@ export function validationStatusToString(value: number): string {
 
 const roleMap = {
   100: "Admin",
+  50: "Reviewer",
   10: "Standard",
   0: "Guest",
 };
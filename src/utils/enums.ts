@@ -37,6 +37,7 @@ export const statusEnum = [
 
 export const roleEnum = [
   { value: 100, text: "Admin" },
+  { value: 50, text: "Reviewer" },
   { value: 10, text: "Standard" },
   { value: 0, text: "Guest" },
 ];